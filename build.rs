@@ -0,0 +1,15 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // Embed the build time for the `/version` endpoint, honoring reproducible builds.
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH").ok().unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs().to_string())
+            .unwrap_or_default()
+    });
+    println!("cargo:rustc-env=RUSTY_API_BUILD_TIMESTAMP={}", timestamp);
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-changed=build.rs");
+}
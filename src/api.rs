@@ -10,28 +10,88 @@
  * The `Api` struct serves as the main entry point for configuring and starting the server, offering methods for setting
  * up TLS, binding to an address, configuring routes, and more.
  */
-use crate::core::config::load_rustls_config;
-use crate::routes::Routes;
+use crate::core::config::{initialize_crypto_provider, load_sni_tls_config, load_tls_config, SniCert};
+#[cfg(feature = "auth")]
+use crate::core::audit::AuditSink;
+#[cfg(feature = "auth")]
+use crate::core::quota::Quota;
+use crate::core::idempotency::Idempotency;
+#[cfg(feature = "auth")]
+use crate::core::session::{session_validator, TokenMode};
+#[cfg(feature = "auth")]
+use crate::core::auth_routes::{LoginIncludesUser, VerifyPasswordLimit};
+#[cfg(feature = "auth")]
+use crate::core::auth::{JwtCookie, DEFAULT_JWT_COOKIE, JwtSettings, Claims, TokenValidator, DEFAULT_JWT_LEEWAY, DEFAULT_JWT_MAX_SESSION};
+#[cfg(feature = "auth")]
+use crate::core::jwks::JwksVerifier;
+use crate::core::ip::{parse_ip_range, TrustProxy};
+use crate::core::json::DenyUnknownFields;
+use crate::core::logging::{init_stdout_logger, DEFAULT_LOG_LEVEL};
+use log::LevelFilter;
+use crate::core::middleware::{BodyLogger, CatchPanic, ClientIpKeyExtractor, Etag, IpFilter, NormalizePathRedirect, PathNormalization, RateLimit, RateLimitResponse, RequireHeaders, RequireHttps, ResponseEnvelope, security_headers, ServerHeader, SlowRequestLog, TrailingSlash, UriLimit, HeaderLimit, DEFAULT_MAX_HEADERS, DEFAULT_MAX_HEADER_SIZE, DEFAULT_MAX_QUERY_LENGTH, DEFAULT_MAX_URI_LENGTH};
+#[cfg(feature = "auth")]
+use crate::core::password::PasswordAlgorithm;
+use crate::core::response::json_error;
+use crate::core::settings::{CorsSettings, Profile, Settings};
+use crate::error::{ApiError, ConfigIssue};
+use crate::routes::{RouteInfo, Routes, STATIC_CACHE_CONTROL};
 
-use actix_web::{App, HttpServer, web};
-use actix_governor::{Governor, GovernorConfigBuilder};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, ResponseError, web};
+use actix_web::error::JsonPayloadError;
+use actix_web::middleware::{Condition, NormalizePath};
+#[cfg(not(feature = "auth"))]
+use actix_web::middleware::Identity;
+use actix_web::body::MessageBody;
+use actix_web::web::Bytes;
+use actix_web::dev::{AppConfig, ServiceFactory, ServiceRequest, ServiceResponse, Transform};
+use actix_governor::{GovernorConfig, GovernorConfigBuilder};
 use actix_cors::Cors;
-use std::sync::{Arc, Once};
+use ipnet::IpNet;
+use rustls::ServerConfig;
+#[cfg(feature = "sqlite")]
+use sqlx::SqlitePool;
+use futures_util::future::LocalBoxFuture;
+use actix_service::{boxed, IntoServiceFactory, ServiceFactoryExt};
+use actix_web::dev::{Server, ServerHandle};
+use actix_web::rt::task::JoinHandle;
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
-static INIT: Once = Once::new();
+/// Closure applying custom routes to an Actix Web `ServiceConfig`.
+type RoutesConfig = Arc<dyn Fn(&mut web::ServiceConfig) + Send + Sync>;
+
+/// Closure building the `Cors` middleware for each worker.
+type CorsConfig = Arc<dyn Fn() -> Cors + Send + Sync>;
 
 /**
- * Initialize the crypto provider for Rustls.
+ * The user database pool passed to startup hooks and background tasks.
  *
- * This function sets up the default crypto provider for Rustls using the `ring` library.
- * It is called once to ensure that the provider is initialized only once during the lifetime
- * of the application.
+ * This is `SqlitePool` with the `sqlite` feature. Without it there is no user
+ * database, so this type has no values and hooks always receive `None`.
  */
-fn initialize_crypto_provider() {
-    INIT.call_once(|| {
-        let _ = rustls::crypto::CryptoProvider::install_default(rustls::crypto::ring::default_provider());
-    });
-}
+#[cfg(feature = "sqlite")]
+pub type DbPool = SqlitePool;
+
+/**
+ * The user database pool passed to startup hooks and background tasks.
+ *
+ * This is `SqlitePool` with the `sqlite` feature. Without it there is no user
+ * database, so this type has no values and hooks always receive `None`.
+ */
+#[cfg(not(feature = "sqlite"))]
+#[derive(Debug, Clone)]
+pub enum DbPool {}
+
+/// Hook run once after the server has bound, receiving the user database pool if enabled.
+type StartupHook = Box<dyn FnOnce(Option<DbPool>) -> LocalBoxFuture<'static, Result<(), String>> + Send>;
+
+/// Hook run once after the server has stopped.
+type ShutdownHook = Box<dyn FnOnce() + Send>;
+
+/// Recurring task run in the background while the server is running.
+type BackgroundTask = Box<dyn FnMut(Option<DbPool>) -> LocalBoxFuture<'static, ()> + Send>;
 
 /**
  * The `Api` struct is the main entry point for configuring and running the API server.
@@ -63,6 +123,7 @@ fn initialize_crypto_provider() {
  * api.start();
  * ```
  */
+#[derive(Clone)]
 pub struct Api {
     /// Path to the TLS certificate file used for secure HTTPS communication.
     cert_path: String,
@@ -70,6 +131,12 @@ pub struct Api {
     /// Path to the private key used for TLS.
     key_path: String,
 
+    /// Certificates presented instead of the default one to clients requesting their hostname via SNI.
+    sni_certs: Vec<SniCert>,
+
+    /// Whether the server uses TLS. When disabled, plain HTTP is served.
+    tls: bool,
+
     /// Address to bind the API server to (e.g., "127.0.0.1").
     addr: String,
     
@@ -79,20 +146,203 @@ pub struct Api {
     /// Rate limiting configuration: `(requests_per_second, burst_size)`.
     rate_limit: (u64, u32),
 
+    /// IP addresses or CIDR ranges that bypass the rate limiter.
+    rate_limit_allowlist: Vec<String>,
+
+    /// Builds the response for rate-limited clients, or `None` for the default JSON error.
+    rate_limit_response: Option<RateLimitResponse>,
+
+    /// IP addresses or CIDR ranges allowed to access the API. Empty allows everyone.
+    ip_allowlist: Vec<String>,
+
+    /// IP addresses or CIDR ranges denied access to the API.
+    ip_blocklist: Vec<String>,
+
+    /// Whether to trust proxy headers when resolving the client IP.
+    trust_proxy: bool,
+
     /// Optional custom routes configuration, provided as a closure.
-    custom_routes: Option<Arc<dyn Fn(&mut web::ServiceConfig) + Send + Sync>>,
+    custom_routes: Option<RoutesConfig>,
 
     /// Custom CORS configuration, provided as a closure.
-    custom_cors: Arc<dyn Fn() -> Cors + Send + Sync>,
+    custom_cors: CorsConfig,
+
+    /// Whether the CORS middleware is applied.
+    cors_enabled: bool,
+
+    /// How request paths are normalized before routing.
+    path_normalization: PathNormalization,
+
+    /// Per-user request quotas, or `None` to not enforce any.
+    #[cfg(feature = "auth")]
+    quota: Option<Quota>,
+
+    /// Replays responses to retried requests with an `Idempotency-Key`, or `None` to disable.
+    idempotency: Option<Idempotency>,
+
+    /// Requests taking longer than this are logged, or `None` to not log slow requests.
+    slow_request_threshold: Option<Duration>,
+
+    /// Path of the icon file served at `/favicon.ico`, or `None` to not serve one.
+    favicon: Option<String>,
+
+    /// Content served at `/robots.txt`, or `None` to not serve it.
+    robots_txt: Option<String>,
 
     /// Optional enable user database.
+    #[cfg(feature = "sqlite")]
     user_db: bool,
 
+    /// Database URL overriding the `DATABASE_URL` environment variable.
+    #[cfg(feature = "sqlite")]
+    database_url: Option<String>,
+
+    /// Time to wait for a free database connection before failing.
+    #[cfg(feature = "sqlite")]
+    db_acquire_timeout: Duration,
+
+    /// Initial database connection retries: `(attempts, initial_delay)`.
+    #[cfg(feature = "sqlite")]
+    db_connect_retries: (u32, Duration),
+
+    /// Custom `users` columns readable and writable by name, see `core::db::register_profile_fields`.
+    #[cfg(feature = "sqlite")]
+    profile_fields: Vec<String>,
+
+    /// Algorithm used to hash new passwords, if not the default.
+    #[cfg(feature = "auth")]
+    password_algorithm: Option<PasswordAlgorithm>,
+
+    /// Headers every request must carry.
+    required_headers: Vec<String>,
+
+    /// Value of the `Server` response header, or `None` to send no `Server` header.
+    server_header: Option<String>,
+
+    /// Maximum size of JSON request bodies in bytes, or `None` for the Actix defaults.
+    json_limit: Option<usize>,
+
+    /// Whether `JsonInput` bodies with fields the input type does not declare are rejected.
+    deny_unknown_json_fields: bool,
+
+    /// Level of the built-in standard output logger.
+    log_level: LevelFilter,
+
+    /// Maximum length of the request URI, in bytes.
+    max_uri_length: usize,
+
+    /// Maximum length of the query string, in bytes.
+    max_query_length: usize,
+
+    /// Maximum total size of the request headers, in bytes.
+    max_header_size: usize,
+
+    /// Maximum number of request headers.
+    max_headers: usize,
+
+    /// Maximum number of concurrent connections per worker, or `None` for the Actix default.
+    max_connections: Option<usize>,
+
+    /// Maximum number of concurrent TLS handshakes per worker, or `None` for the Actix default.
+    max_connection_rate: Option<usize>,
+
+    /// Whether to add ETags to `GET` responses and honor `If-None-Match`.
+    etag: bool,
+
+    /// Whether JSON responses are wrapped in a `data`/`meta`/`errors` envelope.
+    response_envelope: bool,
+
+    /// Whether to reject requests not made over HTTPS.
+    secure_only: bool,
+
+    /// Body logging middleware, or `None` to not log bodies.
+    body_logger: Option<BodyLogger>,
+
+    /// Whether HSTS and other hardening headers are added to every response.
+    security_headers: bool,
+
+    /// The environment profile applied with `profile`, if any.
+    profile: Option<Profile>,
+
+    /// Path of the built-in version endpoint, if enabled.
+    version_endpoint: Option<String>,
+
+    /// Path of the built-in metrics endpoint, if enabled.
+    #[cfg(feature = "auth")]
+    metrics_endpoint: Option<String>,
+
+    /// Path of the route listing endpoint, served in debug builds only, or `None` if disabled.
+    routes_endpoint: Option<String>,
+
+    /// The routes registered with `configure_routes`.
+    route_list: Arc<Vec<RouteInfo>>,
+
+    /// The routes passed to `configure_routes` that could not be added.
+    route_problems: Vec<String>,
+
+    /// Sink receiving audit records, or `None` for the `audit_log` table.
+    #[cfg(feature = "auth")]
+    audit_sink: Option<Arc<dyn AuditSink>>,
+
+    /// Custom bearer token validator, or `None` for the built-in HS256 validation.
+    #[cfg(feature = "auth")]
+    token_validator: Option<TokenValidator>,
+
+    /// Issuer put in and required of JWTs, if any.
+    #[cfg(feature = "auth")]
+    jwt_issuer: Option<String>,
+
+    /// Audience put in and required of JWTs, if any.
+    #[cfg(feature = "auth")]
+    jwt_audience: Option<String>,
+
+    /// Clock-skew leeway for token expiry, or `None` for the default.
+    #[cfg(feature = "auth")]
+    jwt_leeway: Option<Duration>,
+
+    /// How long after login tokens can be refreshed, or `None` for the default.
+    #[cfg(feature = "auth")]
+    jwt_max_session: Option<Duration>,
+
+    /// Hook run after the server has bound. Shared between clones and run at most once.
+    on_startup: Arc<Mutex<Option<StartupHook>>>,
+
+    /// Hook run after the server has stopped. Shared between clones and run at most once.
+    on_shutdown: Arc<Mutex<Option<ShutdownHook>>>,
+
+    /// Recurring background tasks with their intervals. Shared between clones and started at most once.
+    tasks: Arc<Mutex<Vec<(Duration, BackgroundTask)>>>,
+
+    /// The user database pool, once initialized. Shared between clones.
+    pool: Arc<RwLock<Option<DbPool>>>,
+
     /// Optional custom route configuration for login.
+    #[cfg(feature = "auth")]
     login_route: String,
 
     /// Optional custom route configuration for register.
+    #[cfg(feature = "auth")]
     register_route: String,
+
+    /// Whether the login response includes the user profile.
+    #[cfg(feature = "auth")]
+    login_includes_user: bool,
+
+    /// Failed checks allowed per user at the verify-password route, shared by the workers.
+    #[cfg(feature = "auth")]
+    verify_password_limit: VerifyPasswordLimit,
+
+    /// Name of the cookie carrying the JWT, or `None` for header-only authentication.
+    #[cfg(feature = "auth")]
+    jwt_cookie: Option<String>,
+
+    /// Whether the login route issues stateless JWTs or server-side sessions.
+    #[cfg(feature = "auth")]
+    token_mode: TokenMode,
+
+    /// Optional admin user `(username, password)` created on startup if missing.
+    #[cfg(feature = "auth")]
+    admin_seed: Option<(String, String)>,
 }
 
 impl Api {
@@ -119,15 +369,193 @@ impl Api {
         Self {
             cert_path: "certs/cert.pem".into(),
             key_path: "certs/key.pem".into(),
+            sni_certs: Vec::new(),
+            tls: true,
             addr: "127.0.0.1".into(),
             port: 8443,
             rate_limit: (3, 20),
+            rate_limit_allowlist: Vec::new(),
+            rate_limit_response: None,
+            ip_allowlist: Vec::new(),
+            ip_blocklist: Vec::new(),
+            trust_proxy: false,
             custom_routes: None,
-            custom_cors: Arc::new(|| Cors::default()),
+            custom_cors: Arc::new(Cors::default),
+            cors_enabled: true,
+            path_normalization: PathNormalization::Rewrite(TrailingSlash::Trim),
+            #[cfg(feature = "auth")]
+            quota: None,
+            idempotency: None,
+            slow_request_threshold: None,
+            favicon: None,
+            robots_txt: None,
+            #[cfg(feature = "sqlite")]
             user_db: false,
+            #[cfg(feature = "sqlite")]
+            database_url: None,
+            #[cfg(feature = "sqlite")]
+            db_acquire_timeout: crate::core::db::DEFAULT_ACQUIRE_TIMEOUT,
+            #[cfg(feature = "sqlite")]
+            db_connect_retries: (3, Duration::from_millis(500)),
+            #[cfg(feature = "sqlite")]
+            profile_fields: Vec::new(),
+            #[cfg(feature = "auth")]
+            password_algorithm: None,
+            required_headers: Vec::new(),
+            server_header: None,
+            json_limit: None,
+            deny_unknown_json_fields: false,
+            log_level: DEFAULT_LOG_LEVEL,
+            max_uri_length: DEFAULT_MAX_URI_LENGTH,
+            max_query_length: DEFAULT_MAX_QUERY_LENGTH,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_connections: None,
+            max_connection_rate: None,
+            etag: false,
+            response_envelope: false,
+            secure_only: false,
+            body_logger: None,
+            security_headers: false,
+            profile: None,
+            version_endpoint: None,
+            #[cfg(feature = "auth")]
+            metrics_endpoint: None,
+            routes_endpoint: None,
+            route_list: Arc::new(Vec::new()),
+            route_problems: Vec::new(),
+            #[cfg(feature = "auth")]
+            audit_sink: None,
+            #[cfg(feature = "auth")]
+            token_validator: None,
+            #[cfg(feature = "auth")]
+            jwt_issuer: None,
+            #[cfg(feature = "auth")]
+            jwt_audience: None,
+            #[cfg(feature = "auth")]
+            jwt_leeway: None,
+            #[cfg(feature = "auth")]
+            jwt_max_session: None,
+            on_startup: Arc::new(Mutex::new(None)),
+            on_shutdown: Arc::new(Mutex::new(None)),
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            pool: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "auth")]
             login_route: "/login".into(),
+            #[cfg(feature = "auth")]
             register_route: "/register".into(),
+            #[cfg(feature = "auth")]
+            login_includes_user: false,
+            #[cfg(feature = "auth")]
+            verify_password_limit: VerifyPasswordLimit::default(),
+            #[cfg(feature = "auth")]
+            jwt_cookie: None,
+            #[cfg(feature = "auth")]
+            token_mode: TokenMode::Jwt,
+            #[cfg(feature = "auth")]
+            admin_seed: None,
+        }
+    }
+
+    /**
+     * Create a new instance of the API server from a TOML configuration file.
+     *
+     * Values present in the file replace the defaults of `Api::new`. Builder methods
+     * called afterwards still override them. See `Settings` for the file format.
+     *
+     * # Arguments
+     * * `path` - Path to the TOML configuration file.
+     *
+     * # Returns
+     * A new `Api` instance, or an `ApiError` if the file cannot be read or parsed.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let path = std::env::temp_dir().join("rusty_api_from_config_file.toml");
+     * std::fs::write(&path, "port = 9443\n[rate_limit]\nper_second = 10\nburst_size = 50\n").unwrap();
+     *
+     * let api = Api::from_config_file(&path).unwrap().bind("0.0.0.0", 8000);
+     * assert_eq!(api.get_rate_limit(), (10, 50));
+     * assert_eq!(api.get_port(), 8000); // The builder overrides the file
+     * ```
+     */
+    pub fn from_config_file(path: impl AsRef<std::path::Path>) -> Result<Self, ApiError> {
+        Ok(Self::new().with_settings(Settings::from_file(path)?))
+    }
+
+    /**
+     * Create a new instance of the API server from environment variables.
+     *
+     * Variables from a `.env` file are loaded first. Values present in the environment
+     * replace the defaults of `Api::new`, and builder methods called afterwards still
+     * override them. See `Settings::from_env` for the supported variables.
+     *
+     * # Returns
+     * A new `Api` instance, or an `ApiError` if a variable has an invalid value.
+     *
+     * # Example
+     * ```rust,no_run
+     * use rusty_api::Api;
+     *
+     * // RUSTY_API_PORT=9443 RUSTY_API_CERT_PATH=/etc/api/cert.pem cargo run
+     * Api::from_env()
+     *     .expect("Invalid environment configuration")
+     *     .start();
+     * ```
+     */
+    pub fn from_env() -> Result<Self, ApiError> {
+        dotenv::dotenv().ok();
+        Ok(Self::new().with_settings(Settings::from_env()?))
+    }
+
+    /**
+     * Apply loaded settings to the API server.
+     *
+     * Only values present in the settings are applied; everything else is left unchanged.
+     * A profile is applied first, so the other settings override its defaults.
+     * Setting a database URL also enables the user database.
+     *
+     * # Arguments
+     * * `settings` - The settings to apply.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        if let Some(profile) = settings.profile {
+            self = self.profile(profile);
+        }
+        if let Some(cert_path) = settings.cert_path {
+            self.cert_path = cert_path;
+        }
+        if let Some(key_path) = settings.key_path {
+            self.key_path = key_path;
+        }
+        if let Some(addr) = settings.addr {
+            self.addr = addr;
+        }
+        if let Some(port) = settings.port {
+            self.port = port;
+        }
+        if let Some(rate_limit) = settings.rate_limit {
+            self.rate_limit = (rate_limit.per_second, rate_limit.burst_size);
+        }
+        if let Some(cors) = settings.cors {
+            self.custom_cors = Arc::new(move || cors.to_cors());
+            self.cors_enabled = true;
         }
+        #[cfg(feature = "sqlite")]
+        if let Some(database_url) = settings.database_url {
+            self.user_db = true;
+            self.database_url = Some(database_url);
+        }
+        #[cfg(feature = "auth")]
+        if let Some(algorithm) = settings.password_algorithm {
+            self.password_algorithm = Some(algorithm);
+        }
+        self
     }
 
     /**
@@ -155,6 +583,65 @@ impl Api {
         self
     }
 
+    /**
+     * Present a separate certificate to clients requesting the given hostname.
+     *
+     * The certificate is selected by the SNI hostname in the TLS ClientHello, so
+     * one server can host several domains. Clients requesting any other hostname,
+     * or none, receive the default certificate set with `certs`. The certificate
+     * must be valid for the hostname; this is checked when the server is built.
+     *
+     * # Arguments
+     * * `hostname` - The DNS name, e.g. `api.example.com`.
+     * * `cert` - Path to the certificate file.
+     * * `key` - Path to the private key file.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, ApiError, ConfigError};
+     *
+     * let api = Api::new()
+     *     .certs("certs/default.pem", "certs/default-key.pem")
+     *     .add_sni_cert("api.example.com", "certs/api.pem", "certs/api-key.pem")
+     *     .add_sni_cert("admin.example.com", "certs/admin.pem", "certs/admin-key.pem");
+     * assert_eq!(api.get_sni_certs()[1].hostname, "admin.example.com");
+     *
+     * let result = Api::new().add_sni_cert("not a hostname", "certs/api.pem", "certs/api-key.pem").build();
+     * assert!(matches!(result, Err(ApiError::InvalidConfig(_))));
+     * ```
+     */
+    pub fn add_sni_cert(mut self, hostname: &str, cert: &str, key: &str) -> Self {
+        self.sni_certs.push(SniCert { hostname: hostname.into(), cert_path: cert.into(), key_path: key.into() });
+        self
+    }
+
+    /**
+     * Serve plain HTTP instead of HTTPS.
+     *
+     * The certificate and key are not loaded. Only use this for local development
+     * or behind a proxy that terminates TLS; tokens and passwords are otherwise
+     * sent in the clear. A warning is logged when the server is built.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().certs("missing/cert.pem", "missing/key.pem").no_tls();
+     * assert!(!api.get_tls());
+     * assert!(api.validate().is_ok());
+     * ```
+     */
+    pub fn no_tls(mut self) -> Self {
+        self.tls = false;
+        self
+    }
+
     /**
      * Set the rate limit for API requests.
      *
@@ -179,6 +666,130 @@ impl Api {
         self
     }
 
+    /**
+     * Exempt trusted clients from the rate limit.
+     *
+     * Requests from these addresses skip the rate limiter entirely, which is useful
+     * for internal monitoring and health checks. Entries can be single addresses or
+     * CIDR ranges. Invalid entries are reported by `build`. When `trust_proxy` is
+     * enabled, the client IP from the proxy headers is matched.
+     *
+     * # Arguments
+     * * `ranges` - IP addresses or CIDR ranges, e.g. `"10.0.0.0/8"` or `"127.0.0.1"`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().rate_limit_allowlist(&["127.0.0.1", "10.0.0.0/8"]);
+     * assert_eq!(api.get_rate_limit_allowlist(), ["127.0.0.1", "10.0.0.0/8"]);
+     * ```
+     *
+     * Allowlisted clients are never throttled, while other clients still are:
+     * ```rust
+     * use rusty_api::{Api, Routes, HttpResponse, Method};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/health", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new()
+     *         .rate_limit(60, 1)
+     *         .rate_limit_allowlist(&["10.0.0.0/8"])
+     *         .configure_routes(routes);
+     *     let app = test::init_service(api.test_app().await).await;
+     *
+     *     for _ in 0..5 {
+     *         let req = test::TestRequest::get().uri("/health").peer_addr("10.1.2.3:1000".parse().unwrap()).to_request();
+     *         assert!(test::call_service(&app, req).await.status().is_success());
+     *     }
+     *
+     *     let outside = || test::TestRequest::get().uri("/health").peer_addr("192.168.0.1:1000".parse().unwrap()).to_request();
+     *     assert!(test::try_call_service(&app, outside()).await.is_ok());
+     *     let throttled = test::try_call_service(&app, outside()).await.err().unwrap();
+     *     assert_eq!(throttled.error_response().status(), 429);
+     * });
+     * ```
+     */
+    pub fn rate_limit_allowlist(mut self, ranges: &[&str]) -> Self {
+        self.rate_limit_allowlist = ranges.iter().map(|range| range.to_string()).collect();
+        self
+    }
+
+    /**
+     * Customize the response sent to rate-limited clients.
+     *
+     * By default, throttled clients receive a `429 Too Many Requests` JSON error
+     * (`{"error": "Too many requests, retry in Ns"}`) with a `Retry-After` header.
+     * The function receives the number of seconds until the client may retry, and
+     * a `Retry-After` header is added if the returned response does not set one.
+     *
+     * # Arguments
+     * * `response` - Builds the response from the number of seconds to wait.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, Routes, HttpResponse, Method, StatusCode, json_error};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/health", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new()
+     *         .rate_limit(60, 1)
+     *         .rate_limit_response(|retry_after| {
+     *             json_error(StatusCode::TOO_MANY_REQUESTS, format!("Slow down, try again in {} seconds", retry_after))
+     *         })
+     *         .configure_routes(routes);
+     *     let app = test::init_service(api.test_app().await).await;
+     *
+     *     let req = || test::TestRequest::get().uri("/health").peer_addr("192.168.0.1:1000".parse().unwrap()).to_request();
+     *     assert!(test::try_call_service(&app, req()).await.is_ok());
+     *     let throttled = test::try_call_service(&app, req()).await.err().unwrap().error_response();
+     *     assert_eq!(throttled.status(), 429);
+     *     assert!(throttled.headers().contains_key("Retry-After"));
+     * });
+     * ```
+     */
+    pub fn rate_limit_response<F>(mut self, response: F) -> Self
+    where
+        F: Fn(u64) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.rate_limit_response = Some(Arc::new(response));
+        self
+    }
+
+    /**
+     * Trust proxy headers when resolving the client IP.
+     *
+     * When enabled, the client IP is read from the `Forwarded` or `X-Forwarded-For`
+     * header instead of the socket peer address. Only enable this when the server is
+     * reachable exclusively through a reverse proxy, as clients can otherwise spoof
+     * their address.
+     *
+     * # Arguments
+     * * `trust` - Whether to trust proxy headers.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().trust_proxy(true);
+     * assert!(api.get_trust_proxy());
+     * ```
+     */
+    pub fn trust_proxy(mut self, trust: bool) -> Self {
+        self.trust_proxy = trust;
+        self
+    }
+
     /**
      * Set the address and port for the API server.
      *
@@ -232,6 +843,8 @@ impl Api {
      * ```
      */
     pub fn configure_routes(mut self, routes: Routes) -> Self {
+        self.route_list = Arc::new(routes.list());
+        self.route_problems = routes.problems().to_vec();
         self.custom_routes = Some(Arc::new(move |cfg| routes.configure(cfg)));
         self
     }
@@ -262,225 +875,3615 @@ impl Api {
         F: Fn() -> Cors + Send + Sync + 'static,
     {
         self.custom_cors = Arc::new(cors_config);
-        self
-    }
-
-    /// Enable user database with default login and register routes.
-    pub fn enable_user_db(self) -> Self {
-        self.enable_user_db_with_routes("/login", "/register")
-    }
-
-    /// Enable user database with optional custom login and register routes.
-    pub fn enable_user_db_with_routes(mut self, login_route: &str, register_route: &str) -> Self {
-        self.user_db = true;
-        self.login_route = login_route.into();
-        self.register_route = register_route.into();
+        self.cors_enabled = true;
         self
     }
 
     /**
-     * Start the API server.
-     * 
-     * This method initializes the server and begins listening for incoming requests.
-     * It will block the current thread until the server is stopped.
+     * Configure CORS from rules described as data.
      *
-     * # Example
-     * ```rust,no_run
-     * use rusty_api::Api;
+     * This is equivalent to `configure_cors(move || settings.to_cors())`. Use
+     * `CorsSettings::strict` for a production preset with a concrete method and
+     * header list and a preflight cache age.
      *
-     * let api = Api::new().start();
-     */
-    pub fn start(self) {
-        let rt = actix_web::rt::System::new();
-        if let Err(e) = rt.block_on(async {
-            println!("INFO: Starting API server...");
-
-            dotenv::dotenv().ok();
-            let pool = if self.user_db {
-                Some(crate::core::db::init_db().await.expect("Failed to init DB"))
-            } else {
-                None
-            };
-
-            let tls_config = load_rustls_config(&self.cert_path, &self.key_path).expect("TLS failed");
-
-            let governor_config = GovernorConfigBuilder::default()
-                .per_second(self.rate_limit.0)
-                .burst_size(self.rate_limit.1)
-                .finish()
-                .unwrap();
-
-            let cors_config = self.custom_cors.clone();
-
-            let bind_addr = format!("{}:{}", self.addr, self.port);
-
-            println!("INFO: Server binding to {}", bind_addr);
-            HttpServer::new(move || {
-            let cors = (cors_config)();
-                let mut app = App::new()
-                    .wrap(cors)
-                    .wrap(Governor::new(&governor_config));
-
-                // Add app_data for the pool if it exists
-                if let Some(pool) = pool.clone() {
-                    app = app.app_data(web::Data::new(pool));
-                    app = app.configure(|cfg| {
-                        crate::core::auth_routes::configure_auth_routes(
-                            cfg,
-                            &self.login_route,
-                            &self.register_route
-                        );
-                    });
-                }
-
-                // Apply custom routes if provided
-                if let Some(custom_routes) = &self.custom_routes {
-                    app = app.configure(|cfg| custom_routes(cfg));
-                }
-
-                app
-            })
-            .bind_rustls_0_23((self.addr.to_string(), self.port), tls_config)?
-            .run()
-            .await
-        }) {
-            println!("ERROR: Failed to start API server: {:?}", e);
-        }
-    }
-
-    /**
-     * Get the path to the TLS certificate file.
+     * # Arguments
+     * * `settings` - The CORS rules.
      *
      * # Returns
-     * A string representing the path to the certificate file.
+     * A mutable reference to the `Api` instance.
      *
      * # Example
      * ```rust
      * use rusty_api::Api;
+     * use rusty_api::core::settings::CorsSettings;
      *
-     * let api = Api::new().certs("path/to/cert.pem", "path/to/key.pem");
-     * assert_eq!(api.get_cert_path(), "path/to/cert.pem");
+     * let api = Api::new().cors_settings(CorsSettings::strict(&["https://example.com"]).max_age(600));
+     * assert!(api.get_cors_enabled());
      * ```
      */
-    pub fn get_cert_path(&self) -> &str { &self.cert_path }
+    pub fn cors_settings(self, settings: CorsSettings) -> Self {
+        self.configure_cors(move || settings.to_cors())
+    }
 
     /**
-     * Get the path to the TLS private key file.
+     * Disable the CORS middleware entirely.
+     *
+     * By default every response passes through the CORS middleware. When the API
+     * runs behind a gateway that handles CORS itself, this avoids sending the
+     * headers twice, which browsers reject. Calling `configure_cors` afterwards
+     * enables CORS again.
      *
      * # Returns
-     * A string representing the path to the private key file.
+     * A mutable reference to the `Api` instance.
      *
      * # Example
      * ```rust
-     * use rusty_api::Api;
+     * use rusty_api::{Api, Cors, HttpResponse, Method, Routes};
+     * use actix_web::test;
      *
-     * let api = Api::new().certs("path/to/cert.pem", "path/to/key.pem");
-     * assert_eq!(api.get_key_path(), "path/to/key.pem");
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/data", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new().configure_routes(routes).configure_cors(|| Cors::default().allow_any_origin()).no_cors();
+     *     assert!(!api.get_cors_enabled());
+     *     let app = test::init_service(api.test_app().await).await;
+     *
+     *     let req = test::TestRequest::get()
+     *         .uri("/data")
+     *         .insert_header(("Origin", "https://example.com"))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert!(response.headers().get("Access-Control-Allow-Origin").is_none());
+     * });
      * ```
      */
-    pub fn get_key_path(&self) -> &str { &self.key_path }
+    pub fn no_cors(mut self) -> Self {
+        self.cors_enabled = false;
+        self
+    }
 
     /**
-     * Get the address the server is bound to.
+     * Configure how request paths are normalized before routing.
+     *
+     * Actix Web treats `/users` and `/users/` as different paths. By default,
+     * consecutive slashes are merged and trailing slashes trimmed, so a route
+     * registered as `/users` also serves `/users/` and `//users`. Use
+     * `PathNormalization::Redirect` to send clients to the canonical URL instead,
+     * or `PathNormalization::Off` to route paths exactly as sent.
+     *
+     * # Arguments
+     * * `normalization` - The normalization to apply to every request.
      *
      * # Returns
-     * A string representing the address.
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, PathNormalization, Routes, TrailingSlash};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = || Routes::new().add_route(Method::GET, "/users", || async { HttpResponse::Ok().finish() });
+     *     let get = || test::TestRequest::get().uri("/users/").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     let app = test::init_service(Api::new().configure_routes(routes()).test_app().await).await;
+     *     assert_eq!(test::call_service(&app, get()).await.status(), 200);
+     *
+     *     let api = Api::new().configure_routes(routes()).normalize_path(PathNormalization::Redirect(TrailingSlash::Trim));
+     *     let app = test::init_service(api.test_app().await).await;
+     *     assert_eq!(test::call_service(&app, get()).await.status(), 308);
+     *
+     *     let api = Api::new().configure_routes(routes()).normalize_path(PathNormalization::Off);
+     *     let app = test::init_service(api.test_app().await).await;
+     *     assert_eq!(test::call_service(&app, get()).await.status(), 404);
+     * });
+     * ```
+     */
+    pub fn normalize_path(mut self, normalization: PathNormalization) -> Self {
+        self.path_normalization = normalization;
+        self
+    }
+
+    /**
+     * Enforce per-user request quotas, e.g. for API product tiers.
+     *
+     * Authenticated requests are counted against a daily or monthly allowance
+     * for the user's privilege level, stored in the user database, and rejected
+     * with `429 Too Many Requests` once it is used up. This is separate from
+     * `rate_limit`, which throttles bursts per IP address. Requires the user
+     * database to be enabled.
+     *
+     * # Arguments
+     * * `quota` - The quota limits, see `core::quota::Quota`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, PrivilegeLevel};
+     * use rusty_api::core::quota::{Quota, QuotaPeriod};
+     *
+     * let api = Api::new()
+     *     .enable_user_db()
+     *     .quota(Quota::new(QuotaPeriod::Daily).limit(PrivilegeLevel::User, 1000));
+     * assert_eq!(api.get_quota().unwrap().get_limit(PrivilegeLevel::User), Some(1000));
+     * assert_eq!(api.get_quota().unwrap().get_limit(PrivilegeLevel::Admin), None);
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn quota(mut self, quota: Quota) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /**
+     * Replay responses to retried `POST` and `PATCH` requests carrying an `Idempotency-Key` header.
+     *
+     * The first response for each key is kept in memory for `ttl_secs` seconds
+     * and replayed on retries with the same key, method, path, credentials and
+     * body, instead of running the handler again. Reusing a key with a different
+     * body receives a `422 Unprocessable Entity`, and a retry arriving while the
+     * first request is still running receives a `409 Conflict`. Requests without
+     * credentials, such as logins, and server errors are never replayed. See
+     * `core::idempotency::Idempotency`.
+     *
+     * # Arguments
+     * * `ttl_secs` - How long responses are replayed, in seconds.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let api = Api::new()
+     *         .idempotency_keys(3600)
+     *         .configure_routes(Routes::new().add_route(Method::POST, "/orders", || async { HttpResponse::Created().finish() }));
+     *     assert_eq!(api.get_idempotency_ttl(), Some(std::time::Duration::from_secs(3600)));
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let post = || test::TestRequest::post()
+     *         .uri("/orders")
+     *         .insert_header(("Idempotency-Key", "order-1"))
+     *         .insert_header(("X-API-Key", "client-key"))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *
+     *     assert!(test::call_service(&app, post()).await.headers().get("Idempotent-Replayed").is_none());
+     *     let retry = test::call_service(&app, post()).await;
+     *     assert_eq!(retry.status(), 201);
+     *     assert_eq!(retry.headers().get("Idempotent-Replayed").unwrap(), "true");
+     * });
+     * ```
+     */
+    pub fn idempotency_keys(mut self, ttl_secs: u64) -> Self {
+        self.idempotency = Some(Idempotency::new(Duration::from_secs(ttl_secs)));
+        self
+    }
+
+    /**
+     * Log a warning for requests taking longer than the given number of milliseconds.
+     *
+     * Slow requests still complete normally; the log line names the method,
+     * path, status, duration and `X-Request-Id` header, which helps to spot
+     * performance regressions, e.g. in the login and database routes.
+     *
+     * # Arguments
+     * * `ms` - The threshold in milliseconds. Must be greater than zero.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
      *
      * # Example
      * ```rust
      * use rusty_api::Api;
-     * 
-     * let api = Api::new().bind("123.4.5.6", 7891);
-     * assert_eq!(api.get_addr(), "123.4.5.6");
+     * use std::time::Duration;
+     *
+     * let api = Api::new().slow_request_threshold(500);
+     * assert_eq!(api.get_slow_request_threshold(), Some(Duration::from_millis(500)));
      * ```
      */
-    pub fn get_addr(&self) -> &str { &self.addr }
+    pub fn slow_request_threshold(mut self, ms: u64) -> Self {
+        self.slow_request_threshold = Some(Duration::from_millis(ms));
+        self
+    }
 
     /**
-     * Get the port the server is bound to.
+     * Serve an icon file at `/favicon.ico`.
+     *
+     * Browsers request the favicon on every visit, so serving one avoids
+     * `404` noise in the logs. The file is read once when the server starts,
+     * and requests for it are exempt from the rate limiter. The content type
+     * is derived from the file extension (`.ico`, `.png` or `.svg`).
+     *
+     * # Arguments
+     * * `path` - The path of the icon file.
      *
      * # Returns
-     * A u16 representing the port.
-     * 
+     * A mutable reference to the `Api` instance.
+     *
      * # Example
      * ```rust
      * use rusty_api::Api;
-     * 
-     * let api = Api::new().bind("123.4.5.6", 7891);
-     * assert_eq!(api.get_port(), 7891);
+     * use actix_web::test;
+     *
+     * let path = std::env::temp_dir().join("rusty_api_favicon.ico");
+     * std::fs::write(&path, b"icon").unwrap();
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let api = Api::new().favicon(path.to_str().unwrap());
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let req = test::TestRequest::get().uri("/favicon.ico").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.headers().get("Content-Type").unwrap(), "image/x-icon");
+     *     assert_eq!(test::read_body(response).await, "icon");
+     * });
      * ```
      */
-    pub fn get_port(&self) -> u16 { self.port }
+    pub fn favicon(mut self, path: &str) -> Self {
+        self.favicon = Some(path.to_string());
+        self
+    }
 
     /**
-     * Get the rate limit configuration.
+     * Serve the given content at `/robots.txt`.
+     *
+     * Crawlers request `/robots.txt` before anything else, so serving it avoids
+     * `404` noise in the logs. Requests for it are exempt from the rate limiter.
+     *
+     * # Arguments
+     * * `content` - The content of `robots.txt`.
      *
      * # Returns
-     * A tuple containing the rate limit configuration: `(requests_per_second, burst_size)`.
+     * A mutable reference to the `Api` instance.
      *
      * # Example
      * ```rust
      * use rusty_api::Api;
+     * use actix_web::test;
      *
-     * let api = Api::new().rate_limit(5, 10);
-     * assert_eq!(api.get_rate_limit(), (5, 10));
+     * actix_web::rt::System::new().block_on(async {
+     *     let api = Api::new().rate_limit(60, 1).robots_txt("User-agent: *\nDisallow: /\n");
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let get = || test::TestRequest::get().uri("/robots.txt").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     assert_eq!(test::call_and_read_body(&app, get()).await, "User-agent: *\nDisallow: /\n");
+     *     // Not rate limited, even though the burst size is 1.
+     *     assert_eq!(test::call_service(&app, get()).await.status(), 200);
+     * });
      * ```
      */
-    pub fn get_rate_limit(&self) -> (u64, u32) { self.rate_limit }
+    pub fn robots_txt(mut self, content: &str) -> Self {
+        self.robots_txt = Some(content.to_string());
+        self
+    }
+
+    /// Enable user database with default login and register routes.
+    ///
+    /// The login and register routes require the `auth` feature.
+    #[cfg(feature = "sqlite")]
+    pub fn enable_user_db(mut self) -> Self {
+        self.user_db = true;
+        #[cfg(feature = "auth")]
+        {
+            self = self.enable_user_db_with_routes("/login", "/register");
+        }
+        self
+    }
+
+    /// Enable user database with optional custom login and register routes.
+    #[cfg(feature = "auth")]
+    pub fn enable_user_db_with_routes(mut self, login_route: &str, register_route: &str) -> Self {
+        self.user_db = true;
+        self.login_route = login_route.into();
+        self.register_route = register_route.into();
+        self
+    }
 
     /**
-     * Get the address and port the server is bound to as a single string.
+     * Include the user profile in the login response.
+     *
+     * By default the login route responds with `{"token": "..."}`. With this enabled,
+     * the response also carries the user's public fields, so frontends don't need
+     * a second `/whoami` call:
+     * `{"token": "...", "user": {"id": 1, "username": "alice", "privilege_level": 0, "created_at": ..., "updated_at": ...}}`.
+     * The password hash is never included.
      *
      * # Returns
-     * A string representing the address and port in the format "address:port".
-     * 
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{set_jwt_secret, Api};
+     * use rusty_api::core::auth::register_user;
+     * use rusty_api::core::user::RegisterInput;
+     * use actix_web::test;
+     *
+     * set_jwt_secret("test-secret");
+     * actix_web::rt::System::new().block_on(async {
+     *     let api = Api::new().in_memory_db().login_includes_user();
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let pool = api.db_pool().unwrap();
+     *     register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+     *
+     *     let req = test::TestRequest::post()
+     *         .uri("/login")
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .set_json(serde_json::json!({"username": "alice", "password": "Secret123"}))
+     *         .to_request();
+     *     let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+     *     assert!(body["token"].is_string());
+     *     assert_eq!(body["user"]["username"], "alice");
+     *     assert!(body["user"].get("password_hash").is_none());
+     * });
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn login_includes_user(mut self) -> Self {
+        self.login_includes_user = true;
+        self
+    }
+
+    /**
+     * Limit failed password checks at `/verify-password` per user.
+     *
+     * Once a user has failed `max_failures` checks within `window`, further
+     * checks receive a `429 Too Many Requests` until the window ends, so a stolen
+     * token cannot be used to guess the password. A correct password resets the
+     * count. The default is 5 failures per 15 minutes.
+     *
+     * # Arguments
+     * * `max_failures` - Failed checks allowed per user within the window.
+     * * `window` - How long failed checks are counted.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
      * # Example
      * ```rust
      * use rusty_api::Api;
+     * use std::time::Duration;
      *
-     * let api = Api::new().bind("123.4.5.6", 7891);
-     * assert_eq!(api.get_bind_addr(), "123.4.5.6:7891");
+     * let api = Api::new().verify_password_limit(3, Duration::from_secs(60));
+     * assert_eq!(api.get_verify_password_limit(), (3, Duration::from_secs(60)));
      * ```
      */
-    pub fn get_bind_addr(&self) -> String { format!("{}:{}", self.addr, self.port) }
+    #[cfg(feature = "auth")]
+    pub fn verify_password_limit(mut self, max_failures: u32, window: Duration) -> Self {
+        self.verify_password_limit = VerifyPasswordLimit::new(max_failures, window);
+        self
+    }
 
     /**
-     * Get the rate limit per second.
+     * Enable cookie-based sessions with the default cookie name.
+     *
+     * The login route additionally sets the JWT in a `Secure; HttpOnly;
+     * SameSite=Strict` cookie named `DEFAULT_JWT_COOKIE`, authenticated routes
+     * read the token from the cookie when the `Authorization` header is absent,
+     * and `POST /logout` clears the cookie. Scripts cannot read the cookie, which
+     * protects the token from XSS. Header-based authentication keeps working.
      *
      * # Returns
-     * A u64 representing the number of requests allowed per second.
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{set_jwt_secret, Api, HttpRequest, HttpResponse, Method, Routes};
+     * use rusty_api::core::auth::register_user;
+     * use rusty_api::core::user::RegisterInput;
+     * use actix_web::test;
+     *
+     * async fn me(_req: HttpRequest, user_id: i32) -> HttpResponse {
+     *     HttpResponse::Ok().body(user_id.to_string())
+     * }
+     *
+     * set_jwt_secret("test-secret");
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route_with_auth(Method::GET, "/me", me);
+     *     let api = Api::new().in_memory_db().configure_routes(routes).jwt_cookie();
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let pool = api.db_pool().unwrap();
+     *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+     *
+     *     let req = test::TestRequest::post()
+     *         .uri("/login")
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .set_json(serde_json::json!({"username": "alice", "password": "Secret123"}))
+     *         .to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     let cookie = response.response().cookies().next().unwrap().into_owned();
+     *     assert!(cookie.http_only().unwrap() && cookie.secure().unwrap());
+     *
+     *     let req = test::TestRequest::get().uri("/me").cookie(cookie).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     assert_eq!(test::call_and_read_body(&app, req).await, user.id.to_string());
+     *
+     *     let req = test::TestRequest::post().uri("/logout").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.response().cookies().next().unwrap().value(), "");
+     * });
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn jwt_cookie(self) -> Self {
+        self.jwt_cookie_named(DEFAULT_JWT_COOKIE)
+    }
+
+    /**
+     * Enable cookie-based sessions with a custom cookie name.
+     *
+     * See `jwt_cookie`.
+     *
+     * # Arguments
+     * * `name` - The name of the cookie carrying the JWT.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    #[cfg(feature = "auth")]
+    pub fn jwt_cookie_named(mut self, name: &str) -> Self {
+        self.jwt_cookie = Some(name.to_string());
+        self
+    }
+
+    /**
+     * Choose between stateless JWTs and server-side sessions.
+     *
+     * With `TokenMode::Jwt`, the default, the login route issues JWTs, which
+     * stay valid until they expire. With `TokenMode::Session`, it issues random
+     * session IDs stored in the `sessions` table instead, and every
+     * authenticated request looks its session up. Sessions can be revoked
+     * instantly, e.g. the logout route deletes the caller's session, at the cost
+     * of a database query per request. Session mode requires the user database
+     * and replaces any validator set with `token_validator` or `jwt_jwks`.
+     *
+     * # Arguments
+     * * `mode` - How tokens are issued and validated.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpRequest, HttpResponse, Method, Routes};
+     * use rusty_api::core::auth::register_user;
+     * use rusty_api::core::session::TokenMode;
+     * use rusty_api::core::user::RegisterInput;
+     * use actix_web::test;
+     *
+     * async fn me(_req: HttpRequest, user_id: i32) -> HttpResponse {
+     *     HttpResponse::Ok().body(user_id.to_string())
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route_with_auth(Method::GET, "/me", me);
+     *     let api = Api::new().in_memory_db().token_mode(TokenMode::Session).configure_routes(routes);
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let pool = api.db_pool().unwrap();
+     *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+     *     let post = |uri: &str| test::TestRequest::post().uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap());
+     *
+     *     let login = post("/login").set_json(serde_json::json!({"username": "alice", "password": "Secret123"})).to_request();
+     *     let body: serde_json::Value = test::call_and_read_body_json(&app, login).await;
+     *     let bearer = ("Authorization", format!("Bearer {}", body["token"].as_str().unwrap()));
+     *
+     *     let me = || test::TestRequest::get().uri("/me").insert_header(bearer.clone()).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     assert_eq!(test::call_and_read_body(&app, me()).await, user.id.to_string());
+     *
+     *     test::call_service(&app, post("/logout").insert_header(bearer.clone()).to_request()).await;
+     *     assert_eq!(test::call_service(&app, me()).await.status(), 401);
+     * });
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn token_mode(mut self, mode: TokenMode) -> Self {
+        self.token_mode = mode;
+        self
+    }
+
+    /**
+     * Enable the user database backed by a private in-memory SQLite database.
+     *
+     * The schema is created on startup and the data lives for as long as the server
+     * runs, which makes this convenient for tests, demos and ephemeral deployments.
+     * The default login and register routes are enabled.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
      *
      * # Example
      * ```rust
      * use rusty_api::Api;
+     * use actix_web::test;
      *
-     * let api = Api::new().rate_limit(5, 10);
-     * assert_eq!(api.get_rate_limit_per_second(), 5);
+     * actix_web::rt::System::new().block_on(async {
+     *     let api = Api::new().in_memory_db();
+     *     assert_eq!(api.get_database_url(), Some("sqlite::memory:"));
+     *
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let req = test::TestRequest::post()
+     *         .uri("/register")
+     *         .set_json(serde_json::json!({ "username": "alice", "password": "Secret123" }))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     assert_eq!(test::call_service(&app, req).await.status(), 201);
+     * });
      * ```
      */
-    pub fn get_rate_limit_per_second(&self) -> u64 { self.rate_limit.0 }
+    #[cfg(feature = "sqlite")]
+    pub fn in_memory_db(mut self) -> Self {
+        self.user_db = true;
+        self.database_url = Some(crate::core::db::IN_MEMORY_DATABASE_URL.into());
+        self
+    }
 
     /**
-     * Get the rate limit burst size.
+     * Set how long to wait for a free database connection.
+     *
+     * When the pool is exhausted or the SQLite file is locked for longer than this,
+     * database operations fail and handlers respond with `503 Service Unavailable`.
+     * Defaults to 30 seconds.
+     *
+     * # Arguments
+     * * `timeout` - Maximum time to wait for a connection.
      *
      * # Returns
-     * A u32 representing the maximum burst size for requests.
+     * A mutable reference to the `Api` instance.
      *
      * # Example
      * ```rust
      * use rusty_api::Api;
+     * use std::time::Duration;
      *
-     * let api = Api::new().rate_limit(5, 10);
-     * assert_eq!(api.get_rate_limit_burst_size(), 10);
+     * let api = Api::new().db_acquire_timeout(Duration::from_secs(5));
+     * assert_eq!(api.get_db_acquire_timeout(), Duration::from_secs(5));
      * ```
      */
-    pub fn get_rate_limit_burst_size(&self) -> u32 { self.rate_limit.1 }
+    #[cfg(feature = "sqlite")]
+    pub fn db_acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.db_acquire_timeout = timeout;
+        self
+    }
 
     /**
-     * Get the custom CORS configuration.
+     * Configure retries for the initial database connection.
+     *
+     * If the database cannot be reached on startup, the connection is retried with
+     * exponential backoff, doubling the delay after each failure. If every attempt
+     * fails, `BuiltApi::start` returns an `ApiError::Database` instead of panicking.
+     * Defaults to 3 attempts with an initial delay of 500ms.
+     *
+     * # Arguments
+     * * `attempts` - Total number of connection attempts (at least one is always made).
+     * * `initial_delay` - Delay before the first retry.
      *
      * # Returns
-     * A reference to the custom CORS configuration closure.
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use std::time::Duration;
+     *
+     * let api = Api::new().db_connect_retries(5, Duration::from_secs(1));
+     * assert_eq!(api.get_db_connect_retries(), (5, Duration::from_secs(1)));
+     * ```
+     */
+    #[cfg(feature = "sqlite")]
+    pub fn db_connect_retries(mut self, attempts: u32, initial_delay: Duration) -> Self {
+        self.db_connect_retries = (attempts, initial_delay);
+        self
+    }
+
+    /**
+     * Allow custom columns of the `users` table to be read and written by name.
+     *
+     * `get_user_field`, `set_user_field` and friends only accept the built-in
+     * fields in `core::db::READABLE_USER_FIELDS` and `WRITABLE_USER_FIELDS` and
+     * these profile fields, which users can also change through `PATCH /account`.
+     * The columns must be added to the table by the application. Built-in
+     * columns cannot be registered.
+     *
+     * # Arguments
+     * * `fields` - The column names.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().user_profile_fields(&["display_name", "role"]);
+     * assert_eq!(api.get_user_profile_fields(), ["display_name", "role"]);
+     *
+     * let issues = Api::new().user_profile_fields(&["password_hash"]).validate().unwrap_err();
+     * assert_eq!(issues[0].field, "user_profile_fields");
+     * ```
      */
-    pub fn get_custom_routes(&self) -> Option<&Arc<dyn Fn(&mut web::ServiceConfig) + Send + Sync>> { self.custom_routes.as_ref() }
-}
\ No newline at end of file
+    #[cfg(feature = "sqlite")]
+    pub fn user_profile_fields(mut self, fields: &[&str]) -> Self {
+        self.profile_fields = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    /**
+     * Set the algorithm used to hash new passwords.
+     *
+     * The algorithm is applied process-wide when the user database is initialized.
+     * Existing hashes keep verifying regardless of the algorithm they were created
+     * with, so switching from bcrypt to argon2 does not lock out existing users.
+     *
+     * # Arguments
+     * * `algorithm` - The algorithm for new registrations.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use rusty_api::core::password::PasswordAlgorithm;
+     *
+     * let api = Api::new().password_algorithm(PasswordAlgorithm::Argon2);
+     * assert_eq!(api.get_password_algorithm(), Some(PasswordAlgorithm::Argon2));
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn password_algorithm(mut self, algorithm: PasswordAlgorithm) -> Self {
+        self.password_algorithm = Some(algorithm);
+        self
+    }
+
+    /**
+     * Require every request to carry the given headers.
+     *
+     * Requests missing any of the headers are rejected with a `400 Bad Request`
+     * naming the missing header. CORS preflight requests are answered before the
+     * check. Use `Routes::add_route_require_headers` to require headers on a single route.
+     *
+     * # Arguments
+     * * `headers` - The names of the required headers.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/ping", || async { HttpResponse::Ok().body("pong") });
+     *     let api = Api::new().configure_routes(routes).require_headers(&["X-Client-Id"]);
+     *     let app = test::init_service(api.test_app().await).await;
+     *
+     *     let req = test::TestRequest::get().uri("/ping").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.status(), 400);
+     *     let body: serde_json::Value = test::read_body_json(response).await;
+     *     assert_eq!(body["error"], "Missing required header: X-Client-Id");
+     *
+     *     let req = test::TestRequest::get()
+     *         .uri("/ping")
+     *         .insert_header(("x-client-id", "web"))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     assert_eq!(test::call_service(&app, req).await.status(), 200);
+     * });
+     * ```
+     */
+    pub fn require_headers(mut self, headers: &[&str]) -> Self {
+        self.required_headers = headers.iter().map(|header| header.to_string()).collect();
+        self
+    }
+
+    /**
+     * Restrict access to clients inside the given IP ranges.
+     *
+     * Requests from other clients are rejected with a `403 Forbidden` before the
+     * rate limiter and the handlers run. The client IP respects `trust_proxy`.
+     * An empty list (the default) allows every client that is not blocklisted.
+     *
+     * # Arguments
+     * * `ranges` - IP addresses or CIDR ranges, e.g. `"10.0.0.0/8"` or `"127.0.0.1"`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new()
+     *         .configure_routes(routes)
+     *         .ip_allowlist(&["10.0.0.0/8"])
+     *         .ip_blocklist(&["10.0.0.66"]);
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let get = |peer: &str| test::TestRequest::get().uri("/").peer_addr(peer.parse().unwrap()).to_request();
+     *
+     *     assert_eq!(test::call_service(&app, get("10.1.2.3:1000")).await.status(), 200);
+     *     assert_eq!(test::call_service(&app, get("10.0.0.66:1000")).await.status(), 403);
+     *     assert_eq!(test::call_service(&app, get("192.168.0.1:1000")).await.status(), 403);
+     * });
+     * ```
+     */
+    pub fn ip_allowlist(mut self, ranges: &[&str]) -> Self {
+        self.ip_allowlist = ranges.iter().map(|range| range.to_string()).collect();
+        self
+    }
+
+    /**
+     * Deny access to clients inside the given IP ranges.
+     *
+     * Requests from these clients are rejected with a `403 Forbidden` before the
+     * rate limiter and the handlers run, even if they are also allowlisted.
+     * The client IP respects `trust_proxy`.
+     *
+     * # Arguments
+     * * `ranges` - IP addresses or CIDR ranges, e.g. `"203.0.113.0/24"`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    pub fn ip_blocklist(mut self, ranges: &[&str]) -> Self {
+        self.ip_blocklist = ranges.iter().map(|range| range.to_string()).collect();
+        self
+    }
+
+    /**
+     * Set or suppress the `Server` response header.
+     *
+     * With `Some(value)`, every response carries `Server: value`. With `None` (the
+     * default), no `Server` header is sent, even if a handler sets one, so the
+     * framework and its version are not revealed.
+     *
+     * # Arguments
+     * * `value` - The header value, or `None` to suppress the header.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new().configure_routes(routes).server_header(Some("my-api".to_string()));
+     *     let app = test::init_service(api.test_app().await).await;
+     *
+     *     let req = test::TestRequest::get().uri("/").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.headers().get("Server").unwrap(), "my-api");
+     * });
+     * ```
+     */
+    pub fn server_header(mut self, value: Option<String>) -> Self {
+        self.server_header = value;
+        self
+    }
+
+    /**
+     * Set the maximum size of JSON request bodies.
+     *
+     * The limit applies to `web::Json` extractors and to the routes added with
+     * `Routes::add_route_with_json_body`. Oversized bodies are rejected with a
+     * `413 Payload Too Large`. Regardless of this setting, malformed JSON is
+     * rejected with a `400 Bad Request` and a JSON body describing the parse error.
+     *
+     * # Arguments
+     * * `bytes` - The maximum body size in bytes.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{web, Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * async fn echo(body: web::Json<serde_json::Value>) -> HttpResponse {
+     *     HttpResponse::Ok().json(body.into_inner())
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::POST, "/echo", echo);
+     *     let app = test::init_service(Api::new().configure_routes(routes).json_limit(16).test_app().await).await;
+     *     let post = |body: &'static str| test::TestRequest::post()
+     *         .uri("/echo")
+     *         .insert_header(("Content-Type", "application/json"))
+     *         .set_payload(body)
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *
+     *     assert_eq!(test::call_service(&app, post(r#"{"a":1}"#)).await.status(), 200);
+     *     assert_eq!(test::call_service(&app, post(r#"{"a":"far too long"}"#)).await.status(), 413);
+     *
+     *     let response = test::call_service(&app, post("{oops")).await;
+     *     assert_eq!(response.status(), 400);
+     *     let body: serde_json::Value = test::read_body_json(response).await;
+     *     assert!(body["error"].as_str().unwrap().starts_with("Invalid JSON body"));
+     * });
+     * ```
+     */
+    pub fn json_limit(mut self, bytes: usize) -> Self {
+        self.json_limit = Some(bytes);
+        self
+    }
+
+    /**
+     * Reject JSON bodies with fields the input type does not declare.
+     *
+     * Applies to bodies read with the `core::json::JsonInput` extractor, which the
+     * built-in routes such as `/register` and `/login` use. Unknown fields are
+     * rejected with a `400 Bad Request` naming the field, helping to catch
+     * client bugs. By default they are ignored.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let app = test::init_service(Api::new().in_memory_db().deny_unknown_json_fields().test_app().await).await;
+     *     let request = test::TestRequest::post()
+     *         .uri("/register")
+     *         .set_json(serde_json::json!({"username": "alice", "password": "Secret123", "admin": true}))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     assert_eq!(test::call_service(&app, request).await.status(), 400);
+     * });
+     * ```
+     */
+    pub fn deny_unknown_json_fields(mut self) -> Self {
+        self.deny_unknown_json_fields = true;
+        self
+    }
+
+    /**
+     * Set the level of the built-in logger.
+     *
+     * The server logs through the `log` crate. When it is built, and no logger
+     * has been installed yet, it installs one writing `LEVEL: message` lines to
+     * standard output, which prints the scheme, listening addresses, user
+     * database status and routes at `Info`. Set `LevelFilter::Warn` to only see
+     * problems, or `LevelFilter::Off` to print nothing. Applications that install
+     * their own logger, such as `env_logger`, before building keep it, and this
+     * level is ignored. Defaults to `DEFAULT_LOG_LEVEL` (`Info`).
+     *
+     * # Arguments
+     * * `level` - The most verbose level to print.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, LevelFilter};
+     *
+     * let api = Api::new().log_level(LevelFilter::Warn);
+     * assert_eq!(api.get_log_level(), LevelFilter::Warn);
+     * ```
+     */
+    pub fn log_level(mut self, level: LevelFilter) -> Self {
+        self.log_level = level;
+        self
+    }
+
+    /**
+     * Set the maximum length of the request URI.
+     *
+     * Requests whose path and query string exceed the limit are rejected with
+     * `414 URI Too Long` before routing. Defaults to `DEFAULT_MAX_URI_LENGTH` (8 KiB).
+     *
+     * # Arguments
+     * * `bytes` - The maximum URI length in bytes. Must be greater than zero.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/search", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new().configure_routes(routes).max_uri_length(1024).max_query_length(32);
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let get = |uri: &str| test::TestRequest::get().uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     assert_eq!(test::call_service(&app, get("/search?q=rust")).await.status(), 200);
+     *     let long = format!("/search?q={}", "a".repeat(64));
+     *     assert_eq!(test::call_service(&app, get(&long)).await.status(), 414);
+     * });
+     * ```
+     */
+    pub fn max_uri_length(mut self, bytes: usize) -> Self {
+        self.max_uri_length = bytes;
+        self
+    }
+
+    /**
+     * Set the maximum length of the query string.
+     *
+     * Requests whose query string exceeds the limit are rejected with
+     * `414 URI Too Long` before routing, which also bounds the work done when
+     * checking route passwords. Defaults to `DEFAULT_MAX_QUERY_LENGTH` (8 KiB).
+     *
+     * # Arguments
+     * * `bytes` - The maximum query string length in bytes. Must be greater than zero.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    pub fn max_query_length(mut self, bytes: usize) -> Self {
+        self.max_query_length = bytes;
+        self
+    }
+
+    /**
+     * Set the maximum total size of the request headers.
+     *
+     * The size is the sum of the lengths of every header name and value.
+     * Requests exceeding the limit are rejected with `431 Request Header Fields
+     * Too Large` before routing. The check runs after Actix has parsed and
+     * buffered the headers, so a lower limit does not reduce the memory a
+     * request can use; it only turns oversized header sets into a `431` for
+     * handlers that should never see them. The memory bound comes from Actix,
+     * whose read buffer rejects larger header sets on its own. Defaults to
+     * `DEFAULT_MAX_HEADER_SIZE` (128 KiB), the size of that buffer.
+     *
+     * # Arguments
+     * * `bytes` - The maximum header size in bytes. Must be greater than zero.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new().configure_routes(routes).max_header_size(1024).max_headers(8);
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let get = |value: String| test::TestRequest::get()
+     *         .insert_header(("x-custom", value))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *
+     *     assert_eq!(test::call_service(&app, get("small".into())).await.status(), 200);
+     *     assert_eq!(test::call_service(&app, get("a".repeat(2048))).await.status(), 431);
+     * });
+     * ```
+     */
+    pub fn max_header_size(mut self, bytes: usize) -> Self {
+        self.max_header_size = bytes;
+        self
+    }
+
+    /**
+     * Set the maximum number of request headers.
+     *
+     * Requests with more headers are rejected with `431 Request Header Fields
+     * Too Large` before routing. Like `max_header_size`, the check runs after
+     * Actix has parsed the headers, so it only lowers the limit rather than
+     * saving memory. Defaults to `DEFAULT_MAX_HEADERS` (96), the fixed limit of
+     * the Actix HTTP/1 parser, which also rejects requests with more headers on
+     * its own.
+     *
+     * # Arguments
+     * * `count` - The maximum number of headers. Must be greater than zero.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    pub fn max_headers(mut self, count: usize) -> Self {
+        self.max_headers = count;
+        self
+    }
+
+    /**
+     * Limit the number of concurrent connections.
+     *
+     * Once the limit is reached, new connections wait until existing ones close.
+     * This bounds resource usage at the connection layer, which the request rate
+     * limiter does not cover. The limit applies per worker thread; the Actix
+     * default is 25,000.
+     *
+     * # Arguments
+     * * `max` - The maximum number of concurrent connections per worker. Must be greater than zero.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().max_connections(1000).max_connection_rate(64);
+     * assert_eq!(api.get_max_connections(), Some(1000));
+     * assert_eq!(api.get_max_connection_rate(), Some(64));
+     * ```
+     */
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /**
+     * Limit the number of concurrent TLS handshakes.
+     *
+     * TLS handshakes are expensive, so bounding them protects the server from
+     * handshake floods. The limit applies per worker thread; the Actix default is 256.
+     *
+     * # Arguments
+     * * `max` - The maximum number of concurrent handshakes per worker. Must be greater than zero.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    pub fn max_connection_rate(mut self, max: usize) -> Self {
+        self.max_connection_rate = Some(max);
+        self
+    }
+
+    /**
+     * Add weak ETags to `GET` responses and answer matching `If-None-Match` with `304 Not Modified`.
+     *
+     * The ETag is a hash of the response body, so handlers need no changes. Only
+     * `200 OK` responses with a sized body are handled; streaming responses are
+     * left untouched. Disabled by default.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/role", || async { HttpResponse::Ok().body("admin") });
+     *     let app = test::init_service(Api::new().configure_routes(routes).enable_etag().test_app().await).await;
+     *     let get = || test::TestRequest::get().uri("/role").peer_addr("127.0.0.1:12345".parse().unwrap());
+     *
+     *     let response = test::call_service(&app, get().to_request()).await;
+     *     let etag = response.headers().get("ETag").unwrap().clone();
+     *
+     *     let response = test::call_service(&app, get().insert_header(("If-None-Match", etag)).to_request()).await;
+     *     assert_eq!(response.status(), 304);
+     * });
+     * ```
+     */
+    pub fn enable_etag(mut self) -> Self {
+        self.etag = true;
+        self
+    }
+
+    /**
+     * Wrap every JSON response in a `{"data": ..., "meta": ..., "errors": [...]}` envelope.
+     *
+     * Successful bodies are put in `data` and error bodies, including those of the
+     * built-in middleware such as the rate limiter, in `errors`. `meta` carries the
+     * `X-Request-Id` header and any `EnvelopeMeta` a handler adds to its response.
+     * Already-enveloped and non-JSON bodies are left untouched. See `ResponseEnvelope`.
+     * Disabled by default.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     * use serde_json::{json, Value};
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/user", || async { HttpResponse::Ok().json(json!({"id": 1})) });
+     *     let app = test::init_service(Api::new().configure_routes(routes).response_envelope().test_app().await).await;
+     *     let req = test::TestRequest::get().uri("/user").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     let body: Value = test::call_and_read_body_json(&app, req).await;
+     *     assert_eq!(body["data"]["id"], 1);
+     * });
+     * ```
+     */
+    pub fn response_envelope(mut self) -> Self {
+        self.response_envelope = true;
+        self
+    }
+
+    /**
+     * Reject every request not made over HTTPS with `403 Forbidden`.
+     *
+     * This guards all routes, including the auth endpoints, against being served
+     * insecurely if a plain HTTP listener is ever added in front of the server.
+     * To keep some routes (e.g. health checks) reachable over HTTP, leave this off
+     * and use `Routes::add_route_secure_only` for the sensitive routes instead.
+     * Respects `trust_proxy`. Disabled by default.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/secret", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new().configure_routes(routes).trust_proxy(true).secure_only();
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let get = || test::TestRequest::get().uri("/secret").peer_addr("127.0.0.1:12345".parse().unwrap());
+     *
+     *     assert_eq!(test::call_service(&app, get().to_request()).await.status(), 403);
+     *     let response = test::call_service(&app, get().insert_header(("X-Forwarded-Proto", "https")).to_request()).await;
+     *     assert_eq!(response.status(), 200);
+     * });
+     * ```
+     */
+    pub fn secure_only(mut self) -> Self {
+        self.secure_only = true;
+        self
+    }
+
+    /**
+     * Add HSTS and other hardening headers to every response.
+     *
+     * The headers in `core::middleware::SECURITY_HEADERS` are added unless a
+     * handler already set them: `Strict-Transport-Security`,
+     * `X-Content-Type-Options: nosniff`, `X-Frame-Options: DENY` and
+     * `Referrer-Policy: no-referrer`. Responses rejected by the rate limiter,
+     * IP filter or request limits carry them as well. Disabled by default and
+     * enabled by `Profile::Prod`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new().configure_routes(routes).rate_limit(60, 1).security_headers();
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let req = || test::TestRequest::get().uri("/").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     let response = test::call_service(&app, req()).await;
+     *     assert_eq!(response.headers().get("X-Frame-Options").unwrap(), "DENY");
+     *     let limited = test::try_call_service(&app, req()).await.err().unwrap().error_response();
+     *     assert_eq!(limited.status(), 429);
+     *     assert_eq!(limited.headers().get("X-Frame-Options").unwrap(), "DENY");
+     * });
+     * ```
+     */
+    pub fn security_headers(mut self) -> Self {
+        self.security_headers = true;
+        self
+    }
+
+    /**
+     * Apply a bundle of defaults for the environment the API runs in.
+     *
+     * - `Profile::Dev` allows any origin, method and header through CORS and logs
+     *   request and response bodies.
+     * - `Profile::Prod` rejects requests not made over HTTPS, adds security
+     *   headers, restores the restrictive default CORS rules and turns off body logging.
+     *
+     * The defaults are applied when this is called, so call it first: builder
+     * methods called afterwards override them. TLS certificates are not changed
+     * by either profile. The profile can also be set with `RUSTY_API_PROFILE` or
+     * `profile` in a config file.
+     *
+     * # Arguments
+     * * `profile` - The environment profile to apply.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, Profile};
+     *
+     * let api = Api::new().profile(Profile::Prod);
+     * assert_eq!(api.get_profile(), Some(Profile::Prod));
+     * assert!(api.get_secure_only());
+     * assert!(!api.get_body_logging());
+     *
+     * let api = Api::new().profile(Profile::Dev).no_cors();
+     * assert!(api.get_body_logging());
+     * assert!(!api.get_cors_enabled());
+     * ```
+     */
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
+        match profile {
+            Profile::Dev => {
+                self.custom_cors = Arc::new(Cors::permissive);
+                self.cors_enabled = true;
+                self.body_logger = Some(BodyLogger::default());
+            }
+            Profile::Prod => {
+                self.custom_cors = Arc::new(Cors::default);
+                self.cors_enabled = true;
+                self.body_logger = None;
+                self.secure_only = true;
+                self.security_headers = true;
+            }
+        }
+        self
+    }
+
+    /**
+     * Log request and response bodies for debugging.
+     *
+     * Bodies up to 4096 bytes are logged at the `debug` level, so raise the level
+     * with `log_level` to see them. The values of the keys `password`, `token` and
+     * `api_key` are redacted in JSON bodies, form bodies and the query string,
+     * which is logged with the path. Larger bodies are
+     * only logged by size, and streaming responses are passed through untouched.
+     * Disabled by default; use `debug_body_logging_with` to change the size limit
+     * or the redacted keys.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::POST, "/echo", |body: String| async move { HttpResponse::Ok().body(body) });
+     *     let app = test::init_service(Api::new().configure_routes(routes).debug_body_logging().test_app().await).await;
+     *     let request = test::TestRequest::post()
+     *         .uri("/echo")
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .set_payload(r#"{"password":"Secret123"}"#)
+     *         .to_request();
+     *
+     *     let body = test::call_and_read_body(&app, request).await;
+     *     assert_eq!(body, r#"{"password":"Secret123"}"#);
+     * });
+     * ```
+     */
+    pub fn debug_body_logging(self) -> Self {
+        self.debug_body_logging_with(BodyLogger::default())
+    }
+
+    /**
+     * Log request and response bodies for debugging, with a custom `BodyLogger`.
+     *
+     * # Arguments
+     * * `logger` - The body logger, configuring the size limit and redacted keys.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, BodyLogger};
+     *
+     * let api = Api::new().debug_body_logging_with(BodyLogger::default().max_bytes(1024).redact(&["secret"]));
+     * assert!(api.get_body_logging());
+     * ```
+     */
+    pub fn debug_body_logging_with(mut self, logger: BodyLogger) -> Self {
+        self.body_logger = Some(logger);
+        self
+    }
+
+    /**
+     * Serve the crate version and build information at the given path.
+     *
+     * The endpoint answers `GET` requests with JSON containing the crate version,
+     * the git commit (from the `GIT_SHA` environment variable at build time, if set)
+     * and the build timestamp. It is disabled by default so version information
+     * is not exposed unless wanted.
+     *
+     * # Arguments
+     * * `path` - The URL path of the endpoint, e.g. `"/version"`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let app = test::init_service(Api::new().with_version_endpoint("/version").test_app().await).await;
+     *
+     *     let req = test::TestRequest::get().uri("/version").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+     *     assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+     *     assert!(body["build_timestamp"].is_string());
+     * });
+     * ```
+     */
+    pub fn with_version_endpoint(mut self, path: &str) -> Self {
+        self.version_endpoint = Some(path.to_string());
+        self
+    }
+
+    /**
+     * Serve authentication metrics in the Prometheus text format at the given path.
+     *
+     * The endpoint answers `GET` requests with counters for successful logins,
+     * failed logins by reason, registrations and rejected tokens by reason; see
+     * `core::metrics::AuthMetrics`. It is disabled by default and not
+     * authenticated, so restrict it, e.g. with `ip_allowlist` or at the proxy.
+     *
+     * # Arguments
+     * * `path` - The URL path of the endpoint, e.g. `"/metrics"`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let app = test::init_service(Api::new().in_memory_db().with_metrics_endpoint("/metrics").test_app().await).await;
+     *     let post = |uri: &str| test::TestRequest::post().uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap());
+     *
+     *     let login = post("/login").set_json(serde_json::json!({"username": "nobody", "password": "Secret123"})).to_request();
+     *     assert_eq!(test::call_service(&app, login).await.status(), 400);
+     *
+     *     let req = test::TestRequest::get().uri("/metrics").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let body = test::call_and_read_body(&app, req).await;
+     *     assert!(std::str::from_utf8(&body).unwrap().contains("rusty_api_login_failures_total{reason=\"user_not_found\"} 1"));
+     * });
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn with_metrics_endpoint(mut self, path: &str) -> Self {
+        self.metrics_endpoint = Some(path.to_string());
+        self
+    }
+
+    /**
+     * Serve the list of routes registered with `configure_routes` at the given path.
+     *
+     * The endpoint answers `GET` requests with a JSON array describing each route,
+     * e.g. `[{"method": "GET", "path": "/admin", "auth": "privilege 1"}]`, which
+     * helps to catch typos in paths and confirm authentication is applied. Since it
+     * reveals the API surface, it is only served in debug builds; release builds
+     * log a warning and skip it.
+     *
+     * # Arguments
+     * * `path` - The URL path of the endpoint, e.g. `"/_routes"`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route_with_password(Method::DELETE, "/cache", || async { HttpResponse::Ok().finish() }, "Secret");
+     *     let api = Api::new().configure_routes(routes).with_routes_endpoint("/_routes");
+     *     let app = test::init_service(api.test_app().await).await;
+     *
+     *     let req = test::TestRequest::get().uri("/_routes").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+     *     assert_eq!(body, serde_json::json!([{"method": "DELETE", "path": "/cache", "auth": "password"}]));
+     * });
+     * ```
+     */
+    pub fn with_routes_endpoint(mut self, path: &str) -> Self {
+        self.routes_endpoint = Some(path.to_string());
+        self
+    }
+
+    /**
+     * Send audit records of authentication events to a custom sink.
+     *
+     * By default, logins, failed logins, registrations, privilege changes and API
+     * key rotations are written to the `audit_log` table of the user database.
+     * The sink is applied process-wide when the user database is initialized.
+     *
+     * # Arguments
+     * * `sink` - The sink receiving every audit record.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    #[cfg(feature = "auth")]
+    pub fn audit_sink(mut self, sink: impl AuditSink + 'static) -> Self {
+        self.audit_sink = Some(Arc::new(sink));
+        self
+    }
+
+    /**
+     * Validate bearer tokens with a custom function instead of the built-in HS256 validation.
+     *
+     * This lets the API act as a resource server for an external identity provider,
+     * e.g. by verifying tokens against its JWKS. The validator is used by routes
+     * added with `add_route_with_auth` and by the `RequirePrivilege` middleware,
+     * which still loads the user with ID `claims.sub` from the user database.
+     *
+     * # Arguments
+     * * `validator` - Maps a bearer token to its claims, or returns an error if it is invalid.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, Claims, HttpRequest, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * async fn me(_req: HttpRequest, user_id: i32) -> HttpResponse {
+     *     HttpResponse::Ok().body(user_id.to_string())
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route_with_auth(Method::GET, "/me", me);
+     *     let api = Api::new()
+     *         .token_validator(|token| match token {
+     *             "idp-token" => Ok(Claims { sub: 42, exp: usize::MAX, ..Default::default() }),
+     *             _ => Err(actix_web::error::ErrorUnauthorized("Invalid token")),
+     *         })
+     *         .configure_routes(routes);
+     *     let app = test::init_service(api.test_app().await).await;
+     *
+     *     let req = test::TestRequest::get()
+     *         .uri("/me")
+     *         .insert_header(("Authorization", "Bearer idp-token"))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     assert_eq!(test::call_and_read_body(&app, req).await, "42");
+     * });
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn token_validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&str) -> Result<Claims, actix_web::Error> + Send + Sync + 'static,
+    {
+        self.token_validator = Some(TokenValidator::new(validator));
+        self
+    }
+
+    /**
+     * Validate bearer tokens as `RS256` JWTs signed with keys from a JWKS endpoint.
+     *
+     * This is the usual way to accept tokens issued by providers such as Google,
+     * Okta or Keycloak. Keys are fetched on first use, cached for an hour and
+     * refetched when a token names an unknown `kid`. Use `jwt_jwks` to also check
+     * the issuer and audience. This replaces any validator set with `token_validator`.
+     *
+     * # Arguments
+     * * `url` - The provider's JWKS URL, e.g. `https://idp.example.com/.well-known/jwks.json`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().jwt_jwks_url("https://idp.example.com/.well-known/jwks.json");
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn jwt_jwks_url(self, url: &str) -> Self {
+        self.jwt_jwks(JwksVerifier::new(url))
+    }
+
+    /**
+     * Validate bearer tokens with a configured `JwksVerifier`.
+     *
+     * # Arguments
+     * * `verifier` - The verifier, with the JWKS URL, expected issuer and audience.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use rusty_api::core::jwks::JwksVerifier;
+     *
+     * let api = Api::new().jwt_jwks(
+     *     JwksVerifier::new("https://idp.example.com/.well-known/jwks.json")
+     *         .issuer("https://idp.example.com/")
+     *         .audience("my-api"),
+     * );
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn jwt_jwks(mut self, verifier: JwksVerifier) -> Self {
+        self.token_validator = Some(verifier.into());
+        self
+    }
+
+    /**
+     * Set the issuer of the JWTs issued and accepted by the API.
+     *
+     * Tokens issued by the login and register routes carry this `iss` claim, and
+     * tokens without it are rejected. Together with `jwt_audience`, this stops a
+     * token minted by another service sharing the same secret from being accepted.
+     * The issuer applies to this API only, see `JwtSettings`.
+     *
+     * # Arguments
+     * * `issuer` - The issuer, e.g. `"https://auth.example.com"`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().jwt_issuer("https://auth.example.com").jwt_audience("billing-api");
+     * assert_eq!(api.get_jwt_issuer().as_deref(), Some("https://auth.example.com"));
+     * assert_eq!(api.get_jwt_audience().as_deref(), Some("billing-api"));
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn jwt_issuer(mut self, issuer: &str) -> Self {
+        self.jwt_issuer = Some(issuer.to_string());
+        self
+    }
+
+    /**
+     * Set the audience of the JWTs issued and accepted by the API.
+     *
+     * Tokens issued by the login and register routes carry this `aud` claim, and
+     * tokens not intended for this audience are rejected. The audience applies
+     * to this API only, see `JwtSettings`.
+     *
+     * # Arguments
+     * * `audience` - The audience, e.g. `"billing-api"`.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    #[cfg(feature = "auth")]
+    pub fn jwt_audience(mut self, audience: &str) -> Self {
+        self.jwt_audience = Some(audience.to_string());
+        self
+    }
+
+    /**
+     * Set the clock-skew leeway applied when checking token expiry.
+     *
+     * Tokens are accepted until their expiry plus the leeway, so servers with
+     * slightly different clocks agree on token validity. The default is 60 seconds.
+     * The leeway applies to this API only, see `JwtSettings`.
+     *
+     * # Arguments
+     * * `leeway` - The leeway, rounded down to whole seconds.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use std::time::Duration;
+     *
+     * let api = Api::new().jwt_leeway(Duration::from_secs(30));
+     * assert_eq!(api.get_jwt_leeway(), Duration::from_secs(30));
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn jwt_leeway(mut self, leeway: Duration) -> Self {
+        self.jwt_leeway = Some(leeway);
+        self
+    }
+
+    /**
+     * Set how long after login tokens can be refreshed at `/token/refresh`.
+     *
+     * Refreshing a token extends the session, but never past this window after
+     * the user logged in; then the user must log in again. The default is 30
+     * days. The window applies to this API only, see `JwtSettings`.
+     *
+     * # Arguments
+     * * `max_session` - The window, rounded down to whole seconds.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use std::time::Duration;
+     *
+     * let api = Api::new().jwt_max_session(Duration::from_secs(24 * 60 * 60));
+     * assert_eq!(api.get_jwt_max_session(), Duration::from_secs(24 * 60 * 60));
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn jwt_max_session(mut self, max_session: Duration) -> Self {
+        self.jwt_max_session = Some(max_session);
+        self
+    }
+
+    /**
+     * Run a hook once the server has bound to its address.
+     *
+     * The hook receives the user database pool (if enabled), so it can seed data,
+     * warm caches or register with service discovery. If it returns an error, the
+     * server is stopped and `BuiltApi::start` returns `ApiError::Startup` with the message.
+     * Clones of the `Api` share the hook, and it runs at most once.
+     *
+     * # Arguments
+     * * `hook` - An async closure returning `Ok(())` to continue starting.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new()
+     *     .enable_user_db()
+     *     .on_startup(|pool| async move {
+     *         let pool = pool.ok_or("user database is not enabled")?;
+     *         sqlx::query("DELETE FROM users WHERE username = 'temp'")
+     *             .execute(&pool)
+     *             .await
+     *             .map_err(|e| e.to_string())?;
+     *         Ok(())
+     *     });
+     * ```
+     */
+    pub fn on_startup<F, Fut>(self, hook: F) -> Self
+    where
+        F: FnOnce(Option<DbPool>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + 'static,
+    {
+        let hook: StartupHook = Box::new(move |pool| Box::pin(hook(pool)));
+        *self.on_startup.lock().unwrap() = Some(hook);
+        self
+    }
+
+    /**
+     * Run a hook once the server has stopped.
+     *
+     * The hook runs after a graceful shutdown, e.g. on `Ctrl+C`, once in-flight
+     * requests have completed. Clones of the `Api` share the hook, and it runs at most once.
+     *
+     * # Arguments
+     * * `hook` - The cleanup closure.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().on_shutdown(|| log::info!("Deregistering from service discovery"));
+     * ```
+     */
+    pub fn on_shutdown<F>(self, hook: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        *self.on_shutdown.lock().unwrap() = Some(Box::new(hook));
+        self
+    }
+
+    /**
+     * Run a recurring task in the background while the server is running.
+     *
+     * The task first runs once the server has started, then again `interval` after
+     * each run completes, so a slow run never overlaps the next. It receives the
+     * user database pool (if enabled), e.g. to prune expired rows. Tasks run on the
+     * Actix runtime and are stopped once the server has shut down, before the
+     * `on_shutdown` hook runs. Clones of the `Api` share the tasks, and each is started at most once.
+     *
+     * # Arguments
+     * * `interval` - The delay between runs. Must be greater than zero.
+     * * `task` - An async closure called for every run.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use std::time::Duration;
+     *
+     * let api = Api::new()
+     *     .enable_user_db()
+     *     .spawn_task(Duration::from_secs(3600), |pool| async move {
+     *         let Some(pool) = pool else { return };
+     *         if let Err(e) = sqlx::query("DELETE FROM users WHERE username = 'temp'").execute(&pool).await {
+     *             log::warn!("Cleanup failed: {}", e);
+     *         }
+     *     });
+     * ```
+     */
+    pub fn spawn_task<F, Fut>(self, interval: Duration, mut task: F) -> Self
+    where
+        F: FnMut(Option<DbPool>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let task: BackgroundTask = Box::new(move |pool| Box::pin(task(pool)));
+        self.tasks.lock().unwrap().push((interval, task));
+        self
+    }
+
+    /**
+     * Create an admin user on startup if it does not exist yet.
+     *
+     * The user is created with the `Admin` privilege level and a properly hashed
+     * password. Seeding is idempotent: an existing user with the same username is
+     * left untouched. This has no effect unless the user database is enabled.
+     *
+     * # Arguments
+     * * `username` - Username of the admin user.
+     * * `password` - Plaintext password of the admin user.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new()
+     *     .enable_user_db()
+     *     .seed_admin("admin", "ChangeMe123");
+     * assert_eq!(api.get_admin_seed_username(), Some("admin"));
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn seed_admin(mut self, username: &str, password: &str) -> Self {
+        self.admin_seed = Some((username.into(), password.into()));
+        self
+    }
+
+    /**
+     * Validate the configuration and prepare the server for starting.
+     *
+     * This checks the builder values and loads the TLS certificate and private key
+     * up front, so misconfiguration is reported without launching the server.
+     * Invalid builder values are reported together in one `ApiError::InvalidConfig`;
+     * see `validate` for the individual issues. `start` calls this method internally.
+     *
+     * # Returns
+     * A `BuiltApi` ready to be started, or an `ApiError` describing the problem.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, ApiError};
+     *
+     * let result = Api::new().certs("missing/cert.pem", "missing/key.pem").build();
+     * assert!(matches!(result, Err(ApiError::Tls(_))));
+     *
+     * let result = Api::new().bind("", 8443).build();
+     * assert!(matches!(result, Err(ApiError::InvalidConfig(_))));
+     *
+     * let result = Api::new()
+     *     .configure_cors(|| rusty_api::Cors::default().allow_any_origin().send_wildcard().supports_credentials())
+     *     .build();
+     * assert!(matches!(result, Err(ApiError::InvalidConfig(_))));
+     * ```
+     */
+    pub fn build(self) -> Result<BuiltApi, ApiError> {
+        init_stdout_logger(self.log_level);
+        self.check_config()?;
+        if let Some(path) = &self.routes_endpoint
+            && !cfg!(debug_assertions)
+        {
+            log::warn!("The route listing endpoint {} is only served in debug builds", path);
+        }
+
+        let tls_config = if !self.tls {
+            log::warn!("TLS is disabled, serving plain HTTP");
+            if !self.sni_certs.is_empty() {
+                log::warn!("TLS is disabled, ignoring {} SNI certificate(s)", self.sni_certs.len());
+            }
+            None
+        } else if self.sni_certs.is_empty() {
+            Some(load_tls_config(&self.cert_path, &self.key_path).map_err(ApiError::Tls)?)
+        } else {
+            Some(load_sni_tls_config(&self.cert_path, &self.key_path, &self.sni_certs).map_err(ApiError::Tls)?)
+        };
+        let governor_config = self.governor_config()?;
+
+        Ok(BuiltApi { api: self, tls_config, governor_config })
+    }
+
+    /**
+     * Check every configuration value and report all problems at once.
+     *
+     * Unlike `build`, which fails with a single error, this lists every invalid
+     * value together with its field name, which speeds up getting a new
+     * deployment's configuration right. The certificate and key files are
+     * checked for existence, but not parsed.
+     *
+     * # Returns
+     * `Ok(())` if the configuration is valid, or every `ConfigIssue` found.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let issues = Api::new()
+     *     .certs("missing/cert.pem", "missing/key.pem")
+     *     .rate_limit(5, 0)
+     *     .ip_allowlist(&["10.0.0.0/33"])
+     *     .validate()
+     *     .unwrap_err();
+     *
+     * let fields: Vec<&str> = issues.iter().map(|issue| issue.field.as_str()).collect();
+     * assert_eq!(fields, ["rate_limit", "ip_allowlist", "cert_path", "key_path"]);
+     * ```
+     */
+    pub fn validate(&self) -> Result<(), Vec<ConfigIssue>> {
+        let mut issues = self.config_issues();
+        let sni_files = self.sni_certs.iter().flat_map(|sni| [("sni_certs", &sni.cert_path), ("sni_certs", &sni.key_path)]);
+        let tls_files = [("cert_path", &self.cert_path), ("key_path", &self.key_path)].into_iter().chain(sni_files);
+        for (field, path) in tls_files.filter(|_| self.tls) {
+            if let Err(e) = std::fs::metadata(path) {
+                issues.push(ConfigIssue::new(field, format!("cannot read '{}': {}", path, e)));
+            }
+        }
+        if issues.is_empty() { Ok(()) } else { Err(issues) }
+    }
+
+    /// Fail with every `ConfigIssue` joined into one `ApiError::InvalidConfig`.
+    fn check_config(&self) -> Result<(), ApiError> {
+        let issues = self.config_issues();
+        if issues.is_empty() {
+            return Ok(());
+        }
+        let issues: Vec<String> = issues.iter().map(ConfigIssue::to_string).collect();
+        Err(ApiError::InvalidConfig(issues.join("; ")))
+    }
+
+    /// Check the builder values, except the TLS files which `build` loads itself.
+    fn config_issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        if self.addr.trim().is_empty() {
+            issues.push(ConfigIssue::new("addr", "must not be empty"));
+        }
+        if self.rate_limit.0 == 0 || self.rate_limit.1 == 0 {
+            issues.push(ConfigIssue::new(
+                "rate_limit",
+                format!("values must be greater than zero, got ({}, {})", self.rate_limit.0, self.rate_limit.1),
+            ));
+        }
+        for problem in &self.route_problems {
+            issues.push(ConfigIssue::new("routes", problem));
+        }
+        for sni in &self.sni_certs {
+            if rustls::pki_types::DnsName::try_from(sni.hostname.as_str()).is_err() {
+                issues.push(ConfigIssue::new("sni_certs", format!("'{}' is not a valid hostname", sni.hostname)));
+            }
+        }
+        for (field, ranges) in [
+            ("rate_limit_allowlist", &self.rate_limit_allowlist),
+            ("ip_allowlist", &self.ip_allowlist),
+            ("ip_blocklist", &self.ip_blocklist),
+        ] {
+            for range in ranges {
+                if let Err(e) = parse_ip_range(range) {
+                    issues.push(ConfigIssue::new(field, e));
+                }
+            }
+        }
+        if let Err(e) = ServerHeader::new(self.server_header.as_deref()) {
+            issues.push(ConfigIssue::new("server_header", e));
+        }
+        if self.tasks.lock().unwrap().iter().any(|(interval, _)| interval.is_zero()) {
+            issues.push(ConfigIssue::new("spawn_task", "interval must be greater than zero"));
+        }
+        #[cfg(feature = "auth")]
+        if self.jwt_cookie.as_deref().is_some_and(str::is_empty) {
+            issues.push(ConfigIssue::new("jwt_cookie", "name must not be empty"));
+        }
+        if self.max_uri_length == 0 || self.max_query_length == 0 {
+            issues.push(ConfigIssue::new("max_uri_length", "max_uri_length and max_query_length must be greater than zero"));
+        }
+        if self.max_header_size == 0 || self.max_headers == 0 {
+            issues.push(ConfigIssue::new("max_header_size", "max_header_size and max_headers must be greater than zero"));
+        }
+        if self.max_connections == Some(0) || self.max_connection_rate == Some(0) {
+            issues.push(ConfigIssue::new(
+                "max_connections",
+                "max_connections and max_connection_rate must be greater than zero",
+            ));
+        }
+        if let Some(path) = &self.favicon
+            && let Err(e) = std::fs::metadata(path)
+        {
+            issues.push(ConfigIssue::new("favicon", format!("cannot read '{}': {}", path, e)));
+        }
+        if self.slow_request_threshold.is_some_and(|threshold| threshold.is_zero()) {
+            issues.push(ConfigIssue::new("slow_request_threshold", "must be greater than zero"));
+        }
+        if self.secure_only && !self.tls && !self.trust_proxy {
+            issues.push(ConfigIssue::new("secure_only", "rejects every request when TLS is disabled and proxies are not trusted"));
+        }
+        #[cfg(feature = "sqlite")]
+        for field in &self.profile_fields {
+            if let Err(message) = crate::core::db::check_profile_field(field) {
+                issues.push(ConfigIssue::new("user_profile_fields", message));
+            }
+        }
+        #[cfg(feature = "auth")]
+        if self.quota.is_some() && !self.user_db {
+            issues.push(ConfigIssue::new("quota", "requires the user database to be enabled"));
+        }
+        #[cfg(feature = "auth")]
+        if self.token_mode == TokenMode::Session && !self.user_db {
+            issues.push(ConfigIssue::new("token_mode", "session mode requires the user database to be enabled"));
+        }
+        #[cfg(feature = "auth")]
+        if self.token_mode == TokenMode::Session && self.token_validator.is_some() {
+            issues.push(ConfigIssue::new("token_mode", "session mode replaces the validator set with token_validator or jwt_jwks"));
+        }
+        #[cfg(feature = "auth")]
+        if self.verify_password_limit.get_max_failures() == 0 || self.verify_password_limit.get_window().is_zero() {
+            issues.push(ConfigIssue::new("verify_password_limit", "failures and window must be greater than zero"));
+        }
+        if self.idempotency.as_ref().is_some_and(|idempotency| idempotency.get_ttl().is_zero()) {
+            issues.push(ConfigIssue::new("idempotency_keys", "TTL must be greater than zero"));
+        }
+        if self.cors_enabled
+            && let Err(e) = validate_cors((self.custom_cors)())
+        {
+            issues.push(ConfigIssue::new("cors", e));
+        }
+        issues
+    }
+
+    /**
+     * Start the API server.
+     * 
+     * This method validates the configuration with `build`, initializes the server
+     * and begins listening for incoming requests. It will block the current thread
+     * until the server is stopped. Errors are logged rather than returned; use
+     * `build` and `BuiltApi::start` to handle them yourself.
+     *
+     * # Example
+     * ```rust,no_run
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().start();
+     * ```
+     */
+    pub fn start(self) {
+        if let Err(e) = self.build().and_then(BuiltApi::start) {
+            log::error!("Failed to start API server: {}", e);
+        }
+    }
+
+    /**
+     * Run the startup steps without serving traffic, for CI and deployment pipelines.
+     *
+     * This validates the configuration and loads the TLS files with `build`, then
+     * connects to the user database and creates its schema (if enabled), binds the
+     * listening address and builds the application with its routes, and finally
+     * releases everything. The `on_startup` hook and background tasks are not run.
+     * With the `cli` feature, `run_blocking_with_args` calls this on `--check`.
+     *
+     * # Returns
+     * `Ok(())` if the server could start, or the `ApiError` that would stop it.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, ApiError, HttpResponse, Method, Routes};
+     *
+     * let routes = Routes::new().add_route(Method::GET, "/health", || async { HttpResponse::Ok().finish() });
+     * assert!(Api::new().no_tls().bind("127.0.0.1", 0).configure_routes(routes).check().is_ok());
+     *
+     * let result = Api::new().certs("missing/cert.pem", "missing/key.pem").check();
+     * assert!(matches!(result, Err(ApiError::Tls(_))));
+     *
+     * let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+     * let port = taken.local_addr().unwrap().port();
+     * let result = Api::new().no_tls().bind("127.0.0.1", port).check();
+     * assert!(matches!(result, Err(ApiError::Io(_))));
+     * ```
+     */
+    pub fn check(self) -> Result<(), ApiError> {
+        let built = self.build()?;
+        actix_web::rt::System::new().block_on(built.check())
+    }
+
+    /**
+     * Apply the command-line flags of the current process.
+     *
+     * The flags `--bind`, `--port`, `--cert`, `--key` and `--no-tls` override
+     * the values configured so far; see `core::cli::CliArgs`. On `--help` or an
+     * invalid flag, the usage is printed and the process exits. Requires the
+     * `cli` feature.
+     *
+     * # Returns
+     * A mutable reference to the `Api` instance.
+     */
+    #[cfg(feature = "cli")]
+    pub fn with_args(self) -> Self {
+        use clap::Parser;
+        crate::core::cli::CliArgs::parse().apply(self)
+    }
+
+    /**
+     * Apply command-line flags from the given arguments, the first being the program name.
+     *
+     * Like `with_args`, but returns an error instead of exiting. Requires the `cli` feature.
+     *
+     * # Returns
+     * The configured `Api`, or `ApiError::InvalidConfig` describing an invalid flag.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().try_with_args(["server", "--port", "9000", "--no-tls"]).unwrap();
+     * assert_eq!(api.get_bind_addr(), "127.0.0.1:9000");
+     * assert!(!api.get_tls());
+     *
+     * assert!(Api::new().try_with_args(["server", "--cert", "cert.pem"]).is_err());
+     * ```
+     */
+    #[cfg(feature = "cli")]
+    pub fn try_with_args<I, T>(self, args: I) -> Result<Self, ApiError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        crate::core::cli::CliArgs::try_parse_args(args).map(|args| args.apply(self))
+    }
+
+    /**
+     * Apply the command-line flags of the current process and start the server.
+     *
+     * This is `with_args` followed by `start`, for one-file utilities. With
+     * `--check`, `check` runs instead of `start`, and the process exits with a
+     * non-zero status if it fails. Requires the `cli` feature.
+     *
+     * # Example
+     * ```rust,no_run
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     *
+     * // cargo run -- --bind 0.0.0.0 --port 8080 --no-tls
+     * Api::new()
+     *     .configure_routes(Routes::new().add_route(Method::GET, "/", || async { HttpResponse::Ok().body("Hello") }))
+     *     .run_blocking_with_args();
+     * ```
+     */
+    #[cfg(feature = "cli")]
+    pub fn run_blocking_with_args(self) {
+        use clap::Parser;
+        let args = crate::core::cli::CliArgs::parse();
+        let check = args.check;
+        let api = args.apply(self);
+        if !check {
+            return api.start();
+        }
+        if let Err(e) = api.check() {
+            log::error!("Configuration check failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    /**
+     * Build the configured application for in-process testing.
+     *
+     * The returned `App` has the same routes and middleware as the one served by
+     * `start`, including the user database when enabled, but is not bound to a
+     * socket and does not use TLS. Pass it to `actix_web::test::init_service` to
+     * send requests to it. The rate limiter keys requests by peer address, so test
+     * requests must set one with `TestRequest::peer_addr`.
+     *
+     * # Returns
+     * An Actix Web `App` ready to be turned into a test service.
+     *
+     * # Panics
+     * Panics with the same message `build` would fail with if the configuration is
+     * invalid, and with the database error if the user database cannot be initialized.
+     *
+     * ```rust,should_panic
+     * use rusty_api::Api;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     // Panics with "Invalid configuration: rate_limit: values must be greater than zero, got (0, 5)"
+     *     let _ = Api::new().rate_limit(0, 5).test_app().await;
+     * });
+     * ```
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, Routes, HttpResponse, Method};
+     * use actix_web::test;
+     *
+     * async fn hello() -> HttpResponse {
+     *     HttpResponse::Ok().body("Hello!")
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new()
+     *         .add_route(Method::GET, "/hello", hello)
+     *         .add_route_with_password(Method::GET, "/secret", hello, "Password123");
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *
+     *     let req = test::TestRequest::get()
+     *         .uri("/hello")
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     assert!(test::call_service(&app, req).await.status().is_success());
+     *
+     *     let req = test::TestRequest::get()
+     *         .uri("/secret?password=wrong")
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     assert_eq!(test::call_service(&app, req).await.status(), 401);
+     * });
+     * ```
+     */
+    pub async fn test_app(
+        &self,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody + use<>>,
+            Error = actix_web::Error,
+            InitError = (),
+        > + use<>,
+    > {
+        let app = async {
+            self.check_config()?;
+            let pool = self.init_pool().await?;
+            Ok::<_, ApiError>(self.build_app(pool, &self.governor_config()?))
+        };
+        app.await.unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Log whether the user database is enabled and every configured route.
+    fn log_startup_summary(&self) {
+        #[cfg(feature = "sqlite")]
+        let user_db = self.user_db;
+        #[cfg(not(feature = "sqlite"))]
+        let user_db = false;
+
+        if user_db {
+            #[cfg(feature = "auth")]
+            log::info!(
+                "User database enabled, login at POST {} and registration at POST {}",
+                self.login_route, self.register_route
+            );
+            #[cfg(not(feature = "auth"))]
+            log::info!("User database enabled");
+        } else {
+            log::info!("User database disabled");
+        }
+
+        log::info!("{} route(s) configured", self.route_list.len());
+        for route in self.route_list.iter() {
+            let method = route.method.as_ref().map_or("*", actix_web::http::Method::as_str);
+            log::info!("  {} {} (auth: {})", method, route.path, route.auth);
+        }
+    }
+
+    /// Connect to the user database (if enabled) and seed the admin user.
+    ///
+    /// The pool is created once and reused by later calls.
+    #[cfg(feature = "sqlite")]
+    async fn init_pool(&self) -> Result<Option<DbPool>, ApiError> {
+        dotenv::dotenv().ok();
+        if !self.user_db {
+            #[cfg(feature = "auth")]
+            if self.admin_seed.is_some() {
+                log::warn!("seed_admin requires the user database to be enabled");
+            }
+            return Ok(None);
+        }
+        let profile_fields: Vec<&str> = self.profile_fields.iter().map(String::as_str).collect();
+        crate::core::db::register_profile_fields(&profile_fields);
+        if let Some(pool) = self.db_pool() {
+            return Ok(Some(pool));
+        }
+
+        #[cfg(feature = "auth")]
+        {
+            if let Some(algorithm) = self.password_algorithm {
+                crate::core::password::set_password_algorithm(algorithm);
+            }
+            crate::core::auth::prepare_dummy_password_hash();
+        }
+        #[cfg(feature = "auth")]
+        if let Some(sink) = &self.audit_sink {
+            crate::core::audit::set_audit_sink(Arc::clone(sink));
+        }
+
+        let (url, source) = match &self.database_url {
+            Some(url) => (url.clone(), "configured"),
+            None if std::env::var("DATABASE_URL").is_ok() => (crate::core::db::database_url_from_env(), "DATABASE_URL"),
+            None => (crate::core::db::DEFAULT_DATABASE_URL.to_string(), "default"),
+        };
+        match crate::core::db::database_path(&url) {
+            Some(path) => log::info!("Using database {} ({}) at {}", url, source, path.display()),
+            None => log::info!("Using in-memory database {} ({})", url, source),
+        }
+        let (attempts, initial_delay) = self.db_connect_retries;
+        let pool = crate::core::db::init_db_with_retry(&url, self.db_acquire_timeout, attempts, initial_delay)
+            .await
+            .map_err(|e| ApiError::Database(format!("Failed to connect to {}: {}", url, e)))?;
+
+        #[cfg(feature = "auth")]
+        if let Some((username, password)) = &self.admin_seed {
+            let created = crate::core::auth::seed_admin(&pool, username, password)
+                .await
+                .map_err(|e| match e {
+                    crate::core::auth::SeedAdminError::Hashing(e) => ApiError::InvalidConfig(format!("admin_seed: {}", e)),
+                    e => ApiError::Database(format!("Failed to seed admin user: {}", e)),
+                })?;
+            if created {
+                log::info!("Created admin user '{}'", username);
+            }
+        }
+
+        *self.pool.write().unwrap() = Some(pool.clone());
+        crate::core::db::set_global_pool(pool.clone());
+        Ok(Some(pool))
+    }
+
+    /// Without the `sqlite` feature there is no user database to connect to.
+    #[cfg(not(feature = "sqlite"))]
+    async fn init_pool(&self) -> Result<Option<DbPool>, ApiError> {
+        dotenv::dotenv().ok();
+        Ok(None)
+    }
+
+    /// Build the per-user quota middleware, enabled if a quota is configured.
+    #[cfg(feature = "auth")]
+    fn quota_middleware(&self) -> Condition<Quota> {
+        Condition::new(self.quota.is_some(), self.quota.clone().unwrap_or_default())
+    }
+
+    /// Build the per-user quota middleware; quotas require the `auth` feature.
+    #[cfg(not(feature = "auth"))]
+    fn quota_middleware(&self) -> Identity {
+        Identity::default()
+    }
+
+    /// Build the rate limiter configuration shared by all workers.
+    fn governor_config(&self) -> Result<GovernorConfig<ClientIpKeyExtractor>, ApiError> {
+        GovernorConfigBuilder::default()
+            .key_extractor(ClientIpKeyExtractor { trust_proxy: self.trust_proxy })
+            .per_second(self.rate_limit.0)
+            .burst_size(self.rate_limit.1)
+            .finish()
+            .ok_or_else(|| {
+                let (per_second, burst_size) = self.rate_limit;
+                let issue = ConfigIssue::new("rate_limit", format!("cannot build a rate limiter from ({}, {})", per_second, burst_size));
+                ApiError::InvalidConfig(issue.to_string())
+            })
+    }
+
+    /// Build the application with all configured middleware and routes.
+    #[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+    fn build_app(
+        &self,
+        pool: Option<DbPool>,
+        governor_config: &GovernorConfig<ClientIpKeyExtractor>,
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody + use<>>,
+            Error = actix_web::Error,
+            InitError = (),
+        > + use<>,
+    > {
+        let cors = (self.custom_cors)();
+        let parse_ranges = |ranges: &[String]| -> Vec<IpNet> {
+            ranges.iter().filter_map(|range| parse_ip_range(range).ok()).collect()
+        };
+        let allowlist = parse_ranges(&self.rate_limit_allowlist);
+        let ip_filter = IpFilter::new(parse_ranges(&self.ip_allowlist), parse_ranges(&self.ip_blocklist), self.trust_proxy);
+        let required_headers: Vec<&str> = self.required_headers.iter().map(String::as_str).collect();
+        let server_header = ServerHeader::new(self.server_header.as_deref()).unwrap_or_default();
+        let slow_request_log = self.slow_request_threshold.map(SlowRequestLog);
+        let (rewrite, redirect) = match self.path_normalization {
+            PathNormalization::Off => (None, None),
+            PathNormalization::Rewrite(trailing_slash) => (Some(trailing_slash), None),
+            PathNormalization::Redirect(trailing_slash) => (None, Some(trailing_slash)),
+        };
+        let mut rate_limit = RateLimit::new(governor_config, Arc::new(allowlist), self.trust_proxy);
+        if let Some(response) = &self.rate_limit_response {
+            rate_limit = rate_limit.response(Arc::clone(response));
+        }
+        let mut exempt_paths = Vec::new();
+        if self.favicon.is_some() {
+            exempt_paths.push(FAVICON_ROUTE.to_string());
+        }
+        if self.robots_txt.is_some() {
+            exempt_paths.push(ROBOTS_TXT_ROUTE.to_string());
+        }
+        rate_limit = rate_limit.exempt_paths(exempt_paths);
+        let mut app = App::new()
+            .wrap(Condition::new(slow_request_log.is_some(), slow_request_log.unwrap_or(SlowRequestLog(Duration::MAX))))
+            .wrap(Condition::new(self.etag, Etag))
+            .wrap(Condition::new(self.body_logger.is_some(), self.body_logger.clone().unwrap_or_default()))
+            .wrap(CatchPanic)
+            .wrap(RequireHeaders::new(&required_headers))
+            .wrap(Condition::new(self.idempotency.is_some(), self.idempotency.clone().unwrap_or_default()))
+            .wrap(self.quota_middleware())
+            .wrap(Condition::new(self.secure_only, RequireHttps::new()))
+            .wrap(Condition::new(self.cors_enabled, cors))
+            .wrap(rate_limit)
+            .wrap(ip_filter)
+            .wrap(Condition::new(rewrite.is_some(), NormalizePath::new(rewrite.unwrap_or(TrailingSlash::Trim))))
+            .wrap(Condition::new(redirect.is_some(), NormalizePathRedirect(redirect.unwrap_or(TrailingSlash::Trim))))
+            .wrap(UriLimit::new(self.max_uri_length, self.max_query_length))
+            .wrap(HeaderLimit::new(self.max_header_size, self.max_headers))
+            .wrap(Condition::new(self.response_envelope, ResponseEnvelope))
+            // Outside the limits and filters, so their rejections carry the headers too.
+            .wrap(Condition::new(self.security_headers, security_headers()))
+            .wrap(server_header);
+
+        let mut json_config = web::JsonConfig::default().error_handler(json_payload_error);
+        if let Some(limit) = self.json_limit {
+            json_config = json_config.limit(limit);
+            app = app.app_data(web::PayloadConfig::new(limit));
+        }
+        app = app
+            .app_data(json_config)
+            .app_data(DenyUnknownFields(self.deny_unknown_json_fields))
+            .app_data(TrustProxy(self.trust_proxy));
+        #[cfg(feature = "auth")]
+        {
+            app = app
+                .app_data(LoginIncludesUser(self.login_includes_user))
+                .app_data(self.verify_password_limit.clone())
+                .app_data(self.token_mode);
+            let validator = match (self.token_mode, &pool) {
+                (TokenMode::Session, Some(pool)) => Some(session_validator(pool.clone())),
+                _ => self.token_validator.clone(),
+            };
+            if let Some(validator) = validator {
+                app = app.app_data(validator);
+            }
+            if let Some(name) = &self.jwt_cookie {
+                app = app.app_data(JwtCookie(name.clone()));
+            }
+            let global = JwtSettings::global();
+            app = app.app_data(JwtSettings {
+                issuer: self.jwt_issuer.clone().or(global.issuer),
+                audience: self.jwt_audience.clone().or(global.audience),
+                leeway: self.jwt_leeway.unwrap_or(global.leeway),
+                max_session: self.jwt_max_session.unwrap_or(global.max_session),
+            });
+        }
+
+        // Add app_data for the pool if it exists
+        #[cfg(feature = "sqlite")]
+        if let Some(pool) = pool {
+            app = app.app_data(web::Data::new(pool));
+            #[cfg(feature = "auth")]
+            {
+                app = app.configure(|cfg| {
+                    crate::core::auth_routes::configure_auth_routes(
+                        cfg,
+                        &self.login_route,
+                        &self.register_route
+                    );
+                });
+            }
+        }
+
+        if let Some(path) = &self.favicon {
+            match std::fs::read(path) {
+                Ok(icon) => app = app.route(FAVICON_ROUTE, static_route(favicon_content_type(path), icon)),
+                Err(e) => log::warn!("Failed to read favicon '{}': {}", path, e),
+            }
+        }
+        if let Some(content) = &self.robots_txt {
+            app = app.route(ROBOTS_TXT_ROUTE, static_route("text/plain; charset=utf-8", content.clone()));
+        }
+
+        if let Some(path) = &self.version_endpoint {
+            app = app.configure(|cfg| crate::core::version::configure_version_route(cfg, path));
+        }
+        #[cfg(feature = "auth")]
+        if let Some(path) = &self.metrics_endpoint {
+            app = app.configure(|cfg| crate::core::metrics::configure_metrics_route(cfg, path));
+        }
+
+        if let Some(path) = &self.routes_endpoint
+            && cfg!(debug_assertions)
+        {
+            let routes = Arc::clone(&self.route_list);
+            app = app.configure(|cfg| Routes::configure_list_route(cfg, path, routes));
+        }
+
+        // Apply custom routes if provided
+        if let Some(custom_routes) = &self.custom_routes {
+            app = app.configure(|cfg| custom_routes(cfg));
+        }
+
+        app
+    }
+
+    /**
+     * Get the path to the TLS certificate file.
+     *
+     * # Returns
+     * A string representing the path to the certificate file.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().certs("path/to/cert.pem", "path/to/key.pem");
+     * assert_eq!(api.get_cert_path(), "path/to/cert.pem");
+     * ```
+     */
+    pub fn get_cert_path(&self) -> &str { &self.cert_path }
+
+    /**
+     * Get the path to the TLS private key file.
+     *
+     * # Returns
+     * A string representing the path to the private key file.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().certs("path/to/cert.pem", "path/to/key.pem");
+     * assert_eq!(api.get_key_path(), "path/to/key.pem");
+     * ```
+     */
+    pub fn get_key_path(&self) -> &str { &self.key_path }
+
+    /**
+     * Get the certificates presented to clients by SNI hostname.
+     *
+     * # Returns
+     * The certificates added with `add_sni_cert`, in order.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().add_sni_cert("api.example.com", "certs/api.pem", "certs/api-key.pem");
+     * assert_eq!(api.get_sni_certs()[0].cert_path, "certs/api.pem");
+     * ```
+     */
+    pub fn get_sni_certs(&self) -> &[SniCert] { &self.sni_certs }
+
+    /**
+     * Check if the server uses TLS.
+     *
+     * # Returns
+     * `false` if `no_tls` was called.
+     */
+    pub fn get_tls(&self) -> bool { self.tls }
+
+    /**
+     * Get the address the server is bound to.
+     *
+     * # Returns
+     * A string representing the address.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * 
+     * let api = Api::new().bind("123.4.5.6", 7891);
+     * assert_eq!(api.get_addr(), "123.4.5.6");
+     * ```
+     */
+    pub fn get_addr(&self) -> &str { &self.addr }
+
+    /**
+     * Get the port the server is bound to.
+     *
+     * # Returns
+     * A u16 representing the port.
+     * 
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * 
+     * let api = Api::new().bind("123.4.5.6", 7891);
+     * assert_eq!(api.get_port(), 7891);
+     * ```
+     */
+    pub fn get_port(&self) -> u16 { self.port }
+
+    /**
+     * Get the rate limit configuration.
+     *
+     * # Returns
+     * A tuple containing the rate limit configuration: `(requests_per_second, burst_size)`.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().rate_limit(5, 10);
+     * assert_eq!(api.get_rate_limit(), (5, 10));
+     * ```
+     */
+    pub fn get_rate_limit(&self) -> (u64, u32) { self.rate_limit }
+
+    /**
+     * Get the IP addresses and CIDR ranges that bypass the rate limiter.
+     *
+     * # Returns
+     * The entries passed to `rate_limit_allowlist`.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().rate_limit_allowlist(&["10.0.0.0/8"]);
+     * assert_eq!(api.get_rate_limit_allowlist(), ["10.0.0.0/8"]);
+     * ```
+     */
+    pub fn get_rate_limit_allowlist(&self) -> &[String] { &self.rate_limit_allowlist }
+
+    /**
+     * Check whether proxy headers are trusted when resolving the client IP.
+     *
+     * # Returns
+     * `true` if `trust_proxy` is enabled.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new();
+     * assert!(!api.get_trust_proxy());
+     * ```
+     */
+    pub fn get_trust_proxy(&self) -> bool { self.trust_proxy }
+
+    /**
+     * Get whether the login response includes the user profile.
+     *
+     * # Returns
+     * `true` if `login_includes_user` is enabled.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_login_includes_user(&self) -> bool { self.login_includes_user }
+
+    /**
+     * Get the failed password checks allowed per user at `/verify-password`.
+     *
+     * # Returns
+     * The allowed failures and the window they are counted in.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_verify_password_limit(&self) -> (u32, Duration) {
+        (self.verify_password_limit.get_max_failures(), self.verify_password_limit.get_window())
+    }
+
+    /**
+     * Get the name of the cookie carrying the JWT.
+     *
+     * # Returns
+     * The cookie name, or `None` if cookie sessions are disabled.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_jwt_cookie(&self) -> Option<String> { self.jwt_cookie.clone() }
+
+    /**
+     * Get how tokens are issued and validated.
+     *
+     * # Returns
+     * The `TokenMode` set with `token_mode`, `TokenMode::Jwt` by default.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_token_mode(&self) -> TokenMode { self.token_mode }
+
+    /**
+     * Get the address and port the server is bound to as a single string.
+     *
+     * # Returns
+     * A string representing the address and port in the format "address:port".
+     * 
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().bind("123.4.5.6", 7891);
+     * assert_eq!(api.get_bind_addr(), "123.4.5.6:7891");
+     * ```
+     */
+    pub fn get_bind_addr(&self) -> String { format!("{}:{}", self.addr, self.port) }
+
+    /**
+     * Get the rate limit per second.
+     *
+     * # Returns
+     * A u64 representing the number of requests allowed per second.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().rate_limit(5, 10);
+     * assert_eq!(api.get_rate_limit_per_second(), 5);
+     * ```
+     */
+    pub fn get_rate_limit_per_second(&self) -> u64 { self.rate_limit.0 }
+
+    /**
+     * Get the rate limit burst size.
+     *
+     * # Returns
+     * A u32 representing the maximum burst size for requests.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().rate_limit(5, 10);
+     * assert_eq!(api.get_rate_limit_burst_size(), 10);
+     * ```
+     */
+    pub fn get_rate_limit_burst_size(&self) -> u32 { self.rate_limit.1 }
+
+    /**
+     * Get the database URL overriding the `DATABASE_URL` environment variable, if any.
+     *
+     * # Returns
+     * The configured database URL, or `None` if `DATABASE_URL` is used.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().in_memory_db();
+     * assert_eq!(api.get_database_url(), Some("sqlite::memory:"));
+     * ```
+     */
+    #[cfg(feature = "sqlite")]
+    pub fn get_database_url(&self) -> Option<&str> { self.database_url.as_deref() }
+
+    /**
+     * Get the time to wait for a free database connection.
+     *
+     * # Returns
+     * The configured acquire timeout.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use std::time::Duration;
+     *
+     * let api = Api::new();
+     * assert_eq!(api.get_db_acquire_timeout(), Duration::from_secs(30));
+     * ```
+     */
+    #[cfg(feature = "sqlite")]
+    pub fn get_db_acquire_timeout(&self) -> Duration { self.db_acquire_timeout }
+
+    /**
+     * Get the retry configuration for the initial database connection.
+     *
+     * # Returns
+     * A tuple containing `(attempts, initial_delay)`.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     * use std::time::Duration;
+     *
+     * let api = Api::new();
+     * assert_eq!(api.get_db_connect_retries(), (3, Duration::from_millis(500)));
+     * ```
+     */
+    #[cfg(feature = "sqlite")]
+    pub fn get_db_connect_retries(&self) -> (u32, Duration) { self.db_connect_retries }
+
+    /**
+     * Get the custom `users` columns readable and writable by name.
+     *
+     * # Returns
+     * The fields set with `user_profile_fields`.
+     */
+    #[cfg(feature = "sqlite")]
+    pub fn get_user_profile_fields(&self) -> &[String] { &self.profile_fields }
+
+    /**
+     * Get the algorithm used to hash new passwords, if one was set.
+     *
+     * # Returns
+     * The configured `PasswordAlgorithm`, or `None` to use the default (bcrypt).
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_password_algorithm(&self) -> Option<PasswordAlgorithm> { self.password_algorithm }
+
+    /**
+     * Get the headers every request must carry.
+     *
+     * # Returns
+     * The names of the required headers.
+     */
+    pub fn get_required_headers(&self) -> Vec<String> { self.required_headers.clone() }
+
+    /**
+     * Get the user database pool used by the server.
+     *
+     * The pool is created when the server starts (or `test_app` is called) and is
+     * shared between clones of the `Api`, so a clone can be handed to background
+     * tasks before starting the server. The same pool is registered as
+     * `core::db::global_pool` and backs `DB_POOL`.
+     *
+     * # Returns
+     * The pool, or `None` if the user database is disabled or not initialized yet.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let api = Api::new().in_memory_db();
+     *     assert!(api.db_pool().is_none());
+     *
+     *     let _app = api.test_app().await;
+     *     let pool = api.db_pool().unwrap();
+     *     let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users").fetch_one(&pool).await.unwrap();
+     *     assert_eq!(count, 0);
+     * });
+     * ```
+     */
+    pub fn db_pool(&self) -> Option<DbPool> { self.pool.read().unwrap().clone() }
+
+    /**
+     * Get the IP ranges allowed to access the API.
+     *
+     * # Returns
+     * The entries passed to `ip_allowlist`.
+     */
+    pub fn get_ip_allowlist(&self) -> &[String] { &self.ip_allowlist }
+
+    /**
+     * Get the IP ranges denied access to the API.
+     *
+     * # Returns
+     * The entries passed to `ip_blocklist`.
+     */
+    pub fn get_ip_blocklist(&self) -> &[String] { &self.ip_blocklist }
+
+    /**
+     * Get the value of the `Server` response header.
+     *
+     * # Returns
+     * The header value, or `None` if the header is suppressed.
+     */
+    pub fn get_server_header(&self) -> Option<String> { self.server_header.clone() }
+
+    /**
+     * Get the path of the built-in version endpoint.
+     *
+     * # Returns
+     * The path, or `None` if the endpoint is disabled.
+     */
+    pub fn get_version_endpoint(&self) -> Option<String> { self.version_endpoint.clone() }
+
+    /**
+     * Get the path of the built-in metrics endpoint.
+     *
+     * # Returns
+     * The path, or `None` if the endpoint is disabled.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_metrics_endpoint(&self) -> Option<String> { self.metrics_endpoint.clone() }
+
+    /**
+     * Get the path of the route listing endpoint.
+     *
+     * # Returns
+     * The path, or `None` if the endpoint is disabled.
+     */
+    pub fn get_routes_endpoint(&self) -> Option<String> { self.routes_endpoint.clone() }
+
+    /**
+     * Check whether ETag support is enabled.
+     *
+     * # Returns
+     * `true` if `enable_etag` was called.
+     */
+    pub fn get_etag(&self) -> bool { self.etag }
+
+    /**
+     * Check if JSON responses are wrapped in an envelope.
+     *
+     * # Returns
+     * `true` if `response_envelope` is enabled.
+     */
+    pub fn get_response_envelope(&self) -> bool { self.response_envelope }
+
+    /**
+     * Get whether the CORS middleware is applied.
+     *
+     * # Returns
+     * `false` if `no_cors` was called.
+     */
+    pub fn get_cors_enabled(&self) -> bool { self.cors_enabled }
+
+    /**
+     * Get how request paths are normalized before routing.
+     *
+     * # Returns
+     * The configured `PathNormalization`.
+     */
+    pub fn get_path_normalization(&self) -> PathNormalization { self.path_normalization }
+
+    /**
+     * Get the per-user request quotas.
+     *
+     * # Returns
+     * The configured `Quota`, or `None` if quotas are not enforced.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_quota(&self) -> Option<&Quota> { self.quota.as_ref() }
+
+    /**
+     * Get how long responses to requests with an `Idempotency-Key` are replayed.
+     *
+     * # Returns
+     * The replay TTL, or `None` if idempotency keys are disabled.
+     */
+    pub fn get_idempotency_ttl(&self) -> Option<Duration> { self.idempotency.as_ref().map(Idempotency::get_ttl) }
+
+    /**
+     * Get the threshold above which requests are logged as slow.
+     *
+     * # Returns
+     * The threshold, or `None` if slow requests are not logged.
+     */
+    pub fn get_slow_request_threshold(&self) -> Option<Duration> { self.slow_request_threshold }
+
+    /**
+     * Get the path of the icon file served at `/favicon.ico`.
+     *
+     * # Returns
+     * The icon file path, or `None` if no favicon is served.
+     */
+    pub fn get_favicon(&self) -> Option<String> { self.favicon.clone() }
+
+    /**
+     * Get the content served at `/robots.txt`.
+     *
+     * # Returns
+     * The `robots.txt` content, or `None` if it is not served.
+     */
+    pub fn get_robots_txt(&self) -> Option<String> { self.robots_txt.clone() }
+
+    /**
+     * Get whether requests not made over HTTPS are rejected.
+     *
+     * # Returns
+     * `true` if `secure_only` is enabled.
+     */
+    pub fn get_secure_only(&self) -> bool { self.secure_only }
+
+    /**
+     * Check whether body logging is enabled.
+     *
+     * # Returns
+     * `true` if `debug_body_logging` or `debug_body_logging_with` was called.
+     */
+    pub fn get_body_logging(&self) -> bool { self.body_logger.is_some() }
+
+    /**
+     * Check if security headers are added to every response.
+     *
+     * # Returns
+     * `true` if `security_headers` is enabled.
+     */
+    pub fn get_security_headers(&self) -> bool { self.security_headers }
+
+    /**
+     * Get the environment profile applied with `profile`.
+     *
+     * # Returns
+     * The `Profile`, or `None` if no profile was applied.
+     */
+    pub fn get_profile(&self) -> Option<Profile> { self.profile }
+
+    /**
+     * Get the maximum size of JSON request bodies.
+     *
+     * # Returns
+     * The limit in bytes, or `None` if the Actix defaults are used.
+     */
+    pub fn get_json_limit(&self) -> Option<usize> { self.json_limit }
+
+    /**
+     * Get the level of the built-in logger.
+     *
+     * # Returns
+     * The level set with `log_level`.
+     */
+    pub fn get_log_level(&self) -> LevelFilter { self.log_level }
+
+    /**
+     * Check whether unknown JSON fields are rejected.
+     *
+     * # Returns
+     * `true` if `deny_unknown_json_fields` is enabled.
+     */
+    pub fn get_deny_unknown_json_fields(&self) -> bool { self.deny_unknown_json_fields }
+
+    /**
+     * Get the maximum number of concurrent connections per worker.
+     *
+     * # Returns
+     * The limit, or `None` if the Actix default is used.
+     */
+    pub fn get_max_connections(&self) -> Option<usize> { self.max_connections }
+
+    /**
+     * Get the maximum length of the request URI.
+     *
+     * # Returns
+     * The limit in bytes.
+     */
+    pub fn get_max_uri_length(&self) -> usize { self.max_uri_length }
+
+    /**
+     * Get the maximum length of the query string.
+     *
+     * # Returns
+     * The limit in bytes.
+     */
+    pub fn get_max_query_length(&self) -> usize { self.max_query_length }
+
+    /**
+     * Get the maximum total size of the request headers.
+     *
+     * # Returns
+     * The limit in bytes.
+     */
+    pub fn get_max_header_size(&self) -> usize { self.max_header_size }
+
+    /**
+     * Get the maximum number of request headers.
+     *
+     * # Returns
+     * The limit.
+     */
+    pub fn get_max_headers(&self) -> usize { self.max_headers }
+
+    /**
+     * Get the maximum number of concurrent TLS handshakes per worker.
+     *
+     * # Returns
+     * The limit, or `None` if the Actix default is used.
+     */
+    pub fn get_max_connection_rate(&self) -> Option<usize> { self.max_connection_rate }
+
+    /**
+     * Get the issuer of the JWTs issued and accepted by the API.
+     *
+     * # Returns
+     * The issuer, or `None` if the `iss` claim is not used.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_jwt_issuer(&self) -> Option<String> { self.jwt_issuer.clone() }
+
+    /**
+     * Get the audience of the JWTs issued and accepted by the API.
+     *
+     * # Returns
+     * The audience, or `None` if the `aud` claim is not used.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_jwt_audience(&self) -> Option<String> { self.jwt_audience.clone() }
+
+    /**
+     * Get the clock-skew leeway applied when checking token expiry.
+     *
+     * # Returns
+     * The leeway set with `jwt_leeway`, or the 60 second default.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_jwt_leeway(&self) -> Duration { self.jwt_leeway.unwrap_or(DEFAULT_JWT_LEEWAY) }
+
+    /**
+     * Get how long after login tokens can be refreshed.
+     *
+     * # Returns
+     * The window set with `jwt_max_session`, or the 30 day default.
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_jwt_max_session(&self) -> Duration { self.jwt_max_session.unwrap_or(DEFAULT_JWT_MAX_SESSION) }
+
+    /**
+     * Get the username of the admin user seeded on startup, if any.
+     *
+     * # Returns
+     * The username passed to `seed_admin`, or `None` if no admin is seeded.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Api;
+     *
+     * let api = Api::new().seed_admin("admin", "ChangeMe123");
+     * assert_eq!(api.get_admin_seed_username(), Some("admin"));
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn get_admin_seed_username(&self) -> Option<&str> { self.admin_seed.as_ref().map(|(username, _)| username.as_str()) }
+
+    /**
+     * Get the custom CORS configuration.
+     *
+     * # Returns
+     * A reference to the custom CORS configuration closure.
+     */
+    pub fn get_custom_routes(&self) -> Option<&RoutesConfig> { self.custom_routes.as_ref() }
+}
+
+impl Default for Api {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Path of the route serving the icon set with `Api::favicon`.
+pub const FAVICON_ROUTE: &str = "/favicon.ico";
+
+/// Path of the route serving the content set with `Api::robots_txt`.
+pub const ROBOTS_TXT_ROUTE: &str = "/robots.txt";
+
+/// Guess the content type of a favicon from its file extension.
+fn favicon_content_type(path: &str) -> &'static str {
+    match std::path::Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("png") => "image/png",
+        Some(ext) if ext.eq_ignore_ascii_case("svg") => "image/svg+xml",
+        _ => "image/x-icon",
+    }
+}
+
+/// A `GET` route serving a constant body with the static `Cache-Control` header.
+fn static_route(content_type: &'static str, body: impl Into<Bytes>) -> actix_web::Route {
+    let body = body.into();
+    web::get().to(move || {
+        let body = body.clone();
+        async move {
+            HttpResponse::Ok()
+                .content_type(content_type)
+                .insert_header((actix_web::http::header::CACHE_CONTROL, STATIC_CACHE_CONTROL))
+                .body(body)
+        }
+    })
+}
+
+/**
+ * Check that the `Cors` middleware can be initialized.
+ *
+ * Actix only reports invalid CORS options, such as credentials combined with a
+ * wildcard origin, when each worker builds its app. Initializing the middleware
+ * once up front turns that into a configuration error from `build`.
+ */
+fn validate_cors(cors: Cors) -> Result<(), String> {
+    cors.new_transform(actix_web::test::ok_service()).into_inner().map(drop).map_err(|_| {
+        "credentials cannot be combined with a wildcard origin and send_wildcard, \
+         and allowed origins and headers must be valid"
+            .to_string()
+    })
+}
+
+/**
+ * Turn a `web::Json` extraction error into the standard JSON error response.
+ *
+ * A missing or wrong `Content-Type` is a common mistake when calling the auth
+ * endpoints, so it is answered with a `400` naming the expected type.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(Api::new().in_memory_db().test_app().await).await;
+ *     let req = test::TestRequest::post()
+ *         .uri("/register")
+ *         .insert_header(("Content-Type", "text/plain"))
+ *         .set_payload(r#"{"username": "alice", "password": "Secret123"}"#)
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *
+ *     let response = test::call_service(&app, req).await;
+ *     assert_eq!(response.status(), 400);
+ *     let body: serde_json::Value = test::read_body_json(response).await;
+ *     assert_eq!(body["error"], "Invalid JSON body: expected Content-Type: application/json");
+ * });
+ * ```
+ */
+fn json_payload_error(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let response = match err {
+        JsonPayloadError::ContentType => {
+            json_error(actix_web::http::StatusCode::BAD_REQUEST, "Invalid JSON body: expected Content-Type: application/json")
+        }
+        _ => json_error(err.status_code(), format!("Invalid JSON body: {}", err)),
+    };
+    actix_web::error::InternalError::from_response(err, response).into()
+}
+
+/**
+ * A validated `Api` configuration, ready to be started.
+ *
+ * A `BuiltApi` is created by `Api::build`, which has already checked the builder
+ * values and loaded the TLS configuration.
+ *
+ * # Example
+ * ```rust,no_run
+ * use rusty_api::Api;
+ *
+ * match Api::new().certs("certs/cert.pem", "certs/key.pem").build() {
+ *     Ok(api) => api.start().expect("Server failed"),
+ *     Err(e) => eprintln!("Invalid configuration: {}", e),
+ * }
+ * ```
+ */
+pub struct BuiltApi {
+    /// The validated configuration.
+    api: Api,
+
+    /// TLS configuration loaded from the certificate and key paths, or `None` if TLS is disabled.
+    tls_config: Option<ServerConfig>,
+
+    /// Rate limiter configuration shared by all workers.
+    governor_config: GovernorConfig<ClientIpKeyExtractor>,
+}
+
+impl BuiltApi {
+    /**
+     * Start the API server.
+     *
+     * This method initializes the user database (if enabled) and begins listening
+     * for incoming requests. It will block the current thread until the server is stopped.
+     * The database connection is retried as configured with `Api::db_connect_retries`.
+     *
+     * # Returns
+     * `Ok(())` once the server stops, or an `ApiError` if it could not be started.
+     */
+    pub fn start(self) -> Result<(), ApiError> {
+        let rt = actix_web::rt::System::new();
+        rt.block_on(async move { self.start_with_handle().await?.wait().await })
+    }
+
+    /**
+     * Start the API server in the background and return once it is listening.
+     *
+     * Unlike `start`, this does not block: the returned `RunningApi` reports the
+     * concrete addresses the server bound to, including the port chosen by the
+     * OS when binding to port `0`, and can be used to stop the server. It must be
+     * called from within an Actix system, e.g. `#[actix_web::main]`.
+     *
+     * Once listening, the scheme and addresses, whether the user database is
+     * enabled and every route added with `Api::configure_routes` are logged at
+     * `INFO` with the `log` crate, as a sanity check of the deployment. Install a
+     * logger such as `env_logger` to see them.
+     *
+     * # Returns
+     * The running server, or an `ApiError` if it could not be started.
+     *
+     * # Example
+     * ```rust,no_run
+     * use rusty_api::Api;
+     *
+     * #[actix_web::main]
+     * async fn main() {
+     *     let running = Api::new()
+     *         .certs("certs/cert.pem", "certs/key.pem")
+     *         .bind("127.0.0.1", 0)
+     *         .build()
+     *         .expect("Invalid configuration")
+     *         .start_with_handle()
+     *         .await
+     *         .expect("Failed to start server");
+     *
+     *     let port = running.addrs()[0].port();
+     *     println!("Listening on https://127.0.0.1:{}", port);
+     *
+     *     running.stop(true).await.expect("Server failed");
+     * }
+     * ```
+     *
+     * The startup log, captured by a minimal logger:
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use std::sync::Mutex;
+     *
+     * static LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+     *
+     * struct Capture;
+     * impl log::Log for Capture {
+     *     fn enabled(&self, _: &log::Metadata) -> bool { true }
+     *     fn log(&self, record: &log::Record) { LINES.lock().unwrap().push(record.args().to_string()); }
+     *     fn flush(&self) {}
+     * }
+     * log::set_logger(&Capture).unwrap();
+     * log::set_max_level(log::LevelFilter::Info);
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/health", || async { HttpResponse::Ok().finish() });
+     *     let api = Api::new().no_tls().bind("127.0.0.1", 0).configure_routes(routes).enable_user_db().in_memory_db();
+     *     let running = api.build().unwrap().start_with_handle().await.unwrap();
+     *     running.stop(true).await.unwrap();
+     * });
+     *
+     * let lines = LINES.lock().unwrap();
+     * assert!(lines.iter().any(|line| line.starts_with("Listening on http://127.0.0.1:")));
+     * assert!(lines.contains(&"User database enabled, login at POST /login and registration at POST /register".to_string()));
+     * assert!(lines.contains(&"  GET /health (auth: none)".to_string()));
+     * ```
+     */
+    pub async fn start_with_handle(self) -> Result<RunningApi, ApiError> {
+        let BuiltApi { api, tls_config, governor_config } = self;
+        log::info!("Starting API server...");
+
+        let pool = api.init_pool().await?;
+        let bind_addr = api.get_bind_addr();
+        let hook_pool = pool.clone();
+
+        log::info!("Server binding to {}", bind_addr);
+        let listeners = bind_listeners(&api.addr, api.port)?;
+        let addrs = listeners.iter().map(TcpListener::local_addr).collect::<Result<Vec<_>, _>>()?;
+        let server = serve(&api, pool.clone(), tls_config.clone(), governor_config.clone(), &listeners)?;
+
+        // The server only accepts connections once polled, so dropping it on a
+        // failed startup hook closes the listeners without serving any request.
+        let startup = api.on_startup.lock().unwrap().take();
+        if let Some(hook) = startup {
+            hook(hook_pool.clone()).await.map_err(ApiError::Startup)?;
+        }
+
+        let tasks = std::mem::take(&mut *api.tasks.lock().unwrap())
+            .into_iter()
+            .map(|(interval, mut task)| {
+                let pool = hook_pool.clone();
+                actix_web::rt::spawn(async move {
+                    loop {
+                        task(pool.clone()).await;
+                        actix_web::rt::time::sleep(interval).await;
+                    }
+                })
+            })
+            .collect();
+
+        let scheme = if tls_config.is_some() { "https" } else { "http" };
+        for addr in &addrs {
+            log::info!("Listening on {}://{}", scheme, addr);
+        }
+        api.log_startup_summary();
+        Ok(RunningApi {
+            addrs,
+            handle: RunningHandle::new(server.handle()),
+            server: actix_web::rt::spawn(server),
+            tasks,
+            on_shutdown: Arc::clone(&api.on_shutdown),
+            api,
+            pool: hook_pool,
+            tls_config,
+            governor_config,
+            listeners,
+        })
+    }
+
+    /**
+     * Run the startup steps without serving traffic.
+     *
+     * See `Api::check`. It must be called from within an Actix system.
+     *
+     * # Returns
+     * `Ok(())` if the server could start, or the `ApiError` that would stop it.
+     */
+    pub async fn check(self) -> Result<(), ApiError> {
+        let BuiltApi { api, governor_config, .. } = self;
+        log::info!("Checking API server configuration...");
+
+        let pool = api.init_pool().await?;
+        log::info!("Checking that {} can be bound", api.get_bind_addr());
+        drop(bind_listeners(&api.addr, api.port)?);
+        api.build_app(pool, &governor_config)
+            .into_factory()
+            .new_service(AppConfig::default())
+            .await
+            .map_err(|_| ApiError::Startup("Failed to initialize the application".into()))?;
+
+        log::info!("Configuration check passed");
+        Ok(())
+    }
+
+    /// Get the validated `Api` configuration.
+    pub fn api(&self) -> &Api { &self.api }
+}
+
+/// Bind every address the host resolves to, like `HttpServer::bind`.
+fn bind_listeners(addr: &str, port: u16) -> std::io::Result<Vec<TcpListener>> {
+    let mut listeners = Vec::new();
+    let mut error = None;
+    for addr in (addr, port).to_socket_addrs()? {
+        match TcpListener::bind(addr) {
+            Ok(listener) => listeners.push(listener),
+            Err(e) => error = Some(e),
+        }
+    }
+    match error {
+        Some(e) if listeners.is_empty() => Err(e),
+        _ if listeners.is_empty() => Err(std::io::Error::other("address did not resolve")),
+        _ => Ok(listeners),
+    }
+}
+
+/// Create the server accepting connections on the given listeners, which it shares with other servers.
+fn serve(
+    api: &Api,
+    pool: Option<DbPool>,
+    tls_config: Option<ServerConfig>,
+    governor_config: GovernorConfig<ClientIpKeyExtractor>,
+    listeners: &[TcpListener],
+) -> std::io::Result<Server> {
+    let app_api = api.clone();
+    // The app is boxed so its middleware stack is compiled once, not once
+    // for each of the plain and TLS listeners.
+    let mut server = HttpServer::new(move || {
+        let app = app_api.build_app(pool.clone(), &governor_config).into_factory();
+        boxed::factory(app.map(ServiceResponse::map_into_boxed_body))
+    });
+    if let Some(max) = api.max_connections {
+        server = server.max_connections(max);
+    }
+    if let Some(max) = api.max_connection_rate {
+        server = server.max_connection_rate(max);
+    }
+    for listener in listeners {
+        let listener = listener.try_clone()?;
+        server = match &tls_config {
+            Some(tls_config) => server.listen_rustls_0_23(listener, tls_config.clone())?,
+            None => server.listen(listener)?,
+        };
+    }
+    Ok(server.run())
+}
+
+/**
+ * A server started in the background with `BuiltApi::start_with_handle`.
+ *
+ * The `on_shutdown` hook runs once the server has stopped, when awaited through
+ * `stop` or `wait`.
+ */
+pub struct RunningApi {
+    /// The addresses the server is listening on.
+    addrs: Vec<SocketAddr>,
+
+    /// Handle used to stop the server, following it across `reload_routes`.
+    handle: RunningHandle,
+
+    /// The spawned server task.
+    server: JoinHandle<std::io::Result<()>>,
+
+    /// The spawned background tasks, aborted once the server has stopped.
+    tasks: Vec<JoinHandle<()>>,
+
+    /// Hook run after the server has stopped.
+    on_shutdown: Arc<Mutex<Option<ShutdownHook>>>,
+
+    /// The configuration the server was started with, used to reload routes.
+    api: Api,
+
+    /// The user database, shared with servers started by `reload_routes`.
+    pool: Option<DbPool>,
+
+    /// TLS configuration, shared with servers started by `reload_routes`.
+    tls_config: Option<ServerConfig>,
+
+    /// Rate limiter configuration, shared with servers started by `reload_routes`.
+    governor_config: GovernorConfig<ClientIpKeyExtractor>,
+
+    /// The listening sockets, kept open across `reload_routes`.
+    listeners: Vec<TcpListener>,
+}
+
+impl RunningApi {
+    /// Get the addresses the server is listening on, with the actual ports assigned.
+    pub fn addrs(&self) -> &[SocketAddr] { &self.addrs }
+
+    /// Get a handle that can stop the server from elsewhere, e.g. another task.
+    /// The handle keeps controlling the live server after `reload_routes`.
+    pub fn handle(&self) -> RunningHandle { self.handle.clone() }
+
+    /**
+     * Replace the routes configured with `Api::configure_routes` without a restart.
+     *
+     * A new server with the new routes starts accepting on the same sockets,
+     * then the old server stops gracefully: requests it already accepted
+     * complete, and no connection is refused in between. Everything else,
+     * including the user database and background tasks, is kept; the
+     * `on_startup` hook does not run again. To reload on `SIGHUP`, call this
+     * from a task listening for the signal, e.g. with `tokio::signal::unix`.
+     *
+     * # Arguments
+     * * `routes` - The routes replacing the current ones.
+     *
+     * # Returns
+     * `Ok(())` once the new routes are live and the old server has stopped, or an
+     * `ApiError` if some routes could not be added (see `Routes::problems`) or
+     * the new server could not be started, in which case the old routes stay live.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/v1", || async { HttpResponse::Ok().body("v1") });
+     *     let mut running = Api::new()
+     *         .no_tls()
+     *         .bind("127.0.0.1", 0)
+     *         .configure_routes(routes)
+     *         .build()
+     *         .unwrap()
+     *         .start_with_handle()
+     *         .await
+     *         .unwrap();
+     *     let addr = running.addrs()[0];
+     *     let url = |path: &str| format!("http://{}{}", addr, path);
+     *     let status = |url: String| async move { awc::Client::default().get(url).send().await.unwrap().status() };
+     *
+     *     assert_eq!(status(url("/v1")).await, 200);
+     *     assert_eq!(status(url("/v2")).await, 404);
+     *     let handle = running.handle();
+     *
+     *     let routes = Routes::new().add_route(Method::GET, "/v2", || async { HttpResponse::Ok().body("v2") });
+     *     running.reload_routes(routes).await.unwrap();
+     *
+     *     assert_eq!(status(url("/v2")).await, 200);
+     *     assert_eq!(status(url("/v1")).await, 404);
+     *
+     *     // Handles taken before the reload stop the new server.
+     *     handle.stop(true).await;
+     *     running.wait().await.unwrap();
+     *     assert!(awc::Client::default().get(url("/v2")).send().await.is_err());
+     * });
+     * ```
+     */
+    pub async fn reload_routes(&mut self, routes: Routes) -> Result<(), ApiError> {
+        if !routes.problems().is_empty() {
+            return Err(ApiError::InvalidConfig(format!("routes: {}", routes.problems().join("; "))));
+        }
+        let api = self.api.clone().configure_routes(routes);
+        let server = serve(&api, self.pool.clone(), self.tls_config.clone(), self.governor_config.clone(), &self.listeners)?;
+
+        let new_handle = server.handle();
+        let old_server = std::mem::replace(&mut self.server, actix_web::rt::spawn(server));
+        let (old_handle, stopped) = self.handle.replace(new_handle.clone());
+        self.api = api;
+        if stopped {
+            // A stop raced the reload, so the new server must not outlive it.
+            new_handle.stop(true).await;
+        }
+        log::info!("Routes reloaded, stopping the previous server");
+        old_handle.stop(true).await;
+        old_server.await.map_err(|e| ApiError::Io(std::io::Error::other(e)))??;
+        Ok(())
+    }
+
+    /**
+     * Stop the server and wait for it to shut down.
+     *
+     * # Arguments
+     * * `graceful` - Whether to let in-flight requests complete before stopping.
+     *
+     * # Returns
+     * `Ok(())` once the server has stopped, or an `ApiError` if it stopped with an error.
+     */
+    pub async fn stop(self, graceful: bool) -> Result<(), ApiError> {
+        self.handle.stop(graceful).await;
+        self.wait().await
+    }
+
+    /**
+     * Wait for the server to stop, e.g. on `Ctrl+C`, then stop the background tasks
+     * and run the `on_shutdown` hook.
+     *
+     * # Returns
+     * `Ok(())` once the server has stopped, or an `ApiError` if it stopped with an error.
+     */
+    pub async fn wait(self) -> Result<(), ApiError> {
+        let result = self.server.await;
+        for task in self.tasks {
+            task.abort();
+        }
+        let shutdown = self.on_shutdown.lock().unwrap().take();
+        if let Some(hook) = shutdown {
+            hook();
+        }
+        result.map_err(|e| ApiError::Io(std::io::Error::other(e)))??;
+        Ok(())
+    }
+}
+
+/**
+ * A handle controlling a server started with `BuiltApi::start_with_handle`.
+ *
+ * Unlike an Actix `ServerHandle`, it follows the server across
+ * `RunningApi::reload_routes`, so handles given out before a reload still stop
+ * the live server. Cloning a handle shares it.
+ */
+#[derive(Clone)]
+pub struct RunningHandle(Arc<Mutex<HandleState>>);
+
+/// The current server handle, and whether a stop was requested.
+struct HandleState {
+    handle: ServerHandle,
+    stopped: bool,
+}
+
+impl RunningHandle {
+    /// Create a handle for the given server.
+    fn new(handle: ServerHandle) -> Self {
+        Self(Arc::new(Mutex::new(HandleState { handle, stopped: false })))
+    }
+
+    /// Point the handle at a new server, returning the old server's handle and whether a stop was requested.
+    fn replace(&self, handle: ServerHandle) -> (ServerHandle, bool) {
+        let mut state = self.0.lock().unwrap();
+        (std::mem::replace(&mut state.handle, handle), state.stopped)
+    }
+
+    /// Get the handle of the server currently running.
+    fn current(&self) -> ServerHandle {
+        self.0.lock().unwrap().handle.clone()
+    }
+
+    /**
+     * Stop the server.
+     *
+     * # Arguments
+     * * `graceful` - Whether to let in-flight requests complete before stopping.
+     */
+    pub async fn stop(&self, graceful: bool) {
+        let handle = {
+            let mut state = self.0.lock().unwrap();
+            state.stopped = true;
+            state.handle.clone()
+        };
+        handle.stop(graceful).await;
+    }
+
+    /// Stop accepting new connections, keeping existing ones.
+    pub async fn pause(&self) {
+        self.current().pause().await;
+    }
+
+    /// Resume accepting new connections after `pause`.
+    pub async fn resume(&self) {
+        self.current().resume().await;
+    }
+}
@@ -0,0 +1,65 @@
+/*!
+ * Prints the hash of a password, for inserting users into the database by hand.
+ *
+ * The password is read from the first line of standard input, so it does not
+ * end up in the shell history, or taken from the command line. The algorithm
+ * is taken from `--algorithm`, then `RUSTY_API_PASSWORD_ALGORITHM`, and
+ * defaults to bcrypt, matching the server.
+ *
+ * ```bash
+ * echo 'ChangeMe123' | rusty-api-hash-password
+ * rusty-api-hash-password --algorithm argon2 'ChangeMe123'
+ * ```
+ */
+use rusty_api::core::password::{set_password_algorithm, PasswordAlgorithm};
+use std::io::BufRead;
+use std::process::exit;
+
+const USAGE: &str = "Usage: rusty-api-hash-password [--algorithm bcrypt|argon2] [PASSWORD]\n\
+                     Reads the password from standard input when PASSWORD is omitted.";
+
+fn main() {
+    let mut algorithm = std::env::var("RUSTY_API_PASSWORD_ALGORITHM").ok();
+    let mut password = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--algorithm" => algorithm = Some(args.next().unwrap_or_else(|| fail(USAGE))),
+            _ if password.is_none() => password = Some(arg),
+            _ => fail(USAGE),
+        }
+    }
+
+    if let Some(algorithm) = algorithm {
+        match algorithm.parse::<PasswordAlgorithm>() {
+            Ok(algorithm) => set_password_algorithm(algorithm),
+            Err(e) => fail(&e),
+        }
+    }
+
+    let password = password.unwrap_or_else(|| {
+        let mut line = String::new();
+        if let Err(e) = std::io::stdin().lock().read_line(&mut line) {
+            fail(&format!("Failed to read the password: {}", e));
+        }
+        line.trim_end_matches(['\r', '\n']).to_string()
+    });
+    if password.is_empty() {
+        fail("The password must not be empty");
+    }
+
+    match rusty_api::hash_password(&password) {
+        Ok(hash) => println!("{}", hash),
+        Err(e) => fail(&e.to_string()),
+    }
+}
+
+/// Print the message to standard error and exit with a failure status.
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    exit(1)
+}
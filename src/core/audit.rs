@@ -0,0 +1,209 @@
+/*!
+ * The `audit` module records an audit trail of authentication events.
+ *
+ * Logins, failed logins, registrations, privilege changes and API key rotations
+ * are recorded by the functions in `core::auth`. Records never contain passwords,
+ * tokens or API keys.
+ *
+ * This module features:
+ * - **Pluggable Sinks**: Implement `AuditSink` to ship records elsewhere, e.g. to a SIEM.
+ * - **Database Sink**: `DbAuditSink`, the default, writes records to the `audit_log` table.
+ * - **Client IPs**: Records include the client IP when recorded inside `with_client_ip`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::audit::{AuditRecord, AuditSink};
+ * use futures_util::future::BoxFuture;
+ *
+ * struct StdoutSink;
+ *
+ * impl AuditSink for StdoutSink {
+ *     fn record(&self, record: AuditRecord) -> BoxFuture<'_, ()> {
+ *         Box::pin(async move {
+ *             println!("AUDIT: {} {} user={:?} ip={:?}", record.timestamp, record.event.as_str(), record.user_id, record.ip);
+ *         })
+ *     }
+ * }
+ *
+ * let api = rusty_api::Api::new().enable_user_db().audit_sink(StdoutSink);
+ * ```
+ */
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use sqlx::SqlitePool;
+
+static AUDIT_SINK: RwLock<Option<Arc<dyn AuditSink>>> = RwLock::new(None);
+
+tokio::task_local! {
+    static CLIENT_IP: Option<IpAddr>;
+}
+
+/// An authentication event recorded in the audit trail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEvent {
+    /// A user logged in.
+    Login,
+    /// A login attempt failed.
+    LoginFailed,
+    /// A user registered.
+    Register,
+    /// A user's privilege level was changed.
+    PrivilegeChanged,
+    /// A user's API key was rotated, revoking the previous key.
+    ApiKeyRotated,
+    /// A user account was enabled by an admin.
+    AccountEnabled,
+    /// A user account was disabled by an admin.
+    AccountDisabled,
+}
+
+impl AuditEvent {
+    /// Get the name stored in the `event` column of the `audit_log` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEvent::Login => "login",
+            AuditEvent::LoginFailed => "login_failed",
+            AuditEvent::Register => "register",
+            AuditEvent::PrivilegeChanged => "privilege_changed",
+            AuditEvent::ApiKeyRotated => "api_key_rotated",
+            AuditEvent::AccountEnabled => "account_enabled",
+            AuditEvent::AccountDisabled => "account_disabled",
+        }
+    }
+}
+
+/// A single entry of the audit trail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// When the event happened.
+    pub timestamp: DateTime<Utc>,
+    /// The user the event concerns, if known.
+    pub user_id: Option<i32>,
+    /// The event.
+    pub event: AuditEvent,
+    /// The client IP address, if known.
+    pub ip: Option<IpAddr>,
+}
+
+impl AuditRecord {
+    /// Create a record for an event happening now, using the client IP of the current `with_client_ip` scope.
+    pub fn new(event: AuditEvent, user_id: Option<i32>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            user_id,
+            event,
+            ip: CLIENT_IP.try_with(|ip| *ip).ok().flatten(),
+        }
+    }
+}
+
+/**
+ * A destination for audit records.
+ *
+ * Failures should be handled by the sink itself, e.g. by logging them, so that
+ * auditing never fails the operation being audited.
+ */
+pub trait AuditSink: Send + Sync {
+    /// Record an audit event.
+    fn record(&self, record: AuditRecord) -> BoxFuture<'_, ()>;
+}
+
+impl<T: AuditSink + ?Sized> AuditSink for Arc<T> {
+    fn record(&self, record: AuditRecord) -> BoxFuture<'_, ()> {
+        (**self).record(record)
+    }
+}
+
+/// The default audit sink, writing records to the `audit_log` table.
+#[derive(Debug, Clone)]
+pub struct DbAuditSink {
+    pool: SqlitePool,
+}
+
+impl DbAuditSink {
+    /// Create a sink writing to the `audit_log` table of the given database.
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl AuditSink for DbAuditSink {
+    fn record(&self, record: AuditRecord) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let result = sqlx::query("INSERT INTO audit_log (timestamp, user_id, event, ip) VALUES (?, ?, ?, ?)")
+                .bind(record.timestamp.to_rfc3339())
+                .bind(record.user_id)
+                .bind(record.event.as_str())
+                .bind(record.ip.map(|ip| ip.to_string()))
+                .execute(&self.pool)
+                .await;
+
+            if let Err(e) = result {
+                log::error!("Failed to write audit record '{}': {}", record.event.as_str(), e);
+            }
+        })
+    }
+}
+
+/**
+ * Replace the default database sink for the rest of the process.
+ *
+ * # Arguments
+ * - `sink`: The sink receiving every audit record.
+ */
+pub fn set_audit_sink(sink: impl AuditSink + 'static) {
+    *AUDIT_SINK.write().unwrap() = Some(Arc::new(sink));
+}
+
+/**
+ * Record an audit event.
+ *
+ * The event is passed to the sink set with `set_audit_sink`, or written to the
+ * `audit_log` table of `pool` if none was set.
+ *
+ * # Arguments
+ * - `pool`: The database used by the default sink.
+ * - `event`: The event to record.
+ * - `user_id`: The user the event concerns, if known.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::audit::{audit, with_client_ip, AuditEvent};
+ * use rusty_api::core::db::{init_db_with_url, IN_MEMORY_DATABASE_URL};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let pool = init_db_with_url(IN_MEMORY_DATABASE_URL).await.unwrap();
+ *     let ip = "203.0.113.7".parse().ok();
+ *     with_client_ip(ip, audit(&pool, AuditEvent::Login, Some(1))).await;
+ *
+ *     let (event, ip): (String, String) = sqlx::query_as("SELECT event, ip FROM audit_log")
+ *         .fetch_one(&pool).await.unwrap();
+ *     assert_eq!((event.as_str(), ip.as_str()), ("login", "203.0.113.7"));
+ * });
+ * ```
+ */
+pub async fn audit(pool: &SqlitePool, event: AuditEvent, user_id: Option<i32>) {
+    let record = AuditRecord::new(event, user_id);
+    let sink = AUDIT_SINK.read().unwrap().clone();
+    match sink {
+        Some(sink) => sink.record(record).await,
+        None => DbAuditSink::new(pool.clone()).record(record).await,
+    }
+}
+
+/**
+ * Run a future with the client IP attached to the audit records it creates.
+ *
+ * The built-in auth routes use this so that records include the caller's address.
+ *
+ * # Arguments
+ * - `ip`: The client IP address.
+ * - `future`: The future to run.
+ */
+pub async fn with_client_ip<F: Future>(ip: Option<IpAddr>, future: F) -> F::Output {
+    CLIENT_IP.scope(ip, future).await
+}
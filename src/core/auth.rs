@@ -1,93 +1,1812 @@
-use crate::core::user::{LoginResponse, User};
-use bcrypt::{hash, verify};
-use jsonwebtoken::{encode, Header, EncodingKey};
+use crate::core::user::{LoginResponse, Privilege, PrivilegeLevel, User};
+use crate::core::audit::{audit, AuditEvent};
+use crate::core::metrics::{self, LoginFailure, TokenFailure};
+use crate::core::db::{classify_db_error, db_error_response};
+use crate::core::response::json_error;
+use crate::core::cache::session_cookie;
+use crate::core::password::{default_hasher, hasher_for, PasswordHashError};
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, ResponseError};
+use jsonwebtoken::{encode, errors::ErrorKind, Algorithm, Header, EncodingKey, Validation};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use futures_util::future::{ready, FutureExt, LocalBoxFuture};
 use std::env;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use sqlx::Row;
 
-#[derive(Serialize, Deserialize)]
+/// JWT secret set with `set_jwt_secret`, taking precedence over the `JWT_SECRET` environment variable.
+static JWT_SECRET_OVERRIDE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Issuer put in and required of JWTs, set with `set_jwt_issuer`.
+static JWT_ISSUER: RwLock<Option<String>> = RwLock::new(None);
+
+/// Audience put in and required of JWTs, set with `set_jwt_audience`.
+static JWT_AUDIENCE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Default clock-skew leeway for JWT expiry, matching `jsonwebtoken`'s default.
+pub const DEFAULT_JWT_LEEWAY: Duration = Duration::from_secs(60);
+
+/// Clock-skew leeway for JWT expiry in seconds, set with `set_jwt_leeway`.
+static JWT_LEEWAY_SECS: AtomicU64 = AtomicU64::new(DEFAULT_JWT_LEEWAY.as_secs());
+
+/// Default time after login during which tokens can be refreshed.
+pub const DEFAULT_JWT_MAX_SESSION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Time after login during which tokens can be refreshed in seconds, set with `set_jwt_max_session`.
+static JWT_MAX_SESSION_SECS: AtomicU64 = AtomicU64::new(DEFAULT_JWT_MAX_SESSION.as_secs());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: i32,
     pub exp: usize,
+    #[serde(default)]
+    pub privilege_level: i32,
+    /// The issuer of the token, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// The audiences the token is intended for. A single audience may be sent as a plain string.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub aud: Vec<String>,
+    /// When the user logged in, as a Unix timestamp. Kept when the token is refreshed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>,
+}
+
+impl Claims {
+    /**
+     * Get the privilege level stored in the token as the given `Privilege` type.
+     *
+     * # Returns
+     * The mapped privilege, or `None` if the level is unknown to `P`.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Claims, PrivilegeLevel};
+     *
+     * let claims = Claims { sub: 1, privilege_level: 1, ..Default::default() };
+     * assert_eq!(claims.privilege::<PrivilegeLevel>(), Some(PrivilegeLevel::Admin));
+     * ```
+     */
+    pub fn privilege<P: Privilege>(&self) -> Option<P> {
+        P::from_level(self.privilege_level)
+    }
+}
+
+/// Deserialize a claim that may be a single string or an array of strings.
+pub(crate) fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// Errors that can occur when changing a user's privilege level.
+#[derive(Debug)]
+pub enum PrivilegeError {
+    /// No user exists with the given ID.
+    UserNotFound,
+    /// The change would leave the system without any enabled admin.
+    LastAdmin,
+    /// The underlying database query failed.
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for PrivilegeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrivilegeError::UserNotFound => write!(f, "User not found"),
+            PrivilegeError::LastAdmin => write!(f, "Cannot demote or disable the last remaining admin"),
+            PrivilegeError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+/// Errors that can occur when seeding the admin user with `seed_admin`.
+#[derive(Debug)]
+pub enum SeedAdminError {
+    /// The admin password could not be hashed.
+    Hashing(PasswordHashError),
+    /// The underlying database query failed.
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for SeedAdminError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeedAdminError::Hashing(e) => write!(f, "Failed to hash the admin password: {}", e),
+            SeedAdminError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SeedAdminError {}
+
+/// Errors that can occur when generating a JWT.
+#[derive(Debug)]
+pub enum JwtError {
+    /// Neither `set_jwt_secret` was called nor `JWT_SECRET` is set.
+    MissingSecret,
+    /// The claims could not be encoded or signed.
+    Encoding(jsonwebtoken::errors::Error),
+}
+
+impl std::fmt::Display for JwtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JwtError::MissingSecret => write!(f, "JWT_SECRET must be set"),
+            JwtError::Encoding(e) => write!(f, "Failed to encode JWT: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
+
+/// Errors that can occur when registering or logging in a user.
+#[derive(Debug)]
+pub enum AuthError {
+    /// A user with the given username already exists.
+    UsernameTaken,
+    /// No user exists with the given username, or the password does not match.
+    /// Both cases share one error so clients cannot tell which usernames exist.
+    InvalidCredentials,
+    /// The account has been disabled by an admin.
+    AccountDisabled,
+    /// The username was refused by `check_username`, with the reason.
+    InvalidUsername(String),
+    /// The password is longer than the configured hasher accepts, in bytes.
+    PasswordTooLong(usize),
+    /// The password could not be hashed.
+    Hashing(String),
+    /// The login token could not be generated.
+    Token(JwtError),
+    /// The underlying database query failed.
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::UsernameTaken => write!(f, "Username already taken"),
+            AuthError::InvalidCredentials => write!(f, "Invalid username or password"),
+            AuthError::AccountDisabled => write!(f, "Account is disabled"),
+            AuthError::InvalidUsername(reason) => write!(f, "{}", reason),
+            AuthError::PasswordTooLong(max) => write!(f, "Password must be at most {} bytes", max),
+            AuthError::Hashing(e) => write!(f, "Password hashing failed: {}", e),
+            AuthError::Token(e) => write!(f, "Token generation failed: {}", e),
+            AuthError::Database(e) => write!(f, "{}", classify_db_error(e).1),
+        }
+    }
+}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::UsernameTaken => StatusCode::CONFLICT,
+            AuthError::AccountDisabled => StatusCode::FORBIDDEN,
+            AuthError::InvalidCredentials | AuthError::InvalidUsername(_) | AuthError::PasswordTooLong(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            AuthError::Hashing(_) | AuthError::Token(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AuthError::Database(e) => classify_db_error(e).0,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            AuthError::Database(e) => db_error_response(e),
+            _ => json_error(self.status_code(), self.to_string()),
+        }
+    }
+}
+
+/// A row of a bulk registration that could not be imported.
+#[derive(Debug, Serialize)]
+pub struct BulkRowError {
+    /// The position of the row in the input, starting at `0`.
+    pub index: usize,
+    /// The username of the row.
+    pub username: String,
+    /// Why the row could not be imported, e.g. `Username already taken`.
+    pub error: String,
 }
 
-pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
-    hash(password, 12)
+/// Errors that can occur when registering users in bulk.
+#[derive(Debug)]
+pub enum BulkError {
+    /// One or more rows could not be imported, so no user was registered.
+    Rows(Vec<BulkRowError>),
+    /// The password hashing task failed.
+    Hashing(String),
+    /// The transaction could not be started or committed.
+    Database(sqlx::Error),
 }
 
+impl std::fmt::Display for BulkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BulkError::Rows(rows) => write!(f, "{} row(s) could not be imported, no users were registered", rows.len()),
+            BulkError::Hashing(e) => write!(f, "Password hashing failed: {}", e),
+            BulkError::Database(e) => write!(f, "{}", classify_db_error(e).1),
+        }
+    }
+}
+
+impl ResponseError for BulkError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            BulkError::Rows(_) => StatusCode::BAD_REQUEST,
+            BulkError::Hashing(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BulkError::Database(e) => classify_db_error(e).0,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            BulkError::Rows(rows) => HttpResponse::build(self.status_code())
+                .json(serde_json::json!({ "error": self.to_string(), "failures": rows })),
+            BulkError::Database(e) => db_error_response(e),
+            _ => json_error(self.status_code(), self.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for BulkError {
+    fn from(e: sqlx::Error) -> Self {
+        BulkError::Database(e)
+    }
+}
+
+impl From<sqlx::Error> for AuthError {
+    fn from(e: sqlx::Error) -> Self {
+        AuthError::Database(e)
+    }
+}
+
+impl From<sqlx::Error> for PrivilegeError {
+    fn from(e: sqlx::Error) -> Self {
+        PrivilegeError::Database(e)
+    }
+}
+
+impl From<sqlx::Error> for SeedAdminError {
+    fn from(e: sqlx::Error) -> Self {
+        SeedAdminError::Database(e)
+    }
+}
+
+/**
+ * Hash a password with the configured default hasher (bcrypt unless changed).
+ *
+ * The hash is stored as-is in the `users` table's `password_hash` column, so it
+ * can be used to insert a user by hand. The `rusty-api-hash-password` binary
+ * prints one from the command line. See `crate::core::password` for selecting
+ * another algorithm.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{hash_password, verify_password};
+ *
+ * let hash = hash_password("ChangeMe123").unwrap();
+ * assert!(hash.starts_with("$2b$"));
+ * assert!(verify_password("ChangeMe123", &hash));
+ * ```
+ */
+pub fn hash_password(password: &str) -> Result<String, PasswordHashError> {
+    default_hasher().hash(password)
+}
+
+/// Hash verified by `login_user` when the username is unknown, cached per hasher.
+static DUMMY_PASSWORD_HASH: RwLock<Option<String>> = RwLock::new(None);
+
+/**
+ * Hash the dummy password with the configured hasher, unless already done.
+ *
+ * The `Api` calls this when the user database is initialized, so the first
+ * login with an unknown username does not pay for hashing on top of verifying,
+ * which would reveal that the username is unknown.
+ */
+pub(crate) fn prepare_dummy_password_hash() {
+    dummy_password_hash();
+}
+
+/// Get a hash from the configured hasher to verify against when the user does not exist.
+fn dummy_password_hash() -> Option<String> {
+    let hasher = default_hasher();
+    if let Some(hash) = DUMMY_PASSWORD_HASH.read().unwrap().as_ref().filter(|hash| hasher.recognizes(hash)) {
+        return Some(hash.clone());
+    }
+    let hash = hasher.hash("rusty-api-dummy-password").ok()?;
+    *DUMMY_PASSWORD_HASH.write().unwrap() = Some(hash.clone());
+    Some(hash)
+}
+
+/**
+ * Check that a password is not longer than the configured hasher accepts.
+ *
+ * Bcrypt, the default, ignores everything after the first 72 bytes, so longer
+ * passwords are refused rather than silently truncated.
+ *
+ * # Returns
+ * `Ok(())`, or `AuthError::PasswordTooLong` with the limit in bytes.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::auth::{check_password_length, AuthError};
+ *
+ * assert!(check_password_length("Secret123").is_ok());
+ * assert!(matches!(check_password_length(&"x".repeat(100)), Err(AuthError::PasswordTooLong(72))));
+ * ```
+ */
+pub fn check_password_length(password: &str) -> Result<(), AuthError> {
+    match default_hasher().max_password_bytes() {
+        Some(max) if password.len() > max => Err(AuthError::PasswordTooLong(max)),
+        _ => Ok(()),
+    }
+}
+
+/**
+ * Verify a password against a stored hash.
+ *
+ * The algorithm is detected from the hash prefix, so bcrypt and argon2 hashes
+ * both verify regardless of the configured default.
+ */
 pub fn verify_password(password: &str, hash: &str) -> bool {
-    verify(password, hash).unwrap_or(false)
+    hasher_for(hash).is_some_and(|hasher| hasher.verify(password, hash))
+}
+
+/**
+ * Set the secret used to sign and validate JWTs.
+ *
+ * This overrides the `JWT_SECRET` environment variable for the rest of the
+ * process, which is mainly useful in tests.
+ *
+ * # Arguments
+ * - `secret`: The secret used for HS256 signing.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{generate_test_jwt, set_jwt_secret, Api, HttpRequest, HttpResponse, Method, PrivilegeLevel, Routes};
+ * use actix_web::test;
+ * use std::time::Duration;
+ *
+ * async fn me(_req: HttpRequest, user_id: i32) -> HttpResponse {
+ *     HttpResponse::Ok().body(user_id.to_string())
+ * }
+ *
+ * set_jwt_secret("test-secret");
+ * actix_web::rt::System::new().block_on(async {
+ *     let routes = Routes::new().add_route_with_auth(Method::GET, "/me", me);
+ *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+ *
+ *     let token = generate_test_jwt(7, PrivilegeLevel::User, Duration::from_secs(60));
+ *     let req = test::TestRequest::get()
+ *         .uri("/me")
+ *         .insert_header(("Authorization", format!("Bearer {}", token)))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *     assert_eq!(test::call_and_read_body(&app, req).await, "7");
+ * });
+ * ```
+ */
+pub fn set_jwt_secret(secret: &str) {
+    *JWT_SECRET_OVERRIDE.write().unwrap() = Some(secret.to_string());
+}
+
+/**
+ * Set the issuer of JWTs for the rest of the process.
+ *
+ * Tokens generated afterwards carry this `iss` claim, and `validate_token`
+ * rejects tokens without it. An `Api` set up with `Api::jwt_issuer` uses
+ * its own issuer instead, see `JwtSettings`.
+ *
+ * # Arguments
+ * - `issuer`: The expected issuer, e.g. `"https://auth.example.com"`.
+ */
+pub fn set_jwt_issuer(issuer: &str) {
+    *JWT_ISSUER.write().unwrap() = Some(issuer.to_string());
+}
+
+/**
+ * Set the audience of JWTs for the rest of the process.
+ *
+ * Tokens generated afterwards carry this `aud` claim, and `validate_token`
+ * rejects tokens not intended for it. An `Api` set up with `Api::jwt_audience`
+ * uses its own audience instead, see `JwtSettings`.
+ *
+ * # Arguments
+ * - `audience`: The expected audience, e.g. `"billing-api"`.
+ */
+pub fn set_jwt_audience(audience: &str) {
+    *JWT_AUDIENCE.write().unwrap() = Some(audience.to_string());
+}
+
+/**
+ * Set the clock-skew leeway applied when checking token expiry, for the rest of the process.
+ *
+ * Tokens are accepted until `exp + leeway`, so small clock differences between
+ * the issuing and validating servers do not reject tokens early. The default is
+ * `DEFAULT_JWT_LEEWAY` (60 seconds). An `Api` set up with `Api::jwt_leeway`
+ * uses its own leeway instead, see `JwtSettings`.
+ *
+ * # Arguments
+ * - `leeway`: The leeway, rounded down to whole seconds.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::auth::set_jwt_leeway;
+ * use rusty_api::{set_jwt_secret, validate_token, Claims};
+ * use jsonwebtoken::{encode, EncodingKey, Header};
+ * use std::time::Duration;
+ *
+ * set_jwt_secret("test-secret");
+ * set_jwt_leeway(Duration::from_secs(30));
+ *
+ * let expired_by = |secs: i64| {
+ *     let exp = (chrono::Utc::now().timestamp() - secs) as usize;
+ *     let claims = Claims { sub: 1, exp, ..Default::default() };
+ *     encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test-secret")).unwrap()
+ * };
+ *
+ * assert!(validate_token(&expired_by(5)).is_ok());
+ * assert!(validate_token(&expired_by(120)).is_err());
+ * ```
+ */
+pub fn set_jwt_leeway(leeway: Duration) {
+    JWT_LEEWAY_SECS.store(leeway.as_secs(), Ordering::Relaxed);
+}
+
+/**
+ * Set how long after login tokens can be refreshed, for the rest of the process.
+ *
+ * `refresh_access_token` never extends a session past this window, so a stolen
+ * token cannot be kept alive forever; the user must log in again. The default
+ * is `DEFAULT_JWT_MAX_SESSION` (30 days). An `Api` set up with
+ * `Api::jwt_max_session` uses its own window instead, see `JwtSettings`.
+ *
+ * # Arguments
+ * - `max_session`: The window, rounded down to whole seconds.
+ */
+pub fn set_jwt_max_session(max_session: Duration) {
+    JWT_MAX_SESSION_SECS.store(max_session.as_secs(), Ordering::Relaxed);
+}
+
+/// Get the maximum session length set with `set_jwt_max_session`.
+pub(crate) fn jwt_max_session() -> Duration {
+    Duration::from_secs(JWT_MAX_SESSION_SECS.load(Ordering::Relaxed))
+}
+
+/// Get the issuer set with `set_jwt_issuer`.
+fn jwt_issuer() -> Option<String> {
+    JWT_ISSUER.read().unwrap().clone()
+}
+
+/// Get the audience set with `set_jwt_audience`.
+fn jwt_audience() -> Option<String> {
+    JWT_AUDIENCE.read().unwrap().clone()
+}
+
+/// Get the leeway set with `set_jwt_leeway`.
+pub(crate) fn jwt_leeway() -> Duration {
+    Duration::from_secs(JWT_LEEWAY_SECS.load(Ordering::Relaxed))
+}
+
+/**
+ * The JWT issuer, audience, leeway and maximum session of an `Api`, stored as app data.
+ *
+ * Each `Api` registers its own settings, so several APIs in one process can
+ * issue and accept tokens differently. Settings the `Api` leaves unset are taken
+ * from the process-wide ones set with `set_jwt_issuer`, `set_jwt_audience`,
+ * `set_jwt_leeway` and `set_jwt_max_session`, which the free functions such as
+ * `validate_token` use.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Api, HttpRequest, HttpResponse, Method, PrivilegeLevel, Routes};
+ * use rusty_api::core::auth::JwtSettings;
+ * use actix_web::test;
+ *
+ * async fn me(_req: HttpRequest, user_id: i32) -> HttpResponse {
+ *     HttpResponse::Ok().body(user_id.to_string())
+ * }
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     rusty_api::set_jwt_secret("test-secret");
+ *     let routes = Routes::new().add_route_with_auth(Method::GET, "/me", me);
+ *     let api = Api::new().configure_routes(routes).jwt_issuer("https://billing.example.com");
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let me = |token: &str| test::TestRequest::get()
+ *         .uri("/me")
+ *         .insert_header(("Authorization", format!("Bearer {}", token)))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *
+ *     let settings = JwtSettings { issuer: Some("https://billing.example.com".into()), ..JwtSettings::global() };
+ *     let user = rusty_api::core::user::User { id: 7, ..Default::default() };
+ *     assert_eq!(test::call_service(&app, me(&settings.generate(&user).unwrap())).await.status(), 200);
+ *
+ *     // Tokens of the process-wide settings lack the issuer this API requires
+ *     let token = rusty_api::generate_test_jwt(7, PrivilegeLevel::User, std::time::Duration::from_secs(60));
+ *     assert_eq!(test::call_service(&app, me(&token)).await.status(), 401);
+ *     assert!(JwtSettings::global().issuer.is_none());
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtSettings {
+    /// Issuer put in and required of tokens.
+    pub issuer: Option<String>,
+    /// Audience put in and required of tokens.
+    pub audience: Option<String>,
+    /// Clock-skew leeway applied when checking token expiry.
+    pub leeway: Duration,
+    /// Time after login during which tokens can be refreshed.
+    pub max_session: Duration,
+}
+
+impl JwtSettings {
+    /// Get the process-wide settings made with the `set_jwt_*` functions.
+    pub fn global() -> Self {
+        Self {
+            issuer: jwt_issuer(),
+            audience: jwt_audience(),
+            leeway: jwt_leeway(),
+            max_session: jwt_max_session(),
+        }
+    }
+
+    /// Get the settings registered by the request's `Api`, or the process-wide ones.
+    pub fn of(req: &HttpRequest) -> Self {
+        req.app_data::<JwtSettings>().cloned().unwrap_or_else(Self::global)
+    }
+
+    /// Validate a token like `validate_token`, with these settings.
+    pub fn validate(&self, token: &str) -> Result<Claims, actix_web::Error> {
+        decode_token(token, &claims_validation(Algorithm::HS256, self.issuer.as_deref(), self.audience.as_deref(), self.leeway))
+    }
+
+    /// Generate a token for the user like `generate_jwt`, with these settings.
+    pub fn generate(&self, user: &User) -> Result<String, JwtError> {
+        let now = chrono::Utc::now().timestamp() as usize;
+        let exp = (chrono::Utc::now() + chrono::Duration::days(JWT_TTL_DAYS)).timestamp() as usize;
+        encode_claims(&self.user_claims(user, exp, now))
+    }
+
+    /// Re-issue a token like `refresh_access_token`, with these settings.
+    pub fn refresh(&self, claims: &Claims, user: &User) -> Result<String, actix_web::Error> {
+        let now = chrono::Utc::now().timestamp() as usize;
+        if claims.exp <= now {
+            return Err(actix_web::error::ErrorUnauthorized("Token expired"));
+        }
+        let session_end = claims
+            .auth_time
+            .map(|auth_time| auth_time.saturating_add(self.max_session.as_secs() as usize))
+            .unwrap_or(0);
+        if session_end <= now {
+            return Err(actix_web::error::ErrorUnauthorized("Session expired, please log in again"));
+        }
+
+        let exp = (chrono::Utc::now() + chrono::Duration::days(JWT_TTL_DAYS)).timestamp() as usize;
+        let auth_time = claims.auth_time.unwrap_or(now);
+        encode_claims(&self.user_claims(user, exp.min(session_end), auth_time))
+            .map_err(actix_web::error::ErrorInternalServerError)
+    }
+
+    /// Build the claims of a token for the user, expiring at `exp` and logged in at `auth_time`.
+    fn user_claims(&self, user: &User, exp: usize, auth_time: usize) -> Claims {
+        Claims {
+            sub: user.id,
+            exp,
+            privilege_level: user.privilege_level,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone().into_iter().collect(),
+            auth_time: Some(auth_time),
+        }
+    }
 }
 
-pub fn generate_jwt(user: &User) -> String {
+/**
+ * Build the `Validation` for the given algorithm, requiring the issuer and audience if set.
+ *
+ * The leeway is applied to the expiry check. Without an audience, the
+ * `jsonwebtoken` default applies: tokens carrying an `aud` claim are rejected.
+ */
+pub(crate) fn claims_validation(algorithm: Algorithm, issuer: Option<&str>, audience: Option<&str>, leeway: Duration) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    validation.leeway = leeway.as_secs();
+    if let Some(issuer) = issuer {
+        validation.set_issuer(&[issuer]);
+        validation.required_spec_claims.insert("iss".to_string());
+    }
+    if let Some(audience) = audience {
+        validation.set_audience(&[audience]);
+        validation.required_spec_claims.insert("aud".to_string());
+    }
+    validation
+}
+
+/// Get the JWT secret, preferring the one set with `set_jwt_secret` over `JWT_SECRET`.
+fn jwt_secret() -> Result<String, JwtError> {
+    if let Some(secret) = JWT_SECRET_OVERRIDE.read().unwrap().as_ref() {
+        return Ok(secret.clone());
+    }
+    env::var("JWT_SECRET").map_err(|_| JwtError::MissingSecret)
+}
+
+/**
+ * Generate a JWT for the given user, valid for 7 days.
+ *
+ * # Returns
+ * The encoded JWT, or a `JwtError` if no secret is configured or encoding fails.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::auth::generate_jwt;
+ * use rusty_api::core::user::User;
+ * use rusty_api::{set_jwt_secret, validate_token};
+ *
+ * set_jwt_secret("test-secret");
+ * let user = User { id: 3, username: "alice".into(), ..Default::default() };
+ * let token = generate_jwt(&user).unwrap();
+ * assert_eq!(validate_token(&token).unwrap().sub, 3);
+ * ```
+ */
+pub fn generate_jwt(user: &User) -> Result<String, JwtError> {
+    JwtSettings::global().generate(user)
+}
+
+/**
+ * Re-issue a still-valid token for the user with a new expiry, for sliding sessions.
+ *
+ * The new token is built from the freshly loaded `user`, so privilege changes
+ * since login take effect, and keeps the login time of the current token. It
+ * expires 7 days from now, but never later than the maximum session set with
+ * `set_jwt_max_session` after login. Expired tokens, even within the leeway,
+ * and tokens past the maximum session are rejected: the user must log in again.
+ *
+ * # Arguments
+ * - `claims`: The validated claims of the current token.
+ * - `user`: The user the token belongs to, as currently stored.
+ *
+ * # Returns
+ * The new token, or an `Unauthorized` error if the token cannot be refreshed.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::auth::{refresh_access_token, set_jwt_max_session};
+ * use rusty_api::core::user::User;
+ * use rusty_api::{generate_test_jwt, set_jwt_secret, validate_token, PrivilegeLevel};
+ * use std::time::Duration;
+ *
+ * set_jwt_secret("test-secret");
+ * let claims = validate_token(&generate_test_jwt(7, PrivilegeLevel::Admin, Duration::from_secs(60))).unwrap();
+ * let demoted = User { id: 7, privilege_level: PrivilegeLevel::User as i32, ..User::default() };
+ *
+ * let refreshed = validate_token(&refresh_access_token(&claims, &demoted).unwrap()).unwrap();
+ * assert_eq!(refreshed.sub, 7);
+ * assert_eq!(refreshed.privilege_level, PrivilegeLevel::User as i32);
+ * assert_eq!(refreshed.auth_time, claims.auth_time);
+ * assert!(refreshed.exp > claims.exp);
+ *
+ * set_jwt_max_session(Duration::ZERO);
+ * assert!(refresh_access_token(&claims, &demoted).is_err());
+ * ```
+ */
+pub fn refresh_access_token(claims: &Claims, user: &User) -> Result<String, actix_web::Error> {
+    JwtSettings::global().refresh(claims, user)
+}
+
+/**
+ * Generate a valid JWT for the given user ID and privilege level without a database.
+ *
+ * This is intended for testing routes added with `add_route_with_auth` or other
+ * token-protected routes. The token is signed with the same secret as `generate_jwt`.
+ *
+ * # Arguments
+ * - `user_id`: The user ID stored in the `sub` claim.
+ * - `privilege`: The privilege level stored in the `privilege_level` claim.
+ * - `ttl`: How long the token stays valid.
+ *
+ * # Returns
+ * The encoded JWT.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{generate_test_jwt, set_jwt_secret, validate_token, PrivilegeLevel};
+ * use std::time::Duration;
+ *
+ * set_jwt_secret("test-secret");
+ * let token = generate_test_jwt(42, PrivilegeLevel::Admin, Duration::from_secs(300));
+ *
+ * let claims = validate_token(&token).unwrap();
+ * assert_eq!(claims.sub, 42);
+ * assert_eq!(claims.privilege_level, 1);
+ *
+ * // TTLs past the representable range are clamped rather than panicking
+ * let forever = generate_test_jwt(42, PrivilegeLevel::User, Duration::MAX);
+ * assert!(validate_token(&forever).is_ok());
+ * ```
+ */
+pub fn generate_test_jwt(user_id: i32, privilege: impl Privilege, ttl: std::time::Duration) -> String {
+    let now = chrono::Utc::now();
+    // Clamp huge TTLs to the latest representable time instead of overflowing
+    let exp = chrono::Duration::from_std(ttl)
+        .ok()
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC);
     let claims = Claims {
-        sub: user.id,
-        exp: (chrono::Utc::now() + chrono::Duration::days(7)).timestamp() as usize,
+        sub: user_id,
+        exp: exp.timestamp() as usize,
+        privilege_level: privilege.level(),
+        iss: jwt_issuer(),
+        aud: jwt_audience().into_iter().collect(),
+        auth_time: Some(now.timestamp() as usize),
     };
-    let secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref())).unwrap()
+    encode_claims(&claims).expect("Failed to generate test JWT")
+}
+
+/// Sign the claims with the configured JWT secret.
+fn encode_claims(claims: &Claims) -> Result<String, JwtError> {
+    let secret = jwt_secret()?;
+    encode(&Header::default(), claims, &EncodingKey::from_secret(secret.as_ref())).map_err(JwtError::Encoding)
 }
 
+/// Check whether a database error is a UNIQUE or PRIMARY KEY constraint violation.
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    matches!(e, sqlx::Error::Database(db_err) if db_err.is_unique_violation())
+}
+
+/**
+ * Register a new user.
+ *
+ * The password is hashed before being stored. Registering a username that is
+ * already taken fails with `AuthError::UsernameTaken`, which is returned to
+ * clients as a `409 Conflict`. Usernames refused by `user::check_username` fail
+ * with `AuthError::InvalidUsername`, a `400 Bad Request`. Passwords longer than the hasher accepts (72 bytes
+ * for bcrypt) fail with `AuthError::PasswordTooLong`, a `400 Bad Request`, so two
+ * long passwords sharing their first 72 bytes can never collide.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(Api::new().in_memory_db().test_app().await).await;
+ *     let register = || test::TestRequest::post()
+ *         .uri("/register")
+ *         .set_json(serde_json::json!({ "username": "alice", "password": "Secret123" }))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *
+ *     assert_eq!(test::call_service(&app, register()).await.status(), 201);
+ *
+ *     let response = test::call_service(&app, register()).await;
+ *     assert_eq!(response.status(), 409);
+ *     let body: serde_json::Value = test::read_body_json(response).await;
+ *     assert_eq!(body["error"], "Username already taken");
+ *
+ *     let long_password = "p".repeat(100);
+ *     let request = test::TestRequest::post()
+ *         .uri("/register")
+ *         .set_json(serde_json::json!({ "username": "bob", "password": long_password }))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *     let response = test::call_service(&app, request).await;
+ *     assert_eq!(response.status(), 400);
+ *     let body: serde_json::Value = test::read_body_json(response).await;
+ *     assert_eq!(body["error"], "Password must be at most 72 bytes");
+ *
+ *     let request = test::TestRequest::post()
+ *         .uri("/register")
+ *         .set_json(serde_json::json!({ "username": " bob", "password": "Secret123" }))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *     assert_eq!(test::call_service(&app, request).await.status(), 400);
+ * });
+ * ```
+ */
 pub async fn register_user(
     pool: &sqlx::SqlitePool,
     input: crate::core::user::RegisterInput,
-) -> Result<User, String> {
+) -> Result<User, AuthError> {
+    crate::core::user::check_username(&input.username).map_err(AuthError::InvalidUsername)?;
+
     // Hash password
-    let password_hash = hash_password(&input.password).map_err(|e| e.to_string())?;
+    check_password_length(&input.password)?;
+    let password_hash = hash_password(&input.password).map_err(|e| AuthError::Hashing(e.to_string()))?;
     
     // Insert user
     let user = sqlx::query_as::<_, User>(
-        "INSERT INTO users (username, password_hash) VALUES (?, ?) RETURNING id, username, password_hash"
+        "INSERT INTO users (username, password_hash, created_at, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+         RETURNING id, username, password_hash, privilege_level, created_at, updated_at, enabled"
     )
     .bind(&input.username)
     .bind(&password_hash)
     .fetch_one(pool)
     .await
-    .map_err(|e| format!("Database error: {}", e))?;
-    
+    .map_err(|e| if is_unique_violation(&e) { AuthError::UsernameTaken } else { AuthError::Database(e) })?;
+
+    audit(pool, AuditEvent::Register, Some(user.id)).await;
+    metrics::record_registrations(1);
     Ok(user)
 }
 
+/**
+ * Register many users at once, e.g. when migrating from another system.
+ *
+ * Passwords are hashed in parallel on a blocking thread, then all users are
+ * inserted in a single transaction. Every row is attempted, so the error lists
+ * all failing rows (e.g. duplicate usernames, within the input or with existing
+ * users) with their position. If any row fails, the transaction is rolled back
+ * and no user is registered, so the import can be fixed and retried as a whole.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `inputs`: The users to register.
+ *
+ * # Returns
+ * The registered users in input order, or a `BulkError` listing the failing rows.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{register_user, register_users_bulk, BulkError};
+ * use rusty_api::core::user::RegisterInput;
+ *
+ * let input = |username: &str| RegisterInput { username: username.into(), password: "Secret123".into() };
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *     register_user(&pool, input("alice")).await.unwrap();
+ *
+ *     match register_users_bulk(&pool, vec![input("bob"), input("alice"), input("carol")]).await {
+ *         Err(BulkError::Rows(rows)) => {
+ *             assert_eq!(rows.len(), 1);
+ *             assert_eq!((rows[0].index, rows[0].username.as_str()), (1, "alice"));
+ *         }
+ *         _ => panic!("expected a failing row"),
+ *     }
+ *
+ *     let users = register_users_bulk(&pool, vec![input("bob"), input("carol")]).await.unwrap();
+ *     assert_eq!(users.len(), 2);
+ *     assert_eq!(users[1].username, "carol");
+ * });
+ * ```
+ */
+pub async fn register_users_bulk(
+    pool: &sqlx::SqlitePool,
+    inputs: Vec<crate::core::user::RegisterInput>,
+) -> Result<Vec<User>, BulkError> {
+    let passwords: Vec<String> = inputs.iter().map(|input| input.password.clone()).collect();
+    let hashes = actix_web::rt::task::spawn_blocking(move || hash_passwords(&passwords))
+        .await
+        .map_err(|e| BulkError::Hashing(e.to_string()))?;
+
+    let mut tx = pool.begin().await?;
+    let mut users = Vec::with_capacity(inputs.len());
+    let mut failures = Vec::new();
+    for (index, (input, hash)) in inputs.into_iter().zip(hashes).enumerate() {
+        let result = if let Err(reason) = crate::core::user::check_username(&input.username) {
+            Err(AuthError::InvalidUsername(reason))
+        } else {
+            match hash {
+                Ok(password_hash) => sqlx::query_as::<_, User>(
+                    "INSERT INTO users (username, password_hash, created_at, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                     RETURNING id, username, password_hash, privilege_level, created_at, updated_at, enabled"
+                )
+                .bind(&input.username)
+                .bind(&password_hash)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| if is_unique_violation(&e) { AuthError::UsernameTaken } else { AuthError::Database(e) }),
+                Err(e) => Err(check_password_length(&input.password).err().unwrap_or(AuthError::Hashing(e.to_string()))),
+            }
+        };
+
+        match result {
+            Ok(user) => users.push(user),
+            Err(e) => failures.push(BulkRowError { index, username: input.username, error: e.to_string() }),
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(BulkError::Rows(failures));
+    }
+    tx.commit().await?;
+
+    for user in &users {
+        audit(pool, AuditEvent::Register, Some(user.id)).await;
+    }
+    metrics::record_registrations(users.len() as u64);
+    Ok(users)
+}
+
+/// Hash passwords in parallel, one chunk per available CPU.
+fn hash_passwords(passwords: &[String]) -> Vec<Result<String, PasswordHashError>> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = passwords.len().div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = passwords
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|password| hash_password(password)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+/**
+ * Check a user's current password without logging them in.
+ *
+ * This is intended for step-up authentication, e.g. confirming a sensitive
+ * action. No token is issued and no audit record is written.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user.
+ * - `password`: The password to check.
+ *
+ * # Returns
+ * `true` if the password matches, `false` if it does not or the user does not exist.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{register_user, verify_user_password};
+ * use rusty_api::core::user::RegisterInput;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *
+ *     assert!(verify_user_password(&pool, user.id, "Secret123").await.unwrap());
+ *     assert!(!verify_user_password(&pool, user.id, "Guess").await.unwrap());
+ *     assert!(!verify_user_password(&pool, user.id + 1, "Secret123").await.unwrap());
+ * });
+ * ```
+ */
+pub async fn verify_user_password(pool: &sqlx::SqlitePool, user_id: i32, password: &str) -> Result<bool, sqlx::Error> {
+    let hash: Option<(String,)> = sqlx::query_as("SELECT password_hash FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(hash.is_some_and(|(hash,)| verify_password(password, &hash)))
+}
+
+/**
+ * Log a user in, returning a JWT.
+ *
+ * An unknown username and a wrong password both fail with
+ * `AuthError::InvalidCredentials`, and a dummy hash is verified for unknown
+ * usernames so both take about as long, so clients cannot enumerate usernames.
+ * The audit log and `core::metrics` still record the specific reason.
+ *
+ * # Returns
+ * The token and the user, or an `AuthError`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{login_user, register_user};
+ * use rusty_api::core::user::{LoginInput, RegisterInput};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     rusty_api::set_jwt_secret("test-secret");
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *     register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *     let login = |username: &str, password: &str| LoginInput { username: username.into(), password: password.into() };
+ *
+ *     assert!(login_user(&pool, login("alice", "Secret123")).await.is_ok());
+ *     let unknown = login_user(&pool, login("mallory", "Secret123")).await.err().unwrap();
+ *     let wrong = login_user(&pool, login("alice", "Guess")).await.err().unwrap();
+ *     assert_eq!(unknown.to_string(), "Invalid username or password");
+ *     assert_eq!(unknown.to_string(), wrong.to_string());
+ * });
+ * ```
+ */
 pub async fn login_user(
     pool: &sqlx::SqlitePool,
     input: crate::core::user::LoginInput,
-) -> Result<LoginResponse, String> {
+) -> Result<LoginResponse, AuthError> {
+    login_user_with(pool, input, &JwtSettings::global()).await
+}
+
+/// Log a user in like `login_user`, issuing the JWT with the given settings.
+pub(crate) async fn login_user_with(
+    pool: &sqlx::SqlitePool,
+    input: crate::core::user::LoginInput,
+    settings: &JwtSettings,
+) -> Result<LoginResponse, AuthError> {
+    let user = authenticate_login(pool, &input).await?;
+
+    // Generate JWT
+    let token = settings.generate(&user).map_err(AuthError::Token)?;
+    audit(pool, AuditEvent::Login, Some(user.id)).await;
+    metrics::record_login();
+    Ok(LoginResponse { token, user: Some(user) })
+}
+
+/**
+ * Log a user in, returning a server-side session ID instead of a JWT.
+ *
+ * The credentials are checked like in `login_user`, then a session is created
+ * with `core::session::create_session`. No JWT secret is needed. This is used
+ * by the login route when `Api::token_mode` is `TokenMode::Session`.
+ *
+ * # Returns
+ * The session ID as the token and the user, or an `AuthError`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{login_user_with_session, register_user};
+ * use rusty_api::core::session::validate_session;
+ * use rusty_api::core::user::{LoginInput, RegisterInput};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *
+ *     let login = login_user_with_session(&pool, LoginInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *     assert_eq!(validate_session(&pool, &login.token).await.unwrap().unwrap().sub, user.id);
+ * });
+ * ```
+ */
+pub async fn login_user_with_session(
+    pool: &sqlx::SqlitePool,
+    input: crate::core::user::LoginInput,
+) -> Result<LoginResponse, AuthError> {
+    login_user_with_session_capped(pool, input, jwt_max_session()).await
+}
+
+/// Log a user in like `login_user_with_session`, capping the session at `max_session` after login.
+pub(crate) async fn login_user_with_session_capped(
+    pool: &sqlx::SqlitePool,
+    input: crate::core::user::LoginInput,
+    max_session: Duration,
+) -> Result<LoginResponse, AuthError> {
+    let user = authenticate_login(pool, &input).await?;
+    let token = crate::core::session::create_session_capped(pool, user.id, max_session).await?;
+    audit(pool, AuditEvent::Login, Some(user.id)).await;
+    metrics::record_login();
+    Ok(LoginResponse { token, user: Some(user) })
+}
+
+/// Check the login credentials, auditing failed attempts.
+async fn authenticate_login(
+    pool: &sqlx::SqlitePool,
+    input: &crate::core::user::LoginInput,
+) -> Result<User, AuthError> {
     // Find user
-    let row = sqlx::query("SELECT id, username, password_hash FROM users WHERE username = ?")
+    let Some(row) = sqlx::query("SELECT id, username, password_hash, privilege_level, created_at, updated_at, enabled FROM users WHERE username = ?")
         .bind(&input.username)
         .fetch_optional(pool)
-        .await
-        .map_err(|e| format!("Database error: {}", e))?
-        .ok_or("User not found")?;
+        .await?
+    else {
+        // Spend as long as for a wrong password, so timing does not reveal the username is unknown
+        if let Some(hash) = dummy_password_hash() {
+            verify_password(&input.password, &hash);
+        }
+        audit(pool, AuditEvent::LoginFailed, None).await;
+        metrics::record_login_failure(LoginFailure::UserNotFound);
+        return Err(AuthError::InvalidCredentials);
+    };
 
     let user = User {
         id: row.get("id"),
         username: row.get("username"),
         password_hash: row.get("password_hash"),
+        privilege_level: row.get("privilege_level"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        enabled: row.get("enabled"),
     };
     
     // Verify password
     if !verify_password(&input.password, &user.password_hash) {
-        return Err("Invalid password".to_string());
+        audit(pool, AuditEvent::LoginFailed, Some(user.id)).await;
+        metrics::record_login_failure(LoginFailure::InvalidPassword);
+        return Err(AuthError::InvalidCredentials);
+    }
+
+    // Only reveal the account status to clients that know the password
+    if !user.enabled {
+        audit(pool, AuditEvent::LoginFailed, Some(user.id)).await;
+        metrics::record_login_failure(LoginFailure::AccountDisabled);
+        return Err(AuthError::AccountDisabled);
+    }
+    Ok(user)
+}
+
+/**
+ * Create an admin user if no user with the given username exists yet.
+ *
+ * This function is idempotent, so it is safe to call on every startup. The
+ * password is hashed with `hash_password` before being stored. An existing
+ * user with the same username is left untouched.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `username`: The username of the admin user.
+ * - `password`: The plaintext password of the admin user.
+ *
+ * # Returns
+ * `Ok(true)` if the admin user was created, `Ok(false)` if the username already existed,
+ * or a `SeedAdminError` if the password cannot be hashed or a query fails.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{get_user_by_id, seed_admin, SeedAdminError};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *
+ *     assert!(seed_admin(&pool, "admin", "Secret123").await.unwrap());
+ *     assert!(!seed_admin(&pool, "admin", "Other123").await.unwrap());
+ *     let admin = get_user_by_id(&pool, 1).await.unwrap().unwrap();
+ *     assert_eq!(admin.privilege_level, 1);
+ *     assert!(admin.created_at.is_some() && admin.updated_at.is_some());
+ *
+ *     let too_long = "x".repeat(100);
+ *     let result = seed_admin(&pool, "root", &too_long).await;
+ *     assert!(matches!(result, Err(SeedAdminError::Hashing(_))));
+ * });
+ * ```
+ */
+pub async fn seed_admin(
+    pool: &sqlx::SqlitePool,
+    username: &str,
+    password: &str,
+) -> Result<bool, SeedAdminError> {
+    let existing = sqlx::query("SELECT id FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(pool)
+        .await?;
+    if existing.is_some() {
+        return Ok(false);
+    }
+
+    let password_hash = hash_password(password).map_err(SeedAdminError::Hashing)?;
+    let result = sqlx::query(
+        "INSERT OR IGNORE INTO users (username, password_hash, privilege_level, created_at, updated_at)
+         VALUES (?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)"
+    )
+    .bind(username)
+    .bind(&password_hash)
+    .bind(PrivilegeLevel::Admin.level())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/**
+ * Get a user by ID.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user to look up.
+ *
+ * # Returns
+ * The user, or `None` if no user exists with the given ID, or a `sqlx::Error`
+ * if the query fails.
+ */
+pub async fn get_user_by_id(pool: &sqlx::SqlitePool, user_id: i32) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>("SELECT id, username, password_hash, privilege_level, created_at, updated_at, enabled FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/**
+ * Generate a new API key for a user, replacing the previous one.
+ *
+ * The key is a random UUID. Only its hash is stored, so the key is returned only
+ * once and callers must hand it to the user straight away. The previous key stops
+ * working as soon as this returns.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user whose key should be rotated.
+ *
+ * # Returns
+ * The new API key, or `sqlx::Error::RowNotFound` if the user does not exist.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::auth::{get_user_by_api_key, register_user, rotate_api_key};
+ * use rusty_api::core::db::{init_db_with_url, IN_MEMORY_DATABASE_URL};
+ * use rusty_api::core::user::RegisterInput;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let pool = init_db_with_url(IN_MEMORY_DATABASE_URL).await.unwrap();
+ *     let input = RegisterInput { username: "alice".into(), password: "Secret123".into() };
+ *     let user = register_user(&pool, input).await.unwrap();
+ *
+ *     let old_key = rotate_api_key(&pool, user.id).await.unwrap();
+ *     let new_key = rotate_api_key(&pool, user.id).await.unwrap();
+ *
+ *     assert!(get_user_by_api_key(&pool, &old_key).await.unwrap().is_none());
+ *     assert_eq!(get_user_by_api_key(&pool, &new_key).await.unwrap().unwrap().id, user.id);
+ * });
+ * ```
+ */
+pub async fn rotate_api_key(pool: &sqlx::SqlitePool, user_id: i32) -> Result<String, sqlx::Error> {
+    let api_key = uuid::Uuid::new_v4().to_string();
+
+    let result = sqlx::query("UPDATE users SET api_key_hash = ? WHERE id = ?")
+        .bind(hash_api_key(&api_key))
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    audit(pool, AuditEvent::ApiKeyRotated, Some(user_id)).await;
+    Ok(api_key)
+}
+
+/**
+ * Hash an API key for storage and lookup.
+ *
+ * API keys are random and high-entropy, so a fast SHA-256 digest is enough to
+ * keep a database leak from exposing usable keys.
+ *
+ * # Returns
+ * The hex-encoded SHA-256 digest of the key.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::auth::hash_api_key;
+ *
+ * let hash = hash_api_key("my-key");
+ * assert_eq!(hash.len(), 64);
+ * assert_eq!(hash, hash_api_key("my-key"));
+ * ```
+ */
+pub fn hash_api_key(api_key: &str) -> String {
+    Sha256::digest(api_key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/**
+ * Get the user owning an API key.
+ *
+ * The key is hashed with `hash_api_key` before the lookup.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `api_key`: The API key sent by the client.
+ *
+ * # Returns
+ * The user, or `None` if the key is unknown or has been rotated.
+ */
+pub async fn get_user_by_api_key(pool: &sqlx::SqlitePool, api_key: &str) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>("SELECT id, username, password_hash, privilege_level, created_at, updated_at, enabled FROM users WHERE api_key_hash = ?")
+        .bind(hash_api_key(api_key))
+        .fetch_optional(pool)
+        .await
+}
+
+/**
+ * Change a user's privilege level.
+ *
+ * The check and the update run in a single transaction. Demoting the last
+ * remaining admin is refused so the system can never be locked out of its
+ * admin routes. Any level at or above `PrivilegeLevel::Admin` counts as an admin.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user to update.
+ * - `level`: The new privilege level.
+ *
+ * # Returns
+ * The updated user, or a `PrivilegeError` describing why the change was refused.
+ */
+pub async fn set_privilege(
+    pool: &sqlx::SqlitePool,
+    user_id: i32,
+    level: impl Privilege,
+) -> Result<User, PrivilegeError> {
+    let admin = PrivilegeLevel::Admin.level();
+    let mut tx = pool.begin().await?;
+
+    let (current, enabled): (i32, bool) = sqlx::query_as("SELECT privilege_level, enabled FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(PrivilegeError::UserNotFound)?;
+
+    if enabled && current >= admin && level.level() < admin && enabled_admins(&mut tx).await? <= 1 {
+        return Err(PrivilegeError::LastAdmin);
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET privilege_level = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ? RETURNING id, username, password_hash, privilege_level, created_at, updated_at, enabled"
+    )
+    .bind(level.level())
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    audit(pool, AuditEvent::PrivilegeChanged, Some(user.id)).await;
+    Ok(user)
+}
+
+/// Count the enabled users at or above `PrivilegeLevel::Admin`.
+async fn enabled_admins(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>) -> Result<i64, sqlx::Error> {
+    let (admins,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE privilege_level >= ? AND enabled = 1")
+        .bind(PrivilegeLevel::Admin.level())
+        .fetch_one(&mut **tx)
+        .await?;
+    Ok(admins)
+}
+
+/**
+ * Enable or disable a user account.
+ *
+ * Disabled accounts are kept, but `login_user` refuses them with
+ * `AuthError::AccountDisabled` and `require_privilege` answers their tokens and
+ * API keys with a `403 Forbidden`. Tokens are not revoked: routes checking only
+ * the token's claims, such as those added with `Routes::add_route_with_auth`,
+ * keep accepting them until they expire, unless `Api::token_mode` is
+ * `TokenMode::Session`. Disabling the last enabled admin is
+ * refused so the system can never be locked out of its admin routes.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user to update.
+ * - `enabled`: Whether the user may log in.
+ *
+ * # Returns
+ * The updated user, or a `PrivilegeError` describing why the change was refused.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{login_user, register_user, set_user_enabled, AuthError};
+ * use rusty_api::core::user::{LoginInput, RegisterInput};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     rusty_api::set_jwt_secret("test-secret");
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *     let login = || LoginInput { username: "alice".into(), password: "Secret123".into() };
+ *
+ *     let user = set_user_enabled(&pool, user.id, false).await.unwrap();
+ *     assert!(!user.enabled);
+ *     assert!(matches!(login_user(&pool, login()).await, Err(AuthError::AccountDisabled)));
+ *
+ *     set_user_enabled(&pool, user.id, true).await.unwrap();
+ *     assert!(login_user(&pool, login()).await.is_ok());
+ * });
+ * ```
+ */
+pub async fn set_user_enabled(pool: &sqlx::SqlitePool, user_id: i32, enabled: bool) -> Result<User, PrivilegeError> {
+    let mut tx = pool.begin().await?;
+
+    let (current, was_enabled): (i32, bool) = sqlx::query_as("SELECT privilege_level, enabled FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(PrivilegeError::UserNotFound)?;
+
+    if !enabled && was_enabled && current >= PrivilegeLevel::Admin.level() && enabled_admins(&mut tx).await? <= 1 {
+        return Err(PrivilegeError::LastAdmin);
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET enabled = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ? RETURNING id, username, password_hash, privilege_level, created_at, updated_at, enabled"
+    )
+    .bind(enabled)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    let event = if enabled { AuditEvent::AccountEnabled } else { AuditEvent::AccountDisabled };
+    audit(pool, event, Some(user.id)).await;
+    Ok(user)
+}
+
+/**
+ * Extract the bearer token from the `Authorization` header of a request.
+ *
+ * # Returns
+ * The token without the `Bearer ` prefix, or `None` if the header is missing or malformed.
+ */
+pub fn extract_bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+/// How long tokens issued by `generate_jwt` stay valid, in days.
+pub const JWT_TTL_DAYS: i64 = 7;
+
+/// Default name of the cookie carrying the JWT when cookie sessions are enabled.
+pub const DEFAULT_JWT_COOKIE: &str = "rusty_api_token";
+
+/**
+ * The name of the cookie carrying the JWT, stored as app data by the `Api`.
+ *
+ * See `Api::jwt_cookie`. When registered, the login route sets the cookie,
+ * the logout route clears it, and `extract_token` falls back to it.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtCookie(pub String);
+
+impl JwtCookie {
+    /**
+     * Build the cookie carrying the token.
+     *
+     * The cookie is `Secure`, `HttpOnly` and `SameSite=Strict`, so scripts cannot
+     * read it and browsers do not send it with cross-site requests.
+     */
+    pub fn session(&self, token: &str) -> Cookie<'static> {
+        Cookie::build(self.0.clone(), token.to_string())
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .max_age(CookieDuration::days(JWT_TTL_DAYS))
+            .finish()
+    }
+
+    /// Build an expired cookie removing the token from the browser.
+    pub fn removal(&self) -> Cookie<'static> {
+        let mut cookie = self.session("");
+        cookie.make_removal();
+        cookie
+    }
+}
+
+/**
+ * Extract the JWT of a request.
+ *
+ * The bearer token in the `Authorization` header is used if present. Otherwise,
+ * if cookie sessions are enabled with `Api::jwt_cookie`, the token is read from
+ * the session cookie.
+ *
+ * # Returns
+ * The token, or `None` if the request carries neither.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::auth::{extract_token, JwtCookie};
+ * use actix_web::cookie::Cookie;
+ * use actix_web::test::TestRequest;
+ *
+ * let req = TestRequest::default().insert_header(("Authorization", "Bearer abc")).to_http_request();
+ * assert_eq!(extract_token(&req).as_deref(), Some("abc"));
+ *
+ * let req = TestRequest::default()
+ *     .app_data(JwtCookie("session".into()))
+ *     .cookie(Cookie::new("session", "xyz"))
+ *     .to_http_request();
+ * assert_eq!(extract_token(&req).as_deref(), Some("xyz"));
+ * ```
+ */
+pub fn extract_token(req: &HttpRequest) -> Option<String> {
+    if let Some(token) = extract_bearer_token(req) {
+        return Some(token.to_string());
+    }
+    session_cookie(req)
+}
+
+/**
+ * Extract the API key from the `X-API-Key` header of a request.
+ *
+ * # Returns
+ * The API key, or `None` if the header is missing or not valid text.
+ */
+pub fn extract_api_key(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("X-API-Key")
+        .and_then(|h| h.to_str().ok())
+}
+
+/**
+ * Authenticate the request and check the user holds at least the given privilege level.
+ *
+ * The bearer token is validated and the user's current privilege level is
+ * loaded from the database, so privilege changes take effect immediately.
+ * Disabled accounts are refused with a `403 Forbidden`.
+ * Requests without a bearer token may authenticate with the session cookie, if
+ * enabled, or an `X-API-Key` header instead.
+ * The level can be a built-in `PrivilegeLevel` or any type implementing `Privilege`.
+ *
+ * # Arguments
+ * - `req`: The HTTP request carrying the `Authorization` header.
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `level`: The minimum privilege level required.
+ *
+ * # Returns
+ * The authenticated user, or the `401`/`403` response to send back.
+ */
+pub async fn require_privilege(
+    req: &HttpRequest,
+    pool: &sqlx::SqlitePool,
+    level: impl Privilege,
+) -> Result<User, HttpResponse> {
+    let user = match (extract_token(req), extract_api_key(req)) {
+        (Some(token), _) => {
+            let claims = validate_request_token(req, &token).await.map_err(token_error_response)?;
+            match get_user_by_id(pool, claims.sub).await {
+                Ok(Some(user)) => user,
+                Ok(None) => return Err(HttpResponse::Unauthorized().body("Invalid token")),
+                Err(e) => return Err(db_error_response(&e)),
+            }
+        }
+        (None, Some(api_key)) => match get_user_by_api_key(pool, api_key).await {
+            Ok(Some(user)) => user,
+            Ok(None) => return Err(HttpResponse::Unauthorized().body("Invalid API key")),
+            Err(e) => return Err(db_error_response(&e)),
+        },
+        (None, None) => return Err(HttpResponse::Unauthorized().body("Missing or invalid token")),
+    };
+
+    if !user.enabled {
+        return Err(HttpResponse::Forbidden().body("Account is disabled"));
+    }
+    if user.privilege_level < level.level() {
+        return Err(HttpResponse::Forbidden().body("Insufficient privilege"));
+    }
+    Ok(user)
+}
+
+/**
+ * Authenticate the request and check its token grants at least the given privilege level.
+ *
+ * Unlike `require_privilege`, the level is read from the `privilege_level`
+ * claim of the validated token, so no database query is made. Privilege
+ * changes only take effect once the user receives a new token, e.g. by logging
+ * in again. API keys are not accepted, as they carry no claims.
+ *
+ * # Arguments
+ * - `req`: The HTTP request carrying the token.
+ * - `level`: The minimum privilege level required.
+ *
+ * # Returns
+ * The token's claims, or the `401`/`403` response to send back.
+ */
+pub async fn require_token_privilege(req: &HttpRequest, level: impl Privilege) -> Result<Claims, HttpResponse> {
+    let token = extract_token(req).ok_or_else(|| HttpResponse::Unauthorized().body("Missing or invalid token"))?;
+    let claims = validate_request_token(req, &token).await.map_err(token_error_response)?;
+    if claims.privilege_level < level.level() {
+        return Err(HttpResponse::Forbidden().body("Insufficient privilege"));
+    }
+    Ok(claims)
+}
+
+/**
+ * A custom token validator, replacing the built-in HS256 validation.
+ *
+ * Register one with `Api::token_validator` to accept tokens issued by an external
+ * identity provider. It is used by `add_route_with_auth` routes and the
+ * `RequirePrivilege` middleware; `validate_token` itself is unaffected.
+ * Validators that need to do I/O, such as fetching signing keys, can be created
+ * with `TokenValidator::new_async`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Claims, TokenValidator};
+ *
+ * let validator = TokenValidator::new(|token| match token {
+ *     "trusted" => Ok(Claims { sub: 1, exp: usize::MAX, ..Default::default() }),
+ *     _ => Err(actix_web::error::ErrorUnauthorized("Invalid token")),
+ * });
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     assert_eq!(validator.validate("trusted").await.unwrap().sub, 1);
+ *     assert!(validator.validate("forged").await.is_err());
+ * });
+ * ```
+ */
+#[derive(Clone)]
+pub struct TokenValidator(Arc<ValidateFn>);
+
+/// The function wrapped by a `TokenValidator`.
+type ValidateFn = dyn Fn(&str) -> LocalBoxFuture<'static, Result<Claims, actix_web::Error>> + Send + Sync;
+
+impl TokenValidator {
+    /// Create a validator from a function mapping a token to its claims.
+    pub fn new<F>(validate: F) -> Self
+    where
+        F: Fn(&str) -> Result<Claims, actix_web::Error> + Send + Sync + 'static,
+    {
+        Self(Arc::new(move |token| ready(validate(token)).boxed_local()))
+    }
+
+    /// Create a validator from an async function mapping a token to its claims.
+    pub fn new_async<F, Fut>(validate: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Claims, actix_web::Error>> + 'static,
+    {
+        Self(Arc::new(move |token| validate(token.to_string()).boxed_local()))
+    }
+
+    /// Validate a token, returning its claims.
+    pub async fn validate(&self, token: &str) -> Result<Claims, actix_web::Error> {
+        (self.0)(token).await
+    }
+}
+
+/**
+ * Build the response for a token that failed validation.
+ *
+ * Rejected tokens receive a `401 Unauthorized` with `Invalid token`. Other
+ * failures, such as a database outage while looking up a session, keep the
+ * validator's response, so clients do not mistake them for a logout.
+ */
+pub(crate) fn token_error_response(e: actix_web::Error) -> HttpResponse {
+    let response = e.error_response();
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return HttpResponse::Unauthorized().body("Invalid token");
+    }
+    response
+}
+
+/**
+ * Validate a token with the request's `TokenValidator`, or with its `Api`'s `JwtSettings` if none is registered.
+ *
+ * # Arguments
+ * - `req`: The HTTP request, whose app data may hold a `TokenValidator` and `JwtSettings`.
+ * - `token`: The bearer token to validate.
+ *
+ * # Returns
+ * The token's claims, or an `Unauthorized` error if the token is invalid.
+ */
+pub async fn validate_request_token(req: &HttpRequest, token: &str) -> Result<Claims, actix_web::Error> {
+    match req.app_data::<TokenValidator>() {
+        Some(validator) => validator.validate(token).await,
+        None => JwtSettings::of(req).validate(token),
     }
-    
-    // Generate JWT
-    let token = generate_jwt(&user);
-    Ok(LoginResponse { token })
 }
 
 /**
  * Middleware to extract and validate JWT token from the request.
+ *
+ * When an issuer or audience is set with `set_jwt_issuer` or `set_jwt_audience`,
+ * tokens whose `iss` or `aud` claims do not match are rejected.
  */
 pub fn validate_token(token: &str) -> Result<Claims, actix_web::Error> {
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+    JwtSettings::global().validate(token)
+}
+
+/// A validated token's claims together with how long it remains valid.
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+    /// The token's claims.
+    pub claims: Claims,
+    /// Seconds until the token expires, or `0` if it is only accepted thanks to the expiry leeway.
+    pub expires_in: u64,
+}
+
+impl TokenInfo {
+    /**
+     * Check whether the token expires within the given duration.
+     *
+     * # Arguments
+     * - `within`: How soon the token must expire to count as expiring.
+     *
+     * # Returns
+     * `true` if the token expires within `within`.
+     */
+    pub fn expires_within(&self, within: Duration) -> bool {
+        self.expires_in <= within.as_secs()
+    }
+}
+
+/**
+ * Validate a token like `validate_token`, also reporting how long it remains valid.
+ *
+ * Handlers can use `expires_in` to hint clients to refresh their token before
+ * it expires, e.g. with a response header.
+ *
+ * # Arguments
+ * - `token`: The token to validate.
+ *
+ * # Returns
+ * The token's claims and remaining lifetime, or an `Unauthorized` error if the token is invalid.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{generate_test_jwt, set_jwt_secret, validate_token_detailed, PrivilegeLevel};
+ * use std::time::Duration;
+ *
+ * set_jwt_secret("test-secret");
+ * let token = generate_test_jwt(7, PrivilegeLevel::User, Duration::from_secs(300));
+ *
+ * let info = validate_token_detailed(&token).unwrap();
+ * assert_eq!(info.claims.sub, 7);
+ * assert!(info.expires_in > 290 && info.expires_in <= 300);
+ * assert!(info.expires_within(Duration::from_secs(600)));
+ * assert!(!info.expires_within(Duration::from_secs(60)));
+ * ```
+ */
+pub fn validate_token_detailed(token: &str) -> Result<TokenInfo, actix_web::Error> {
+    let claims = validate_token(token)?;
+    let expires_in = (claims.exp as i64).saturating_sub(chrono::Utc::now().timestamp()).max(0) as u64;
+    Ok(TokenInfo { claims, expires_in })
+}
+
+/**
+ * Validate a token, requiring the given issuer and audience instead of the configured ones.
+ *
+ * # Arguments
+ * - `token`: The token to validate.
+ * - `issuer`: The required `iss` claim, or `None` to not check it.
+ * - `audience`: The required `aud` claim, or `None` to reject tokens carrying one.
+ *
+ * # Returns
+ * The token's claims, or an `Unauthorized` error if the token is invalid or its
+ * issuer or audience does not match.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::auth::validate_token_with;
+ * use rusty_api::{generate_test_jwt, set_jwt_secret, PrivilegeLevel};
+ * use std::time::Duration;
+ *
+ * set_jwt_secret("test-secret");
+ * let token = generate_test_jwt(7, PrivilegeLevel::User, Duration::from_secs(60));
+ *
+ * assert!(validate_token_with(&token, None, None).is_ok());
+ * assert!(validate_token_with(&token, Some("https://auth.example.com"), None).is_err());
+ * assert!(validate_token_with(&token, None, Some("billing-api")).is_err());
+ * ```
+ */
+pub fn validate_token_with(token: &str, issuer: Option<&str>, audience: Option<&str>) -> Result<Claims, actix_web::Error> {
+    decode_token(token, &claims_validation(Algorithm::HS256, issuer, audience, jwt_leeway()))
+}
+
+/// Decode a token signed with the configured JWT secret, recording failures in the metrics.
+fn decode_token(token: &str, validation: &Validation) -> Result<Claims, actix_web::Error> {
+    let secret = jwt_secret().map_err(actix_web::error::ErrorInternalServerError)?;
 
     match jsonwebtoken::decode::<Claims>(
         token,
         &jsonwebtoken::DecodingKey::from_secret(secret.as_ref()),
-        &jsonwebtoken::Validation::default(),
+        validation,
     ) {
         Ok(decoded) => Ok(decoded.claims),
-        Err(_) => Err(actix_web::error::ErrorUnauthorized("Invalid token")),
+        Err(e) => {
+            metrics::record_token_failure(match e.kind() {
+                ErrorKind::ExpiredSignature => TokenFailure::Expired,
+                ErrorKind::InvalidSignature => TokenFailure::InvalidSignature,
+                _ => TokenFailure::Invalid,
+            });
+            Err(actix_web::error::ErrorUnauthorized("Invalid token"))
+        }
     }
 }
\ No newline at end of file
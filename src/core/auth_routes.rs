@@ -5,22 +5,200 @@
  * the necessary input and output structures. It uses Actix Web for routing
  * and SQLx for database interaction.
  */
-use actix_web::{web, HttpResponse};
-use crate::core::auth::{login_user, register_user};
-use crate::core::user::{LoginInput, RegisterInput};
+use actix_web::http::header::{HeaderValue, RETRY_AFTER};
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, ResponseError};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::core::audit::with_client_ip;
+use crate::core::auth::{extract_token, get_user_by_id, login_user_with, login_user_with_session_capped, JwtCookie, JwtSettings, register_user, register_users_bulk, rotate_api_key, set_privilege, set_user_enabled, validate_request_token, verify_user_password, PrivilegeError};
+use crate::core::db::{db_error_response, list_users, patch_user, PoolStats, SELF_EDITABLE_USER_FIELDS};
+use crate::core::json::JsonInput;
+use crate::core::ip::request_client_ip;
+use crate::core::middleware::{AuthenticatedUser, RequirePrivilege};
+use crate::core::pagination::Pagination;
+use crate::core::session::{refresh_session_capped, revoke_session, validate_session, TokenMode};
+use crate::core::response::{json_created, json_error, json_ok};
+use crate::core::user::{AccountStatusInput, ApiKeyResponse, LoginInput, LoginResponse, PrivilegeInput, PrivilegeLevel, RegisterInput, VerifyPasswordInput};
+
+/// Path of the admin route listing users, one page at a time.
+pub const USERS_ROUTE: &str = "/admin/users";
+
+/// Path of the admin route used to change a user's privilege level.
+pub const PRIVILEGE_ROUTE: &str = "/admin/users/{id}/privilege";
+
+/// Path of the admin route used to enable or disable a user account.
+pub const ACCOUNT_STATUS_ROUTE: &str = "/admin/users/{id}/enabled";
+
+/// Path of the admin route used to register many users at once.
+pub const BULK_REGISTER_ROUTE: &str = "/admin/users/bulk";
+
+/// Path of the admin route reporting the user database connection pool's usage.
+pub const POOL_ROUTE: &str = "/admin/pool";
+
+/// Path of the route used by an authenticated user to rotate their API key.
+pub const API_KEY_ROTATE_ROUTE: &str = "/account/api-key/rotate";
+
+/// Path of the route clearing the session cookie.
+pub const LOGOUT_ROUTE: &str = "/logout";
+
+/// Path of the route re-issuing a still-valid token with a new expiry.
+pub const TOKEN_REFRESH_ROUTE: &str = "/token/refresh";
+
+/// Path of the route returning the authenticated user.
+pub const WHOAMI_ROUTE: &str = "/whoami";
+
+/// Path of the route used by an authenticated user to update their own fields.
+pub const ACCOUNT_ROUTE: &str = "/account";
+
+/// Path of the route used by an authenticated user to confirm their current password.
+pub const VERIFY_PASSWORD_ROUTE: &str = "/verify-password";
+
+/**
+ * Whether the login route includes the user profile in its response, stored as app data by the `Api`.
+ *
+ * See `Api::login_includes_user`.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoginIncludesUser(pub bool);
+
+/// Failed checks per user allowed at `VERIFY_PASSWORD_ROUTE` within `DEFAULT_VERIFY_PASSWORD_WINDOW`.
+pub const DEFAULT_VERIFY_PASSWORD_FAILURES: u32 = 5;
+
+/// Window in which failed checks at `VERIFY_PASSWORD_ROUTE` are counted.
+pub const DEFAULT_VERIFY_PASSWORD_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/**
+ * Counts failed checks at `VERIFY_PASSWORD_ROUTE` per user, stored as app data by the `Api`.
+ *
+ * Once a user has failed `max_failures` times within the window, further checks
+ * receive a `429 Too Many Requests` with a `Retry-After` header until the window
+ * ends, so a stolen token cannot be used to guess the account's password. A
+ * correct password resets the count. Clones share the counts.
+ *
+ * See `Api::verify_password_limit`.
+ */
+#[derive(Debug, Clone)]
+pub struct VerifyPasswordLimit {
+    max_failures: u32,
+    window: Duration,
+    failures: Arc<Mutex<HashMap<i32, (u32, Instant)>>>,
+}
+
+impl Default for VerifyPasswordLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_VERIFY_PASSWORD_FAILURES, DEFAULT_VERIFY_PASSWORD_WINDOW)
+    }
+}
+
+impl VerifyPasswordLimit {
+    /// Create a limit allowing `max_failures` failed checks per user within `window`.
+    pub fn new(max_failures: u32, window: Duration) -> Self {
+        Self { max_failures, window, failures: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Get the failed checks allowed per user within the window.
+    pub fn get_max_failures(&self) -> u32 {
+        self.max_failures
+    }
+
+    /// Get the window in which failed checks are counted.
+    pub fn get_window(&self) -> Duration {
+        self.window
+    }
+
+    /// Get how long the user must wait before checking again, or `None` if checks are allowed.
+    fn locked_for(&self, user_id: i32) -> Option<Duration> {
+        let mut failures = self.failures.lock().unwrap();
+        failures.retain(|_, (_, first)| first.elapsed() < self.window);
+        match failures.get(&user_id) {
+            Some((count, first)) if *count >= self.max_failures => Some(self.window.saturating_sub(first.elapsed())),
+            _ => None,
+        }
+    }
+
+    /// Count a failed check of the user.
+    fn record_failure(&self, user_id: i32) {
+        let mut failures = self.failures.lock().unwrap();
+        failures.entry(user_id).or_insert((0, Instant::now())).0 += 1;
+    }
+
+    /// Forget the failed checks of the user.
+    fn reset(&self, user_id: i32) {
+        self.failures.lock().unwrap().remove(&user_id);
+    }
+}
 
 /**
  * Configure routes for user authentication and registration.
  *
  * This function sets up the routes for user login and registration, using
- * Actix Web's `ServiceConfig`. It also applies middleware for JWT validation.
+ * Actix Web's `ServiceConfig`, along with `LOGOUT_ROUTE` (`POST`) clearing
+ * the session cookie, `TOKEN_REFRESH_ROUTE` (`POST`) extending the caller's
+ * session, the admin-only `USERS_ROUTE` (`GET`) listing users with
+ * `?limit=&offset=` pagination, the admin-only `PRIVILEGE_ROUTE`
+ * (`PUT`) for changing a user's privilege level, the admin-only
+ * `ACCOUNT_STATUS_ROUTE` (`PUT`) for enabling or disabling an account, the admin-only
+ * `BULK_REGISTER_ROUTE` (`POST`) for importing many users, the admin-only
+ * `POOL_ROUTE` (`GET`) reporting the connection pool's usage, the authenticated
+ * `API_KEY_ROTATE_ROUTE` (`POST`) for rotating the caller's API key, the
+ * authenticated `WHOAMI_ROUTE` (`GET`) describing the caller, the
+ * authenticated `ACCOUNT_ROUTE` (`PATCH`) updating the caller's own fields and
+ * the authenticated `VERIFY_PASSWORD_ROUTE` (`POST`) confirming the caller's password.
  *
  * # Arguments
  * - `cfg`: A mutable reference to the Actix Web `ServiceConfig`.
  */
 pub fn configure_auth_routes(cfg: &mut web::ServiceConfig, login_path: &str, register_path: &str) {
     cfg.route(login_path, web::post().to(login))
-       .route(register_path, web::post().to(register));
+       .route(register_path, web::post().to(register))
+       .route(LOGOUT_ROUTE, web::post().to(logout))
+       .route(TOKEN_REFRESH_ROUTE, web::post().to(refresh_token))
+       .service(
+           web::resource(USERS_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::Admin))
+               .route(web::get().to(list_users_page))
+       )
+       .service(
+           web::resource(PRIVILEGE_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::Admin))
+               .route(web::put().to(update_privilege))
+       )
+       .service(
+           web::resource(ACCOUNT_STATUS_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::Admin))
+               .route(web::put().to(update_account_status))
+       )
+       .service(
+           web::resource(BULK_REGISTER_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::Admin))
+               .route(web::post().to(register_bulk))
+       )
+       .service(
+           web::resource(POOL_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::Admin))
+               .route(web::get().to(pool_stats))
+       )
+       .service(
+           web::resource(API_KEY_ROTATE_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::User))
+               .route(web::post().to(rotate_key))
+       )
+       .service(
+           web::resource(WHOAMI_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::User))
+               .route(web::get().to(whoami))
+       )
+       .service(
+           web::resource(ACCOUNT_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::User))
+               .route(web::patch().to(patch_account))
+       )
+       .service(
+           web::resource(VERIFY_PASSWORD_ROUTE)
+               .wrap(RequirePrivilege(PrivilegeLevel::User))
+               .route(web::post().to(verify_account_password))
+       );
 }
 
 /**
@@ -29,8 +207,12 @@ pub fn configure_auth_routes(cfg: &mut web::ServiceConfig, login_path: &str, reg
  * This function handles user login requests. It extracts the login input
  * from the request, calls the `login_user` function to authenticate the user,
  * and returns a JSON response with the login token or an error message.
+ * The user profile is only included when `LoginIncludesUser` is set. When
+ * cookie sessions are enabled with `JwtCookie`, the token is also set in the
+ * session cookie.
  *
  * # Arguments
+ * - `req`: The HTTP request, used to record the client IP in the audit log.
  * - `pool`: A reference to the SQLx SQLite connection pool.
  * - `input`: The login input data, containing the username and password.
  *
@@ -38,15 +220,177 @@ pub fn configure_auth_routes(cfg: &mut web::ServiceConfig, login_path: &str, reg
  * An `HttpResponse` containing the login token or an error message.
  */
 async fn login(
+    req: HttpRequest,
     pool: web::Data<sqlx::SqlitePool>,
-    input: web::Json<LoginInput>,
+    input: JsonInput<LoginInput>,
 ) -> HttpResponse {
-    match login_user(&pool, input.into_inner()).await {
-        Ok(response) => HttpResponse::Ok().json(response),
-        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    let settings = JwtSettings::of(&req);
+    let login = async {
+        match req.app_data::<TokenMode>() {
+            Some(TokenMode::Session) => login_user_with_session_capped(&pool, input.into_inner(), settings.max_session).await,
+            _ => login_user_with(&pool, input.into_inner(), &settings).await,
+        }
+    };
+    match with_client_ip(request_client_ip(&req), login).await {
+        Ok(mut response) => {
+            if !req.app_data::<LoginIncludesUser>().is_some_and(|include| include.0) {
+                response.user = None;
+            }
+            let cookie = req.app_data::<JwtCookie>().map(|cookie| cookie.session(&response.token));
+            let mut response = json_ok(response);
+            if let Some(cookie) = cookie
+                && let Err(e) = response.add_cookie(&cookie)
+            {
+                return json_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+            }
+            response
+        }
+        Err(e) => e.error_response(),
     }
 }
 
+/**
+ * Logout route handler.
+ *
+ * This function clears the session cookie set by the login route when cookie
+ * sessions are enabled. In session mode, see `Api::token_mode`, the caller's
+ * session is also revoked. JWTs are stateless, so header-based clients log out
+ * by discarding their token; for them this route does nothing.
+ *
+ * # Arguments
+ * - `req`: The HTTP request, whose app data may hold the `JwtCookie`.
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ *
+ * # Returns
+ * A `204 No Content` response, removing the cookie if enabled.
+ */
+async fn logout(req: HttpRequest, pool: web::Data<sqlx::SqlitePool>) -> HttpResponse {
+    if req.app_data::<TokenMode>() == Some(&TokenMode::Session)
+        && let Some(token) = extract_token(&req)
+        && let Err(e) = revoke_session(&pool, &token).await
+    {
+        return db_error_response(&e);
+    }
+    let mut response = HttpResponse::NoContent().finish();
+    if let Some(cookie) = req.app_data::<JwtCookie>()
+        && let Err(e) = response.add_cookie(&cookie.removal())
+    {
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    }
+    response
+}
+
+/**
+ * Token refresh route handler.
+ *
+ * This function re-issues the caller's still-valid token with a new expiry,
+ * for sliding sessions, using `refresh_access_token`. The token is read and
+ * validated like on authenticated routes, including with a custom
+ * `TokenValidator`, and the user must still exist. The new token carries the
+ * user's current privilege level. The response has the
+ * same shape as the login response, and the session cookie is renewed when
+ * cookie sessions are enabled with `JwtCookie`. Expired tokens and sessions
+ * past the maximum session length receive a `401 Unauthorized`. In session
+ * mode, the session is extended with `refresh_session` and its ID returned.
+ *
+ * # Arguments
+ * - `req`: The HTTP request carrying the token.
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ *
+ * # Returns
+ * An `HttpResponse` containing the new token or an error message.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{generate_jwt, register_user, set_privilege};
+ * use rusty_api::core::user::{PrivilegeLevel, RegisterInput};
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     rusty_api::set_jwt_secret("test-secret");
+ *     let api = Api::new().in_memory_db();
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *
+ *     let refresh = |token: &str| test::TestRequest::post()
+ *         .uri("/token/refresh")
+ *         .insert_header(("Authorization", format!("Bearer {}", token)))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *
+ *     let body: serde_json::Value = test::call_and_read_body_json(&app, refresh(&generate_jwt(&user).unwrap())).await;
+ *     assert!(body["token"].is_string());
+ *     assert_eq!(test::call_service(&app, refresh("not-a-token")).await.status(), 401);
+ *
+ *     // A demoted admin's refreshed token carries the new privilege level.
+ *     let bob = register_user(&pool, RegisterInput { username: "bob".into(), password: "Secret123".into() }).await.unwrap();
+ *     set_privilege(&pool, bob.id, PrivilegeLevel::Admin).await.unwrap();
+ *     let admin = set_privilege(&pool, user.id, PrivilegeLevel::Admin).await.unwrap();
+ *     let token = generate_jwt(&admin).unwrap();
+ *     set_privilege(&pool, user.id, PrivilegeLevel::User).await.unwrap();
+ *     let body: serde_json::Value = test::call_and_read_body_json(&app, refresh(&token)).await;
+ *     let claims = rusty_api::validate_token(body["token"].as_str().unwrap()).unwrap();
+ *     assert_eq!(claims.privilege_level, PrivilegeLevel::User as i32);
+ * });
+ * ```
+ */
+async fn refresh_token(req: HttpRequest, pool: web::Data<sqlx::SqlitePool>) -> HttpResponse {
+    let Some(token) = extract_token(&req) else {
+        return json_error(StatusCode::UNAUTHORIZED, "Missing or invalid token");
+    };
+    if req.app_data::<TokenMode>() == Some(&TokenMode::Session) {
+        return refresh_session_token(&req, &pool, token).await;
+    }
+    let claims = match validate_request_token(&req, &token).await {
+        Ok(claims) => claims,
+        Err(e) => return json_error(e.as_response_error().status_code(), e.to_string()),
+    };
+    let user = match get_user_by_id(&pool, claims.sub).await {
+        Ok(Some(user)) if user.enabled => user,
+        Ok(Some(_)) => return json_error(StatusCode::FORBIDDEN, "Account is disabled"),
+        Ok(None) => return json_error(StatusCode::UNAUTHORIZED, "Invalid token"),
+        Err(e) => return db_error_response(&e),
+    };
+    let token = match JwtSettings::of(&req).refresh(&claims, &user) {
+        Ok(token) => token,
+        Err(e) => return json_error(e.as_response_error().status_code(), e.to_string()),
+    };
+
+    let cookie = req.app_data::<JwtCookie>().map(|cookie| cookie.session(&token));
+    let mut response = json_ok(LoginResponse { token, user: None });
+    if let Some(cookie) = cookie
+        && let Err(e) = response.add_cookie(&cookie)
+    {
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    }
+    response
+}
+
+/// Extend the caller's session in session mode, answering like `refresh_token`.
+async fn refresh_session_token(req: &HttpRequest, pool: &sqlx::SqlitePool, token: String) -> HttpResponse {
+    match validate_session(pool, &token).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return json_error(StatusCode::UNAUTHORIZED, "Invalid session"),
+        Err(e) => return db_error_response(&e),
+    }
+    match refresh_session_capped(pool, &token, JwtSettings::of(req).max_session).await {
+        Ok(true) => {}
+        Ok(false) => return json_error(StatusCode::UNAUTHORIZED, "Session expired, please log in again"),
+        Err(e) => return db_error_response(&e),
+    }
+
+    let cookie = req.app_data::<JwtCookie>().map(|cookie| cookie.session(&token));
+    let mut response = json_ok(LoginResponse { token, user: None });
+    if let Some(cookie) = cookie
+        && let Err(e) = response.add_cookie(&cookie)
+    {
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    }
+    response
+}
+
 /**
  * Register route handler.
  * 
@@ -55,6 +399,7 @@ async fn login(
  * and returns a JSON response with the user data or an error message.
  *
  * # Arguments
+ * - `req`: The HTTP request, used to record the client IP in the audit log.
  * - `pool`: A reference to the SQLx SQLite connection pool.
  * - `input`: The registration input data, containing the username and password.
  *
@@ -62,11 +407,486 @@ async fn login(
  * An `HttpResponse` containing the user data or an error message.
  */
 async fn register(
+    req: HttpRequest,
+    pool: web::Data<sqlx::SqlitePool>,
+    input: JsonInput<RegisterInput>,
+) -> HttpResponse {
+    match with_client_ip(request_client_ip(&req), register_user(&pool, input.into_inner())).await {
+        Ok(user) => json_created(user),
+        Err(e) => e.error_response(),
+    }
+}
+
+/**
+ * Bulk registration route handler.
+ *
+ * This function handles admin requests to import many users at once. The body
+ * is a JSON array of registration inputs. On success, the created users are
+ * returned with `201 Created`. If any row fails, nothing is imported and the
+ * response is a `400 Bad Request` listing every failing row, e.g.
+ * `{"error": "...", "failures": [{"index": 1, "username": "alice", "error": "Username already taken"}]}`.
+ *
+ * # Arguments
+ * - `req`: The HTTP request, used to record the client IP in the audit log.
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `input`: The users to register.
+ *
+ * # Returns
+ * An `HttpResponse` containing the created users or the failing rows.
+ */
+async fn register_bulk(
+    req: HttpRequest,
+    pool: web::Data<sqlx::SqlitePool>,
+    input: JsonInput<Vec<RegisterInput>>,
+) -> HttpResponse {
+    match with_client_ip(request_client_ip(&req), register_users_bulk(&pool, input.into_inner())).await {
+        Ok(users) => json_created(users),
+        Err(e) => e.error_response(),
+    }
+}
+
+/**
+ * User listing route handler.
+ *
+ * This function handles admin requests listing users, ordered by ID. The page
+ * is read with the `Pagination` extractor, and its `limit` and `offset` are
+ * added to the envelope metadata when `Api::response_envelope` is enabled.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `page`: The requested page.
+ *
+ * # Returns
+ * An `HttpResponse` containing the users (without password hashes) or an error message.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{set_jwt_secret, Api};
+ * use rusty_api::core::auth::{generate_jwt, register_user, set_privilege};
+ * use rusty_api::core::user::{PrivilegeLevel, RegisterInput};
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     set_jwt_secret("test-secret");
+ *     let api = Api::new().in_memory_db();
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let pool = api.db_pool().unwrap();
+ *     for name in ["admin", "bob", "carol"] {
+ *         register_user(&pool, RegisterInput { username: name.into(), password: "Secret123".into() }).await.unwrap();
+ *     }
+ *     let admin = set_privilege(&pool, 1, PrivilegeLevel::Admin).await.unwrap();
+ *
+ *     let req = test::TestRequest::get()
+ *         .uri("/admin/users?limit=2&offset=1")
+ *         .insert_header(("Authorization", format!("Bearer {}", generate_jwt(&admin).unwrap())))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *     let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+ *     assert_eq!(body[0]["username"], "bob");
+ *     assert_eq!(body[1]["username"], "carol");
+ *     assert!(body[0].get("password_hash").is_none());
+ * });
+ * ```
+ */
+async fn list_users_page(pool: web::Data<sqlx::SqlitePool>, page: Pagination) -> HttpResponse {
+    match list_users(&pool, page.limit, page.offset).await {
+        Ok(users) => {
+            let mut response = json_ok(users);
+            response.extensions_mut().insert(page.meta());
+            response
+        }
+        Err(e) => db_error_response(&e),
+    }
+}
+
+/**
+ * Pool statistics route handler.
+ *
+ * This function handles admin requests for the user database connection pool's
+ * usage, to diagnose `503` responses caused by pool exhaustion in production.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ *
+ * # Returns
+ * An `HttpResponse` containing the `PoolStats`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{set_jwt_secret, Api};
+ * use rusty_api::core::auth::{generate_jwt, register_user, set_privilege};
+ * use rusty_api::core::user::{PrivilegeLevel, RegisterInput};
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     set_jwt_secret("test-secret");
+ *     let api = Api::new().in_memory_db();
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "admin".into(), password: "Secret123".into() }).await.unwrap();
+ *
+ *     let req = test::TestRequest::get()
+ *         .uri("/admin/pool")
+ *         .insert_header(("Authorization", format!("Bearer {}", generate_jwt(&user).unwrap())))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *     assert_eq!(test::call_service(&app, req).await.status(), 403);
+ *
+ *     let admin = set_privilege(&pool, user.id, PrivilegeLevel::Admin).await.unwrap();
+ *     let req = test::TestRequest::get()
+ *         .uri("/admin/pool")
+ *         .insert_header(("Authorization", format!("Bearer {}", generate_jwt(&admin).unwrap())))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *     let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+ *     assert_eq!(body["max_connections"], 10);
+ *     assert_eq!(body["closed"], false);
+ * });
+ * ```
+ */
+async fn pool_stats(pool: web::Data<sqlx::SqlitePool>) -> HttpResponse {
+    json_ok(PoolStats::of(&pool))
+}
+
+/**
+ * Privilege route handler.
+ *
+ * This function handles admin requests to change a user's privilege level.
+ * The route is wrapped in `RequirePrivilege`, so only admins reach it. Demoting
+ * the last remaining admin is refused with a `409 Conflict`.
+ *
+ * # Arguments
+ * - `req`: The HTTP request, used to record the client IP in the audit log.
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `path`: The ID of the user to update.
+ * - `input`: The new privilege level.
+ *
+ * # Returns
+ * An `HttpResponse` containing the updated user (without password hash) or an error message.
+ */
+async fn update_privilege(
+    req: HttpRequest,
     pool: web::Data<sqlx::SqlitePool>,
-    input: web::Json<RegisterInput>,
+    path: web::Path<i32>,
+    input: JsonInput<PrivilegeInput>,
 ) -> HttpResponse {
-    match register_user(&pool, input.into_inner()).await {
-        Ok(user) => HttpResponse::Created().json(user),
-        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    let update = set_privilege(&pool, path.into_inner(), input.privilege_level);
+    match with_client_ip(request_client_ip(&req), update).await {
+        Ok(user) => json_ok(user),
+        Err(e @ PrivilegeError::UserNotFound) => json_error(StatusCode::NOT_FOUND, e.to_string()),
+        Err(e @ PrivilegeError::LastAdmin) => json_error(StatusCode::CONFLICT, e.to_string()),
+        Err(PrivilegeError::Database(e)) => db_error_response(&e),
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Account status route handler.
+ *
+ * This function handles admin requests to enable or disable a user account,
+ * with a body such as `{"enabled": false}`. The route is wrapped in
+ * `RequirePrivilege`, so only admins reach it. Disabling the last enabled admin
+ * is refused with a `409 Conflict`.
+ *
+ * # Arguments
+ * - `req`: The HTTP request, used to record the client IP in the audit log.
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `path`: The ID of the user to update.
+ * - `input`: Whether the account is enabled.
+ *
+ * # Returns
+ * An `HttpResponse` containing the updated user (without password hash) or an error message.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{set_jwt_secret, Api};
+ * use rusty_api::core::auth::{generate_jwt, register_user, set_privilege};
+ * use rusty_api::core::user::{PrivilegeLevel, RegisterInput};
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     set_jwt_secret("test-secret");
+ *     let api = Api::new().in_memory_db();
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let pool = api.db_pool().unwrap();
+ *     let register = |name: &str| RegisterInput { username: name.into(), password: "Secret123".into() };
+ *     let admin = register_user(&pool, register("admin")).await.unwrap();
+ *     let admin = set_privilege(&pool, admin.id, PrivilegeLevel::Admin).await.unwrap();
+ *     let bob = register_user(&pool, register("bob")).await.unwrap();
+ *
+ *     let disable = test::TestRequest::put()
+ *         .uri(&format!("/admin/users/{}/enabled", bob.id))
+ *         .insert_header(("Authorization", format!("Bearer {}", generate_jwt(&admin).unwrap())))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .set_json(serde_json::json!({ "enabled": false }))
+ *         .to_request();
+ *     let body: serde_json::Value = test::call_and_read_body_json(&app, disable).await;
+ *     assert_eq!(body["enabled"], false);
+ *
+ *     let login = test::TestRequest::post()
+ *         .uri("/login")
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .set_json(serde_json::json!({ "username": "bob", "password": "Secret123" }))
+ *         .to_request();
+ *     assert_eq!(test::call_service(&app, login).await.status(), 403);
+ * });
+ * ```
+ */
+async fn update_account_status(
+    req: HttpRequest,
+    pool: web::Data<sqlx::SqlitePool>,
+    path: web::Path<i32>,
+    input: JsonInput<AccountStatusInput>,
+) -> HttpResponse {
+    let update = set_user_enabled(&pool, path.into_inner(), input.enabled);
+    match with_client_ip(request_client_ip(&req), update).await {
+        Ok(user) => json_ok(user),
+        Err(e @ PrivilegeError::UserNotFound) => json_error(StatusCode::NOT_FOUND, e.to_string()),
+        Err(e @ PrivilegeError::LastAdmin) => json_error(StatusCode::CONFLICT, e.to_string()),
+        Err(PrivilegeError::Database(e)) => db_error_response(&e),
+    }
+}
+
+/**
+ * API key rotation route handler.
+ *
+ * This function generates a new API key for the authenticated user and returns
+ * it once. The previous key stops working immediately.
+ *
+ * # Arguments
+ * - `req`: The HTTP request, used to record the client IP in the audit log.
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user`: The user authenticated by the `RequirePrivilege` middleware.
+ *
+ * # Returns
+ * An `HttpResponse` containing the new API key or an error message.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{set_jwt_secret, Api};
+ * use actix_web::test;
+ *
+ * set_jwt_secret("test-secret");
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(Api::new().in_memory_db().test_app().await).await;
+ *     let peer = "127.0.0.1:12345".parse().unwrap();
+ *     let credentials = serde_json::json!({ "username": "alice", "password": "Secret123" });
+ *
+ *     let request = test::TestRequest::post().uri("/register").set_json(&credentials).peer_addr(peer).to_request();
+ *     test::call_service(&app, request).await;
+ *     let request = test::TestRequest::post().uri("/login").set_json(&credentials).peer_addr(peer).to_request();
+ *     let login: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+ *     let token = login["token"].as_str().unwrap();
+ *
+ *     let rotate = |header: (&'static str, String)| test::TestRequest::post()
+ *         .uri("/account/api-key/rotate")
+ *         .insert_header(header)
+ *         .peer_addr(peer)
+ *         .to_request();
+ *
+ *     let body: serde_json::Value = test::call_and_read_body_json(&app, rotate(("Authorization", format!("Bearer {}", token)))).await;
+ *     let old_key = body["api_key"].as_str().unwrap().to_string();
+ *
+ *     // The key authenticates the next rotation and is rejected afterwards.
+ *     assert_eq!(test::call_service(&app, rotate(("X-API-Key", old_key.clone()))).await.status(), 200);
+ *     assert_eq!(test::call_service(&app, rotate(("X-API-Key", old_key))).await.status(), 401);
+ * });
+ * ```
+ */
+async fn rotate_key(
+    req: HttpRequest,
+    pool: web::Data<sqlx::SqlitePool>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    match with_client_ip(request_client_ip(&req), rotate_api_key(&pool, user.0.id)).await {
+        Ok(api_key) => json_ok(ApiKeyResponse { api_key }),
+        Err(e) => db_error_response(&e),
+    }
+}
+
+/**
+ * Password verification route handler.
+ *
+ * This function checks the authenticated user's current password, e.g. before
+ * a sensitive action, without issuing a new token. It returns `200 OK` if the
+ * password matches and `401 Unauthorized` otherwise. After too many failed
+ * checks, see `VerifyPasswordLimit`, it returns `429 Too Many Requests`.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user`: The user authenticated by the `RequirePrivilege` middleware.
+ * - `input`: The password to check.
+ *
+ * # Returns
+ * An empty `HttpResponse` or an error message.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{generate_jwt, register_user};
+ * use rusty_api::core::user::RegisterInput;
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     rusty_api::set_jwt_secret("test-secret");
+ *     let api = Api::new().in_memory_db();
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *     let token = generate_jwt(&user).unwrap();
+ *
+ *     let verify = |password: &str| test::TestRequest::post()
+ *         .uri("/verify-password")
+ *         .insert_header(("Authorization", format!("Bearer {}", token)))
+ *         .set_json(serde_json::json!({ "password": password }))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *
+ *     assert_eq!(test::call_service(&app, verify("Secret123")).await.status(), 200);
+ *     for _ in 0..5 {
+ *         assert_eq!(test::call_service(&app, verify("Guess")).await.status(), 401);
+ *     }
+ *     let response = test::call_service(&app, verify("Secret123")).await;
+ *     assert_eq!(response.status(), 429);
+ *     assert!(response.headers().contains_key("Retry-After"));
+ * });
+ * ```
+ */
+async fn verify_account_password(
+    req: HttpRequest,
+    pool: web::Data<sqlx::SqlitePool>,
+    user: AuthenticatedUser,
+    input: JsonInput<VerifyPasswordInput>,
+) -> HttpResponse {
+    let limit = req.app_data::<VerifyPasswordLimit>();
+    if let Some(wait) = limit.and_then(|limit| limit.locked_for(user.0.id)) {
+        let mut response = json_error(StatusCode::TOO_MANY_REQUESTS, "Too many failed attempts, try again later");
+        response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(wait.as_secs().max(1)));
+        return response;
+    }
+    match verify_user_password(&pool, user.0.id, &input.password).await {
+        Ok(true) => {
+            if let Some(limit) = limit {
+                limit.reset(user.0.id);
+            }
+            HttpResponse::Ok().finish()
+        }
+        Ok(false) => {
+            if let Some(limit) = limit {
+                limit.record_failure(user.0.id);
+            }
+            json_error(StatusCode::UNAUTHORIZED, "Invalid password")
+        }
+        Err(e) => db_error_response(&e),
+    }
+}
+
+/**
+ * Whoami route handler.
+ *
+ * This function returns the user authenticated by an `X-API-Key` header or a
+ * bearer token, which helps debugging authentication setups. The password hash
+ * is never included. Requests without valid credentials receive a `401 Unauthorized`.
+ *
+ * # Arguments
+ * - `user`: The user authenticated by the `RequirePrivilege` middleware.
+ *
+ * # Returns
+ * An `HttpResponse` containing the user's id, username and privilege level.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{register_user, rotate_api_key};
+ * use rusty_api::core::user::RegisterInput;
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *     let api_key = rotate_api_key(&pool, user.id).await.unwrap();
+ *
+ *     let whoami = |key: &str| test::TestRequest::get()
+ *         .uri("/whoami")
+ *         .insert_header(("X-API-Key", key))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *
+ *     let body: serde_json::Value = test::call_and_read_body_json(&app, whoami(&api_key)).await;
+ *     assert_eq!(body["username"], "alice");
+ *     assert_eq!(body["privilege_level"], 0);
+ *     assert!(body.get("password_hash").is_none());
+ *
+ *     assert_eq!(test::call_service(&app, whoami("wrong")).await.status(), 401);
+ * });
+ * ```
+ */
+async fn whoami(user: AuthenticatedUser) -> HttpResponse {
+    json_ok(user.0)
+}
+
+/**
+ * Account update route handler.
+ *
+ * This function applies a partial JSON update to the authenticated user with
+ * `patch_user`, changing only the fields present in the body. Only the fields in
+ * `SELF_EDITABLE_USER_FIELDS` can be changed; any other field, and an invalid
+ * username, is rejected with a `400 Bad Request`.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user`: The user authenticated by the `RequirePrivilege` middleware.
+ * - `fields`: The fields to change and their new values.
+ *
+ * # Returns
+ * An `HttpResponse` containing the updated user or an error message.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{generate_test_jwt, set_jwt_secret, Api, PrivilegeLevel};
+ * use rusty_api::core::auth::register_user;
+ * use rusty_api::core::user::RegisterInput;
+ * use actix_web::test;
+ * use std::time::Duration;
+ *
+ * set_jwt_secret("test-secret");
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *     let token = generate_test_jwt(user.id, PrivilegeLevel::User, Duration::from_secs(60));
+ *
+ *     let patch = |body: serde_json::Value| test::TestRequest::patch()
+ *         .uri("/account")
+ *         .insert_header(("Authorization", format!("Bearer {}", token)))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .set_json(body)
+ *         .to_request();
+ *
+ *     let updated: serde_json::Value = test::call_and_read_body_json(&app, patch(serde_json::json!({"username": "alicia"}))).await;
+ *     assert_eq!(updated["username"], "alicia");
+ *
+ *     let response = test::call_service(&app, patch(serde_json::json!({"password_hash": "x"}))).await;
+ *     assert_eq!(response.status(), 400);
+ *
+ *     let response = test::call_service(&app, patch(serde_json::json!({"username": ""}))).await;
+ *     assert_eq!(response.status(), 400);
+ * });
+ * ```
+ */
+async fn patch_account(
+    pool: web::Data<sqlx::SqlitePool>,
+    user: AuthenticatedUser,
+    fields: web::Json<serde_json::Map<String, serde_json::Value>>,
+) -> HttpResponse {
+    if let Err(e) = patch_user(&pool, user.0.id, &fields, &SELF_EDITABLE_USER_FIELDS).await {
+        return e.error_response();
+    }
+    match get_user_by_id(&pool, user.0.id).await {
+        Ok(Some(user)) => json_ok(user),
+        Ok(None) => json_error(StatusCode::NOT_FOUND, "User not found"),
+        Err(e) => db_error_response(&e),
+    }
+}
@@ -0,0 +1,189 @@
+/*!
+ * The `cache` module provides in-memory response caching for expensive read endpoints.
+ *
+ * A `RouteCache` stores successful responses keyed by path and query string, and
+ * serves them for a fixed TTL without invoking the handler. Each cached route has
+ * its own least-recently-used cache with a bounded number of entries.
+ *
+ * This module features:
+ * - **TTL Expiry**: Entries are served until they are older than the TTL, then refreshed.
+ * - **Bounded Memory**: At most `capacity` entries are kept, evicting the least recently used.
+ * - **Private Responses**: Requests carrying credentials bypass the cache unless opted in.
+ *
+ * Routes are cached with `Routes::add_route_cached` or `Routes::add_route_with_cache`.
+ */
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::response::json_error;
+
+/// Get the session cookie of a request, if cookie sessions are enabled with `Api::jwt_cookie`.
+#[cfg(feature = "auth")]
+pub(crate) fn session_cookie(req: &HttpRequest) -> Option<String> {
+    let cookie = req.app_data::<crate::core::auth::JwtCookie>()?;
+    req.cookie(&cookie.0).map(|cookie| cookie.value().to_string())
+}
+
+/// Get the session cookie of a request; there are no cookie sessions without the `auth` feature.
+#[cfg(not(feature = "auth"))]
+pub(crate) fn session_cookie(_req: &HttpRequest) -> Option<String> {
+    None
+}
+
+/// Default maximum number of responses cached per route.
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// Largest response body stored in the cache, in bytes.
+pub const MAX_CACHED_BODY: usize = 1024 * 1024;
+
+/**
+ * Response cache for a single route.
+ *
+ * Only `200 OK` responses with a sized body of at most `MAX_CACHED_BODY` bytes
+ * are stored, and never responses that set cookies or send `Cache-Control:
+ * no-store` or `private`. Requests with an `Authorization` or `X-API-Key` header,
+ * a session cookie or a `password` query parameter, bypass the cache unless `include_authenticated`
+ * is set; the credentials are then part of the cache key, so users never see
+ * each other's responses.
+ *
+ * Cloning a cache shares its entries.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::cache::RouteCache;
+ * use std::time::Duration;
+ *
+ * let cache = RouteCache::new(Duration::from_secs(30)).capacity(256);
+ * assert_eq!(cache.get_ttl(), Duration::from_secs(30));
+ * ```
+ */
+#[derive(Clone)]
+pub struct RouteCache {
+    ttl: Duration,
+    include_authenticated: bool,
+    entries: Arc<Mutex<LruCache<String, CachedResponse>>>,
+}
+
+/// A stored response with the time it was stored.
+struct CachedResponse {
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+impl RouteCache {
+    /**
+     * Create a cache serving stored responses for the given TTL.
+     *
+     * # Arguments
+     * - `ttl`: How long a stored response is served before the handler runs again.
+     */
+    pub fn new(ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(DEFAULT_CACHE_CAPACITY).unwrap();
+        Self {
+            ttl,
+            include_authenticated: false,
+            entries: Arc::new(Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    /// Set the maximum number of cached responses. A capacity of zero is treated as one.
+    pub fn capacity(self, capacity: usize) -> Self {
+        self.entries.lock().unwrap().resize(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN));
+        self
+    }
+
+    /// Also cache responses to requests carrying credentials, keyed by those credentials.
+    pub fn include_authenticated(mut self) -> Self {
+        self.include_authenticated = true;
+        self
+    }
+
+    /// Get the TTL of cached responses.
+    pub fn get_ttl(&self) -> Duration { self.ttl }
+
+    /// Get the cache key for a request, or `None` if the request must bypass the cache.
+    pub(crate) fn key(&self, req: &HttpRequest) -> Option<String> {
+        let authorization = req.headers().get(header::AUTHORIZATION).and_then(|h| h.to_str().ok());
+        let api_key = req.headers().get("X-API-Key").and_then(|h| h.to_str().ok());
+        let session = session_cookie(req);
+        let has_password = req.query_string().split('&').any(|pair| pair.starts_with("password="));
+
+        let uri = req.uri().path_and_query().map_or(req.path(), |pq| pq.as_str());
+        if authorization.is_none() && api_key.is_none() && session.is_none() {
+            return (!has_password || self.include_authenticated).then(|| uri.to_string());
+        }
+        if !self.include_authenticated {
+            return None;
+        }
+        Some(format!(
+            "{}\n{}\n{}\n{}",
+            uri,
+            authorization.unwrap_or_default(),
+            api_key.unwrap_or_default(),
+            session.unwrap_or_default()
+        ))
+    }
+
+    /// Get a fresh cached response for the key.
+    pub(crate) fn lookup(&self, key: &str) -> Option<HttpResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.get(key)?.stored_at.elapsed() > self.ttl {
+            entries.pop(key);
+            return None;
+        }
+        let entry = entries.peek(key)?;
+
+        let mut response = HttpResponse::Ok().body(entry.body.clone());
+        for (name, value) in &entry.headers {
+            response.headers_mut().append(name.clone(), value.clone());
+        }
+        Some(response)
+    }
+
+    /// Store the response under the key if it is cacheable, and return it to be sent.
+    pub(crate) async fn store(&self, key: String, response: HttpResponse) -> HttpResponse {
+        if !is_cacheable(&response) {
+            return response;
+        }
+
+        let (response, body) = response.into_parts();
+        let body = match actix_web::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        };
+
+        let entry = CachedResponse {
+            headers: response.headers().clone(),
+            body: body.clone(),
+            stored_at: Instant::now(),
+        };
+        self.entries.lock().unwrap().put(key, entry);
+
+        response.set_body(body).map_into_boxed_body()
+    }
+}
+
+/// Check whether a response may be stored in the cache.
+fn is_cacheable(response: &HttpResponse) -> bool {
+    let headers = response.headers();
+    let private = headers
+        .get(header::CACHE_CONTROL)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|value| {
+            let value = value.to_ascii_lowercase();
+            value.contains("no-store") || value.contains("private")
+        });
+
+    response.status() == StatusCode::OK
+        && !private
+        && !headers.contains_key(header::SET_COOKIE)
+        && matches!(response.body().size(), BodySize::Sized(n) if n as usize <= MAX_CACHED_BODY)
+}
@@ -0,0 +1,101 @@
+/*!
+ * The `cli` module configures the API server from command-line flags.
+ *
+ * One-file utilities and demos can take their bind address, port and TLS files
+ * from the command line without setting up an argument parser themselves.
+ * Requires the `cli` feature.
+ *
+ * This module features:
+ * - **Standard Flags**: `--bind`, `--port`, `--cert`, `--key` and `--no-tls`.
+ * - **Dry Run**: `--check` runs `Api::check` instead of starting the server.
+ * - **Help Output**: `--help` prints the flags and exits.
+ *
+ * The flags are applied with `Api::with_args` or `Api::run_blocking_with_args`.
+ */
+use std::ffi::OsString;
+
+use clap::Parser;
+
+use crate::api::Api;
+use crate::error::ApiError;
+
+/**
+ * The command-line flags understood by `Api::with_args`.
+ *
+ * Every flag is optional; missing flags keep the values already configured on the `Api`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::cli::CliArgs;
+ *
+ * let args = CliArgs::try_parse_args(["server", "--bind", "0.0.0.0", "--port", "8080", "--no-tls"]).unwrap();
+ * assert_eq!(args.bind.as_deref(), Some("0.0.0.0"));
+ * assert_eq!(args.port, Some(8080));
+ * assert!(args.no_tls);
+ * assert!(!args.check);
+ *
+ * assert!(CliArgs::try_parse_args(["server", "--port", "http"]).is_err());
+ * ```
+ */
+#[derive(Debug, Clone, Default, Parser)]
+#[command(about = "Start the API server")]
+pub struct CliArgs {
+    /// Address to bind the server to, e.g. 0.0.0.0.
+    #[arg(long, value_name = "ADDR")]
+    pub bind: Option<String>,
+    /// Port to bind the server to.
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Path to the TLS certificate file.
+    #[arg(long, value_name = "PATH", requires = "key")]
+    pub cert: Option<String>,
+    /// Path to the TLS private key file.
+    #[arg(long, value_name = "PATH", requires = "cert")]
+    pub key: Option<String>,
+    /// Serve plain HTTP instead of HTTPS.
+    #[arg(long, conflicts_with_all = ["cert", "key"])]
+    pub no_tls: bool,
+    /// Check the configuration, database and bind address, then exit without serving.
+    #[arg(long)]
+    pub check: bool,
+}
+
+impl CliArgs {
+    /**
+     * Parse flags from the given arguments, the first being the program name.
+     *
+     * # Returns
+     * The parsed flags, or `ApiError::InvalidConfig` describing an invalid or unknown flag.
+     */
+    pub fn try_parse_args<I, T>(args: I) -> Result<Self, ApiError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        Self::try_parse_from(args).map_err(|e| ApiError::InvalidConfig(e.to_string()))
+    }
+
+    /**
+     * Apply the flags to an `Api`, overriding the values they set.
+     *
+     * # Arguments
+     * - `api`: The API server to configure.
+     *
+     * # Returns
+     * The configured `Api`.
+     */
+    pub fn apply(self, mut api: Api) -> Api {
+        if self.bind.is_some() || self.port.is_some() {
+            let addr = self.bind.unwrap_or_else(|| api.get_addr().to_string());
+            let port = self.port.unwrap_or_else(|| api.get_port());
+            api = api.bind(&addr, port);
+        }
+        if let (Some(cert), Some(key)) = (&self.cert, &self.key) {
+            api = api.certs(cert, key);
+        }
+        if self.no_tls {
+            api = api.no_tls();
+        }
+        api
+    }
+}
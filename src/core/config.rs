@@ -9,6 +9,8 @@
  * - **Certificate Loading**: Reads and parses PEM-encoded certificate chains.
  * - **Private Key Loading**: Reads and parses PEM-encoded private keys.
  * - **Rustls Integration**: Creates a `ServerConfig` for secure HTTPS communication.
+ * - **SNI**: Presents a per-hostname certificate, see `SniCert` and `load_sni_tls_config`.
+ * - **Protocol Negotiation**: HTTP/2 and HTTP/1.1 are advertised via ALPN, see `ALPN_PROTOCOLS`.
  *
  * # Example
  * ```rust,no_run
@@ -19,47 +21,229 @@
  *    .start(); // Starting the API server will call this module internally.
  * ```
  */
-use rustls::{pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject}, ServerConfig};
+use crate::error::ConfigError;
+use rustls::{pki_types::{CertificateDer, PrivateKeyDer, pem::{self, PemObject}}, ServerConfig};
+use rustls::server::{ClientHello, ResolvesServerCert, ResolvesServerCertUsingSni};
+use rustls::sign::CertifiedKey;
+use std::io::ErrorKind;
 use std::path::Path;
+use std::sync::{Arc, Once};
 
-/// Loads the TLS configuration for the API server.
-pub fn load_rustls_config(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Option<ServerConfig> {
-    let cert_path = cert_path.as_ref();
-    let key_path = key_path.as_ref();
+static INIT: Once = Once::new();
 
-    // Load the certificate chain from the provided file
-    let cert_chain: Vec<CertificateDer> = match CertificateDer::pem_file_iter(cert_path)
-        .map(|res| res.flatten().collect::<Vec<_>>())
-    {
-        Ok(chain) if !chain.is_empty() => chain,
-        Ok(_) => {
-            println!("Error: No certificates found in {}", cert_path.display());
-            return None;
-        }
-        Err(e) => {
-            println!("Error: Failed to parse PEM file at {}: {}", cert_path.display(), e);
-            return None;
-        }
-    };
+/**
+ * The protocols advertised via ALPN during the TLS handshake, in order of preference.
+ *
+ * Actix Web puts these first on every rustls listener, so clients supporting
+ * HTTP/2 negotiate it and all others fall back to HTTP/1.1. They are not set on
+ * the `ServerConfig` returned by `load_rustls_config`, as that would advertise
+ * them twice. Because Actix always prefers `h2`, HTTP/2 cannot be turned off
+ * through the `ServerConfig`; this constant documents the fixed behavior.
+ */
+pub const ALPN_PROTOCOLS: [&str; 2] = ["h2", "http/1.1"];
 
-    // Load the private key from the provided file
-    let key_der = match PrivateKeyDer::from_pem_file(key_path) {
-        Ok(key) => key,
-        Err(_) => {
-            println!("Error: No private key found in {}", key_path.display());
-            return None;
-        }
-    };
+/**
+ * Initialize the crypto provider for Rustls.
+ *
+ * This function sets up the default crypto provider for Rustls using the `ring` library.
+ * It is called once to ensure that the provider is initialized only once during the lifetime
+ * of the application.
+ */
+pub(crate) fn initialize_crypto_provider() {
+    INIT.call_once(|| {
+        let _ = rustls::crypto::CryptoProvider::install_default(rustls::crypto::ring::default_provider());
+    });
+}
+
+/**
+ * A certificate presented to clients requesting one hostname via SNI.
+ *
+ * Added with `Api::add_sni_cert`.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SniCert {
+    /// The DNS name the certificate is presented for, e.g. `api.example.com`.
+    pub hostname: String,
+    /// Path to the PEM file holding the certificate chain.
+    pub cert_path: String,
+    /// Path to the PEM file holding the private key.
+    pub key_path: String,
+}
+
+/**
+ * Loads the TLS configuration for the API server, logging any error.
+ *
+ * See `load_tls_config` for a version returning the `ConfigError`.
+ */
+pub fn load_rustls_config(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Option<ServerConfig> {
+    load_tls_config(cert_path, key_path)
+        .map_err(|e| log::error!("{}", e))
+        .ok()
+}
+
+/**
+ * Loads the TLS configuration for the API server.
+ *
+ * # Arguments
+ * - `cert_path`: The PEM file holding the certificate chain.
+ * - `key_path`: The PEM file holding the private key.
+ *
+ * # Returns
+ * The `ServerConfig`, or a `ConfigError` telling a missing file apart from an unparseable one.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{load_tls_config, ConfigError};
+ *
+ * let result = load_tls_config("missing/cert.pem", "missing/key.pem");
+ * assert!(matches!(result, Err(ConfigError::FileNotFound(_))));
+ * ```
+ */
+pub fn load_tls_config(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Result<ServerConfig, ConfigError> {
+    initialize_crypto_provider();
+    let (cert_chain, key_der) = load_pem_files(cert_path.as_ref(), key_path.as_ref())?;
 
     // Build and return the Rustls server configuration
-    match ServerConfig::builder()
+    ServerConfig::builder()
         .with_no_client_auth()
         .with_single_cert(cert_chain, key_der)
-    {
-        Ok(config) => Some(config),
-        Err(e) => {
-            println!("Error: Failed to build TLS configuration: {}", e);
-            None
-        }
+        .map_err(|e| ConfigError::InvalidTls(e.to_string()))
+}
+
+/**
+ * Loads a TLS configuration presenting a certificate chosen by the client's SNI hostname.
+ *
+ * Clients requesting one of the `sni_certs` hostnames receive its certificate.
+ * All others, including clients sending no SNI, receive the default certificate.
+ *
+ * # Arguments
+ * - `cert_path`: The PEM file holding the default certificate chain.
+ * - `key_path`: The PEM file holding the default private key.
+ * - `sni_certs`: The per-hostname certificates.
+ *
+ * # Returns
+ * The `ServerConfig`, or a `ConfigError` if a file cannot be loaded or a
+ * certificate is not valid for its hostname.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{load_sni_tls_config, ConfigError, SniCert};
+ * use rustls::pki_types::{CertificateDer, ServerName};
+ * use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConnection};
+ * use std::sync::Arc;
+ *
+ * // Write a self-signed certificate for the names, returning its paths and DER
+ * let dir = std::env::temp_dir().join(format!("rusty-api-sni-{}", std::process::id()));
+ * std::fs::create_dir_all(&dir).unwrap();
+ * let generate = |file: &str, names: &[&str]| {
+ *     let names = names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+ *     let certified = rcgen::generate_simple_self_signed(names).unwrap();
+ *     let (cert_path, key_path) = (dir.join(format!("{file}.pem")), dir.join(format!("{file}-key.pem")));
+ *     std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+ *     std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+ *     let path = |path: std::path::PathBuf| path.to_string_lossy().into_owned();
+ *     (path(cert_path), path(key_path), certified.cert.der().clone())
+ * };
+ * let (default_cert, default_key, default_der) = generate("default", &["localhost", "other.example.com"]);
+ * let (api_cert, api_key, api_der) = generate("api", &["api.example.com"]);
+ *
+ * let sni_certs = [SniCert { hostname: "api.example.com".into(), cert_path: api_cert, key_path: api_key }];
+ * let server_config = Arc::new(load_sni_tls_config(&default_cert, &default_key, &sni_certs).unwrap());
+ *
+ * // Complete a handshake in memory, returning the certificate the server presented
+ * let presented = |hostname: &'static str| -> CertificateDer<'static> {
+ *     let mut roots = RootCertStore::empty();
+ *     roots.add(default_der.clone()).unwrap();
+ *     roots.add(api_der.clone()).unwrap();
+ *     let client_config = ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+ *     let mut client = ClientConnection::new(Arc::new(client_config), ServerName::try_from(hostname).unwrap()).unwrap();
+ *     let mut server = ServerConnection::new(server_config.clone()).unwrap();
+ *     while client.is_handshaking() || server.is_handshaking() {
+ *         let mut buffer = Vec::new();
+ *         client.write_tls(&mut buffer).unwrap();
+ *         server.read_tls(&mut buffer.as_slice()).unwrap();
+ *         server.process_new_packets().unwrap();
+ *         let mut buffer = Vec::new();
+ *         server.write_tls(&mut buffer).unwrap();
+ *         client.read_tls(&mut buffer.as_slice()).unwrap();
+ *         client.process_new_packets().unwrap();
+ *     }
+ *     client.peer_certificates().unwrap()[0].clone().into_owned()
+ * };
+ *
+ * assert_eq!(presented("api.example.com"), api_der);
+ * // Names without their own certificate fall back to the default one
+ * assert_eq!(presented("other.example.com"), default_der);
+ * std::fs::remove_dir_all(&dir).unwrap();
+ *
+ * let result = load_sni_tls_config("missing/cert.pem", "missing/key.pem", &sni_certs);
+ * assert!(matches!(result, Err(ConfigError::FileNotFound(_))));
+ * ```
+ */
+pub fn load_sni_tls_config(
+    cert_path: impl AsRef<Path>,
+    key_path: impl AsRef<Path>,
+    sni_certs: &[SniCert],
+) -> Result<ServerConfig, ConfigError> {
+    initialize_crypto_provider();
+    let builder = ServerConfig::builder().with_no_client_auth();
+    let provider = builder.crypto_provider().clone();
+    let certified_key = |cert_path: &Path, key_path: &Path| {
+        let (cert_chain, key_der) = load_pem_files(cert_path, key_path)?;
+        CertifiedKey::from_der(cert_chain, key_der, &provider).map_err(|e| ConfigError::InvalidTls(e.to_string()))
+    };
+
+    let default = Arc::new(certified_key(cert_path.as_ref(), key_path.as_ref())?);
+    let mut by_name = ResolvesServerCertUsingSni::new();
+    for sni in sni_certs {
+        let key = certified_key(Path::new(&sni.cert_path), Path::new(&sni.key_path))?;
+        by_name
+            .add(&sni.hostname, key)
+            .map_err(|e| ConfigError::InvalidTls(format!("certificate for {}: {}", sni.hostname, e)))?;
+    }
+
+    Ok(builder.with_cert_resolver(Arc::new(SniResolver { by_name, default })))
+}
+
+/// Resolves certificates by SNI hostname, falling back to the default certificate.
+#[derive(Debug)]
+struct SniResolver {
+    by_name: ResolvesServerCertUsingSni,
+    default: Arc<CertifiedKey>,
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.by_name.resolve(client_hello).or_else(|| Some(self.default.clone()))
+    }
+}
+
+/// Reads the certificate chain and private key from PEM files.
+fn load_pem_files(
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), ConfigError> {
+    // Load the certificate chain from the provided file
+    let cert_chain: Vec<CertificateDer<'static>> = CertificateDer::pem_file_iter(cert_path)
+        .map(|res| res.flatten().collect())
+        .map_err(|e| pem_error(cert_path, e))?;
+    if cert_chain.is_empty() {
+        return Err(ConfigError::InvalidFile(cert_path.to_path_buf(), "no certificates found".into()));
+    }
+
+    // Load the private key from the provided file
+    let key_der = PrivateKeyDer::from_pem_file(key_path).map_err(|e| match e {
+        pem::Error::NoItemsFound => ConfigError::InvalidFile(key_path.to_path_buf(), "no private key found".into()),
+        e => pem_error(key_path, e),
+    })?;
+
+    Ok((cert_chain, key_der))
+}
+
+/// Maps a PEM error to a `ConfigError`, reporting a missing file as `FileNotFound`.
+fn pem_error(path: &Path, e: pem::Error) -> ConfigError {
+    match e {
+        pem::Error::Io(e) if e.kind() == ErrorKind::NotFound => ConfigError::FileNotFound(path.to_path_buf()),
+        e => ConfigError::InvalidFile(path.to_path_buf(), e.to_string()),
     }
-}
\ No newline at end of file
+}
@@ -4,33 +4,620 @@
  * This module handles the database connection and provides functions to interact
  * with the database, including querying and updating user fields.
  */
-use sqlx::{Pool, Sqlite, SqlitePool};
+use sqlx::{Pool, Row, Sqlite, SqlitePool};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use std::collections::HashMap;
 use std::env;
-use actix_web::HttpResponse;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::Duration;
+use actix_web::http::header::{HeaderValue, RETRY_AFTER};
+use actix_web::{http::StatusCode, HttpResponse};
 use crate::DB_POOL;
+use crate::core::response::json_error;
+use crate::core::user::User;
+use futures_util::{stream, Stream, StreamExt};
+use serde::Serialize;
+
+static ACTIVE_POOL: RwLock<Option<SqlitePool>> = RwLock::new(None);
+static PROFILE_FIELDS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Built-in columns of the `users` table that can be read by field name.
+pub const READABLE_USER_FIELDS: [&str; 6] = ["id", "username", "privilege_level", "created_at", "updated_at", "enabled"];
+
+/// Built-in columns of the `users` table that can be written by field name.
+pub const WRITABLE_USER_FIELDS: [&str; 1] = ["username"];
+
+/// Every built-in column of the `users` table, none of which can be registered as a profile field.
+pub const BUILT_IN_USER_FIELDS: [&str; 8] =
+    ["id", "username", "password_hash", "privilege_level", "api_key_hash", "created_at", "updated_at", "enabled"];
+
+/// Database URL for a private in-memory SQLite database.
+pub const IN_MEMORY_DATABASE_URL: &str = "sqlite::memory:";
+
+/// Default time to wait for a free pool connection before giving up.
+pub const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Database URL used when neither `Api::database_url` nor `DATABASE_URL` is set: `users.db` in the working directory.
+pub const DEFAULT_DATABASE_URL: &str = "sqlite:./users.db";
 
 /**
  * Initialize the database connection.
  *
  * This function creates a connection pool to the SQLite database specified in
  * the `DATABASE_URL` environment variable. If the variable is not set, it defaults
- * to `sqlite:./users.db.db`.
+ * to `DEFAULT_DATABASE_URL`. The `users` table is created if it does not exist yet.
  *
  * # Returns
  * A `Result` containing the connection pool or an error if the connection fails.
  */
 pub async fn init_db() -> Result<Pool<Sqlite>, sqlx::Error> {
-    let db_url = env::var("DATABASE_URL").unwrap_or("sqlite:./users.db".to_string());
-    let pool = SqlitePool::connect(&db_url).await?;
+    init_db_with_url(&database_url_from_env()).await
+}
+
+/// Get the database URL from the `DATABASE_URL` environment variable, or `DEFAULT_DATABASE_URL`.
+pub fn database_url_from_env() -> String {
+    env::var("DATABASE_URL").unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string())
+}
+
+/**
+ * Resolve the file a SQLite database URL points at.
+ *
+ * Relative paths are resolved against the current working directory, so
+ * `sqlite:users.db`, `sqlite://users.db` and `sqlite:./users.db` all name the
+ * same file. Logging the resolved path makes it obvious when a server opens a
+ * different file than the one that was migrated.
+ *
+ * # Arguments
+ * - `db_url`: The SQLite database URL.
+ *
+ * # Returns
+ * The absolute path of the database file, or `None` for in-memory databases.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::db::{database_path, IN_MEMORY_DATABASE_URL};
+ *
+ * let cwd = std::env::current_dir().unwrap();
+ * assert_eq!(database_path("sqlite:./users.db"), Some(cwd.join("users.db")));
+ * assert_eq!(database_path("sqlite://users.db?mode=rwc"), Some(cwd.join("users.db")));
+ * assert_eq!(database_path("sqlite:///var/lib/api/users.db"), Some("/var/lib/api/users.db".into()));
+ * assert_eq!(database_path(IN_MEMORY_DATABASE_URL), None);
+ * ```
+ */
+pub fn database_path(db_url: &str) -> Option<PathBuf> {
+    if is_in_memory(db_url) {
+        return None;
+    }
+    let path = db_url.strip_prefix("sqlite:").unwrap_or(db_url);
+    let path = path.strip_prefix("//").unwrap_or(path);
+    let path = path.split('?').next().unwrap_or(path);
+    let path = Path::new(path);
+    let path = path.strip_prefix(".").unwrap_or(path);
+    if path.is_absolute() {
+        Some(path.to_path_buf())
+    } else {
+        env::current_dir().ok().map(|cwd| cwd.join(path))
+    }
+}
+
+/**
+ * Initialize the database connection for the given URL.
+ *
+ * In-memory URLs such as `sqlite::memory:` are supported: all connections of the
+ * pool share the same database, and one connection is kept open for the lifetime
+ * of the pool so the data is not discarded when the pool is idle.
+ *
+ * # Arguments
+ * - `db_url`: The SQLite database URL.
+ *
+ * # Returns
+ * A `Result` containing the connection pool or an error if the connection fails.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::db::{init_db_with_url, IN_MEMORY_DATABASE_URL};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let pool = init_db_with_url(IN_MEMORY_DATABASE_URL).await.unwrap();
+ *     sqlx::query("INSERT INTO users (username, password_hash) VALUES ('alice', 'hash')")
+ *         .execute(&pool)
+ *         .await
+ *         .unwrap();
+ *
+ *     // Every connection of the pool sees the same in-memory database.
+ *     let mut first = pool.acquire().await.unwrap();
+ *     let mut second = pool.acquire().await.unwrap();
+ *     let count = "SELECT COUNT(*) FROM users";
+ *     assert_eq!(sqlx::query_scalar::<_, i64>(count).fetch_one(&mut *first).await.unwrap(), 1);
+ *     assert_eq!(sqlx::query_scalar::<_, i64>(count).fetch_one(&mut *second).await.unwrap(), 1);
+ * });
+ * ```
+ */
+pub async fn init_db_with_url(db_url: &str) -> Result<Pool<Sqlite>, sqlx::Error> {
+    init_db_with_options(db_url, DEFAULT_ACQUIRE_TIMEOUT).await
+}
+
+/**
+ * Initialize the database connection for the given URL and acquire timeout.
+ *
+ * # Arguments
+ * - `db_url`: The SQLite database URL.
+ * - `acquire_timeout`: How long to wait for a free connection before failing with `PoolTimedOut`.
+ *
+ * # Returns
+ * A `Result` containing the connection pool or an error if the connection fails.
+ */
+pub async fn init_db_with_options(db_url: &str, acquire_timeout: Duration) -> Result<Pool<Sqlite>, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(db_url)?;
+    let mut pool_options = SqlitePoolOptions::new().acquire_timeout(acquire_timeout);
+    if is_in_memory(db_url) {
+        // An in-memory database is dropped once its last connection closes.
+        pool_options = pool_options
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None);
+    }
+
+    let pool = pool_options.connect_with(options).await?;
+    create_schema(&pool).await?;
 
     Ok(pool)
 }
 
+/**
+ * Initialize the database connection, retrying with exponential backoff on failure.
+ *
+ * Transient failures (e.g. the database volume not being mounted yet during a
+ * restart) are retried up to `attempts` times in total, doubling the delay after
+ * each failure. Configuration errors such as a malformed URL are not retried.
+ *
+ * # Arguments
+ * - `db_url`: The SQLite database URL.
+ * - `acquire_timeout`: How long to wait for a free connection before failing with `PoolTimedOut`.
+ * - `attempts`: The total number of connection attempts, at least one is always made.
+ * - `initial_delay`: The delay before the first retry.
+ *
+ * # Returns
+ * A `Result` containing the connection pool or the error of the last attempt.
+ */
+pub async fn init_db_with_retry(
+    db_url: &str,
+    acquire_timeout: Duration,
+    attempts: u32,
+    initial_delay: Duration,
+) -> Result<Pool<Sqlite>, sqlx::Error> {
+    let attempts = attempts.max(1);
+    let mut delay = initial_delay;
+    let mut attempt = 1;
+
+    loop {
+        match init_db_with_options(db_url, acquire_timeout).await {
+            Ok(pool) => return Ok(pool),
+            Err(e @ sqlx::Error::Configuration(_)) => return Err(e),
+            Err(e) if attempt >= attempts => return Err(e),
+            Err(e) => {
+                log::warn!(
+                    "Database connection attempt {}/{} failed: {}. Retrying in {:?}",
+                    attempt, attempts, e, delay
+                );
+                actix_web::rt::time::sleep(delay).await;
+                delay = delay.saturating_mul(2);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Check whether a database URL refers to an in-memory SQLite database.
+fn is_in_memory(db_url: &str) -> bool {
+    db_url.contains(":memory:") || db_url.contains("mode=memory")
+}
+
+/**
+ * Create the `users`, `audit_log`, `sessions` and `request_quotas` tables and add any columns
+ * missing from older databases.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ */
+pub async fn create_schema(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS users (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username TEXT NOT NULL UNIQUE,
+            password_hash TEXT NOT NULL,
+            privilege_level INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            enabled INTEGER NOT NULL DEFAULT 1
+        )"
+    )
+    .execute(pool)
+    .await?;
+
+    add_column_if_missing(pool, "users", "privilege_level", "INTEGER NOT NULL DEFAULT 0").await?;
+    add_column_if_missing(pool, "users", "api_key_hash", "TEXT").await?;
+    // SQLite cannot add columns defaulting to `CURRENT_TIMESTAMP`, so older
+    // databases get plain columns and inserts set the timestamps explicitly.
+    add_column_if_missing(pool, "users", "created_at", "TEXT").await?;
+    add_column_if_missing(pool, "users", "updated_at", "TEXT").await?;
+    add_column_if_missing(pool, "users", "enabled", "INTEGER NOT NULL DEFAULT 1").await?;
+    sqlx::query(
+        "CREATE TRIGGER IF NOT EXISTS users_updated_at AFTER UPDATE ON users
+         FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+         BEGIN
+             UPDATE users SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+         END"
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS users_api_key_hash ON users (api_key_hash)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            user_id INTEGER,
+            event TEXT NOT NULL,
+            ip TEXT
+        )"
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            id_hash TEXT PRIMARY KEY,
+            user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL
+        )"
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS sessions_user_id ON sessions (user_id)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS request_quotas (
+            user_id INTEGER NOT NULL,
+            period TEXT NOT NULL,
+            count INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (user_id, period)
+        )"
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Add a column to a table unless it already exists.
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), sqlx::Error> {
+    let exists: Option<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?) WHERE name = ?")
+        .bind(table)
+        .bind(column)
+        .fetch_optional(pool)
+        .await?;
+
+    if exists.is_none() {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+
+/// Seconds clients are asked to wait before retrying when the database is unavailable.
+pub const DB_RETRY_AFTER_SECS: u64 = 5;
+
+/**
+ * Map a database error to an HTTP status code and a client-facing message.
+ *
+ * The returned message avoids leaking internals. Nothing is logged here;
+ * `db_error_response` logs the full error once when building the response.
+ * The mapping is:
+ * - `ColumnNotFound` (an unknown field name) becomes `400 Bad Request`.
+ * - `RowNotFound` becomes `404 Not Found`.
+ * - A UNIQUE constraint violation becomes `409 Conflict`.
+ * - A pool timeout, a closed pool or a busy/locked database becomes `503 Service Unavailable`.
+ * - Anything else becomes `500 Internal Server Error`, including the SQLite error code if any.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::db::classify_db_error;
+ * use rusty_api::StatusCode;
+ *
+ * let (status, _) = classify_db_error(&sqlx::Error::PoolTimedOut);
+ * assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+ *
+ * let (status, _) = classify_db_error(&sqlx::Error::RowNotFound);
+ * assert_eq!(status, StatusCode::NOT_FOUND);
+ * ```
+ */
+pub fn classify_db_error(e: &sqlx::Error) -> (StatusCode, String) {
+    match e {
+        sqlx::Error::ColumnNotFound(column) => (StatusCode::BAD_REQUEST, format!("Unknown field '{}'", column)),
+        sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "Record not found".into()),
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Database is busy, please try again later".into(),
+        ),
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            (StatusCode::CONFLICT, "Resource already exists".into())
+        }
+        sqlx::Error::Database(db_err) => {
+            let code = db_err.code().map(|code| code.to_string());
+            // SQLite primary result codes live in the low byte of extended codes.
+            let primary = code.as_deref().and_then(|code| code.parse::<i32>().ok()).map(|code| code & 0xff);
+            match primary {
+                Some(5) | Some(6) => (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Database is busy, please try again later".into(),
+                ),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Database error (code {})", code.as_deref().unwrap_or("unknown")),
+                ),
+            }
+        }
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Database error".into()),
+    }
+}
+
+/**
+ * Build the JSON error response for a database error.
+ *
+ * The full error is logged, and the status and message sent to the client
+ * come from `classify_db_error`. When the database is
+ * unavailable (`503`), a `Retry-After` header of `DB_RETRY_AFTER_SECS` is added
+ * so clients with retry logic back off during a brief outage.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::db::db_error_response;
+ *
+ * let response = db_error_response(&sqlx::Error::PoolTimedOut);
+ * assert_eq!(response.status(), 503);
+ * assert_eq!(response.headers().get("Retry-After").unwrap(), "5");
+ *
+ * let response = db_error_response(&sqlx::Error::RowNotFound);
+ * assert!(response.headers().get("Retry-After").is_none());
+ * ```
+ */
+pub fn db_error_response(e: &sqlx::Error) -> HttpResponse {
+    log::error!("Database error: {}", e);
+    let (status, message) = classify_db_error(e);
+    with_retry_after(json_error(status, message))
+}
+
+/// Log a database error and build a plain-text response for it, for the field helpers.
+fn db_error_text_response(e: &sqlx::Error) -> HttpResponse {
+    log::error!("Database error: {}", e);
+    let (status, message) = classify_db_error(e);
+    with_retry_after(HttpResponse::build(status).body(message))
+}
+
+/// Add a `Retry-After` header to `503 Service Unavailable` responses that lack one.
+fn with_retry_after(mut response: HttpResponse) -> HttpResponse {
+    if response.status() == StatusCode::SERVICE_UNAVAILABLE && !response.headers().contains_key(RETRY_AFTER) {
+        response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(DB_RETRY_AFTER_SECS));
+    }
+    response
+}
+
+/**
+ * Register the pool created by the `Api` as the process-wide user database pool.
+ *
+ * This is called when the `Api` initializes its user database, so `global_pool`,
+ * `DB_POOL`, `get_user_field` and `set_user_field` all use the same pool as the
+ * request handlers. Registering another pool replaces the previous one.
+ */
+pub fn set_global_pool(pool: SqlitePool) {
+    *ACTIVE_POOL.write().unwrap() = Some(pool);
+}
+
+/**
+ * Get the user database pool registered by the `Api`.
+ *
+ * Use this from code running outside of request handlers, e.g. background tasks.
+ *
+ * # Returns
+ * The pool, or `None` if no `Api` has initialized its user database yet.
+ */
+pub fn global_pool() -> Option<SqlitePool> {
+    ACTIVE_POOL.read().unwrap().clone()
+}
+
+/**
+ * Register custom columns of the `users` table as readable and writable profile fields.
+ *
+ * Only the columns in `READABLE_USER_FIELDS` and `WRITABLE_USER_FIELDS` and the
+ * registered profile fields can be accessed by name with `get_user_field`,
+ * `set_user_field` and friends. This is called with the fields set with
+ * `Api::user_profile_fields` when the `Api` initializes its user database.
+ * Registering replaces the previously registered fields.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::db::{get_user_fields, init_db_with_url, register_profile_fields, IN_MEMORY_DATABASE_URL};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let pool = init_db_with_url(IN_MEMORY_DATABASE_URL).await.unwrap();
+ *     sqlx::query("ALTER TABLE users ADD COLUMN role TEXT").execute(&pool).await.unwrap();
+ *     sqlx::query("INSERT INTO users (username, password_hash, role) VALUES ('alice', 'x', 'editor')")
+ *         .execute(&pool).await.unwrap();
+ *
+ *     register_profile_fields(&["role"]);
+ *     assert_eq!(get_user_fields(&pool, 1, &["role"]).await.unwrap()["role"], "editor");
+ * });
+ * ```
+ */
+pub fn register_profile_fields(fields: &[&str]) {
+    *PROFILE_FIELDS.write().unwrap() = fields.iter().map(|field| field.to_string()).collect();
+}
+
+/**
+ * Check that a name can be registered as a profile field.
+ *
+ * # Returns
+ * `Ok(())`, or why the name is refused: it is not a plain SQL identifier or is a built-in column.
+ */
+pub fn check_profile_field(field: &str) -> Result<(), String> {
+    let mut chars = field.chars();
+    let identifier = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !identifier {
+        return Err(format!("'{}' is not a valid column name", field));
+    }
+    if BUILT_IN_USER_FIELDS.contains(&field) {
+        return Err(format!("'{}' is a built-in column", field));
+    }
+    Ok(())
+}
+
+/// Get the registered pool, falling back to the `DATABASE_URL`-based `DB_POOL`.
+fn shared_pool() -> SqlitePool {
+    global_pool().unwrap_or_else(|| DB_POOL.clone())
+}
+
+/**
+ * A snapshot of the connection pool's usage.
+ *
+ * When `in_use` stays at `max_connections`, requests wait for a connection and
+ * fail with `503 Service Unavailable` once the acquire timeout passes.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::db::PoolStats;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *
+ *     let stats = PoolStats::of(&pool);
+ *     assert_eq!(stats.size, stats.idle + stats.in_use);
+ *     assert!(stats.size <= stats.max_connections);
+ *     assert!(!stats.closed);
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PoolStats {
+    /// Open connections, in use or idle, including ones being opened.
+    pub size: u32,
+    /// Open connections waiting to be used.
+    pub idle: u32,
+    /// Connections currently checked out by queries or being opened.
+    pub in_use: u32,
+    /// The most connections the pool opens.
+    pub max_connections: u32,
+    /// Whether the pool has been closed.
+    pub closed: bool,
+}
+
+impl PoolStats {
+    /// Take a snapshot of the given pool.
+    pub fn of(pool: &SqlitePool) -> Self {
+        let size = pool.size();
+        let idle = pool.num_idle() as u32;
+        Self {
+            size,
+            idle,
+            in_use: size.saturating_sub(idle),
+            max_connections: pool.options().get_max_connections(),
+            closed: pool.is_closed(),
+        }
+    }
+}
+
+/**
+ * Get one page of users, ordered by ID.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `limit`: The maximum number of users returned.
+ * - `offset`: The number of users skipped.
+ *
+ * # Returns
+ * The users of the page, empty past the last user.
+ */
+pub async fn list_users(pool: &SqlitePool, limit: u32, offset: u64) -> Result<Vec<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        "SELECT id, username, password_hash, privilege_level, created_at, updated_at, enabled FROM users ORDER BY id LIMIT ? OFFSET ?"
+    )
+    .bind(limit)
+    .bind(i64::try_from(offset).unwrap_or(i64::MAX))
+    .fetch_all(pool)
+    .await
+}
+
+/**
+ * Stream all users, ordered by ID, fetching one page at a time.
+ *
+ * Only one page of users is held in memory at once, which makes this suitable
+ * for large exports with `ndjson_response`.
+ *
+ * # Arguments
+ * - `pool`: The SQLx SQLite connection pool.
+ * - `page_size`: The number of users fetched per query.
+ *
+ * # Returns
+ * A stream of users. A database error ends the stream after being yielded.
+ */
+pub fn stream_users(pool: SqlitePool, page_size: u32) -> impl Stream<Item = Result<User, sqlx::Error>> {
+    let page_size = page_size.max(1);
+    let pages = stream::unfold(Some(0), move |last_id| {
+        let pool = pool.clone();
+        async move {
+            let last_id = last_id?;
+            let page = sqlx::query_as::<_, User>(
+                "SELECT id, username, password_hash, privilege_level, created_at, updated_at, enabled FROM users WHERE id > ? ORDER BY id LIMIT ?"
+            )
+            .bind(last_id)
+            .bind(page_size)
+            .fetch_all(&pool)
+            .await;
+
+            match page {
+                Ok(users) if users.is_empty() => None,
+                Ok(users) => {
+                    let next = (users.len() as u32 == page_size).then(|| users[users.len() - 1].id);
+                    Some((Ok(users), next))
+                }
+                Err(e) => Some((Err(e), None)),
+            }
+        }
+    });
+
+    pages.flat_map(|page| {
+        let items: Vec<Result<User, sqlx::Error>> = match page {
+            Ok(users) => users.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        };
+        stream::iter(items)
+    })
+}
 
 /**
  * Get a user field from the database.
  *
  * This function retrieves a specific field from the `users` table for a given user ID.
+ * Only the fields in `READABLE_USER_FIELDS` and the registered profile fields can
+ * be read, so the password and API key hashes never leave the database.
  *
  * # Arguments
  * - `user_id`: The ID of the user to retrieve the field for.
@@ -38,16 +625,39 @@ pub async fn init_db() -> Result<Pool<Sqlite>, sqlx::Error> {
  *
  * # Returns
  * An `HttpResponse` containing the value of the field or an error message if the field is not found.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{get_user_field, set_user_field, Api};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     sqlx::query("INSERT INTO users (username, password_hash) VALUES ('alice', 'x')")
+ *         .execute(&api.db_pool().unwrap()).await.unwrap();
+ *
+ *     assert_eq!(get_user_field(1, "username").await.status(), 200);
+ *     assert_eq!(get_user_field(1, "password_hash").await.status(), 400);
+ *     assert_eq!(set_user_field(1, "privilege_level", "100").await.status(), 400);
+ *     assert_eq!(set_user_field(1, "password_hash", "x").await.status(), 400);
+ * });
+ * ```
  */
 pub async fn get_user_field(user_id: i32, field: &str) -> HttpResponse {
+    let pool = shared_pool();
+    if let Err(e) = check_user_fields(&[field], false) {
+        let (status, message) = classify_db_error(&e);
+        return with_retry_after(HttpResponse::build(status).body(message));
+    }
+
     let query = format!("SELECT {} FROM users WHERE id = ?", field);
     let result: Option<(String,)> = match sqlx::query_as(&query)
         .bind(user_id)
-        .fetch_optional(&*DB_POOL)
+        .fetch_optional(&pool)
         .await
     {
         Ok(result) => result,
-        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+        Err(e) => return db_error_text_response(&e),
     };
 
     match result {
@@ -56,10 +666,80 @@ pub async fn get_user_field(user_id: i32, field: &str) -> HttpResponse {
     }
 }
 
+/**
+ * Get several user fields in a single query.
+ *
+ * Only the built-in fields in `READABLE_USER_FIELDS` and the registered profile
+ * fields can be read, so `password_hash` and `api_key_hash` are refused like
+ * unknown columns. The fields are read with one `SELECT`, avoiding a query per
+ * field when building e.g. a profile response. Values are returned as text;
+ * `NULL` values are left out of the map.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user to read.
+ * - `fields`: The names of the fields to read.
+ *
+ * # Returns
+ * A map from field name to value, `sqlx::Error::RowNotFound` if the user does not
+ * exist, or `sqlx::Error::ColumnNotFound` if a field is not readable.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::db::{get_user_fields, init_db_with_url, IN_MEMORY_DATABASE_URL};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let pool = init_db_with_url(IN_MEMORY_DATABASE_URL).await.unwrap();
+ *     sqlx::query("INSERT INTO users (username, password_hash) VALUES ('alice', 'x')")
+ *         .execute(&pool).await.unwrap();
+ *
+ *     let fields = get_user_fields(&pool, 1, &["username", "privilege_level"]).await.unwrap();
+ *     assert_eq!(fields["username"], "alice");
+ *     assert_eq!(fields["privilege_level"], "0");
+ *
+ *     let result = get_user_fields(&pool, 1, &["username", "nope FROM users; --"]).await;
+ *     assert!(matches!(result, Err(sqlx::Error::ColumnNotFound(_))));
+ *     for secret in ["password_hash", "api_key_hash"] {
+ *         let result = get_user_fields(&pool, 1, &["username", secret]).await;
+ *         assert!(matches!(result, Err(sqlx::Error::ColumnNotFound(_))));
+ *     }
+ *     assert!(matches!(get_user_fields(&pool, 2, &["username"]).await, Err(sqlx::Error::RowNotFound)));
+ * });
+ * ```
+ */
+pub async fn get_user_fields(
+    pool: &SqlitePool,
+    user_id: i32,
+    fields: &[&str],
+) -> Result<HashMap<String, String>, sqlx::Error> {
+    if fields.is_empty() {
+        return Ok(HashMap::new());
+    }
+    check_user_fields(fields, false)?;
+
+    let columns: Vec<String> = fields.iter().map(|field| format!("CAST({} AS TEXT)", field)).collect();
+    let query = format!("SELECT {} FROM users WHERE id = ?", columns.join(", "));
+    let row = sqlx::query(&query)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+    let mut values = HashMap::with_capacity(fields.len());
+    for (index, field) in fields.iter().enumerate() {
+        if let Some(value) = row.try_get::<Option<String>, _>(index)? {
+            values.insert(field.to_string(), value);
+        }
+    }
+    Ok(values)
+}
+
 /**
  * Set a user field in the database.
  *
  * This function updates a specific field in the `users` table for a given user ID.
+ * Only the fields in `WRITABLE_USER_FIELDS` and the registered profile fields can
+ * be written; see `get_user_field` for an example.
  *
  * # Arguments
  * - `user_id`: The ID of the user to update the field for.
@@ -70,11 +750,17 @@ pub async fn get_user_field(user_id: i32, field: &str) -> HttpResponse {
  * An `HttpResponse` indicating the success or failure of the operation.
  */
 pub async fn set_user_field(user_id: i32, field: &str, value: &str) -> HttpResponse {
+    let pool = shared_pool();
+    if let Err(e) = check_user_fields(&[field], true) {
+        let (status, message) = classify_db_error(&e);
+        return with_retry_after(HttpResponse::build(status).body(message));
+    }
+
     let query = format!("UPDATE users SET {} = ? WHERE id = ?", field);
     let result = sqlx::query(&query)
         .bind(value)
         .bind(user_id)
-        .execute(&*DB_POOL)
+        .execute(&pool)
         .await;
 
     match result {
@@ -82,6 +768,216 @@ pub async fn set_user_field(user_id: i32, field: &str, value: &str) -> HttpRespo
             HttpResponse::Ok().body(format!("Field '{}' updated successfully", field))
         }
         Ok(_) => HttpResponse::NotFound().body(format!("User with ID '{}' not found", user_id)),
-        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+        Err(e) => db_error_text_response(&e),
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Set several user fields in a single statement.
+ *
+ * All field names are checked against `WRITABLE_USER_FIELDS` and the registered
+ * profile fields before anything is written, and the update runs in a
+ * transaction, so either every field is updated or none is. The password hash,
+ * privilege level, API key and account status have dedicated functions in
+ * `core::auth` instead.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user to update.
+ * - `fields`: The `(field, value)` pairs to set.
+ *
+ * # Returns
+ * The number of rows changed (`0` if the user does not exist or `fields` is empty),
+ * or `sqlx::Error::ColumnNotFound` if a field name is not writable.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::db::{init_db_with_url, set_user_fields, IN_MEMORY_DATABASE_URL};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let pool = init_db_with_url(IN_MEMORY_DATABASE_URL).await.unwrap();
+ *     sqlx::query("INSERT INTO users (username, password_hash) VALUES ('alice', 'x')")
+ *         .execute(&pool).await.unwrap();
+ *
+ *     let changed = set_user_fields(&pool, 1, &[("username", "alicia")]).await.unwrap();
+ *     assert_eq!(changed, 1);
+ *
+ *     // Sensitive columns cannot be written by name.
+ *     for field in ["privilege_level", "password_hash", "api_key_hash", "enabled", "id"] {
+ *         let result = set_user_fields(&pool, 1, &[(field, "100")]).await;
+ *         assert!(matches!(result, Err(sqlx::Error::ColumnNotFound(_))));
+ *     }
+ *
+ *     // An unknown field rejects the whole batch.
+ *     let result = set_user_fields(&pool, 1, &[("username", "bob"), ("nope; DROP TABLE users", "x")]).await;
+ *     assert!(matches!(result, Err(sqlx::Error::ColumnNotFound(_))));
+ *     let (name,): (String,) = sqlx::query_as("SELECT username FROM users WHERE id = 1")
+ *         .fetch_one(&pool).await.unwrap();
+ *     assert_eq!(name, "alicia");
+ * });
+ * ```
+ */
+pub async fn set_user_fields(
+    pool: &SqlitePool,
+    user_id: i32,
+    fields: &[(&str, &str)],
+) -> Result<u64, sqlx::Error> {
+    if fields.is_empty() {
+        return Ok(0);
+    }
+
+    let names: Vec<&str> = fields.iter().map(|(field, _)| *field).collect();
+    check_user_fields(&names, true)?;
+
+    let mut tx = pool.begin().await?;
+
+    let assignments: Vec<String> = names.iter().map(|field| format!("{} = ?", field)).collect();
+    let query = format!("UPDATE users SET {} WHERE id = ?", assignments.join(", "));
+    let mut update = sqlx::query(&query);
+    for (_, value) in fields {
+        update = update.bind(*value);
+    }
+    let result = update.bind(user_id).execute(&mut *tx).await?;
+
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+/// User fields that users can change themselves through `patch_user`.
+pub const SELF_EDITABLE_USER_FIELDS: [&str; 1] = ["username"];
+
+/// Errors that can occur when applying a partial user update.
+#[derive(Debug)]
+pub enum PatchUserError {
+    /// The field is not in the list of fields the caller may change.
+    NotEditable(String),
+    /// The new username was refused by `user::check_username`, with the reason.
+    InvalidUsername(String),
+    /// The value is not a string, number or boolean.
+    InvalidValue(String),
+    /// The field is unknown, or the update failed.
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for PatchUserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchUserError::NotEditable(field) => write!(f, "Field '{}' cannot be changed", field),
+            PatchUserError::InvalidUsername(reason) => write!(f, "{}", reason),
+            PatchUserError::InvalidValue(field) => write!(f, "Field '{}' must be a string, number or boolean", field),
+            PatchUserError::Database(e) => write!(f, "{}", classify_db_error(e).1),
+        }
+    }
+}
+
+impl actix_web::ResponseError for PatchUserError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PatchUserError::NotEditable(_) | PatchUserError::InvalidUsername(_) | PatchUserError::InvalidValue(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            PatchUserError::Database(e) => classify_db_error(e).0,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            PatchUserError::Database(e) => db_error_response(e),
+            _ => json_error(self.status_code(), self.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for PatchUserError {
+    fn from(e: sqlx::Error) -> Self {
+        PatchUserError::Database(e)
+    }
+}
+
+/**
+ * Apply a partial update from a JSON object to a user.
+ *
+ * Each key must be one of the `editable` fields, and each value a string,
+ * number or boolean. Every other field is refused, so a self-service update
+ * passing `SELF_EDITABLE_USER_FIELDS` cannot touch the ID, password hash,
+ * privilege level, API key, timestamps, account status or any column added
+ * later. New usernames go through `user::check_username`, like registration.
+ * The update is applied with `set_user_fields`, as a single statement.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user to update.
+ * - `fields`: The fields to change and their new values.
+ * - `editable`: The fields the caller may change, such as `SELF_EDITABLE_USER_FIELDS`.
+ *
+ * # Returns
+ * The number of rows changed, or a `PatchUserError` if a field is not editable
+ * or has an invalid value.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::db::{init_db_with_url, patch_user, PatchUserError, IN_MEMORY_DATABASE_URL, SELF_EDITABLE_USER_FIELDS};
+ * use serde_json::json;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let pool = init_db_with_url(IN_MEMORY_DATABASE_URL).await.unwrap();
+ *     sqlx::query("INSERT INTO users (username, password_hash) VALUES ('alice', 'x')")
+ *         .execute(&pool).await.unwrap();
+ *
+ *     let patch = |body: serde_json::Value| {
+ *         let pool = pool.clone();
+ *         async move { patch_user(&pool, 1, body.as_object().unwrap(), &SELF_EDITABLE_USER_FIELDS).await }
+ *     };
+ *     assert_eq!(patch(json!({"username": "alicia"})).await.unwrap(), 1);
+ *
+ *     for field in ["privilege_level", "enabled", "api_key_hash", "created_at", "nickname"] {
+ *         assert!(matches!(patch(json!({ field: 1 })).await, Err(PatchUserError::NotEditable(_))));
+ *     }
+ *     assert!(matches!(patch(json!({"username": " "})).await, Err(PatchUserError::InvalidUsername(_))));
+ * });
+ * ```
+ */
+pub async fn patch_user(
+    pool: &SqlitePool,
+    user_id: i32,
+    fields: &serde_json::Map<String, serde_json::Value>,
+    editable: &[&str],
+) -> Result<u64, PatchUserError> {
+    let mut values = Vec::with_capacity(fields.len());
+    for (field, value) in fields {
+        if !editable.contains(&field.as_str()) {
+            return Err(PatchUserError::NotEditable(field.clone()));
+        }
+        let value = match value {
+            serde_json::Value::String(value) => value.clone(),
+            serde_json::Value::Number(value) => value.to_string(),
+            serde_json::Value::Bool(value) => (*value as i32).to_string(),
+            _ => return Err(PatchUserError::InvalidValue(field.clone())),
+        };
+        if field == "username" {
+            crate::core::user::check_username(&value).map_err(PatchUserError::InvalidUsername)?;
+        }
+        values.push((field.as_str(), value));
+    }
+
+    let fields: Vec<(&str, &str)> = values.iter().map(|(field, value)| (*field, value.as_str())).collect();
+    Ok(set_user_fields(pool, user_id, &fields).await?)
+}
+
+/**
+ * Check that field names may be read, or written, by name.
+ *
+ * Field names are interpolated into queries, so only the built-in fields in
+ * `READABLE_USER_FIELDS` or `WRITABLE_USER_FIELDS` and the registered profile
+ * fields are accepted. Password hashes and API key hashes are never readable.
+ */
+fn check_user_fields(fields: &[&str], writable: bool) -> Result<(), sqlx::Error> {
+    let built_in: &[&str] = if writable { &WRITABLE_USER_FIELDS } else { &READABLE_USER_FIELDS };
+    let profile = PROFILE_FIELDS.read().unwrap();
+    for field in fields {
+        if !built_in.contains(field) && !profile.iter().any(|name| name == field) {
+            return Err(sqlx::Error::ColumnNotFound(field.to_string()));
+        }
+    }
+    Ok(())
+}
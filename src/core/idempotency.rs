@@ -0,0 +1,336 @@
+/*!
+ * The `idempotency` module replays responses to retried requests carrying an `Idempotency-Key` header.
+ *
+ * Clients retrying a `POST` or `PATCH` after a dropped connection cannot tell
+ * whether the first attempt went through. By sending the same `Idempotency-Key`
+ * with every attempt, the first response is stored and replayed on retries
+ * instead of running the handler again, so side effects such as registering a
+ * user happen once.
+ *
+ * This module features:
+ * - **Response Replay**: The first response for a key is replayed for a TTL, marked with `Idempotent-Replayed: true`.
+ * - **Concurrent Retries**: A retry arriving while the first request is still running receives a `409 Conflict`.
+ * - **Body Fingerprints**: Reusing a key with a different request body receives a `422 Unprocessable Entity`.
+ * - **Scoped Keys**: Keys are scoped to the method, path and credentials, and requests without
+ *   credentials are never stored, so clients cannot see each other's responses.
+ * - **Bounded Memory**: At most `capacity` responses are kept in memory, evicting the least recently used.
+ *
+ * Idempotency keys are enabled with `Api::idempotency_keys`.
+ */
+use std::future::{ready, Ready};
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::body::{BoxBody, EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::PayloadError;
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::web::Bytes;
+use actix_web::{Error, HttpMessage, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+use futures_util::{Stream, StreamExt};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+
+use crate::core::cache::session_cookie;
+use crate::core::response::json_error;
+
+/// Default time a response is replayed for the same key.
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default maximum number of responses kept.
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 10_000;
+
+/// Largest request or response body handled, in bytes. Larger requests and responses are not replayed.
+pub const MAX_IDEMPOTENT_BODY: usize = 1024 * 1024;
+
+/// Header carrying the client-chosen idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header added to replayed responses.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// The SHA-256 hash of a request body.
+type Fingerprint = [u8; 32];
+
+/// The state of a key.
+enum Entry {
+    /// The first request with the key is still running.
+    InFlight { fingerprint: Fingerprint, started: Instant },
+    /// The first request completed with this response.
+    Done { fingerprint: Fingerprint, status: StatusCode, headers: HeaderMap, body: Bytes, stored_at: Instant },
+}
+
+/**
+ * Middleware replaying the first response for each `Idempotency-Key`.
+ *
+ * Only `POST` and `PATCH` requests with the header and credentials (an
+ * `Authorization` or `X-API-Key` header, or a session cookie) are affected, so
+ * anonymous requests such as logins are never replayed to another client. The
+ * first request runs normally and its response is stored for the TTL; retries
+ * with the same key, method, path, credentials and body receive the stored
+ * response with an `Idempotent-Replayed: true` header. Reusing the key with a
+ * different body receives a `422 Unprocessable Entity`, and a retry arriving
+ * while the first request is still running receives a `409 Conflict`. Server
+ * errors (`5xx`) and requests or responses with bodies larger than
+ * `MAX_IDEMPOTENT_BODY` are not stored, so the request can be retried.
+ *
+ * Responses are kept in memory, so they are not shared between processes and
+ * are lost on restart. Cloning the middleware shares its responses.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::idempotency::Idempotency;
+ * use rusty_api::HttpResponse;
+ * use actix_web::{test, web, App};
+ * use std::sync::atomic::{AtomicUsize, Ordering};
+ * use std::sync::Arc;
+ * use std::time::Duration;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let calls = Arc::new(AtomicUsize::new(0));
+ *     let counter = Arc::clone(&calls);
+ *     let app = test::init_service(
+ *         App::new()
+ *             .wrap(Idempotency::new(Duration::from_secs(60)))
+ *             .route("/orders", web::post().to(move || {
+ *                 let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+ *                 async move { HttpResponse::Created().body(format!("order {}", n)) }
+ *             }))
+ *     ).await;
+ *     let post = |body: &'static str| test::TestRequest::post()
+ *         .uri("/orders")
+ *         .insert_header(("Idempotency-Key", "abc123"))
+ *         .insert_header(("Authorization", "Bearer alice"))
+ *         .set_payload(body)
+ *         .to_request();
+ *
+ *     assert_eq!(test::call_and_read_body(&app, post("2 apples")).await, "order 1");
+ *
+ *     let retry = test::call_service(&app, post("2 apples")).await;
+ *     assert_eq!(retry.status(), 201);
+ *     assert_eq!(retry.headers().get("Idempotent-Replayed").unwrap(), "true");
+ *     assert_eq!(test::read_body(retry).await, "order 1");
+ *     assert_eq!(calls.load(Ordering::SeqCst), 1);
+ *
+ *     // The same key with another body is refused.
+ *     assert_eq!(test::call_service(&app, post("3 pears")).await.status(), 422);
+ *
+ *     // Requests without credentials are never replayed.
+ *     let anonymous = || test::TestRequest::post().uri("/orders").insert_header(("Idempotency-Key", "abc123")).to_request();
+ *     assert_eq!(test::call_and_read_body(&app, anonymous()).await, "order 2");
+ *     assert_eq!(test::call_and_read_body(&app, anonymous()).await, "order 3");
+ * });
+ * ```
+ */
+#[derive(Clone)]
+pub struct Idempotency {
+    ttl: Duration,
+    entries: Arc<Mutex<LruCache<String, Entry>>>,
+}
+
+impl Default for Idempotency {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_TTL)
+    }
+}
+
+impl Idempotency {
+    /**
+     * Create the middleware, replaying responses for the given TTL.
+     *
+     * # Arguments
+     * - `ttl`: How long a response is replayed for the same key.
+     */
+    pub fn new(ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(DEFAULT_IDEMPOTENCY_CAPACITY).unwrap();
+        Self { ttl, entries: Arc::new(Mutex::new(LruCache::new(capacity))) }
+    }
+
+    /// Set the maximum number of stored responses. A capacity of zero is treated as one.
+    pub fn capacity(self, capacity: usize) -> Self {
+        self.entries.lock().unwrap().resize(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN));
+        self
+    }
+
+    /// Get the time a response is replayed for the same key.
+    pub fn get_ttl(&self) -> Duration { self.ttl }
+
+    /// Get the scoped key for a request, or `None` if the request is not affected.
+    fn key(req: &ServiceRequest) -> Option<String> {
+        if !matches!(*req.method(), Method::POST | Method::PATCH) {
+            return None;
+        }
+        let key = req.headers().get(IDEMPOTENCY_KEY_HEADER)?.to_str().ok()?;
+        let credential = |name: header::HeaderName| {
+            req.headers().get(name).and_then(|h| h.to_str().ok()).unwrap_or_default().to_string()
+        };
+        let session = session_cookie(req.request()).unwrap_or_default();
+        let authorization = credential(header::AUTHORIZATION);
+        let api_key = credential(HeaderName::from_static("x-api-key"));
+        if authorization.is_empty() && api_key.is_empty() && session.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            key,
+            req.method(),
+            req.path(),
+            authorization,
+            api_key,
+            session
+        ))
+    }
+
+    /// Claim the key for a new request, or get the response to send instead.
+    fn begin(&self, key: &str, fingerprint: Fingerprint) -> Option<HttpResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(Entry::InFlight { fingerprint: first, started }
+                | Entry::Done { fingerprint: first, stored_at: started, .. })
+                if started.elapsed() <= self.ttl && *first != fingerprint =>
+            {
+                Some(json_error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "This Idempotency-Key was already used with a different request body",
+                ))
+            }
+            Some(Entry::InFlight { started, .. }) if started.elapsed() <= self.ttl => Some(json_error(
+                StatusCode::CONFLICT,
+                "A request with this Idempotency-Key is already in progress",
+            )),
+            Some(Entry::Done { status, headers, body, stored_at, .. }) if stored_at.elapsed() <= self.ttl => {
+                let mut response = HttpResponse::build(*status).body(body.clone());
+                for (name, value) in headers {
+                    response.headers_mut().append(name.clone(), value.clone());
+                }
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER), HeaderValue::from_static("true"));
+                Some(response)
+            }
+            _ => {
+                entries.put(key.to_string(), Entry::InFlight { fingerprint, started: Instant::now() });
+                None
+            }
+        }
+    }
+}
+
+/// Releases a claimed key unless the response was stored, e.g. if the handler panicked or the client went away.
+struct Claim {
+    entries: Arc<Mutex<LruCache<String, Entry>>>,
+    key: Option<String>,
+    fingerprint: Fingerprint,
+}
+
+impl Claim {
+    /// Store the response for the key.
+    fn complete(mut self, status: StatusCode, headers: HeaderMap, body: Bytes) {
+        if let Some(key) = self.key.take() {
+            let entry = Entry::Done { fingerprint: self.fingerprint, status, headers, body, stored_at: Instant::now() };
+            self.entries.lock().unwrap().put(key, entry);
+        }
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.entries.lock().unwrap().pop(&key);
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = IdempotencyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotencyMiddleware { service: Rc::new(service), idempotency: self.clone() }))
+    }
+}
+
+/// The service produced by the `Idempotency` middleware.
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+    idempotency: Idempotency,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let Some(key) = Idempotency::key(&req) else {
+            return Box::pin(async move { service.call(req).await.map(ServiceResponse::map_into_left_body) });
+        };
+        let idempotency = self.idempotency.clone();
+
+        Box::pin(async move {
+            // Buffer at most `MAX_IDEMPOTENT_BODY` plus one chunk, then replay it ahead of the rest.
+            let mut payload = req.take_payload();
+            let mut buffered: Vec<Bytes> = Vec::new();
+            let mut size = 0;
+            let mut complete = true;
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk?;
+                size += chunk.len();
+                buffered.push(chunk);
+                if size > MAX_IDEMPOTENT_BODY {
+                    complete = false;
+                    break;
+                }
+            }
+            let fingerprint: Fingerprint = Sha256::digest(buffered.concat()).into();
+            let replay = futures_util::stream::iter(buffered.into_iter().map(Ok)).chain(payload);
+            let replay: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> = Box::pin(replay);
+            req.set_payload(Payload::from(replay));
+            if !complete {
+                return service.call(req).await.map(ServiceResponse::map_into_left_body);
+            }
+
+            if let Some(response) = idempotency.begin(&key, fingerprint) {
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+            let claim = Claim { entries: Arc::clone(&idempotency.entries), key: Some(key), fingerprint };
+
+            let response = service.call(req).await?;
+            let storable = !response.status().is_server_error()
+                && matches!(response.response().body().size(), actix_web::body::BodySize::Sized(n) if n as usize <= MAX_IDEMPOTENT_BODY);
+            if !storable {
+                return Ok(response.map_into_left_body());
+            }
+
+            let (request, response) = response.into_parts();
+            let (head, body) = response.into_parts();
+            let body = actix_web::body::to_bytes(body).await.map_err(|e| {
+                actix_web::error::ErrorInternalServerError(e.into().to_string())
+            })?;
+            let mut headers = head.headers().clone();
+            headers.remove(header::SET_COOKIE);
+            claim.complete(head.status(), headers, body.clone());
+
+            Ok(ServiceResponse::new(request, head.set_body(BoxBody::new(body))).map_into_right_body())
+        })
+    }
+}
@@ -0,0 +1,143 @@
+/*!
+ * The `ip` module provides helpers for working with client IP addresses.
+ *
+ * This module is used by features that key or filter requests by the client's
+ * address, such as the rate limiter allowlist.
+ *
+ * This module features:
+ * - **Client IP Resolution**: Determines the client IP from the socket peer, or from proxy headers when trusted.
+ * - **Request Client IP**: `request_client_ip` resolves the client IP using the `Api` proxy setting.
+ * - **Client IP Extractor**: `ClientIp` gives handlers the resolved client IP.
+ * - **IP Range Parsing**: Parses single addresses and CIDR ranges into `IpNet` values.
+ */
+use actix_web::dev::{ConnectionInfo, Payload};
+use actix_web::{FromRequest, HttpRequest};
+use ipnet::IpNet;
+use std::future::{ready, Ready};
+use std::net::{IpAddr, SocketAddr};
+
+/**
+ * Resolve the client IP address of a request.
+ *
+ * When `trust_proxy` is `true`, the address from the `Forwarded` or
+ * `X-Forwarded-For` header is used if present and valid. Otherwise, or if the
+ * header is missing or malformed, the socket peer address is used.
+ *
+ * Only enable `trust_proxy` when the server is reachable exclusively through a
+ * reverse proxy that sets these headers, as clients can otherwise spoof them.
+ *
+ * # Arguments
+ * - `connection_info`: The connection info of the request.
+ * - `peer_addr`: The socket peer address of the request, if known.
+ * - `trust_proxy`: Whether to trust proxy headers.
+ *
+ * # Returns
+ * The client IP address, or `None` if it cannot be determined.
+ */
+pub fn client_ip(connection_info: &ConnectionInfo, peer_addr: Option<SocketAddr>, trust_proxy: bool) -> Option<IpAddr> {
+    if trust_proxy
+        && let Some(ip) = connection_info.realip_remote_addr().and_then(parse_host_ip)
+    {
+        return Some(ip);
+    }
+    peer_addr.map(|addr| addr.ip())
+}
+
+/**
+ * Whether proxy headers are trusted, stored as app data by the `Api`.
+ *
+ * See `Api::trust_proxy`.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrustProxy(pub bool);
+
+/**
+ * Resolve the client IP address of a request, using the `Api` proxy setting.
+ *
+ * This is `client_ip` with `trust_proxy` read from the `TrustProxy` app data,
+ * defaulting to `false` when it is not set.
+ *
+ * # Returns
+ * The client IP address, or `None` if it cannot be determined.
+ */
+pub fn request_client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let trust_proxy = req.app_data::<TrustProxy>().is_some_and(|trust| trust.0);
+    client_ip(&req.connection_info(), req.peer_addr(), trust_proxy)
+}
+
+/**
+ * Extractor for the client IP address of a request.
+ *
+ * The address is resolved with `request_client_ip`: the socket peer, or the
+ * client named in the `Forwarded` or `X-Forwarded-For` header when
+ * `Api::trust_proxy` is enabled. Missing or malformed proxy headers fall back to
+ * the socket peer. If no address can be determined, the request is rejected with
+ * a `400 Bad Request`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Api, ClientIp, HttpResponse, Method, Routes};
+ * use actix_web::test;
+ *
+ * async fn whereami(ip: ClientIp) -> HttpResponse {
+ *     HttpResponse::Ok().body(ip.0.to_string())
+ * }
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let routes = Routes::new().add_route(Method::GET, "/ip", whereami);
+ *     let app = test::init_service(Api::new().configure_routes(routes).trust_proxy(true).test_app().await).await;
+ *     let get = || test::TestRequest::get().uri("/ip").peer_addr("127.0.0.1:12345".parse().unwrap());
+ *
+ *     assert_eq!(test::call_and_read_body(&app, get().to_request()).await, "127.0.0.1");
+ *     let req = get().insert_header(("X-Forwarded-For", "203.0.113.7, 10.0.0.1")).to_request();
+ *     assert_eq!(test::call_and_read_body(&app, req).await, "203.0.113.7");
+ *     let req = get().insert_header(("X-Forwarded-For", "not-an-ip")).to_request();
+ *     assert_eq!(test::call_and_read_body(&app, req).await, "127.0.0.1");
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl FromRequest for ClientIp {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            request_client_ip(req)
+                .map(ClientIp)
+                .ok_or_else(|| actix_web::error::ErrorBadRequest("Could not determine client IP")),
+        )
+    }
+}
+
+/**
+ * Parse a single IP address or a CIDR range.
+ *
+ * A plain address is treated as a range containing only that address.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::ip::parse_ip_range;
+ *
+ * let range = parse_ip_range("10.0.0.0/8").unwrap();
+ * assert!(range.contains(&"10.1.2.3".parse::<std::net::IpAddr>().unwrap()));
+ * assert!(parse_ip_range("192.168.1.10").is_ok());
+ * assert!(parse_ip_range("not-an-ip").is_err());
+ * ```
+ */
+pub fn parse_ip_range(value: &str) -> Result<IpNet, String> {
+    let value = value.trim();
+    value
+        .parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("Invalid IP address or CIDR range: '{}'", value))
+}
+
+/// Parse an IP from a host value that may carry a port (e.g. `1.2.3.4:80` or `[::1]:80`).
+fn parse_host_ip(host: &str) -> Option<IpAddr> {
+    host.parse::<IpAddr>()
+        .ok()
+        .or_else(|| host.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
@@ -0,0 +1,190 @@
+/*!
+ * The `json` module provides the `JsonInput` extractor used by the built-in routes.
+ *
+ * `JsonInput<T>` reads the body like `web::Json<T>`, so the `JsonConfig` limit
+ * and error handler registered by the `Api` still apply. When
+ * `Api::deny_unknown_json_fields` is enabled, objects with fields that `T` does
+ * not declare are rejected with a `400 Bad Request`, helping to catch client
+ * bugs. Otherwise unknown fields are ignored, as serde does by default.
+ */
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::http::StatusCode;
+use actix_web::{web, Error, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, SeqAccess, Visitor};
+use std::ops::Deref;
+
+use crate::core::response::json_error;
+
+/**
+ * Whether unknown JSON fields are rejected, stored as app data by the `Api`.
+ *
+ * See `Api::deny_unknown_json_fields`.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DenyUnknownFields(pub bool);
+
+/**
+ * A JSON request body, rejecting unknown fields when the `Api` is configured to.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Api, HttpResponse, Method, Routes};
+ * use rusty_api::core::json::JsonInput;
+ * use actix_web::test;
+ *
+ * #[derive(serde::Deserialize)]
+ * struct Greeting { name: String }
+ *
+ * async fn greet(input: JsonInput<Greeting>) -> HttpResponse {
+ *     HttpResponse::Ok().body(format!("Hello, {}", input.name))
+ * }
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let routes = || Routes::new().add_route(Method::POST, "/greet", greet);
+ *     let post = || test::TestRequest::post()
+ *         .uri("/greet")
+ *         .set_json(serde_json::json!({"name": "alice", "admin": true}))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *
+ *     let app = test::init_service(Api::new().configure_routes(routes()).test_app().await).await;
+ *     assert_eq!(test::call_service(&app, post()).await.status(), 200);
+ *
+ *     let api = Api::new().configure_routes(routes()).deny_unknown_json_fields();
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let response = test::call_service(&app, post()).await;
+ *     assert_eq!(response.status(), 400);
+ *     let body: serde_json::Value = test::read_body_json(response).await;
+ *     assert_eq!(body["error"], "Invalid JSON body: unknown field 'admin'");
+ * });
+ * ```
+ */
+#[derive(Debug)]
+pub struct JsonInput<T>(pub T);
+
+impl<T> JsonInput<T> {
+    /// Unwrap into the inner value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for JsonInput<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for JsonInput<T> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let strict = req.app_data::<DenyUnknownFields>().is_some_and(|deny| deny.0);
+        let body = web::Json::<serde_json::Value>::from_request(req, payload);
+        Box::pin(async move {
+            let value = body.await?.into_inner();
+            if strict && let Some(field) = unknown_field::<T>(&value) {
+                return Err(invalid_body(format!("unknown field '{}'", field)));
+            }
+            serde_json::from_value(value).map(JsonInput).map_err(|e| invalid_body(e.to_string()))
+        })
+    }
+}
+
+/// Build the `400 Bad Request` error for a body that does not match `T`.
+fn invalid_body(message: String) -> Error {
+    let message = format!("Invalid JSON body: {}", message);
+    let response = json_error(StatusCode::BAD_REQUEST, &message);
+    InternalError::from_response(message, response).into()
+}
+
+/**
+ * Find the first field of a JSON object, or of the objects in a JSON array,
+ * that the struct `T` (or its element type) does not declare.
+ *
+ * Types that are not structs accept any field.
+ */
+fn unknown_field<T: DeserializeOwned>(value: &serde_json::Value) -> Option<String> {
+    let mut fields = None;
+    let _ = T::deserialize(FieldProbe(&mut fields));
+    let fields = fields?;
+    let objects: Vec<&serde_json::Value> = match value {
+        serde_json::Value::Array(items) => items.iter().collect(),
+        value => vec![value],
+    };
+    objects
+        .into_iter()
+        .filter_map(|object| object.as_object())
+        .flat_map(|object| object.keys())
+        .find(|key| !fields.contains(&key.as_str()))
+        .cloned()
+}
+
+/**
+ * A deserializer that records the field names a derived struct asks for.
+ *
+ * Derived `Deserialize` impls pass their fields to `deserialize_struct`, so
+ * deserializing from the probe reveals them without an instance. Sequences are
+ * followed to their element type; every other request fails.
+ */
+struct FieldProbe<'a>(&'a mut Option<&'static [&'static str]>);
+
+#[derive(Debug)]
+struct ProbeDone;
+
+impl std::fmt::Display for ProbeDone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "probe done")
+    }
+}
+
+impl std::error::Error for ProbeDone {}
+
+impl de::Error for ProbeDone {
+    fn custom<M: std::fmt::Display>(_msg: M) -> Self {
+        ProbeDone
+    }
+}
+
+impl<'de> de::Deserializer<'de> for FieldProbe<'_> {
+    type Error = ProbeDone;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, ProbeDone> {
+        Err(ProbeDone)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, ProbeDone> {
+        *self.0 = Some(fields);
+        Err(ProbeDone)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProbeDone> {
+        visitor.visit_seq(ProbeSeq(self.0))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// A sequence whose single element is a `FieldProbe`.
+struct ProbeSeq<'a>(&'a mut Option<&'static [&'static str]>);
+
+impl<'de> SeqAccess<'de> for ProbeSeq<'_> {
+    type Error = ProbeDone;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>, ProbeDone> {
+        seed.deserialize(FieldProbe(self.0)).map(Some)
+    }
+}
@@ -0,0 +1,298 @@
+/*!
+ * The `jwks` module verifies JWTs issued by external identity providers.
+ *
+ * Providers such as Google, Okta, Auth0 and Keycloak sign their tokens with RSA
+ * keys published as a JSON Web Key Set (JWKS), usually at `/.well-known/jwks.json`.
+ * `JwksVerifier` fetches and caches those keys, and verifies `RS256` tokens by the
+ * `kid` in their header.
+ *
+ * This module features:
+ * - **Key Caching**: Keys are fetched on first use and kept for a configurable TTL.
+ * - **Key Rotation**: A token with an unknown `kid` triggers a refresh, at most once per `JWKS_MIN_REFRESH_INTERVAL`.
+ * - **Claim Checks**: The `iss` and `aud` claims are checked when an issuer or audience is configured.
+ *
+ * Provider tokens are decoded into `ProviderClaims`, whose `sub` is the provider's
+ * own subject identifier. It is mapped to the ID of a local user with
+ * `JwksVerifier::map_subject`; by default a numeric `sub` is taken as the user ID.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::jwks::JwksVerifier;
+ * use std::time::Duration;
+ *
+ * let verifier = JwksVerifier::new("https://idp.example.com/.well-known/jwks.json")
+ *     .issuer("https://idp.example.com/")
+ *     .audience("my-api")
+ *     .ttl(Duration::from_secs(600));
+ *
+ * let api = Api::new().jwt_jwks(verifier);
+ * ```
+ */
+use actix_web::error::ErrorUnauthorized;
+use futures_util::future::{ready, FutureExt, LocalBoxFuture};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::core::config::initialize_crypto_provider;
+use crate::core::auth::{claims_validation, jwt_leeway, one_or_many, Claims, TokenValidator};
+
+/// How long fetched keys are cached by default.
+pub const DEFAULT_JWKS_TTL: Duration = Duration::from_secs(3600);
+
+/// Minimum time between two key fetches, so unknown `kid`s cannot flood the provider.
+pub const JWKS_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/**
+ * The claims of a token issued by an external identity provider.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::jwks::ProviderClaims;
+ *
+ * let claims: ProviderClaims = serde_json::from_str(
+ *     r#"{"sub": "alice@example.com", "exp": 1, "aud": "my-api", "email_verified": true}"#
+ * ).unwrap();
+ * assert_eq!(claims.sub, "alice@example.com");
+ * assert_eq!(claims.aud, ["my-api"]);
+ * assert_eq!(claims.extra["email_verified"], true);
+ * ```
+ */
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderClaims {
+    /// The provider's identifier for the user, e.g. an email address or UUID.
+    pub sub: String,
+    pub exp: usize,
+    /// The issuer of the token, if any.
+    #[serde(default)]
+    pub iss: Option<String>,
+    /// The audiences the token is intended for.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub aud: Vec<String>,
+    /// Every other claim in the token, such as `email` or `groups`.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The function mapping provider claims to a local user ID.
+type SubjectFn = dyn Fn(ProviderClaims) -> LocalBoxFuture<'static, Option<i32>> + Send + Sync;
+
+/**
+ * Verifier for `RS256` JWTs signed with keys from a JWKS endpoint.
+ *
+ * Cloning a verifier shares its key cache.
+ */
+#[derive(Clone)]
+pub struct JwksVerifier {
+    url: String,
+    issuer: Option<String>,
+    audience: Option<String>,
+    ttl: Duration,
+    cache: Arc<RwLock<KeyCache>>,
+    map_subject: Arc<SubjectFn>,
+}
+
+/// Signing keys by `kid`, with the time they were fetched.
+#[derive(Default)]
+struct KeyCache {
+    keys: HashMap<String, DecodingKey>,
+    fetched_at: Option<Instant>,
+    last_attempt: Option<Instant>,
+}
+
+impl JwksVerifier {
+    /**
+     * Create a verifier for the given JWKS URL.
+     *
+     * No keys are fetched until the first token is verified.
+     *
+     * # Arguments
+     * - `url`: The provider's JWKS URL, e.g. `https://idp.example.com/.well-known/jwks.json`.
+     */
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            issuer: None,
+            audience: None,
+            ttl: DEFAULT_JWKS_TTL,
+            cache: Arc::new(RwLock::new(KeyCache::default())),
+            map_subject: Arc::new(|claims| ready(claims.sub.parse().ok()).boxed_local()),
+        }
+    }
+
+    /// Require the `iss` claim to equal the given issuer.
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_string());
+        self
+    }
+
+    /// Require the `aud` claim to contain the given audience.
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+
+    /// Set how long fetched keys are cached before they are fetched again.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /**
+     * Set how a provider's claims are mapped to the ID of a local user.
+     *
+     * Providers identify users by their own `sub`, such as an email address or
+     * UUID, which is rarely the local user ID. The mapping can look the user up
+     * in the database; returning `None` rejects the token. By default a numeric
+     * `sub` is taken as the user ID.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::core::jwks::JwksVerifier;
+     *
+     * # fn example(pool: sqlx::SqlitePool) {
+     * let verifier = JwksVerifier::new("https://idp.example.com/.well-known/jwks.json")
+     *     .map_subject(move |claims| {
+     *         let pool = pool.clone();
+     *         async move {
+     *             sqlx::query_scalar("SELECT id FROM users WHERE username = ?")
+     *                 .bind(&claims.sub)
+     *                 .fetch_optional(&pool)
+     *                 .await
+     *                 .ok()
+     *                 .flatten()
+     *         }
+     *     });
+     * # }
+     * ```
+     */
+    pub fn map_subject<F, Fut>(mut self, map: F) -> Self
+    where
+        F: Fn(ProviderClaims) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<i32>> + 'static,
+    {
+        self.map_subject = Arc::new(move |claims| map(claims).boxed_local());
+        self
+    }
+
+    /// Get the JWKS URL.
+    pub fn get_url(&self) -> &str { &self.url }
+
+    /**
+     * Verify a token and return its claims.
+     *
+     * The signing key is looked up by the `kid` in the token header. Keys are
+     * refetched when the cache is older than the TTL, or when the `kid` is unknown.
+     * The provider's subject is then mapped to a local user with the
+     * `map_subject` function. The privilege level is taken from a numeric
+     * `privilege_level` claim, if the provider sends one.
+     *
+     * # Returns
+     * The local claims, or an `Unauthorized` error if the token is invalid, its
+     * key cannot be found, or its subject maps to no local user.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::core::jwks::JwksVerifier;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let verifier = JwksVerifier::new("http://127.0.0.1:1/jwks.json");
+     *     assert!(verifier.verify("not-a-jwt").await.is_err());
+     * });
+     * ```
+     */
+    pub async fn verify(&self, token: &str) -> Result<Claims, actix_web::Error> {
+        let header = decode_header(token).map_err(|_| ErrorUnauthorized("Invalid token"))?;
+        if header.alg != Algorithm::RS256 {
+            return Err(ErrorUnauthorized("Invalid token"));
+        }
+        let kid = header.kid.ok_or_else(|| ErrorUnauthorized("Invalid token"))?;
+
+        let key = match self.cached_key(&kid, false) {
+            Some(key) => key,
+            None => {
+                // Fall back to expired keys if the provider cannot be reached.
+                self.refresh().await;
+                self.cached_key(&kid, true).ok_or_else(|| ErrorUnauthorized("Invalid token"))?
+            }
+        };
+
+        let mut validation = claims_validation(Algorithm::RS256, self.issuer.as_deref(), self.audience.as_deref(), jwt_leeway());
+        // Provider tokens always carry an audience, so only check it when one is configured.
+        validation.validate_aud = self.audience.is_some();
+
+        let claims = decode::<ProviderClaims>(token, &key, &validation)
+            .map(|decoded| decoded.claims)
+            .map_err(|_| ErrorUnauthorized("Invalid token"))?;
+        let privilege_level = claims.extra.get("privilege_level").and_then(|level| level.as_i64()).unwrap_or(0) as i32;
+        let (exp, iss, aud) = (claims.exp, claims.iss.clone(), claims.aud.clone());
+        let sub = (self.map_subject)(claims).await.ok_or_else(|| ErrorUnauthorized("Invalid token"))?;
+        Ok(Claims { sub, exp, privilege_level, iss, aud, auth_time: None })
+    }
+
+    /// Get a cached key, or `None` if it is unknown or, unless `allow_stale`, the cache has expired.
+    fn cached_key(&self, kid: &str, allow_stale: bool) -> Option<DecodingKey> {
+        let cache = self.cache.read().unwrap();
+        if !allow_stale && cache.fetched_at.is_none_or(|fetched_at| fetched_at.elapsed() > self.ttl) {
+            return None;
+        }
+        cache.keys.get(kid).cloned()
+    }
+
+    /// Fetch the key set, unless a fetch was attempted too recently.
+    async fn refresh(&self) {
+        {
+            let mut cache = self.cache.write().unwrap();
+            if cache.last_attempt.is_some_and(|attempt| attempt.elapsed() < JWKS_MIN_REFRESH_INTERVAL) {
+                return;
+            }
+            cache.last_attempt = Some(Instant::now());
+        }
+
+        match fetch_keys(&self.url).await {
+            Ok(keys) => {
+                let mut cache = self.cache.write().unwrap();
+                cache.keys = keys;
+                cache.fetched_at = Some(Instant::now());
+            }
+            Err(e) => log::warn!("Failed to fetch JWKS from {}: {}", self.url, e),
+        }
+    }
+}
+
+impl From<JwksVerifier> for TokenValidator {
+    fn from(verifier: JwksVerifier) -> Self {
+        TokenValidator::new_async(move |token| {
+            let verifier = verifier.clone();
+            async move { verifier.verify(&token).await }
+        })
+    }
+}
+
+/// Fetch a JWKS document and decode its keys that have a `kid`.
+async fn fetch_keys(url: &str) -> Result<HashMap<String, DecodingKey>, String> {
+    initialize_crypto_provider();
+    let mut response = awc::Client::default()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("unexpected status {}", response.status()));
+    }
+    let set: JwkSet = response.json().limit(1024 * 1024).await.map_err(|e| e.to_string())?;
+
+    Ok(set
+        .keys
+        .iter()
+        .filter_map(|jwk| {
+            let kid = jwk.common.key_id.clone()?;
+            DecodingKey::from_jwk(jwk).ok().map(|key| (kid, key))
+        })
+        .collect())
+}
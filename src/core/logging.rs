@@ -0,0 +1,65 @@
+/*!
+ * The `logging` module provides the logger installed by the `Api` when the
+ * application has not installed one.
+ *
+ * The crate logs through the `log` crate. Without a logger those records are
+ * dropped, so `Api::start` installs a minimal logger writing `LEVEL: message`
+ * lines to standard output, at the level set with `Api::log_level`. Applications
+ * that install their own logger, such as `env_logger`, before starting the
+ * server keep it.
+ */
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Level of the built-in logger unless changed with `Api::log_level`.
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// Logger writing records to standard output as `LEVEL: message`.
+struct StdoutLogger;
+
+impl Log for StdoutLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let level = match record.level() {
+                Level::Warn => "WARNING",
+                level => level.as_str(),
+            };
+            println!("{}: {}", level, record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/**
+ * Install the built-in standard output logger, unless a logger is already installed.
+ *
+ * # Arguments
+ * - `level`: The most verbose level to print.
+ *
+ * # Returns
+ * `true` if the logger was installed, or `false` if another logger was already
+ * installed, in which case its level is left unchanged.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::logging::init_stdout_logger;
+ * use log::LevelFilter;
+ *
+ * assert!(init_stdout_logger(LevelFilter::Warn));
+ * assert_eq!(log::max_level(), LevelFilter::Warn);
+ * assert!(!init_stdout_logger(LevelFilter::Debug));
+ * assert_eq!(log::max_level(), LevelFilter::Warn);
+ * ```
+ */
+pub fn init_stdout_logger(level: LevelFilter) -> bool {
+    static LOGGER: StdoutLogger = StdoutLogger;
+    let installed = log::set_logger(&LOGGER).is_ok();
+    if installed {
+        log::set_max_level(level);
+    }
+    installed
+}
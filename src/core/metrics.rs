@@ -0,0 +1,186 @@
+/*!
+ * The `metrics` module counts authentication outcomes for monitoring.
+ *
+ * Spikes in failed logins or invalid tokens are a common sign of credential
+ * stuffing or token forgery. The counters are kept in memory for the lifetime
+ * of the process and served in the Prometheus text format by the optional
+ * endpoint enabled with `Api::with_metrics_endpoint`.
+ *
+ * This module features:
+ * - **Login Outcomes**: Successful logins and failed logins labeled by reason.
+ * - **Registrations**: Users created by registration, including bulk imports.
+ * - **Token Failures**: Rejected tokens labeled by reason.
+ *
+ * Failure reasons are only exposed as aggregate counts, so clients still
+ * cannot tell which usernames exist.
+ */
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+/// Why a login attempt failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginFailure {
+    /// No user exists with the given username.
+    UserNotFound,
+    /// The password does not match the stored hash.
+    InvalidPassword,
+    /// The account has been disabled by an admin.
+    AccountDisabled,
+}
+
+/// Why a token was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenFailure {
+    /// The token has expired.
+    Expired,
+    /// The token's signature does not match.
+    InvalidSignature,
+    /// The token is malformed, or its claims, issuer or audience are wrong.
+    Invalid,
+    /// The session is unknown, expired or revoked, see `core::session`.
+    InvalidSession,
+}
+
+static LOGINS: AtomicU64 = AtomicU64::new(0);
+static LOGIN_FAILURES: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+static REGISTRATIONS: AtomicU64 = AtomicU64::new(0);
+static TOKEN_FAILURES: [AtomicU64; 4] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Count a successful login.
+pub(crate) fn record_login() {
+    LOGINS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a failed login.
+pub(crate) fn record_login_failure(reason: LoginFailure) {
+    LOGIN_FAILURES[reason as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count registered users.
+pub(crate) fn record_registrations(count: u64) {
+    REGISTRATIONS.fetch_add(count, Ordering::Relaxed);
+}
+
+/// Count a rejected token.
+pub(crate) fn record_token_failure(reason: TokenFailure) {
+    TOKEN_FAILURES[reason as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Failed logins by reason.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LoginFailures {
+    pub user_not_found: u64,
+    pub invalid_password: u64,
+    pub account_disabled: u64,
+}
+
+/// Rejected tokens by reason.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TokenFailures {
+    pub expired: u64,
+    pub invalid_signature: u64,
+    pub invalid: u64,
+    pub invalid_session: u64,
+}
+
+/**
+ * A snapshot of the authentication counters since the process started.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::metrics::AuthMetrics;
+ * use rusty_api::{set_jwt_secret, validate_token};
+ *
+ * set_jwt_secret("test-secret");
+ * let before = AuthMetrics::current();
+ * assert!(validate_token("not-a-token").is_err());
+ *
+ * let after = AuthMetrics::current();
+ * assert_eq!(after.token_failures.invalid, before.token_failures.invalid + 1);
+ * assert!(after.to_prometheus().contains("rusty_api_token_failures_total{reason=\"invalid\"}"));
+ * ```
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AuthMetrics {
+    /// Successful logins.
+    pub logins: u64,
+    /// Failed logins by reason.
+    pub login_failures: LoginFailures,
+    /// Registered users.
+    pub registrations: u64,
+    /// Rejected tokens by reason.
+    pub token_failures: TokenFailures,
+}
+
+impl AuthMetrics {
+    /// Read the current counters.
+    pub fn current() -> Self {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        Self {
+            logins: load(&LOGINS),
+            login_failures: LoginFailures {
+                user_not_found: load(&LOGIN_FAILURES[LoginFailure::UserNotFound as usize]),
+                invalid_password: load(&LOGIN_FAILURES[LoginFailure::InvalidPassword as usize]),
+                account_disabled: load(&LOGIN_FAILURES[LoginFailure::AccountDisabled as usize]),
+            },
+            registrations: load(&REGISTRATIONS),
+            token_failures: TokenFailures {
+                expired: load(&TOKEN_FAILURES[TokenFailure::Expired as usize]),
+                invalid_signature: load(&TOKEN_FAILURES[TokenFailure::InvalidSignature as usize]),
+                invalid: load(&TOKEN_FAILURES[TokenFailure::Invalid as usize]),
+                invalid_session: load(&TOKEN_FAILURES[TokenFailure::InvalidSession as usize]),
+            },
+        }
+    }
+
+    /// Render the counters in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let failures = &self.login_failures;
+        let tokens = &self.token_failures;
+        format!(
+            "# HELP rusty_api_logins_total Successful logins.\n\
+             # TYPE rusty_api_logins_total counter\n\
+             rusty_api_logins_total {}\n\
+             # HELP rusty_api_login_failures_total Failed logins by reason.\n\
+             # TYPE rusty_api_login_failures_total counter\n\
+             rusty_api_login_failures_total{{reason=\"user_not_found\"}} {}\n\
+             rusty_api_login_failures_total{{reason=\"invalid_password\"}} {}\n\
+             rusty_api_login_failures_total{{reason=\"account_disabled\"}} {}\n\
+             # HELP rusty_api_registrations_total Registered users.\n\
+             # TYPE rusty_api_registrations_total counter\n\
+             rusty_api_registrations_total {}\n\
+             # HELP rusty_api_token_failures_total Rejected tokens by reason.\n\
+             # TYPE rusty_api_token_failures_total counter\n\
+             rusty_api_token_failures_total{{reason=\"expired\"}} {}\n\
+             rusty_api_token_failures_total{{reason=\"invalid_signature\"}} {}\n\
+             rusty_api_token_failures_total{{reason=\"invalid\"}} {}\n\
+             rusty_api_token_failures_total{{reason=\"invalid_session\"}} {}\n",
+            self.logins,
+            failures.user_not_found,
+            failures.invalid_password,
+            failures.account_disabled,
+            self.registrations,
+            tokens.expired,
+            tokens.invalid_signature,
+            tokens.invalid,
+            tokens.invalid_session,
+        )
+    }
+}
+
+/**
+ * Configure a `GET` route serving `AuthMetrics::current()` in the Prometheus text format.
+ *
+ * # Arguments
+ * - `cfg`: A mutable reference to the Actix Web `ServiceConfig`.
+ * - `path`: The URL path of the route.
+ */
+pub fn configure_metrics_route(cfg: &mut web::ServiceConfig, path: &str) {
+    cfg.route(path, web::get().to(|| async {
+        HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(AuthMetrics::current().to_prometheus())
+    }));
+}
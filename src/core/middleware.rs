@@ -0,0 +1,2120 @@
+/*!
+ * The `middleware` module provides Actix Web middleware used by rusty-api.
+ *
+ * Middleware in this module can wrap an `App`, a `Scope` or a single `Resource`,
+ * making checks declarative instead of relying on each handler to perform them.
+ *
+ * This module features:
+ * - **Privilege Checks**: `RequirePrivilege` rejects requests from users below a minimum privilege level.
+ * - **Authenticated User Extraction**: `AuthenticatedUser` gives handlers access to the user that passed the check.
+ * - **Token Privilege Checks**: `RequireTokenPrivilege` checks the privilege level in the token itself, without a database query.
+ * - **Rate Limiting**: `RateLimit` applies the governor rate limiter, letting allowlisted clients and exempt paths bypass it.
+ * - **Required Headers**: `RequireHeaders` rejects requests missing any of a set of headers.
+ * - **Path Normalization**: `NormalizePathRedirect` redirects paths with stray slashes to their normalized form.
+ * - **URI Limits**: `UriLimit` rejects overly long URIs and query strings with `414`.
+ * - **Header Limits**: `HeaderLimit` rejects too many or too large request headers with `431`.
+ * - **HTTPS Enforcement**: `RequireHttps` rejects or redirects requests not made over HTTPS.
+ * - **Panic Handling**: `CatchPanic` turns handler panics into logged `500` responses.
+ * - **Slow Request Logging**: `SlowRequestLog` logs a warning for requests slower than a threshold.
+ * - **Server Header**: `ServerHeader` sets or removes the `Server` response header.
+ * - **Security Headers**: `security_headers` adds HSTS and other hardening headers to every response.
+ * - **IP Filtering**: `IpFilter` rejects clients outside an allowlist or inside a blocklist.
+ * - **Conditional Requests**: `Etag` adds weak ETags to `GET` responses and answers `If-None-Match` with `304`.
+ * - **Body Logging**: `BodyLogger` logs request and response bodies with sensitive JSON keys redacted.
+ * - **Response Envelopes**: `ResponseEnvelope` wraps JSON responses in `data`, `meta` and `errors` fields.
+ *
+ * The privilege checks and their extractors require the `auth` feature.
+ */
+use std::any::Any;
+use std::future::{ready, Ready};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_governor::{Governor, GovernorConfig, GovernorMiddleware, KeyExtractor};
+use actix_web::{
+    body::{BodySize, BoxBody, EitherBody, MessageBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LOCATION, RETRY_AFTER, SERVER},
+    http::Method,
+    error::PayloadError,
+    http::StatusCode, Error, HttpMessage, HttpResponse,
+};
+#[cfg(feature = "auth")]
+use actix_web::{web, FromRequest, HttpRequest};
+use actix_web::web::Bytes;
+pub use actix_web::middleware::TrailingSlash;
+use futures_util::future::{Either, FutureExt, LocalBoxFuture, TryFutureExt};
+use futures_util::{Stream, StreamExt};
+use ipnet::IpNet;
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "auth")]
+use crate::core::auth::{require_privilege, require_token_privilege, Claims};
+use crate::core::ip::{client_ip, TrustProxy};
+use crate::core::response::json_error;
+#[cfg(feature = "auth")]
+use crate::core::user::{Privilege, User};
+
+/**
+ * Middleware requiring the caller to hold at least the given privilege level.
+ *
+ * The bearer token in the `Authorization` header is validated and the user's
+ * current privilege level is loaded from the user database. Requests without a
+ * valid token receive a `401 Unauthorized`, and users below the required level
+ * receive a `403 Forbidden`. On success the user is made available to the handler
+ * through the `AuthenticatedUser` extractor.
+ *
+ * The user database must be enabled, otherwise every request is rejected with a
+ * `500 Internal Server Error`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{web, AuthenticatedUser, HttpResponse, PrivilegeLevel, RequirePrivilege};
+ *
+ * async fn stats(user: AuthenticatedUser) -> HttpResponse {
+ *     HttpResponse::Ok().body(format!("Hello, {}", user.0.username))
+ * }
+ *
+ * let admin_scope = web::scope("/admin")
+ *     .wrap(RequirePrivilege(PrivilegeLevel::Admin))
+ *     .route("/stats", web::get().to(stats));
+ * ```
+ */
+#[cfg(feature = "auth")]
+pub struct RequirePrivilege<P: Privilege>(pub P);
+
+#[cfg(feature = "auth")]
+impl<S, B, P> Transform<S, ServiceRequest> for RequirePrivilege<P>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    P: Privilege,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequirePrivilegeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequirePrivilegeMiddleware {
+            service: Rc::new(service),
+            level: self.0.level(),
+        }))
+    }
+}
+
+/// The service produced by the `RequirePrivilege` middleware.
+#[cfg(feature = "auth")]
+pub struct RequirePrivilegeMiddleware<S> {
+    service: Rc<S>,
+    level: i32,
+}
+
+#[cfg(feature = "auth")]
+impl<S, B> Service<ServiceRequest> for RequirePrivilegeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let level = self.level;
+
+        Box::pin(async move {
+            let pool = match req.app_data::<web::Data<sqlx::SqlitePool>>() {
+                Some(pool) => pool.clone(),
+                None => {
+                    let response = HttpResponse::InternalServerError().body("User database is not enabled");
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+            };
+
+            match require_privilege(req.request(), &pool, level).await {
+                Ok(user) => {
+                    req.extensions_mut().insert(AuthenticatedUser(user));
+                    service.call(req).await.map(ServiceResponse::map_into_left_body)
+                }
+                Err(response) => Ok(req.into_response(response).map_into_right_body()),
+            }
+        })
+    }
+}
+
+/**
+ * Extractor for the user authenticated by the `RequirePrivilege` middleware.
+ *
+ * Extraction fails with a `401 Unauthorized` if the route is not wrapped by
+ * `RequirePrivilege`.
+ */
+#[cfg(feature = "auth")]
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser(pub User);
+
+#[cfg(feature = "auth")]
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthenticatedUser>()
+                .cloned()
+                .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated")),
+        )
+    }
+}
+
+/**
+ * Middleware requiring the caller's token to grant at least the given privilege level.
+ *
+ * The token is validated and the level is read from its `privilege_level`
+ * claim, which `generate_jwt` sets from the user's privilege level at login.
+ * No database query is made, so this works without the user database, but a
+ * demoted user keeps their old level until their token expires. Use
+ * `RequirePrivilege` where privilege changes must take effect immediately.
+ *
+ * Requests without a valid token receive a `401 Unauthorized`, and tokens below
+ * the required level receive a `403 Forbidden`. On success the claims are made
+ * available to the handler through the `TokenClaims` extractor.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{generate_test_jwt, set_jwt_secret, web, HttpResponse, PrivilegeLevel, RequireTokenPrivilege, TokenClaims};
+ * use actix_web::{test, App};
+ * use std::time::Duration;
+ *
+ * async fn stats(claims: TokenClaims) -> HttpResponse {
+ *     HttpResponse::Ok().body(format!("Hello, user {}", claims.0.sub))
+ * }
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     set_jwt_secret("test-secret");
+ *     let app = test::init_service(App::new().service(
+ *         web::scope("/admin")
+ *             .wrap(RequireTokenPrivilege(PrivilegeLevel::Admin))
+ *             .route("/stats", web::get().to(stats))
+ *     )).await;
+ *     let get = |token: &str| test::TestRequest::get()
+ *         .uri("/admin/stats")
+ *         .insert_header(("Authorization", format!("Bearer {}", token)))
+ *         .to_request();
+ *
+ *     let user = generate_test_jwt(1, PrivilegeLevel::User, Duration::from_secs(60));
+ *     assert_eq!(test::call_service(&app, get(&user)).await.status(), 403);
+ *
+ *     let admin = generate_test_jwt(2, PrivilegeLevel::Admin, Duration::from_secs(60));
+ *     assert_eq!(test::call_and_read_body(&app, get(&admin)).await, "Hello, user 2");
+ *
+ *     assert_eq!(test::call_service(&app, get("forged")).await.status(), 401);
+ * });
+ * ```
+ */
+#[cfg(feature = "auth")]
+pub struct RequireTokenPrivilege<P: Privilege>(pub P);
+
+#[cfg(feature = "auth")]
+impl<S, B, P> Transform<S, ServiceRequest> for RequireTokenPrivilege<P>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    P: Privilege,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireTokenPrivilegeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireTokenPrivilegeMiddleware {
+            service: Rc::new(service),
+            level: self.0.level(),
+        }))
+    }
+}
+
+/// The service produced by the `RequireTokenPrivilege` middleware.
+#[cfg(feature = "auth")]
+pub struct RequireTokenPrivilegeMiddleware<S> {
+    service: Rc<S>,
+    level: i32,
+}
+
+#[cfg(feature = "auth")]
+impl<S, B> Service<ServiceRequest> for RequireTokenPrivilegeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let level = self.level;
+
+        Box::pin(async move {
+            match require_token_privilege(req.request(), level).await {
+                Ok(claims) => {
+                    req.extensions_mut().insert(TokenClaims(claims));
+                    service.call(req).await.map(ServiceResponse::map_into_left_body)
+                }
+                Err(response) => Ok(req.into_response(response).map_into_right_body()),
+            }
+        })
+    }
+}
+
+/**
+ * Extractor for the token claims checked by the `RequireTokenPrivilege` middleware.
+ *
+ * Extraction fails with a `401 Unauthorized` if the route is not wrapped by
+ * `RequireTokenPrivilege`.
+ */
+#[cfg(feature = "auth")]
+#[derive(Debug, Clone)]
+pub struct TokenClaims(pub Claims);
+
+#[cfg(feature = "auth")]
+impl FromRequest for TokenClaims {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<TokenClaims>()
+                .cloned()
+                .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated")),
+        )
+    }
+}
+
+/**
+ * Middleware rejecting requests that are missing any of the given headers.
+ *
+ * Requests missing a header receive a `400 Bad Request` with a JSON body naming
+ * the first missing header, e.g. `{"error": "Missing required header: X-Client-Id"}`.
+ * Header names are matched case-insensitively. An empty list lets every request through.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{web, HttpResponse, RequireHeaders};
+ *
+ * let scope = web::scope("/api")
+ *     .wrap(RequireHeaders::new(&["X-Client-Id"]))
+ *     .route("/ping", web::get().to(|| async { HttpResponse::Ok().body("pong") }));
+ * ```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RequireHeaders {
+    headers: Arc<Vec<String>>,
+}
+
+impl RequireHeaders {
+    /**
+     * Create the middleware from a list of header names.
+     *
+     * # Arguments
+     * - `headers`: The names of the headers every request must carry.
+     */
+    pub fn new(headers: &[&str]) -> Self {
+        Self { headers: Arc::new(headers.iter().map(|header| header.to_string()).collect()) }
+    }
+
+    /// Get the required header names.
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireHeaders
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireHeadersMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireHeadersMiddleware { service, headers: Arc::clone(&self.headers) }))
+    }
+}
+
+/// The service produced by the `RequireHeaders` middleware.
+pub struct RequireHeadersMiddleware<S> {
+    service: S,
+    headers: Arc<Vec<String>>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireHeadersMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Either<
+        futures_util::future::MapOk<S::Future, fn(ServiceResponse<B>) -> ServiceResponse<EitherBody<B>>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let missing = self.headers.iter().find(|header| !req.headers().contains_key(header.as_str()));
+
+        match missing {
+            None => Either::Left(self.service.call(req).map_ok(ServiceResponse::map_into_left_body as fn(_) -> _)),
+            Some(header) => {
+                let response = json_error(StatusCode::BAD_REQUEST, format!("Missing required header: {}", header));
+                Either::Right(ready(Ok(req.into_response(response).map_into_right_body())))
+            }
+        }
+    }
+}
+
+/**
+ * How request paths are normalized before routing, see `Api::normalize_path`.
+ *
+ * Consecutive slashes are always merged, and trailing slashes are handled
+ * according to the `TrailingSlash` style.
+ */
+#[derive(Debug, Clone, Copy)]
+pub enum PathNormalization {
+    /// Route paths exactly as sent.
+    Off,
+    /// Rewrite the path internally, so `/users/` is served by the `/users` route.
+    Rewrite(TrailingSlash),
+    /// Redirect to the normalized path with `308 Permanent Redirect`, so clients learn the canonical URL.
+    Redirect(TrailingSlash),
+}
+
+/**
+ * Middleware redirecting requests to their normalized path.
+ *
+ * Consecutive slashes are merged and trailing slashes are trimmed, kept or added
+ * as configured. Requests whose path changes receive a `308 Permanent Redirect`
+ * to the normalized path, keeping the query string; the method and body are
+ * preserved by clients following a `308`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HttpResponse, NormalizePathRedirect, TrailingSlash};
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new().wrap(NormalizePathRedirect(TrailingSlash::Trim)).route("/users", web::get().to(HttpResponse::Ok))
+ *     ).await;
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("//users/?page=2").to_request()).await;
+ *     assert_eq!(response.status(), 308);
+ *     assert_eq!(response.headers().get("Location").unwrap(), "/users?page=2");
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/users").to_request()).await;
+ *     assert_eq!(response.status(), 200);
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizePathRedirect(pub TrailingSlash);
+
+impl<S, B> Transform<S, ServiceRequest> for NormalizePathRedirect
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = NormalizePathRedirectMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(NormalizePathRedirectMiddleware { service, trailing_slash: self.0 }))
+    }
+}
+
+/// The service produced by the `NormalizePathRedirect` middleware.
+pub struct NormalizePathRedirectMiddleware<S> {
+    service: S,
+    trailing_slash: TrailingSlash,
+}
+
+impl<S, B> Service<ServiceRequest> for NormalizePathRedirectMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Either<
+        futures_util::future::MapOk<S::Future, fn(ServiceResponse<B>) -> ServiceResponse<EitherBody<B>>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let path = normalize_path(req.path(), self.trailing_slash);
+        if path == req.path() {
+            return Either::Left(self.service.call(req).map_ok(ServiceResponse::map_into_left_body as fn(_) -> _));
+        }
+
+        let location = match req.query_string() {
+            "" => path,
+            query => format!("{}?{}", path, query),
+        };
+        let response = HttpResponse::PermanentRedirect().insert_header((LOCATION, location)).finish();
+        Either::Right(ready(Ok(req.into_response(response).map_into_right_body())))
+    }
+}
+
+/// Merge consecutive slashes in a path and apply the trailing slash style.
+fn normalize_path(path: &str, trailing_slash: TrailingSlash) -> String {
+    let mut normalized = String::with_capacity(path.len() + 1);
+    for c in path.chars() {
+        if c != '/' || !normalized.ends_with('/') {
+            normalized.push(c);
+        }
+    }
+    match trailing_slash {
+        TrailingSlash::Trim if normalized.len() > 1 && normalized.ends_with('/') => {
+            normalized.pop();
+        }
+        TrailingSlash::Always if !normalized.ends_with('/') => normalized.push('/'),
+        _ => {}
+    }
+    normalized
+}
+
+/// Default maximum length of the request URI, in bytes.
+pub const DEFAULT_MAX_URI_LENGTH: usize = 8 * 1024;
+
+/// Default maximum length of the query string, in bytes.
+pub const DEFAULT_MAX_QUERY_LENGTH: usize = 8 * 1024;
+
+/**
+ * Middleware limiting the length of the request URI and query string.
+ *
+ * Requests exceeding either limit receive a `414 URI Too Long` with the JSON body
+ * `{"error": "URI too long"}` before routing, so handlers and password checks
+ * never iterate over enormous query strings. This middleware is part of the
+ * default middleware stack of `Api`, see `Api::max_uri_length`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HttpResponse, UriLimit};
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new().wrap(UriLimit::new(64, 16)).route("/search", web::get().to(HttpResponse::Ok))
+ *     ).await;
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/search?q=short").to_request()).await;
+ *     assert_eq!(response.status(), 200);
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/search?q=much-too-long-query").to_request()).await;
+ *     assert_eq!(response.status(), 414);
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct UriLimit {
+    max_uri: usize,
+    max_query: usize,
+}
+
+impl UriLimit {
+    /**
+     * Create the middleware with the given limits.
+     *
+     * # Arguments
+     * - `max_uri`: The maximum length of the path and query string, in bytes.
+     * - `max_query`: The maximum length of the query string, in bytes.
+     */
+    pub fn new(max_uri: usize, max_query: usize) -> Self {
+        Self { max_uri, max_query }
+    }
+}
+
+impl Default for UriLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_URI_LENGTH, DEFAULT_MAX_QUERY_LENGTH)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for UriLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = UriLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(UriLimitMiddleware { service, limit: *self }))
+    }
+}
+
+/// The service produced by the `UriLimit` middleware.
+pub struct UriLimitMiddleware<S> {
+    service: S,
+    limit: UriLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for UriLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Either<
+        futures_util::future::MapOk<S::Future, fn(ServiceResponse<B>) -> ServiceResponse<EitherBody<B>>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let uri_length = req.uri().path_and_query().map_or(req.path().len(), |pq| pq.as_str().len());
+        if uri_length <= self.limit.max_uri && req.query_string().len() <= self.limit.max_query {
+            return Either::Left(self.service.call(req).map_ok(ServiceResponse::map_into_left_body as fn(_) -> _));
+        }
+
+        let response = json_error(StatusCode::URI_TOO_LONG, "URI too long");
+        Either::Right(ready(Ok(req.into_response(response).map_into_right_body())))
+    }
+}
+
+/// Default maximum number of request headers, matching the fixed limit of the Actix HTTP/1 parser.
+pub const DEFAULT_MAX_HEADERS: usize = 96;
+
+/// Default maximum total size of the request headers, in bytes, matching the Actix HTTP/1 read buffer.
+pub const DEFAULT_MAX_HEADER_SIZE: usize = 128 * 1024;
+
+/**
+ * Middleware limiting the number and total size of the request headers.
+ *
+ * The size of a header set is the sum of the lengths of every header name and
+ * value. Requests exceeding either limit receive a `431 Request Header Fields
+ * Too Large` with the JSON body `{"error": "Request header fields too large"}`
+ * before routing. This middleware is part of the default middleware stack of
+ * `Api`, see `Api::max_header_size`.
+ *
+ * The middleware sees the request only after Actix has parsed and buffered
+ * its headers, so it saves no memory: the Actix HTTP/1 parser, which rejects
+ * requests above the default limits on its own, is what bounds it. Lower
+ * limits only give requests with more or larger headers than the application
+ * accepts a `431` response instead of reaching a handler.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HeaderLimit, HttpResponse};
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new().wrap(HeaderLimit::new(64, 2)).route("/", web::get().to(HttpResponse::Ok))
+ *     ).await;
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().insert_header(("x-a", "1")).to_request()).await;
+ *     assert_eq!(response.status(), 200);
+ *     let response = test::call_service(&app, test::TestRequest::get().insert_header(("x-a", "a".repeat(64))).to_request()).await;
+ *     assert_eq!(response.status(), 431);
+ *     let request = test::TestRequest::get()
+ *         .insert_header(("x-a", "1"))
+ *         .insert_header(("x-b", "2"))
+ *         .insert_header(("x-c", "3"))
+ *         .to_request();
+ *     assert_eq!(test::call_service(&app, request).await.status(), 431);
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderLimit {
+    max_size: usize,
+    max_count: usize,
+}
+
+impl HeaderLimit {
+    /**
+     * Create the middleware with the given limits.
+     *
+     * # Arguments
+     * - `max_size`: The maximum total size of the header names and values, in bytes.
+     * - `max_count`: The maximum number of headers.
+     */
+    pub fn new(max_size: usize, max_count: usize) -> Self {
+        Self { max_size, max_count }
+    }
+}
+
+impl Default for HeaderLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_HEADER_SIZE, DEFAULT_MAX_HEADERS)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HeaderLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = HeaderLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HeaderLimitMiddleware { service, limit: *self }))
+    }
+}
+
+/// The service produced by the `HeaderLimit` middleware.
+pub struct HeaderLimitMiddleware<S> {
+    service: S,
+    limit: HeaderLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for HeaderLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Either<
+        futures_util::future::MapOk<S::Future, fn(ServiceResponse<B>) -> ServiceResponse<EitherBody<B>>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let headers = req.headers();
+        let size: usize = headers.iter().map(|(name, value)| name.as_str().len() + value.len()).sum();
+        if headers.len() <= self.limit.max_count && size <= self.limit.max_size {
+            return Either::Left(self.service.call(req).map_ok(ServiceResponse::map_into_left_body as fn(_) -> _));
+        }
+
+        let response = json_error(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, "Request header fields too large");
+        Either::Right(ready(Ok(req.into_response(response).map_into_right_body())))
+    }
+}
+
+/**
+ * Middleware requiring requests to be made over HTTPS.
+ *
+ * Requests over plain HTTP receive a `403 Forbidden` with the JSON body
+ * `{"error": "HTTPS required"}`, or a `308 Permanent Redirect` to the same URL
+ * with the `https` scheme when created with `RequireHttps::redirect`. This keeps
+ * sensitive routes from being served insecurely when a plain HTTP listener
+ * exists alongside the TLS one.
+ *
+ * The scheme of the connection is used, so a client cannot claim HTTPS with a
+ * header. When `Api::trust_proxy` is enabled, the `Forwarded` and
+ * `X-Forwarded-Proto` headers set by the proxy are used instead.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HttpResponse, RequireHttps};
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new()
+ *             .service(web::scope("/auth").wrap(RequireHttps::new()).route("/login", web::post().to(HttpResponse::Ok)))
+ *             .service(web::scope("/legacy").wrap(RequireHttps::redirect()).route("/login", web::get().to(HttpResponse::Ok)))
+ *     ).await;
+ *
+ *     let response = test::call_service(&app, test::TestRequest::post().uri("/auth/login").to_request()).await;
+ *     assert_eq!(response.status(), 403);
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/legacy/login?next=1").insert_header(("Host", "example.com")).to_request()).await;
+ *     assert_eq!(response.status(), 308);
+ *     assert_eq!(response.headers().get("Location").unwrap(), "https://example.com/legacy/login?next=1");
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequireHttps {
+    redirect: bool,
+}
+
+impl RequireHttps {
+    /// Reject plain HTTP requests with `403 Forbidden`.
+    pub fn new() -> Self {
+        Self { redirect: false }
+    }
+
+    /// Redirect plain HTTP requests to HTTPS with `308 Permanent Redirect`.
+    pub fn redirect() -> Self {
+        Self { redirect: true }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireHttps
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequireHttpsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireHttpsMiddleware { service, redirect: self.redirect }))
+    }
+}
+
+/// The service produced by the `RequireHttps` middleware.
+pub struct RequireHttpsMiddleware<S> {
+    service: S,
+    redirect: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireHttpsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Either<
+        futures_util::future::MapOk<S::Future, fn(ServiceResponse<B>) -> ServiceResponse<EitherBody<B>>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let trust_proxy = req.app_data::<TrustProxy>().is_some_and(|trust| trust.0);
+        let secure = if trust_proxy {
+            req.connection_info().scheme() == "https"
+        } else {
+            req.app_config().secure()
+        };
+        if secure {
+            return Either::Left(self.service.call(req).map_ok(ServiceResponse::map_into_left_body as fn(_) -> _));
+        }
+
+        let response = if self.redirect {
+            let uri = req.uri().path_and_query().map_or(req.path(), |pq| pq.as_str());
+            let location = format!("https://{}{}", req.connection_info().host(), uri);
+            HttpResponse::PermanentRedirect().insert_header((LOCATION, location)).finish()
+        } else {
+            json_error(StatusCode::FORBIDDEN, "HTTPS required")
+        };
+        Either::Right(ready(Ok(req.into_response(response).map_into_right_body())))
+    }
+}
+
+/**
+ * Middleware catching panics in the wrapped services.
+ *
+ * A panicking handler is logged together with the request method, path and
+ * `X-Request-Id` header (if present), and the client receives a
+ * `500 Internal Server Error` with the JSON body `{"error": "Internal server error"}`
+ * instead of a dropped connection. The worker keeps serving other requests.
+ * This middleware is part of the default middleware stack of `Api`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{CatchPanic, HttpResponse};
+ * use actix_web::{test, web, App};
+ *
+ * async fn buggy() -> HttpResponse {
+ *     panic!("something went wrong");
+ * }
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(App::new().wrap(CatchPanic).route("/buggy", web::get().to(buggy))).await;
+ *
+ *     let response = test::try_call_service(&app, test::TestRequest::get().uri("/buggy").to_request()).await;
+ *     let response = response.err().unwrap().error_response();
+ *     assert_eq!(response.status(), 500);
+ *     let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+ *     assert_eq!(body, r#"{"error":"Internal server error"}"#);
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CatchPanic;
+
+impl<S, B> Transform<S, ServiceRequest> for CatchPanic
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = CatchPanicMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CatchPanicMiddleware { service }))
+    }
+}
+
+/// The service produced by the `CatchPanic` middleware.
+pub struct CatchPanicMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for CatchPanicMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        // The request itself cannot be kept: handlers need exclusive access to it.
+        let request_id = req
+            .headers()
+            .get("X-Request-Id")
+            .and_then(|id| id.to_str().ok())
+            .unwrap_or("-")
+            .to_string();
+        let context = format!("{} {} (request id {})", req.method(), req.path(), request_id);
+
+        let future = match panic::catch_unwind(AssertUnwindSafe(|| self.service.call(req))) {
+            Ok(future) => future,
+            Err(payload) => return Box::pin(ready(Err(panic_error(&context, payload)))),
+        };
+
+        Box::pin(async move {
+            match AssertUnwindSafe(future).catch_unwind().await {
+                Ok(result) => result,
+                Err(payload) => Err(panic_error(&context, payload)),
+            }
+        })
+    }
+}
+
+/// Log a caught panic and build the `500` error sent in its place.
+fn panic_error(context: &str, payload: Box<dyn Any + Send>) -> Error {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string());
+
+    log::error!("Handler panicked on {}: {}", context, message);
+
+    let response = json_error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error");
+    actix_web::error::InternalError::from_response("Handler panicked", response).into()
+}
+
+/**
+ * Middleware logging a warning for requests slower than a threshold.
+ *
+ * The time is measured from the moment the request reaches the middleware until
+ * the response is ready, not including streaming the body. Slow requests are
+ * logged with their method, path, status, duration and `X-Request-Id` header
+ * (if present), and still complete normally.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HttpResponse, SlowRequestLog};
+ * use actix_web::{test, web, App};
+ * use std::time::Duration;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new()
+ *             .wrap(SlowRequestLog(Duration::from_millis(10)))
+ *             .route("/slow", web::get().to(|| async {
+ *                 actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+ *                 HttpResponse::Ok().finish()
+ *             }))
+ *     ).await;
+ *
+ *     // Logs "WARNING: Slow request GET /slow returned 200 OK after 20ms (request id -)".
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/slow").to_request()).await;
+ *     assert_eq!(response.status(), 200);
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct SlowRequestLog(pub Duration);
+
+impl<S, B> Transform<S, ServiceRequest> for SlowRequestLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = SlowRequestLogMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SlowRequestLogMiddleware { service, threshold: self.0 }))
+    }
+}
+
+/// The service produced by the `SlowRequestLog` middleware.
+pub struct SlowRequestLogMiddleware<S> {
+    service: S,
+    threshold: Duration,
+}
+
+impl<S, B> Service<ServiceRequest> for SlowRequestLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let threshold = self.threshold;
+        let request_id = req
+            .headers()
+            .get("X-Request-Id")
+            .and_then(|id| id.to_str().ok())
+            .unwrap_or("-")
+            .to_string();
+        let context = format!("{} {}", req.method(), req.path());
+        let start = Instant::now();
+        let future = self.service.call(req);
+
+        Box::pin(async move {
+            let result = future.await;
+            let elapsed = start.elapsed();
+            if elapsed >= threshold {
+                let outcome = match &result {
+                    Ok(response) => format!("returned {}", response.status()),
+                    Err(e) => format!("failed with {}", e.as_response_error().status_code()),
+                };
+                log::warn!(
+                    "Slow request {} {} after {}ms (request id {})",
+                    context, outcome, elapsed.as_millis(), request_id
+                );
+            }
+            result
+        })
+    }
+}
+
+/// Headers added by `security_headers`, as `(name, value)` pairs.
+pub const SECURITY_HEADERS: [(&str, &str); 4] = [
+    ("strict-transport-security", "max-age=31536000; includeSubDomains"),
+    ("x-content-type-options", "nosniff"),
+    ("x-frame-options", "DENY"),
+    ("referrer-policy", "no-referrer"),
+];
+
+/**
+ * Middleware adding the `SECURITY_HEADERS` to every response.
+ *
+ * Headers already set by a handler are left unchanged. Errors returned by inner
+ * middleware, such as a rate limiter's `429 Too Many Requests`, get the headers
+ * as well.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::HttpResponse;
+ * use rusty_api::core::middleware::security_headers;
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new()
+ *             .wrap(security_headers())
+ *             .route("/", web::get().to(|| async { HttpResponse::Ok().finish() }))
+ *     ).await;
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+ *     assert_eq!(response.headers().get("X-Content-Type-Options").unwrap(), "nosniff");
+ * });
+ * ```
+ */
+pub fn security_headers() -> SecurityHeaders {
+    SecurityHeaders
+}
+
+/// Middleware adding the `SECURITY_HEADERS`, created with `security_headers`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SecurityHeaders;
+
+impl<S, B> Transform<S, ServiceRequest> for SecurityHeaders
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = SecurityHeadersMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SecurityHeadersMiddleware { service }))
+    }
+}
+
+/// The service produced by the `SecurityHeaders` middleware.
+pub struct SecurityHeadersMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for SecurityHeadersMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let future = self.service.call(req);
+
+        Box::pin(async move {
+            let add_headers = |headers: &mut actix_web::http::header::HeaderMap| {
+                for (name, value) in SECURITY_HEADERS {
+                    if !headers.contains_key(name) {
+                        headers.insert(HeaderName::from_static(name), HeaderValue::from_static(value));
+                    }
+                }
+            };
+            match future.await {
+                Ok(mut response) => {
+                    add_headers(response.headers_mut());
+                    Ok(response)
+                }
+                // Errors become responses further out, so add the headers to their response now.
+                Err(e) => {
+                    let mut response = e.error_response();
+                    add_headers(response.headers_mut());
+                    Err(actix_web::error::InternalError::from_response(e, response).into())
+                }
+            }
+        })
+    }
+}
+
+/**
+ * Middleware setting or removing the `Server` response header.
+ *
+ * With a value, every response carries `Server: <value>`, replacing any value
+ * set by a handler. Without one, the header is removed from every response so
+ * the server software is not revealed.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HttpResponse, ServerHeader};
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new()
+ *             .wrap(ServerHeader::new(Some("my-api")).unwrap())
+ *             .route("/", web::get().to(|| async { HttpResponse::Ok().finish() }))
+ *             .route("/fail", web::get().to(|| async {
+ *                 Err::<HttpResponse, _>(actix_web::error::ErrorBadRequest("no"))
+ *             }))
+ *     ).await;
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+ *     assert_eq!(response.headers().get("Server").unwrap(), "my-api");
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/fail").to_request()).await;
+ *     assert_eq!(response.status(), 400);
+ *     assert_eq!(response.headers().get("Server").unwrap(), "my-api");
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct ServerHeader(Option<HeaderValue>);
+
+impl ServerHeader {
+    /**
+     * Create the middleware.
+     *
+     * # Arguments
+     * - `value`: The `Server` header value, or `None` to remove the header.
+     *
+     * # Returns
+     * The middleware, or an error message if the value is not a valid header value.
+     */
+    pub fn new(value: Option<&str>) -> Result<Self, String> {
+        value
+            .map(|value| {
+                HeaderValue::from_str(value).map_err(|_| format!("Invalid Server header value: '{}'", value))
+            })
+            .transpose()
+            .map(Self)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ServerHeader
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ServerHeaderMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ServerHeaderMiddleware { service, value: self.0.clone() }))
+    }
+}
+
+/// The service produced by the `ServerHeader` middleware.
+pub struct ServerHeaderMiddleware<S> {
+    service: S,
+    value: Option<HeaderValue>,
+}
+
+impl<S, B> Service<ServiceRequest> for ServerHeaderMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let future = self.service.call(req);
+        let value = self.value.clone();
+
+        Box::pin(async move {
+            let set_header = |headers: &mut actix_web::http::header::HeaderMap| match &value {
+                Some(value) => {
+                    headers.insert(SERVER, value.clone());
+                }
+                None => {
+                    headers.remove(SERVER);
+                }
+            };
+            match future.await {
+                Ok(mut response) => {
+                    set_header(response.headers_mut());
+                    Ok(response)
+                }
+                // Errors become responses further out, so set the header on their response now.
+                Err(e) => {
+                    let mut response = e.error_response();
+                    set_header(response.headers_mut());
+                    Err(actix_web::error::InternalError::from_response(e, response).into())
+                }
+            }
+        })
+    }
+}
+
+/**
+ * Middleware restricting access by client IP address.
+ *
+ * Clients inside a blocklisted range are rejected with a `403 Forbidden`. If the
+ * allowlist is not empty, clients outside all allowlisted ranges are rejected as
+ * well, including clients whose IP cannot be determined. The client IP is taken
+ * from proxy headers only when `trust_proxy` is enabled.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HttpResponse, IpFilter};
+ * use rusty_api::core::ip::parse_ip_range;
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let filter = IpFilter::new(vec![parse_ip_range("10.0.0.0/8").unwrap()], vec![], false);
+ *     let app = test::init_service(
+ *         App::new().wrap(filter).route("/", web::get().to(|| async { HttpResponse::Ok().finish() }))
+ *     ).await;
+ *
+ *     let req = test::TestRequest::get().uri("/").peer_addr("10.0.0.1:1000".parse().unwrap()).to_request();
+ *     assert_eq!(test::call_service(&app, req).await.status(), 200);
+ *     let req = test::TestRequest::get().uri("/").peer_addr("192.168.0.1:1000".parse().unwrap()).to_request();
+ *     assert_eq!(test::call_service(&app, req).await.status(), 403);
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    allowlist: Arc<Vec<IpNet>>,
+    blocklist: Arc<Vec<IpNet>>,
+    trust_proxy: bool,
+}
+
+impl IpFilter {
+    /**
+     * Create the IP filter middleware.
+     *
+     * # Arguments
+     * - `allowlist`: Ranges allowed to access the API. Empty allows every client not blocklisted.
+     * - `blocklist`: Ranges denied access, taking precedence over the allowlist.
+     * - `trust_proxy`: Whether to trust the `Forwarded`/`X-Forwarded-For` headers.
+     */
+    pub fn new(allowlist: Vec<IpNet>, blocklist: Vec<IpNet>, trust_proxy: bool) -> Self {
+        Self { allowlist: Arc::new(allowlist), blocklist: Arc::new(blocklist), trust_proxy }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for IpFilter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = IpFilterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IpFilterMiddleware { service, filter: self.clone() }))
+    }
+}
+
+/// The service produced by the `IpFilter` middleware.
+pub struct IpFilterMiddleware<S> {
+    service: S,
+    filter: IpFilter,
+}
+
+impl<S> IpFilterMiddleware<S> {
+    /// Check whether the request's client may access the API.
+    fn is_allowed(&self, req: &ServiceRequest) -> bool {
+        let filter = &self.filter;
+        if filter.allowlist.is_empty() && filter.blocklist.is_empty() {
+            return true;
+        }
+        match client_ip(&req.connection_info(), req.peer_addr(), filter.trust_proxy) {
+            Some(ip) => {
+                !filter.blocklist.iter().any(|range| range.contains(&ip))
+                    && (filter.allowlist.is_empty() || filter.allowlist.iter().any(|range| range.contains(&ip)))
+            }
+            None => filter.allowlist.is_empty(),
+        }
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for IpFilterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Either<
+        futures_util::future::MapOk<S::Future, fn(ServiceResponse<B>) -> ServiceResponse<EitherBody<B>>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if self.is_allowed(&req) {
+            Either::Left(self.service.call(req).map_ok(ServiceResponse::map_into_left_body as fn(_) -> _))
+        } else {
+            let response = json_error(StatusCode::FORBIDDEN, "Access denied");
+            Either::Right(ready(Ok(req.into_response(response).map_into_right_body())))
+        }
+    }
+}
+
+/**
+ * Middleware adding weak ETags to `GET` and `HEAD` responses.
+ *
+ * The ETag is computed from a hash of the response body. When the request's
+ * `If-None-Match` header contains a matching ETag, the body is dropped and a
+ * `304 Not Modified` is returned instead. Only `200 OK` responses with a sized
+ * body are handled; streaming responses and other statuses are passed through.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Etag, HttpResponse};
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new().wrap(Etag).route("/role", web::get().to(|| async { HttpResponse::Ok().body("admin") }))
+ *     ).await;
+ *
+ *     let response = test::call_service(&app, test::TestRequest::get().uri("/role").to_request()).await;
+ *     let etag = response.headers().get("ETag").unwrap().clone();
+ *     assert!(etag.to_str().unwrap().starts_with("W/\""));
+ *
+ *     let req = test::TestRequest::get().uri("/role").insert_header(("If-None-Match", etag)).to_request();
+ *     let response = test::call_service(&app, req).await;
+ *     assert_eq!(response.status(), 304);
+ *     assert!(test::read_body(response).await.is_empty());
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Etag;
+
+impl<S, B> Transform<S, ServiceRequest> for Etag
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = EtagMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(EtagMiddleware { service }))
+    }
+}
+
+/// The service produced by the `Etag` middleware.
+pub struct EtagMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for EtagMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let is_get = matches!(*req.method(), Method::GET | Method::HEAD);
+        let if_none_match = req
+            .headers()
+            .get(IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let future = self.service.call(req);
+
+        Box::pin(async move {
+            match future.await {
+                Ok(response) => {
+                    let (request, response) = response.into_parts();
+                    let response = tag_response(response.map_into_boxed_body(), is_get, if_none_match).await?;
+                    Ok(ServiceResponse::new(request, response))
+                }
+                Err(e) => {
+                    let response = tag_response(e.error_response(), is_get, if_none_match).await?;
+                    Err(actix_web::error::InternalError::from_response(e, response).into())
+                }
+            }
+        })
+    }
+}
+
+/// Add a weak `ETag` to a sized `200 OK` response to a `GET` or `HEAD`, answering `304` on a match.
+async fn tag_response(response: HttpResponse, is_get: bool, if_none_match: Option<String>) -> Result<HttpResponse, Error> {
+    let sized = matches!(response.body().size(), BodySize::Sized(_));
+    if !is_get || response.status() != StatusCode::OK || !sized {
+        return Ok(response);
+    }
+
+    let (mut head, body) = response.into_parts();
+    let body = actix_web::body::to_bytes(body).await.map_err(|e| {
+        actix_web::error::ErrorInternalServerError(e.to_string())
+    })?;
+
+    let etag = format!("W/\"{}\"", body_digest(&body));
+    head.headers_mut().insert(ETAG, HeaderValue::from_str(&etag).expect("ETag is a valid header value"));
+
+    if if_none_match.is_some_and(|header| etag_matches(&header, &etag)) {
+        *head.status_mut() = StatusCode::NOT_MODIFIED;
+        return Ok(head.set_body(BoxBody::new(())));
+    }
+    Ok(head.set_body(BoxBody::new(body)))
+}
+
+/// Hash a response body into a short hex digest.
+fn body_digest(body: &[u8]) -> String {
+    Sha256::digest(body).iter().take(16).map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Check whether an `If-None-Match` header matches an ETag, using weak comparison.
+fn etag_matches(header: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    header.split(',').any(|candidate| candidate.trim() == "*" || opaque(candidate) == opaque(etag))
+}
+
+/**
+ * Extra `meta` fields for an enveloped response, e.g. pagination.
+ *
+ * Insert it into a response's extensions and the `ResponseEnvelope` middleware
+ * merges the fields into the envelope's `meta` object. Without the middleware
+ * it has no effect.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{EnvelopeMeta, HttpResponse};
+ * use serde_json::json;
+ *
+ * let mut response = HttpResponse::Ok().json(json!([1, 2, 3]));
+ * response.extensions_mut().insert(EnvelopeMeta::new(json!({"page": 1, "total": 3})));
+ * ```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct EnvelopeMeta(pub serde_json::Map<String, serde_json::Value>);
+
+impl EnvelopeMeta {
+    /// Create the meta fields from a JSON object. Other JSON values are ignored.
+    pub fn new(fields: serde_json::Value) -> Self {
+        match fields {
+            serde_json::Value::Object(fields) => Self(fields),
+            _ => Self::default(),
+        }
+    }
+}
+
+/**
+ * Middleware wrapping JSON responses in a `{"data": ..., "meta": ..., "errors": [...]}` envelope.
+ *
+ * Successful responses carry their body in `data`; responses with a `4xx` or
+ * `5xx` status carry it in `errors`, with `{"error": "..."}` bodies turned into
+ * `{"status": 404, "message": "..."}` entries. `meta` holds the request's
+ * `X-Request-Id` header, if any, and the fields of an `EnvelopeMeta` the handler
+ * added to the response. Bodies that already are an envelope (objects with only
+ * `data`, `meta` and `errors` keys), non-JSON bodies and streaming responses are
+ * passed through untouched.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{json_error, HttpResponse, ResponseEnvelope, StatusCode};
+ * use actix_web::{test, web, App};
+ * use serde_json::{json, Value};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new()
+ *             .wrap(ResponseEnvelope)
+ *             .route("/user", web::get().to(|| async { HttpResponse::Ok().json(json!({"id": 1})) }))
+ *             .route("/missing", web::get().to(|| async { json_error(StatusCode::NOT_FOUND, "Not found") }))
+ *             .route("/text", web::get().to(|| async { HttpResponse::Ok().body("plain") }))
+ *             .route("/fail", web::get().to(|| async {
+ *                 Err::<HttpResponse, _>(actix_web::error::InternalError::from_response(
+ *                     "gone",
+ *                     json_error(StatusCode::GONE, "Gone"),
+ *                 ))
+ *             }))
+ *     ).await;
+ *
+ *     let req = test::TestRequest::get().uri("/user").insert_header(("X-Request-Id", "abc")).to_request();
+ *     let body: Value = test::call_and_read_body_json(&app, req).await;
+ *     assert_eq!(body, json!({"data": {"id": 1}, "meta": {"request_id": "abc"}}));
+ *
+ *     let body: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/missing").to_request()).await;
+ *     assert_eq!(body, json!({"errors": [{"status": 404, "message": "Not found"}], "meta": {}}));
+ *
+ *     assert_eq!(test::call_and_read_body(&app, test::TestRequest::get().uri("/text").to_request()).await, "plain");
+ *
+ *     let body: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/fail").to_request()).await;
+ *     assert_eq!(body, json!({"errors": [{"status": 410, "message": "Gone"}], "meta": {}}));
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResponseEnvelope;
+
+impl<S, B> Transform<S, ServiceRequest> for ResponseEnvelope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = ResponseEnvelopeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ResponseEnvelopeMiddleware { service }))
+    }
+}
+
+/// The service produced by the `ResponseEnvelope` middleware.
+pub struct ResponseEnvelopeMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for ResponseEnvelopeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get("X-Request-Id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let future = self.service.call(req);
+
+        Box::pin(async move {
+            match future.await {
+                Ok(response) => {
+                    let (request, response) = response.into_parts();
+                    let response = envelope_response(response.map_into_boxed_body(), request_id).await?;
+                    Ok(ServiceResponse::new(request, response))
+                }
+                // Errors become responses further out, so envelope their response now.
+                Err(e) => {
+                    let response = envelope_response(e.error_response(), request_id).await?;
+                    Err(actix_web::error::InternalError::from_response(e, response).into())
+                }
+            }
+        })
+    }
+}
+
+/// Wrap a sized JSON response body in an envelope, leaving other responses untouched.
+async fn envelope_response(response: HttpResponse, request_id: Option<String>) -> Result<HttpResponse, Error> {
+    let is_json = response
+        .headers()
+        .get(actix_web::http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let sized = matches!(response.body().size(), BodySize::Sized(n) if n > 0);
+    if !is_json || !sized {
+        return Ok(response);
+    }
+
+    let (head, body) = response.into_parts();
+    let body = actix_web::body::to_bytes(body).await.map_err(|e| {
+        actix_web::error::ErrorInternalServerError(e.to_string())
+    })?;
+    let value: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(value) if !is_envelope(&value) => value,
+        _ => return Ok(head.set_body(BoxBody::new(body))),
+    };
+
+    let mut meta = head.extensions().get::<EnvelopeMeta>().cloned().unwrap_or_default().0;
+    if let Some(request_id) = request_id {
+        meta.insert("request_id".into(), request_id.into());
+    }
+    let status = head.status();
+    let envelope = if status.is_client_error() || status.is_server_error() {
+        serde_json::json!({ "errors": [error_entry(status, value)], "meta": meta })
+    } else {
+        serde_json::json!({ "data": value, "meta": meta })
+    };
+    Ok(head.set_body(BoxBody::new(envelope.to_string())))
+}
+
+/// Check whether a JSON body already is a response envelope.
+fn is_envelope(value: &serde_json::Value) -> bool {
+    value.as_object().is_some_and(|object| {
+        (object.contains_key("data") || object.contains_key("errors"))
+            && object.keys().all(|key| matches!(key.as_str(), "data" | "meta" | "errors"))
+    })
+}
+
+/// Turn an error body into an entry of the envelope's `errors` list.
+fn error_entry(status: StatusCode, value: serde_json::Value) -> serde_json::Value {
+    match value.get("error").and_then(serde_json::Value::as_str) {
+        Some(message) => serde_json::json!({ "status": status.as_u16(), "message": message }),
+        None => value,
+    }
+}
+
+/// Default maximum body size logged by `BodyLogger`, in bytes.
+pub const DEFAULT_BODY_LOG_LIMIT: usize = 4096;
+
+/// JSON, form and query string keys redacted by `BodyLogger` by default.
+pub const DEFAULT_REDACTED_KEYS: [&str; 3] = ["password", "token", "api_key"];
+
+/**
+ * Middleware logging request and response bodies for debugging.
+ *
+ * Bodies are logged at the `debug` level, so they only appear when the logger
+ * enables it, e.g. with `Api::log_level(LevelFilter::Debug)`. JSON bodies have
+ * the values of redacted keys (matched case-insensitively, at any depth)
+ * replaced by `"[REDACTED]"`, and so do `application/x-www-form-urlencoded`
+ * request bodies and the query string, which is logged with the path. Bodies
+ * larger than the size limit are not logged, only their
+ * size, and at most the limit (plus one chunk) of a request body is buffered.
+ * Streaming responses are passed through untouched.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::BodyLogger;
+ *
+ * let logger = BodyLogger::default()
+ *     .max_bytes(1024)
+ *     .redact(&["password", "secret"]);
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct BodyLogger {
+    max_bytes: usize,
+    redacted_keys: Arc<Vec<String>>,
+}
+
+impl Default for BodyLogger {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_BODY_LOG_LIMIT,
+            redacted_keys: Arc::new(DEFAULT_REDACTED_KEYS.iter().map(|key| key.to_string()).collect()),
+        }
+    }
+}
+
+impl BodyLogger {
+    /// Set the maximum body size logged, in bytes.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Replace the JSON, form and query string keys whose values are redacted.
+    pub fn redact(mut self, keys: &[&str]) -> Self {
+        self.redacted_keys = Arc::new(keys.iter().map(|key| key.to_string()).collect());
+        self
+    }
+
+    /**
+     * Format a body for logging.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::BodyLogger;
+     *
+     * let logger = BodyLogger::default();
+     * let body = br#"{"username":"alice","password":"Secret123"}"#;
+     * assert_eq!(logger.format_body(body), r#"{"password":"[REDACTED]","username":"alice"}"#);
+     * assert_eq!(BodyLogger::default().max_bytes(4).format_body(body), "<43 bytes, not logged>");
+     * ```
+     */
+    pub fn format_body(&self, body: &[u8]) -> String {
+        if body.len() > self.max_bytes {
+            return format!("<{} bytes, not logged>", body.len());
+        }
+        if body.is_empty() {
+            return "<empty>".to_string();
+        }
+        match serde_json::from_slice::<serde_json::Value>(body) {
+            Ok(mut value) => {
+                redact_json(&mut value, &self.redacted_keys);
+                value.to_string()
+            }
+            Err(_) => String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+
+    /**
+     * Format an `application/x-www-form-urlencoded` body for logging.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::BodyLogger;
+     *
+     * let logger = BodyLogger::default();
+     * assert_eq!(logger.format_form(b"username=alice&Password=Secret%21"), "username=alice&Password=[REDACTED]");
+     * assert_eq!(logger.max_bytes(4).format_form(b"username=alice"), "<14 bytes, not logged>");
+     * ```
+     */
+    pub fn format_form(&self, body: &[u8]) -> String {
+        if body.len() > self.max_bytes {
+            return format!("<{} bytes, not logged>", body.len());
+        }
+        if body.is_empty() {
+            return "<empty>".to_string();
+        }
+        self.redact_form(&String::from_utf8_lossy(body))
+    }
+
+    /// Replace the values of redacted keys in a query string or form body, keeping the other pairs as sent.
+    fn redact_form(&self, form: &str) -> String {
+        form.split('&')
+            .map(|pair| {
+                let redacted = form_urlencoded::parse(pair.as_bytes())
+                    .next()
+                    .is_some_and(|(key, _)| self.redacted_keys.iter().any(|redacted| redacted.eq_ignore_ascii_case(&key)));
+                match pair.split_once('=') {
+                    Some((key, _)) if redacted => format!("{}=[REDACTED]", key),
+                    _ => pair.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+/// Replace the values of redacted keys in a JSON value, at any depth.
+fn redact_json(value: &mut serde_json::Value, keys: &[String]) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if keys.iter().any(|redacted| redacted.eq_ignore_ascii_case(key)) {
+                    *value = serde_json::Value::String("[REDACTED]".to_string());
+                } else {
+                    redact_json(value, keys);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| redact_json(item, keys)),
+        _ => {}
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for BodyLogger
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = BodyLoggerMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(BodyLoggerMiddleware { service: Rc::new(service), logger: self.clone() }))
+    }
+}
+
+/// The service produced by the `BodyLogger` middleware.
+pub struct BodyLoggerMiddleware<S> {
+    service: Rc<S>,
+    logger: BodyLogger,
+}
+
+impl<S, B> Service<ServiceRequest> for BodyLoggerMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let logger = self.logger.clone();
+
+        Box::pin(async move {
+            let context = match req.query_string() {
+                "" => format!("{} {}", req.method(), req.path()),
+                query => format!("{} {}?{}", req.method(), req.path(), logger.redact_form(query)),
+            };
+            let is_form = req
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.to_ascii_lowercase().starts_with("application/x-www-form-urlencoded"));
+
+            // Buffer at most `max_bytes` plus one chunk, then replay it ahead of the rest.
+            let mut payload = req.take_payload();
+            let mut buffered: Vec<Bytes> = Vec::new();
+            let mut size = 0;
+            let mut complete = true;
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk?;
+                size += chunk.len();
+                buffered.push(chunk);
+                if size > logger.max_bytes {
+                    complete = false;
+                    break;
+                }
+            }
+
+            let logged = if !complete {
+                format!("<more than {} bytes, not logged>", logger.max_bytes)
+            } else if is_form {
+                logger.format_form(&buffered.concat())
+            } else {
+                logger.format_body(&buffered.concat())
+            };
+            log::debug!("Request body {}: {}", context, logged);
+
+            let replay = futures_util::stream::iter(buffered.into_iter().map(Ok)).chain(payload);
+            let replay: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> = Box::pin(replay);
+            req.set_payload(Payload::from(replay));
+
+            let response = service.call(req).await?;
+            let size = response.response().body().size();
+            let BodySize::Sized(length) = size else {
+                log::debug!("Response body {}: <streaming, not logged>", context);
+                return Ok(response.map_into_boxed_body());
+            };
+            if length as usize > logger.max_bytes {
+                log::debug!("Response body {}: <{} bytes, not logged>", context, length);
+                return Ok(response.map_into_boxed_body());
+            }
+
+            let (request, response) = response.into_parts();
+            let (head, body) = response.into_parts();
+            let body = actix_web::body::to_bytes(body).await.map_err(|e| {
+                actix_web::error::ErrorInternalServerError(e.into().to_string())
+            })?;
+            log::debug!("Response body {} ({}): {}", context, head.status(), logger.format_body(&body));
+            Ok(ServiceResponse::new(request, head.set_body(BoxBody::new(body))))
+        })
+    }
+}
+
+/**
+ * Rate limiter key extractor using the client IP address.
+ *
+ * This behaves like `actix_governor::PeerIpKeyExtractor`, but uses the address
+ * from proxy headers when `trust_proxy` is enabled.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIpKeyExtractor {
+    /// Whether to trust the `Forwarded`/`X-Forwarded-For` headers.
+    pub trust_proxy: bool,
+}
+
+impl KeyExtractor for ClientIpKeyExtractor {
+    type Key = IpAddr;
+    type KeyExtractionError = &'static str;
+
+    fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+        client_ip(&req.connection_info(), req.peer_addr(), self.trust_proxy)
+            .ok_or("Could not extract client IP address from request")
+    }
+}
+
+/**
+ * Middleware applying the governor rate limiter, except for allowlisted clients.
+ *
+ * Requests whose client IP falls inside one of the allowlisted ranges are passed
+ * straight to the inner service and do not count against the rate limit. The
+ * client IP is resolved the same way as for rate limiting, so proxy headers are
+ * only used when `trust_proxy` is enabled. Requests to exempt paths, such as
+ * `/favicon.ico`, bypass the rate limiter as well.
+ */
+pub struct RateLimit {
+    governor: Governor<ClientIpKeyExtractor>,
+    allowlist: Arc<Vec<IpNet>>,
+    trust_proxy: bool,
+    response: RateLimitResponse,
+    exempt_paths: Arc<Vec<String>>,
+}
+
+/**
+ * Builds the response sent to rate-limited clients.
+ *
+ * The argument is the number of seconds the client should wait before retrying.
+ * A `Retry-After` header is added if the returned response does not set one.
+ */
+pub type RateLimitResponse = Arc<dyn Fn(u64) -> HttpResponse + Send + Sync>;
+
+/**
+ * The default response for rate-limited clients.
+ *
+ * # Arguments
+ * - `retry_after`: Seconds until the client may retry.
+ *
+ * # Returns
+ * A `429 Too Many Requests` JSON error with a `Retry-After` header.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::middleware::default_rate_limit_response;
+ *
+ * let response = default_rate_limit_response(3);
+ * assert_eq!(response.status(), 429);
+ * assert_eq!(response.headers().get("Retry-After").unwrap(), "3");
+ * ```
+ */
+pub fn default_rate_limit_response(retry_after: u64) -> HttpResponse {
+    let mut response = json_error(StatusCode::TOO_MANY_REQUESTS, format!("Too many requests, retry in {}s", retry_after));
+    response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+impl RateLimit {
+    /**
+     * Create the rate limit middleware.
+     *
+     * # Arguments
+     * - `config`: The governor configuration shared by all workers.
+     * - `allowlist`: IP ranges that bypass the rate limiter.
+     */
+    pub fn new(config: &GovernorConfig<ClientIpKeyExtractor>, allowlist: Arc<Vec<IpNet>>, trust_proxy: bool) -> Self {
+        Self {
+            governor: Governor::new(config),
+            allowlist,
+            trust_proxy,
+            response: Arc::new(default_rate_limit_response),
+            exempt_paths: Arc::new(Vec::new()),
+        }
+    }
+
+    /**
+     * Let requests to the given paths bypass the rate limiter.
+     *
+     * # Arguments
+     * - `paths`: The exact request paths to exempt, e.g. `/favicon.ico`.
+     */
+    pub fn exempt_paths(mut self, paths: Vec<String>) -> Self {
+        self.exempt_paths = Arc::new(paths);
+        self
+    }
+
+    /**
+     * Replace the response sent to rate-limited clients.
+     *
+     * # Arguments
+     * - `response`: Builds the response from the number of seconds until the client may retry.
+     */
+    pub fn response(mut self, response: RateLimitResponse) -> Self {
+        self.response = response;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let service = Rc::new(service);
+        let governed = self.governor.new_transform(SharedService(Rc::clone(&service))).into_inner();
+        ready(governed.map(|governed| RateLimitMiddleware {
+            service,
+            governed,
+            allowlist: Arc::clone(&self.allowlist),
+            trust_proxy: self.trust_proxy,
+            response: Arc::clone(&self.response),
+            exempt_paths: Arc::clone(&self.exempt_paths),
+        }))
+    }
+}
+
+/// The service produced by the `RateLimit` middleware.
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    governed: GovernorMiddleware<SharedService<S>, ClientIpKeyExtractor>,
+    allowlist: Arc<Vec<IpNet>>,
+    trust_proxy: bool,
+    response: RateLimitResponse,
+    exempt_paths: Arc<Vec<String>>,
+}
+
+impl<S> RateLimitMiddleware<S> {
+    /// Check whether the request comes from an allowlisted client.
+    fn is_allowlisted(&self, req: &ServiceRequest) -> bool {
+        if self.allowlist.is_empty() {
+            return false;
+        }
+        client_ip(&req.connection_info(), req.peer_addr(), self.trust_proxy)
+            .is_some_and(|ip| self.allowlist.iter().any(|range| range.contains(&ip)))
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, LocalBoxFuture<'static, Result<Self::Response, Self::Error>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if self.is_allowlisted(&req) || self.exempt_paths.iter().any(|path| path == req.path()) {
+            return Either::Left(self.service.call(req));
+        }
+
+        let response = Arc::clone(&self.response);
+        Either::Right(self.governed.call(req).map_err(move |err| {
+            if err.as_response_error().status_code() != StatusCode::TOO_MANY_REQUESTS {
+                return err;
+            }
+
+            // The governor reports whole seconds rounded down, so round up for `Retry-After`.
+            let governed = err.error_response();
+            let wait = governed
+                .headers()
+                .get("x-ratelimit-after")
+                .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+                .unwrap_or(0);
+            let mut response = response(wait + 1);
+            if !response.headers().contains_key(RETRY_AFTER) {
+                response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(wait + 1));
+            }
+            actix_web::error::InternalError::from_response("Too many requests", response).into()
+        }).boxed_local())
+    }
+}
+
+/// A service shared between the rate-limited and the bypass paths of `RateLimitMiddleware`.
+pub struct SharedService<S>(Rc<S>);
+
+impl<S: Service<ServiceRequest>> Service<ServiceRequest> for SharedService<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        self.0.call(req)
+    }
+}
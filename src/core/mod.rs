@@ -1,5 +1,32 @@
 pub mod config;
 pub mod user;
+#[cfg(feature = "auth")]
 pub mod auth;
+#[cfg(feature = "sqlite")]
 pub mod db;
-pub mod auth_routes;
\ No newline at end of file
+#[cfg(feature = "auth")]
+pub mod auth_routes;
+pub mod middleware;
+pub mod ip;
+pub mod settings;
+#[cfg(feature = "auth")]
+pub mod password;
+pub mod response;
+pub mod json;
+pub mod logging;
+#[cfg(feature = "auth")]
+pub mod audit;
+pub mod version;
+#[cfg(feature = "auth")]
+pub mod jwks;
+pub mod cache;
+#[cfg(feature = "auth")]
+pub mod quota;
+pub mod idempotency;
+#[cfg(feature = "auth")]
+pub mod session;
+#[cfg(feature = "auth")]
+pub mod metrics;
+pub mod pagination;
+#[cfg(feature = "cli")]
+pub mod cli;
@@ -0,0 +1,127 @@
+/*!
+ * The `pagination` module reads `?limit=&offset=` query parameters for list endpoints.
+ *
+ * Every list endpoint should page its results the same way, so clients can rely
+ * on one set of rules. The `Pagination` extractor applies them:
+ * - A missing `limit` defaults to `DEFAULT_PAGE_LIMIT`, and larger values are clamped to `MAX_PAGE_LIMIT`.
+ * - A `limit` of `0` is raised to `1`.
+ * - A missing `offset` defaults to `0`.
+ * - Negative or non-numeric values are rejected with a `400 Bad Request`.
+ *
+ * The defaults can be changed for an app or scope by registering `PaginationLimits` as app data.
+ */
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::http::StatusCode;
+use actix_web::{Error, FromRequest, HttpRequest};
+use serde::Serialize;
+use std::future::{ready, Ready};
+
+use crate::core::middleware::EnvelopeMeta;
+use crate::core::response::json_error;
+
+/// Number of items returned when the request does not set `limit`.
+pub const DEFAULT_PAGE_LIMIT: u32 = 50;
+
+/// Largest `limit` accepted; larger values are clamped.
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
+/**
+ * The default and maximum page sizes used by the `Pagination` extractor.
+ *
+ * Register as app data to override `DEFAULT_PAGE_LIMIT` and `MAX_PAGE_LIMIT`.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationLimits {
+    /// Number of items returned when the request does not set `limit`.
+    pub default_limit: u32,
+    /// Largest `limit` accepted.
+    pub max_limit: u32,
+}
+
+impl Default for PaginationLimits {
+    fn default() -> Self {
+        Self { default_limit: DEFAULT_PAGE_LIMIT, max_limit: MAX_PAGE_LIMIT }
+    }
+}
+
+/**
+ * Extractor for the `limit` and `offset` query parameters of a list endpoint.
+ *
+ * See the module documentation for the rules applied.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::pagination::Pagination;
+ * use rusty_api::HttpResponse;
+ * use actix_web::{test, web, App};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(
+ *         App::new().route("/items", web::get().to(|page: Pagination| async move {
+ *             HttpResponse::Ok().body(format!("{} {}", page.limit, page.offset))
+ *         }))
+ *     ).await;
+ *     let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+ *
+ *     assert_eq!(test::call_and_read_body(&app, get("/items")).await, "50 0");
+ *     assert_eq!(test::call_and_read_body(&app, get("/items?limit=500&offset=20")).await, "100 20");
+ *     assert_eq!(test::call_service(&app, get("/items?offset=-1")).await.status(), 400);
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Pagination {
+    /// Maximum number of items to return.
+    pub limit: u32,
+    /// Number of items to skip.
+    pub offset: u64,
+}
+
+impl Pagination {
+    /**
+     * Read the pagination of a query string.
+     *
+     * # Arguments
+     * - `query`: The query string, without the leading `?`.
+     * - `limits`: The default and maximum page sizes.
+     *
+     * # Returns
+     * The pagination, or a message describing the invalid parameter.
+     */
+    pub fn from_query(query: &str, limits: PaginationLimits) -> Result<Self, String> {
+        let mut page = Self { limit: limits.default_limit, offset: 0 };
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "limit" => page.limit = parse_param(key, value)?.clamp(1, u64::from(limits.max_limit.max(1))) as u32,
+                "offset" => page.offset = parse_param(key, value)?,
+                _ => {}
+            }
+        }
+        Ok(page)
+    }
+
+    /// Get the `limit` and `offset` as envelope metadata, see `ResponseEnvelope`.
+    pub fn meta(&self) -> EnvelopeMeta {
+        EnvelopeMeta::new(serde_json::json!({ "limit": self.limit, "offset": self.offset }))
+    }
+}
+
+/// Parse a non-negative integer query parameter.
+fn parse_param(key: &str, value: &str) -> Result<u64, String> {
+    value.parse().map_err(|_| format!("Query parameter '{}' must be a non-negative integer", key))
+}
+
+impl FromRequest for Pagination {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let limits = req.app_data::<PaginationLimits>().copied().unwrap_or_default();
+        ready(Pagination::from_query(req.query_string(), limits).map_err(|message| {
+            let response = json_error(StatusCode::BAD_REQUEST, &message);
+            InternalError::from_response(message, response).into()
+        }))
+    }
+}
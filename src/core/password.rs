@@ -0,0 +1,242 @@
+/*!
+ * The `password` module hashes and verifies user passwords.
+ *
+ * Password hashing is pluggable through the `PasswordHasher` trait. New hashes
+ * are created with the configured default hasher, while verification detects
+ * the algorithm from the stored hash prefix, so existing hashes keep working
+ * after switching algorithms.
+ *
+ * This module features:
+ * - **Bcrypt**: `BcryptHasher`, the default, with a configurable cost.
+ * - **Argon2id**: `Argon2Hasher`, using the recommended default parameters.
+ * - **Algorithm Selection**: `PasswordAlgorithm` and `set_password_hasher` choose the hasher for new passwords.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::password::{Argon2Hasher, BcryptHasher, PasswordHasher};
+ *
+ * let bcrypt = BcryptHasher { cost: 4 }.hash("Secret123").unwrap();
+ * let argon2 = Argon2Hasher.hash("Secret123").unwrap();
+ *
+ * assert!(rusty_api::core::auth::verify_password("Secret123", &bcrypt));
+ * assert!(rusty_api::core::auth::verify_password("Secret123", &argon2));
+ * assert!(!rusty_api::core::auth::verify_password("wrong", &argon2));
+ * ```
+ */
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, SaltString};
+use argon2::{Argon2, PasswordVerifier};
+use serde::Deserialize;
+
+static PASSWORD_HASHER: RwLock<Option<Arc<dyn PasswordHasher>>> = RwLock::new(None);
+
+/// The bcrypt cost used by the default hasher.
+pub const DEFAULT_BCRYPT_COST: u32 = 12;
+
+/// The longest password bcrypt hashes in full, in bytes. Longer passwords are refused.
+pub const BCRYPT_MAX_PASSWORD_BYTES: usize = 72;
+
+/**
+ * A password hashing algorithm.
+ *
+ * Implement this trait to plug in a custom algorithm with `set_password_hasher`.
+ */
+pub trait PasswordHasher: Send + Sync {
+    /// Hash a plaintext password.
+    fn hash(&self, password: &str) -> Result<String, PasswordHashError>;
+
+    /// Verify a plaintext password against a hash created by this hasher.
+    fn verify(&self, password: &str, hash: &str) -> bool;
+
+    /// Check whether a stored hash was created by this hasher, usually by its prefix.
+    fn recognizes(&self, hash: &str) -> bool;
+
+    /// The longest password this hasher accepts, in bytes, or `None` if there is no limit.
+    fn max_password_bytes(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Error returned when a password cannot be hashed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordHashError(pub String);
+
+impl fmt::Display for PasswordHashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to hash password: {}", self.0)
+    }
+}
+
+impl std::error::Error for PasswordHashError {}
+
+/**
+ * Bcrypt password hasher, recognizing `$2a$`, `$2b$`, `$2x$` and `$2y$` hashes.
+ *
+ * Bcrypt only uses the first 72 bytes of a password, so two long passwords
+ * sharing a prefix would hash to the same value. Passwords longer than
+ * `BCRYPT_MAX_PASSWORD_BYTES` are therefore refused instead of silently
+ * truncated. Verification is unchanged, so hashes created before this check
+ * keep working.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::password::{BcryptHasher, PasswordHasher};
+ *
+ * let hasher = BcryptHasher { cost: 4 };
+ * assert!(hasher.hash(&"a".repeat(72)).is_ok());
+ * assert!(hasher.hash(&"a".repeat(100)).is_err());
+ * ```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BcryptHasher {
+    /// The bcrypt cost factor.
+    pub cost: u32,
+}
+
+impl Default for BcryptHasher {
+    fn default() -> Self {
+        Self { cost: DEFAULT_BCRYPT_COST }
+    }
+}
+
+impl PasswordHasher for BcryptHasher {
+    fn hash(&self, password: &str) -> Result<String, PasswordHashError> {
+        if password.len() > BCRYPT_MAX_PASSWORD_BYTES {
+            return Err(PasswordHashError(format!(
+                "bcrypt passwords must be at most {} bytes", BCRYPT_MAX_PASSWORD_BYTES
+            )));
+        }
+        bcrypt::hash(password, self.cost).map_err(|e| PasswordHashError(e.to_string()))
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> bool {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix))
+    }
+
+    fn max_password_bytes(&self) -> Option<usize> {
+        Some(BCRYPT_MAX_PASSWORD_BYTES)
+    }
+}
+
+/// Argon2id password hasher, recognizing `$argon2` PHC strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Argon2Hasher;
+
+impl PasswordHasher for Argon2Hasher {
+    fn hash(&self, password: &str) -> Result<String, PasswordHashError> {
+        let salt = SaltString::generate(&mut OsRng);
+        argon2::PasswordHasher::hash_password(&Argon2::default(), password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| PasswordHashError(e.to_string()))
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> bool {
+        PasswordHash::new(hash)
+            .is_ok_and(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$argon2")
+    }
+}
+
+/**
+ * The built-in password hashing algorithms, for selecting one from configuration.
+ *
+ * Deserializes and parses from `"bcrypt"` or `"argon2"`, ignoring case.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::password::PasswordAlgorithm;
+ *
+ * assert_eq!("Argon2".parse(), Ok(PasswordAlgorithm::Argon2));
+ * assert!("md5".parse::<PasswordAlgorithm>().is_err());
+ * ```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordAlgorithm {
+    /// Bcrypt with `DEFAULT_BCRYPT_COST`.
+    Bcrypt,
+    /// Argon2id with the default parameters.
+    Argon2,
+}
+
+impl PasswordAlgorithm {
+    /// Get the hasher implementing this algorithm.
+    pub fn hasher(self) -> Arc<dyn PasswordHasher> {
+        match self {
+            PasswordAlgorithm::Bcrypt => Arc::new(BcryptHasher::default()),
+            PasswordAlgorithm::Argon2 => Arc::new(Argon2Hasher),
+        }
+    }
+}
+
+impl FromStr for PasswordAlgorithm {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "bcrypt" => Ok(PasswordAlgorithm::Bcrypt),
+            "argon2" => Ok(PasswordAlgorithm::Argon2),
+            _ => Err(format!("unknown password algorithm '{}', expected bcrypt or argon2", value)),
+        }
+    }
+}
+
+/**
+ * Set the hasher used for new passwords for the rest of the process.
+ *
+ * Hashes created by the built-in hashers keep verifying after switching, so
+ * existing users can log in while their passwords are migrated.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::password::{set_password_hasher, BcryptHasher};
+ *
+ * set_password_hasher(BcryptHasher::default());
+ * ```
+ */
+pub fn set_password_hasher(hasher: impl PasswordHasher + 'static) {
+    *PASSWORD_HASHER.write().unwrap() = Some(Arc::new(hasher));
+}
+
+/// Set the hasher used for new passwords from a `PasswordAlgorithm`.
+pub fn set_password_algorithm(algorithm: PasswordAlgorithm) {
+    *PASSWORD_HASHER.write().unwrap() = Some(algorithm.hasher());
+}
+
+/// Get the hasher used for new passwords, defaulting to bcrypt.
+pub fn default_hasher() -> Arc<dyn PasswordHasher> {
+    PASSWORD_HASHER
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| PasswordAlgorithm::Bcrypt.hasher())
+}
+
+/**
+ * Find the hasher that created a stored hash.
+ *
+ * The configured hasher is checked first, followed by the built-in ones.
+ *
+ * # Returns
+ * The matching hasher, or `None` if the hash format is not recognized.
+ */
+pub fn hasher_for(hash: &str) -> Option<Arc<dyn PasswordHasher>> {
+    let configured = default_hasher();
+    if configured.recognizes(hash) {
+        return Some(configured);
+    }
+    [PasswordAlgorithm::Bcrypt, PasswordAlgorithm::Argon2]
+        .into_iter()
+        .map(PasswordAlgorithm::hasher)
+        .find(|hasher| hasher.recognizes(hash))
+}
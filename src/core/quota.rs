@@ -0,0 +1,310 @@
+/*!
+ * The `quota` module enforces per-user request quotas stored in the user database.
+ *
+ * Unlike the burst rate limiter, which throttles clients by IP address in memory,
+ * quotas count every authenticated request against a daily or monthly allowance
+ * tied to the user's privilege level, e.g. for API product tiers. Usage is kept
+ * in the `request_quotas` table, so it survives restarts.
+ *
+ * This module features:
+ * - **Per-Tier Limits**: Each privilege level can have its own allowance, or none for unlimited use.
+ * - **Persistent Usage**: Requests are counted atomically in the database, per user and period.
+ * - **Quota Headers**: Responses carry `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset`.
+ *
+ * Quotas are enabled with `Api::quota`.
+ */
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::http::StatusCode;
+use actix_web::{web, Error};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use futures_util::future::LocalBoxFuture;
+use sqlx::SqlitePool;
+
+use crate::core::auth::require_privilege;
+use crate::core::db::db_error_response;
+use crate::core::response::json_error;
+use crate::core::user::Privilege;
+
+/// The period after which quota usage resets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaPeriod {
+    /// Usage resets at midnight UTC.
+    Daily,
+    /// Usage resets at midnight UTC on the first day of each month.
+    Monthly,
+}
+
+impl QuotaPeriod {
+    /// The key identifying the period containing `now`, e.g. `2024-05-01` or `2024-05`.
+    fn key(&self, now: DateTime<Utc>) -> String {
+        match self {
+            QuotaPeriod::Daily => now.format("%Y-%m-%d").to_string(),
+            QuotaPeriod::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// The number of seconds from `now` until the period resets.
+    fn seconds_until_reset(&self, now: DateTime<Utc>) -> i64 {
+        let today = now.date_naive();
+        let next = match self {
+            QuotaPeriod::Daily => today + Duration::days(1),
+            QuotaPeriod::Monthly => match today.month() {
+                12 => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+                month => NaiveDate::from_ymd_opt(today.year(), month + 1, 1),
+            }
+            .unwrap_or(today),
+        };
+        next.and_hms_opt(0, 0, 0).map_or(0, |reset| (reset.and_utc() - now).num_seconds().max(0))
+    }
+}
+
+/**
+ * Per-user request quotas, keyed by privilege level.
+ *
+ * Every request authenticated with a token, session cookie or API key counts
+ * against the user's allowance for the current period. Users whose privilege
+ * level has no limit are not counted. Once the allowance is used up, requests
+ * receive a `429 Too Many Requests` with a `Retry-After` header until the period
+ * resets. Unauthenticated requests pass through untouched, leaving routes to
+ * reject them as usual.
+ *
+ * Cloning a quota shares its limits.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Api, HttpResponse, Method, PrivilegeLevel, Routes};
+ * use rusty_api::core::auth::{generate_jwt, register_user};
+ * use rusty_api::core::quota::{Quota, QuotaPeriod};
+ * use rusty_api::core::user::RegisterInput;
+ * use actix_web::test;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     rusty_api::set_jwt_secret("test-secret");
+ *     let api = Api::new()
+ *         .in_memory_db()
+ *         .quota(Quota::new(QuotaPeriod::Monthly).limit(PrivilegeLevel::User, 2))
+ *         .configure_routes(Routes::new().add_route(Method::GET, "/data", || async { HttpResponse::Ok().finish() }));
+ *     let app = test::init_service(api.test_app().await).await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *     let token = generate_jwt(&user).unwrap();
+ *
+ *     let get = || test::TestRequest::get()
+ *         .uri("/data")
+ *         .insert_header(("Authorization", format!("Bearer {}", token)))
+ *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+ *         .to_request();
+ *
+ *     let response = test::call_service(&app, get()).await;
+ *     assert_eq!(response.status(), 200);
+ *     assert_eq!(response.headers().get("X-RateLimit-Remaining").unwrap(), "1");
+ *     assert_eq!(test::call_service(&app, get()).await.status(), 200);
+ *
+ *     let response = test::call_service(&app, get()).await;
+ *     assert_eq!(response.status(), 429);
+ *     assert_eq!(response.headers().get("X-RateLimit-Remaining").unwrap(), "0");
+ * });
+ * ```
+ */
+#[derive(Debug, Clone)]
+pub struct Quota {
+    period: QuotaPeriod,
+    limits: Arc<HashMap<i32, u64>>,
+}
+
+impl Default for Quota {
+    fn default() -> Self {
+        Self::new(QuotaPeriod::Monthly)
+    }
+}
+
+impl Quota {
+    /**
+     * Create a quota without any limits.
+     *
+     * # Arguments
+     * - `period`: The period after which usage resets.
+     *
+     * # Returns
+     * A new `Quota` instance.
+     */
+    pub fn new(period: QuotaPeriod) -> Self {
+        Self { period, limits: Arc::new(HashMap::new()) }
+    }
+
+    /**
+     * Set the number of requests users at the given privilege level may make per period.
+     *
+     * # Arguments
+     * - `level`: The privilege level the limit applies to, matched exactly.
+     * - `requests`: The number of requests allowed per period.
+     *
+     * # Returns
+     * The `Quota` instance with the limit added.
+     */
+    pub fn limit(mut self, level: impl Privilege, requests: u64) -> Self {
+        Arc::make_mut(&mut self.limits).insert(level.level(), requests);
+        self
+    }
+
+    /**
+     * Get the period after which usage resets.
+     *
+     * # Returns
+     * The `QuotaPeriod`.
+     */
+    pub fn get_period(&self) -> QuotaPeriod { self.period }
+
+    /**
+     * Get the limit for a privilege level.
+     *
+     * # Returns
+     * The number of requests allowed per period, or `None` if the level is unlimited.
+     */
+    pub fn get_limit(&self, level: impl Privilege) -> Option<u64> { self.limits.get(&level.level()).copied() }
+}
+
+/**
+ * Count a request against a user's quota for the current period.
+ *
+ * The count is only incremented while it is below the limit, in a single
+ * statement, so concurrent requests cannot exceed the quota.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user making the request.
+ * - `period`: The quota period.
+ * - `limit`: The number of requests allowed per period.
+ *
+ * # Returns
+ * The user's usage including this request, or `None` if the quota was already used up.
+ */
+pub async fn consume_quota(
+    pool: &SqlitePool,
+    user_id: i32,
+    period: QuotaPeriod,
+    limit: u64,
+) -> Result<Option<u64>, sqlx::Error> {
+    if limit == 0 {
+        return Ok(None);
+    }
+    let count: Option<(i64,)> = sqlx::query_as(
+        "INSERT INTO request_quotas (user_id, period, count) VALUES (?, ?, 1)
+         ON CONFLICT (user_id, period) DO UPDATE SET count = count + 1 WHERE count < ?
+         RETURNING count"
+    )
+    .bind(user_id)
+    .bind(period.key(Utc::now()))
+    .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+    .fetch_optional(pool)
+    .await?;
+    Ok(count.map(|(count,)| count as u64))
+}
+
+/**
+ * Get how many requests a user has made in the current period.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user.
+ * - `period`: The quota period.
+ *
+ * # Returns
+ * The number of requests counted so far in the current period.
+ */
+pub async fn quota_usage(pool: &SqlitePool, user_id: i32, period: QuotaPeriod) -> Result<u64, sqlx::Error> {
+    let count: Option<(i64,)> = sqlx::query_as("SELECT count FROM request_quotas WHERE user_id = ? AND period = ?")
+        .bind(user_id)
+        .bind(period.key(Utc::now()))
+        .fetch_optional(pool)
+        .await?;
+    Ok(count.map_or(0, |(count,)| count as u64))
+}
+
+/// Add the quota headers to a response.
+fn insert_quota_headers(headers: &mut HeaderMap, limit: u64, remaining: u64, reset: i64) {
+    for (name, value) in [
+        ("x-ratelimit-limit", limit.to_string()),
+        ("x-ratelimit-remaining", remaining.to_string()),
+        ("x-ratelimit-reset", reset.to_string()),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(HeaderName::from_static(name), value);
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Quota
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = QuotaMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(QuotaMiddleware { service: Rc::new(service), quota: self.clone() }))
+    }
+}
+
+/// The service produced by the `Quota` middleware.
+pub struct QuotaMiddleware<S> {
+    service: Rc<S>,
+    quota: Quota,
+}
+
+impl<S, B> Service<ServiceRequest> for QuotaMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let quota = self.quota.clone();
+
+        Box::pin(async move {
+            let Some(pool) = req.app_data::<web::Data<SqlitePool>>().cloned() else {
+                return service.call(req).await.map(ServiceResponse::map_into_left_body);
+            };
+            // Requests that fail authentication are left for the route to reject.
+            let Ok(user) = require_privilege(req.request(), &pool, i32::MIN).await else {
+                return service.call(req).await.map(ServiceResponse::map_into_left_body);
+            };
+            let Some(limit) = quota.limits.get(&user.privilege_level).copied() else {
+                return service.call(req).await.map(ServiceResponse::map_into_left_body);
+            };
+
+            let reset = quota.period.seconds_until_reset(Utc::now());
+            match consume_quota(&pool, user.id, quota.period, limit).await {
+                Ok(Some(used)) => {
+                    let mut response = service.call(req).await?;
+                    insert_quota_headers(response.headers_mut(), limit, limit.saturating_sub(used), reset);
+                    Ok(response.map_into_left_body())
+                }
+                Ok(None) => {
+                    let mut response = json_error(StatusCode::TOO_MANY_REQUESTS, "Quota exceeded");
+                    insert_quota_headers(response.headers_mut(), limit, 0, reset);
+                    response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(reset));
+                    Ok(req.into_response(response).map_into_right_body())
+                }
+                Err(e) => Ok(req.into_response(db_error_response(&e)).map_into_right_body()),
+            }
+        })
+    }
+}
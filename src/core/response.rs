@@ -0,0 +1,219 @@
+/*!
+ * The `response` module provides helpers for building JSON responses.
+ *
+ * These helpers produce the response shape used throughout rusty-api: successful
+ * responses carry the serialized value as their body, and errors carry a JSON
+ * object with a single `error` field, e.g. `{"error": "User not found"}`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{json_error, json_ok, HttpRequest, HttpResponse, StatusCode};
+ *
+ * async fn get_item(req: HttpRequest) -> HttpResponse {
+ *     match req.match_info().get("id") {
+ *         Some("1") => json_ok(serde_json::json!({ "id": 1, "name": "Widget" })),
+ *         _ => json_error(StatusCode::NOT_FOUND, "Item not found"),
+ *     }
+ * }
+ * ```
+ */
+use actix_web::body::BoxBody;
+use actix_web::web::Bytes;
+use futures_util::{Stream, StreamExt};
+use actix_web::http::header::{self, Header};
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+/// Media type of MessagePack responses.
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/**
+ * Build a `200 OK` response with a JSON body.
+ *
+ * # Arguments
+ * - `value`: The value to serialize as the response body.
+ */
+pub fn json_ok(value: impl Serialize) -> HttpResponse {
+    HttpResponse::Ok().json(value)
+}
+
+/**
+ * Build a `201 Created` response with a JSON body.
+ *
+ * # Arguments
+ * - `value`: The value to serialize as the response body.
+ */
+pub fn json_created(value: impl Serialize) -> HttpResponse {
+    HttpResponse::Created().json(value)
+}
+
+/**
+ * Build an error response with the standard `{"error": message}` JSON body.
+ *
+ * # Arguments
+ * - `status`: The HTTP status code of the response.
+ * - `message`: The error message.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{json_error, StatusCode};
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let response = json_error(StatusCode::CONFLICT, "Username already taken");
+ *     assert_eq!(response.status(), StatusCode::CONFLICT);
+ *
+ *     let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+ *     assert_eq!(body, r#"{"error":"Username already taken"}"#);
+ * });
+ * ```
+ */
+pub fn json_error(status: StatusCode, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(serde_json::json!({ "error": message.into() }))
+}
+
+/**
+ * A responder serializing its value as JSON or MessagePack.
+ *
+ * MessagePack is used when the client prefers `application/msgpack` (or
+ * `application/x-msgpack`) in its `Accept` header. JSON is used otherwise,
+ * including for wildcard media ranges and requests without an `Accept` header.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Negotiated;
+ * use actix_web::{test, web, App};
+ *
+ * #[derive(serde::Serialize)]
+ * struct Item { id: i32 }
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let app = test::init_service(App::new().route("/item", web::get().to(|| async { Negotiated(Item { id: 1 }) }))).await;
+ *
+ *     let req = test::TestRequest::get().uri("/item").to_request();
+ *     assert_eq!(test::call_and_read_body(&app, req).await, r#"{"id":1}"#);
+ *
+ *     let req = test::TestRequest::get().uri("/item").insert_header(("Accept", "application/msgpack")).to_request();
+ *     let response = test::call_service(&app, req).await;
+ *     assert_eq!(response.headers().get("Content-Type").unwrap(), "application/msgpack");
+ *     let body = test::read_body(response).await;
+ *     assert_eq!(rmp_serde::from_slice::<serde_json::Value>(&body).unwrap(), serde_json::json!({ "id": 1 }));
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negotiated<T: Serialize>(pub T);
+
+impl<T: Serialize> Responder for Negotiated<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        if !prefers_msgpack(req) {
+            return json_ok(self.0);
+        }
+        match rmp_serde::to_vec_named(&self.0) {
+            Ok(body) => HttpResponse::Ok().content_type(MSGPACK_CONTENT_TYPE).body(body),
+            Err(e) => json_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize response: {}", e)),
+        }
+    }
+}
+
+/// Check whether the client prefers MessagePack over JSON.
+fn prefers_msgpack(req: &HttpRequest) -> bool {
+    let Ok(accept) = header::Accept::parse(req) else {
+        return false;
+    };
+    accept
+        .ranked()
+        .iter()
+        .find_map(|mime| match mime.essence_str() {
+            "application/msgpack" | "application/x-msgpack" => Some(true),
+            "application/json" | "application/*" | "*/*" => Some(false),
+            _ => None,
+        })
+        .unwrap_or(false)
+}
+
+/**
+ * Build a `200 OK` response streaming its body chunk by chunk.
+ *
+ * The body is sent with `Transfer-Encoding: chunked`, so it is never buffered
+ * in memory as a whole. If the stream yields an error, the connection is closed.
+ *
+ * # Arguments
+ * - `content_type`: The `Content-Type` of the body.
+ * - `stream`: The body chunks.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{stream_response, web::Bytes, HttpResponse};
+ * use futures_util::stream;
+ *
+ * async fn export() -> HttpResponse {
+ *     let rows = (1..=3).map(|id| Ok::<_, std::io::Error>(Bytes::from(format!("{},user{}\n", id, id))));
+ *     stream_response("text/csv", stream::iter(rows))
+ * }
+ * ```
+ */
+pub fn stream_response<S, E>(content_type: &str, stream: S) -> HttpResponse
+where
+    S: Stream<Item = Result<Bytes, E>> + 'static,
+    E: Into<Box<dyn std::error::Error>> + 'static,
+{
+    HttpResponse::Ok().content_type(content_type).streaming(stream)
+}
+
+/**
+ * Build a `200 OK` response streaming items as newline-delimited JSON.
+ *
+ * Each item is serialized to a single JSON line as it is produced, using the
+ * `application/x-ndjson` content type.
+ *
+ * # Arguments
+ * - `stream`: The items to send.
+ *
+ * # Example
+ * Streaming all users from the user database, one page at a time:
+ * ```rust
+ * use rusty_api::{ndjson_response, web, Api, HttpResponse, Method, Routes};
+ * use rusty_api::core::db::stream_users;
+ * use actix_web::test;
+ *
+ * async fn export_users(pool: web::Data<sqlx::SqlitePool>) -> HttpResponse {
+ *     ndjson_response(stream_users(pool.get_ref().clone(), 100))
+ * }
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let routes = Routes::new().add_route(Method::GET, "/users/export", export_users);
+ *     let api = Api::new().in_memory_db().configure_routes(routes);
+ *     let app = test::init_service(api.test_app().await).await;
+ *     for name in ["alice", "bob"] {
+ *         sqlx::query("INSERT INTO users (username, password_hash) VALUES (?, 'x')")
+ *             .bind(name).execute(&api.db_pool().unwrap()).await.unwrap();
+ *     }
+ *
+ *     let req = test::TestRequest::get().uri("/users/export").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+ *     let body = test::call_and_read_body(&app, req).await;
+ *     let users: Vec<serde_json::Value> = std::str::from_utf8(&body).unwrap()
+ *         .lines()
+ *         .map(|line| serde_json::from_str(line).unwrap())
+ *         .collect();
+ *     assert_eq!(users.len(), 2);
+ *     assert_eq!((users[0]["id"].as_i64(), users[0]["username"].as_str()), (Some(1), Some("alice")));
+ *     assert_eq!((users[1]["id"].as_i64(), users[1]["username"].as_str()), (Some(2), Some("bob")));
+ *     assert!(users[0].get("password_hash").is_none());
+ * });
+ * ```
+ */
+pub fn ndjson_response<S, T, E>(stream: S) -> HttpResponse
+where
+    S: Stream<Item = Result<T, E>> + 'static,
+    T: Serialize,
+    E: Into<Box<dyn std::error::Error>> + 'static,
+{
+    let lines = stream.map(|item| -> Result<Bytes, Box<dyn std::error::Error>> {
+        let mut line = serde_json::to_vec(&item.map_err(Into::into)?)?;
+        line.push(b'\n');
+        Ok(Bytes::from(line))
+    });
+    stream_response("application/x-ndjson", lines)
+}
@@ -0,0 +1,248 @@
+/*!
+ * The `session` module stores login sessions in the user database, as an alternative to stateless JWTs.
+ *
+ * A JWT stays valid until it expires, so a single token cannot be revoked
+ * without a denylist. In session mode, logging in creates a random session ID
+ * stored in the `sessions` table and returns it as the token instead. Every
+ * authenticated request looks the session up, so deleting it, e.g. on logout,
+ * revokes it immediately, at the cost of a database query per request.
+ *
+ * This module features:
+ * - **Opaque Tokens**: Session IDs are random and only their SHA-256 digest is stored.
+ * - **Instant Revocation**: Logging out or calling `revoke_session` ends a session at once.
+ * - **Account Status**: Sessions of disabled users stop working immediately.
+ * - **Sliding Sessions**: `refresh_session` extends a session, up to the maximum session length.
+ *
+ * Session mode is enabled with `Api::token_mode`.
+ */
+use actix_web::error::{ErrorUnauthorized, InternalError};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use crate::core::auth::{hash_api_key, jwt_max_session, Claims, TokenValidator, JWT_TTL_DAYS};
+use crate::core::db::db_error_response;
+use crate::core::metrics::{record_token_failure, TokenFailure};
+
+/**
+ * How the login route issues tokens and how they are validated, stored as app data by the `Api`.
+ *
+ * See `Api::token_mode`.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenMode {
+    /// Stateless JWTs signed with the JWT secret.
+    #[default]
+    Jwt,
+    /// Random session IDs looked up in the `sessions` table.
+    Session,
+}
+
+/// Get the current time as a Unix timestamp.
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Get the expiry of a session extended now, capped by the maximum session length after login.
+fn session_expiry(created_at: i64, max_session: Duration) -> i64 {
+    let ttl = chrono::Duration::days(JWT_TTL_DAYS).num_seconds();
+    let max_session = i64::try_from(max_session.as_secs()).unwrap_or(i64::MAX);
+    now().saturating_add(ttl).min(created_at.saturating_add(max_session))
+}
+
+/**
+ * Create a session for a user.
+ *
+ * The session expires after the same time as a JWT issued by `generate_jwt`.
+ * Expired sessions of every user are deleted at the same time.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user logging in.
+ *
+ * # Returns
+ * The session ID to send to the client as its token.
+ */
+pub async fn create_session(pool: &SqlitePool, user_id: i32) -> Result<String, sqlx::Error> {
+    create_session_capped(pool, user_id, jwt_max_session()).await
+}
+
+/// Create a session like `create_session`, capping it at `max_session` after login.
+pub(crate) async fn create_session_capped(pool: &SqlitePool, user_id: i32, max_session: Duration) -> Result<String, sqlx::Error> {
+    let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    let created_at = now();
+
+    sqlx::query("DELETE FROM sessions WHERE expires_at <= ?")
+        .bind(created_at)
+        .execute(pool)
+        .await?;
+    sqlx::query("INSERT INTO sessions (id_hash, user_id, created_at, expires_at) VALUES (?, ?, ?, ?)")
+        .bind(hash_api_key(&token))
+        .bind(user_id)
+        .bind(created_at)
+        .bind(session_expiry(created_at, max_session))
+        .execute(pool)
+        .await?;
+    Ok(token)
+}
+
+/**
+ * Look up a session.
+ *
+ * The claims are built from the session and the user's current privilege level,
+ * so privilege changes take effect immediately.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `token`: The session ID sent by the client.
+ *
+ * # Returns
+ * The session's claims, or `None` if the session is unknown, expired or revoked,
+ * or its user is disabled.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::register_user;
+ * use rusty_api::core::session::{create_session, revoke_session, validate_session};
+ * use rusty_api::core::user::RegisterInput;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *
+ *     let token = create_session(&pool, user.id).await.unwrap();
+ *     assert_eq!(validate_session(&pool, &token).await.unwrap().unwrap().sub, user.id);
+ *
+ *     assert!(revoke_session(&pool, &token).await.unwrap());
+ *     assert!(validate_session(&pool, &token).await.unwrap().is_none());
+ * });
+ * ```
+ */
+pub async fn validate_session(pool: &SqlitePool, token: &str) -> Result<Option<Claims>, sqlx::Error> {
+    let session: Option<(i32, i64, i64, i32)> = sqlx::query_as(
+        "SELECT s.user_id, s.created_at, s.expires_at, u.privilege_level FROM sessions s
+         JOIN users u ON u.id = s.user_id
+         WHERE s.id_hash = ? AND s.expires_at > ? AND u.enabled = 1"
+    )
+    .bind(hash_api_key(token))
+    .bind(now())
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(session.map(|(user_id, created_at, expires_at, privilege_level)| Claims {
+        sub: user_id,
+        exp: expires_at as usize,
+        privilege_level,
+        auth_time: Some(created_at as usize),
+        ..Default::default()
+    }))
+}
+
+/**
+ * Extend a session, for sliding sessions.
+ *
+ * Like `refresh_access_token`, the session is extended to expire 7 days from
+ * now, but never later than the maximum session set with `set_jwt_max_session`
+ * after login. The refresh route uses the maximum session of its `Api` instead.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `token`: The session ID sent by the client.
+ *
+ * # Returns
+ * `true` if the session was extended, `false` if it is unknown, expired or past the maximum session length.
+ */
+pub async fn refresh_session(pool: &SqlitePool, token: &str) -> Result<bool, sqlx::Error> {
+    refresh_session_capped(pool, token, jwt_max_session()).await
+}
+
+/// Extend a session like `refresh_session`, capping it at `max_session` after login.
+pub(crate) async fn refresh_session_capped(pool: &SqlitePool, token: &str, max_session: Duration) -> Result<bool, sqlx::Error> {
+    let id_hash = hash_api_key(token);
+    let created_at: Option<(i64,)> = sqlx::query_as("SELECT created_at FROM sessions WHERE id_hash = ? AND expires_at > ?")
+        .bind(&id_hash)
+        .bind(now())
+        .fetch_optional(pool)
+        .await?;
+    let Some((created_at,)) = created_at else {
+        return Ok(false);
+    };
+
+    let expires_at = session_expiry(created_at, max_session);
+    if expires_at <= now() {
+        return Ok(false);
+    }
+    sqlx::query("UPDATE sessions SET expires_at = ? WHERE id_hash = ?")
+        .bind(expires_at)
+        .bind(&id_hash)
+        .execute(pool)
+        .await?;
+    Ok(true)
+}
+
+/**
+ * Revoke a session, e.g. on logout.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `token`: The session ID sent by the client.
+ *
+ * # Returns
+ * `true` if the session existed.
+ */
+pub async fn revoke_session(pool: &SqlitePool, token: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM sessions WHERE id_hash = ?")
+        .bind(hash_api_key(token))
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/**
+ * Revoke every session of a user, e.g. after a password change.
+ *
+ * # Arguments
+ * - `pool`: A reference to the SQLx SQLite connection pool.
+ * - `user_id`: The ID of the user.
+ *
+ * # Returns
+ * The number of sessions revoked.
+ */
+pub async fn revoke_user_sessions(pool: &SqlitePool, user_id: i32) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM sessions WHERE user_id = ?")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/**
+ * Create a `TokenValidator` accepting session IDs from the `sessions` table.
+ *
+ * The `Api` registers this validator in session mode, so `add_route_with_auth`
+ * routes and the `RequirePrivilege` middleware accept session IDs.
+ *
+ * # Arguments
+ * - `pool`: The SQLx SQLite connection pool holding the sessions.
+ */
+pub fn session_validator(pool: SqlitePool) -> TokenValidator {
+    TokenValidator::new_async(move |token| {
+        let pool = pool.clone();
+        async move {
+            match validate_session(&pool, &token).await {
+                Ok(Some(claims)) => Ok(claims),
+                Ok(None) => {
+                    record_token_failure(TokenFailure::InvalidSession);
+                    Err(ErrorUnauthorized("Invalid session"))
+                }
+                Err(e) => {
+                    // Keep the database failure apart from an invalid session, so clients do not log users out
+                    let response = db_error_response(&e);
+                    Err(InternalError::from_response(e, response).into())
+                }
+            }
+        }
+    })
+}
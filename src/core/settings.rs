@@ -0,0 +1,373 @@
+/*!
+ * The `settings` module loads API server configuration from a TOML file or environment variables.
+ *
+ * This module allows deployments to change the bind address, TLS paths, rate limits,
+ * CORS rules and database URL without recompiling. Settings are applied on top of
+ * the `Api` defaults, and builder methods called afterwards still override them.
+ *
+ * This module features:
+ * - **TOML Files**: `Settings::from_toml` and `Settings::from_file` parse a `config.toml`.
+ * - **Environment Variables**: `Settings::from_env` reads `RUSTY_API_*` variables and `DATABASE_URL`.
+ * - **CORS Settings**: `CorsSettings` describes CORS rules as data and builds the `Cors` middleware.
+ * - **Profiles**: `Profile` selects a bundle of development or production defaults.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::settings::Settings;
+ *
+ * let settings = Settings::from_toml(r#"
+ *     addr = "0.0.0.0"
+ *     port = 9443
+ *     cert_path = "/etc/api/cert.pem"
+ *     key_path = "/etc/api/key.pem"
+ *     ## Requires the `sqlite` feature, which `auth` (on by default) enables
+ *     database_url = "sqlite:./users.db"
+ *
+ *     [rate_limit]
+ *     per_second = 10
+ *     burst_size = 50
+ *
+ *     [cors]
+ *     allowed_origins = ["https://example.com"]
+ *     allowed_methods = ["GET", "POST"]
+ * "#).unwrap();
+ *
+ * assert_eq!(settings.port, Some(9443));
+ * assert_eq!(settings.database_url.as_deref(), Some("sqlite:./users.db"));
+ * assert_eq!(settings.rate_limit.unwrap().per_second, 10);
+ * ```
+ */
+use actix_cors::Cors;
+use serde::Deserialize;
+use std::env;
+use std::path::Path;
+
+#[cfg(feature = "auth")]
+use crate::core::password::PasswordAlgorithm;
+use crate::error::ApiError;
+
+/**
+ * Configuration values that can be loaded from a file or the environment.
+ *
+ * Every field is optional; missing values keep the `Api` defaults.
+ */
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// Path to the TLS certificate file.
+    pub cert_path: Option<String>,
+    /// Path to the TLS private key file.
+    pub key_path: Option<String>,
+    /// Address to bind the server to.
+    pub addr: Option<String>,
+    /// Port to bind the server to.
+    pub port: Option<u16>,
+    /// Rate limiting configuration.
+    pub rate_limit: Option<RateLimitSettings>,
+    /// CORS configuration.
+    pub cors: Option<CorsSettings>,
+    /// Database URL for the user database. Setting it enables the user database.
+    #[cfg(feature = "sqlite")]
+    pub database_url: Option<String>,
+    /// Algorithm used to hash new passwords, `"bcrypt"` or `"argon2"`.
+    #[cfg(feature = "auth")]
+    pub password_algorithm: Option<PasswordAlgorithm>,
+    /// Environment profile, `"dev"` or `"prod"`, applied before the other settings.
+    pub profile: Option<Profile>,
+}
+
+/**
+ * A bundle of defaults for the environment the API runs in, applied with `Api::profile`.
+ *
+ * Deserializes from `"dev"` or `"prod"`.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Local development: permissive CORS and request/response body logging.
+    Dev,
+    /// Production: HTTPS only, security headers, restrictive CORS and no body logging.
+    Prod,
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Profile::Dev => "dev",
+            Profile::Prod => "prod",
+        })
+    }
+}
+
+/// Rate limiting values, matching `Api::rate_limit`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitSettings {
+    /// Number of requests allowed per second.
+    pub per_second: u64,
+    /// Maximum burst size for requests.
+    pub burst_size: u32,
+}
+
+/// Preflight cache age used by `CorsSettings::strict`, in seconds.
+pub const DEFAULT_CORS_MAX_AGE: usize = 3600;
+
+/// Methods allowed by `CorsSettings::strict`.
+pub const STRICT_CORS_METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+/// Request headers allowed by `CorsSettings::strict`.
+pub const STRICT_CORS_HEADERS: [&str; 3] = ["Accept", "Authorization", "Content-Type"];
+
+/**
+ * CORS rules described as data.
+ *
+ * A `"*"` entry allows any origin, method or header respectively. Empty lists keep
+ * the restrictive `Cors::default()` behavior. Without a `max_age`, browsers
+ * send a preflight request before every cross-origin call that needs one.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::settings::CorsSettings;
+ *
+ * let settings = CorsSettings {
+ *     allowed_origins: vec!["https://example.com".into()],
+ *     allowed_methods: vec!["GET".into(), "POST".into()],
+ *     max_age: Some(600),
+ *     ..Default::default()
+ * };
+ * let cors = settings.to_cors();
+ * ```
+ */
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsSettings {
+    /// Allowed origins, e.g. `https://example.com`.
+    pub allowed_origins: Vec<String>,
+    /// Allowed HTTP methods, e.g. `GET`.
+    pub allowed_methods: Vec<String>,
+    /// Allowed request headers, e.g. `Content-Type`.
+    pub allowed_headers: Vec<String>,
+    /// How long browsers may cache preflight responses, in seconds.
+    pub max_age: Option<usize>,
+}
+
+impl CorsSettings {
+    /**
+     * A strict preset for production.
+     *
+     * Only the given origins are allowed, with the methods in
+     * `STRICT_CORS_METHODS` and the headers in `STRICT_CORS_HEADERS`, and
+     * preflight responses are cached for `DEFAULT_CORS_MAX_AGE` seconds. The
+     * lists can be adjusted with `methods` and `headers`.
+     *
+     * # Arguments
+     * - `origins`: The allowed origins, e.g. `https://example.com`.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use rusty_api::core::settings::CorsSettings;
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let cors = CorsSettings::strict(&["https://example.com"]).headers(&["Content-Type", "ngrok-skip-browser-warning"]);
+     *     let routes = Routes::new().add_route(Method::POST, "/items", || async { HttpResponse::Ok().finish() });
+     *     let app = test::init_service(Api::new().configure_routes(routes).cors_settings(cors).test_app().await).await;
+     *
+     *     let preflight = test::TestRequest::default()
+     *         .method(Method::OPTIONS)
+     *         .uri("/items")
+     *         .insert_header(("Origin", "https://example.com"))
+     *         .insert_header(("Access-Control-Request-Method", "POST"))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     let response = test::call_service(&app, preflight).await;
+     *     assert_eq!(response.headers().get("Access-Control-Max-Age").unwrap(), "3600");
+     * });
+     * ```
+     */
+    pub fn strict(origins: &[&str]) -> Self {
+        Self {
+            allowed_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            allowed_methods: STRICT_CORS_METHODS.iter().map(|method| method.to_string()).collect(),
+            allowed_headers: STRICT_CORS_HEADERS.iter().map(|header| header.to_string()).collect(),
+            max_age: Some(DEFAULT_CORS_MAX_AGE),
+        }
+    }
+
+    /// Replace the allowed methods.
+    pub fn methods(mut self, methods: &[&str]) -> Self {
+        self.allowed_methods = methods.iter().map(|method| method.to_string()).collect();
+        self
+    }
+
+    /// Replace the allowed request headers.
+    pub fn headers(mut self, headers: &[&str]) -> Self {
+        self.allowed_headers = headers.iter().map(|header| header.to_string()).collect();
+        self
+    }
+
+    /// Set how long browsers may cache preflight responses, in seconds.
+    pub fn max_age(mut self, seconds: usize) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Build the `Cors` middleware described by these settings.
+    pub fn to_cors(&self) -> Cors {
+        let mut cors = Cors::default();
+
+        if self.allowed_origins.iter().any(|origin| origin == "*") {
+            cors = cors.allow_any_origin();
+        } else {
+            for origin in &self.allowed_origins {
+                cors = cors.allowed_origin(origin);
+            }
+        }
+
+        if self.allowed_methods.iter().any(|method| method == "*") {
+            cors = cors.allow_any_method();
+        } else if !self.allowed_methods.is_empty() {
+            cors = cors.allowed_methods(self.allowed_methods.iter().map(String::as_str));
+        }
+
+        if self.allowed_headers.iter().any(|header| header == "*") {
+            cors = cors.allow_any_header();
+        } else if !self.allowed_headers.is_empty() {
+            cors = cors.allowed_headers(self.allowed_headers.iter().map(String::as_str));
+        }
+
+        if let Some(max_age) = self.max_age {
+            cors = cors.max_age(max_age);
+        }
+
+        cors
+    }
+}
+
+impl Settings {
+    /**
+     * Parse settings from a TOML string.
+     *
+     * # Returns
+     * The parsed settings, or `ApiError::InvalidConfig` if the TOML is invalid.
+     */
+    pub fn from_toml(content: &str) -> Result<Self, ApiError> {
+        toml::from_str(content).map_err(|e| ApiError::InvalidConfig(format!("Invalid config file: {}", e)))
+    }
+
+    /**
+     * Load settings from a TOML file.
+     *
+     * # Returns
+     * The parsed settings, or `ApiError::InvalidConfig` if the file cannot be read or parsed.
+     */
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ApiError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ApiError::InvalidConfig(format!("Failed to read config file {}: {}", path.display(), e))
+        })?;
+        Self::from_toml(&content)
+    }
+
+    /**
+     * Load settings from environment variables.
+     *
+     * The following variables are read, all optional:
+     * - `RUSTY_API_CERT_PATH`, `RUSTY_API_KEY_PATH`
+     * - `RUSTY_API_ADDR`, `RUSTY_API_PORT`
+     * - `RUSTY_API_RATE_LIMIT_PER_SECOND`, `RUSTY_API_RATE_LIMIT_BURST_SIZE` (both required to set a rate limit)
+     * - `RUSTY_API_CORS_ORIGINS`, `RUSTY_API_CORS_METHODS`, `RUSTY_API_CORS_HEADERS` (comma-separated)
+     * - `RUSTY_API_CORS_MAX_AGE` (seconds)
+     * - `RUSTY_API_PASSWORD_ALGORITHM` (`bcrypt` or `argon2`)
+     * - `RUSTY_API_PROFILE` (`dev` or `prod`)
+     * - `DATABASE_URL`
+     *
+     * # Returns
+     * The settings, or `ApiError::InvalidConfig` if a numeric variable cannot be parsed.
+     */
+    pub fn from_env() -> Result<Self, ApiError> {
+        let rate_limit = match (
+            parse_env::<u64>("RUSTY_API_RATE_LIMIT_PER_SECOND")?,
+            parse_env::<u32>("RUSTY_API_RATE_LIMIT_BURST_SIZE")?,
+        ) {
+            (Some(per_second), Some(burst_size)) => Some(RateLimitSettings { per_second, burst_size }),
+            _ => None,
+        };
+
+        let cors = CorsSettings {
+            allowed_origins: list_env("RUSTY_API_CORS_ORIGINS"),
+            allowed_methods: list_env("RUSTY_API_CORS_METHODS"),
+            allowed_headers: list_env("RUSTY_API_CORS_HEADERS"),
+            max_age: parse_env("RUSTY_API_CORS_MAX_AGE")?,
+        };
+        let has_cors = !cors.allowed_origins.is_empty()
+            || !cors.allowed_methods.is_empty()
+            || !cors.allowed_headers.is_empty()
+            || cors.max_age.is_some();
+
+        Ok(Self {
+            cert_path: env::var("RUSTY_API_CERT_PATH").ok(),
+            key_path: env::var("RUSTY_API_KEY_PATH").ok(),
+            addr: env::var("RUSTY_API_ADDR").ok(),
+            port: parse_env("RUSTY_API_PORT")?,
+            rate_limit,
+            cors: has_cors.then_some(cors),
+            #[cfg(feature = "sqlite")]
+            database_url: env::var("DATABASE_URL").ok(),
+            #[cfg(feature = "auth")]
+            password_algorithm: parse_password_algorithm()?,
+            profile: parse_profile()?,
+        })
+    }
+}
+
+/// Parse an optional environment variable.
+fn parse_env<T: std::str::FromStr>(name: &str) -> Result<Option<T>, ApiError> {
+    match env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| ApiError::InvalidConfig(format!("{} has an invalid value: '{}'", name, value))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Parse the optional `RUSTY_API_PASSWORD_ALGORITHM` variable.
+#[cfg(feature = "auth")]
+fn parse_password_algorithm() -> Result<Option<PasswordAlgorithm>, ApiError> {
+    match env::var("RUSTY_API_PASSWORD_ALGORITHM") {
+        Ok(value) => value.parse().map(Some).map_err(|_| ApiError::InvalidConfig(format!(
+            "RUSTY_API_PASSWORD_ALGORITHM has an invalid value: '{}'", value
+        ))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Parse the optional `RUSTY_API_PROFILE` variable.
+fn parse_profile() -> Result<Option<Profile>, ApiError> {
+    match env::var("RUSTY_API_PROFILE") {
+        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
+            "dev" => Ok(Some(Profile::Dev)),
+            "prod" => Ok(Some(Profile::Prod)),
+            _ => Err(ApiError::InvalidConfig(format!(
+                "RUSTY_API_PROFILE has an invalid value: '{}'", value
+            ))),
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+/// Read a comma-separated list from an environment variable.
+fn list_env(name: &str) -> Vec<String> {
+    env::var(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
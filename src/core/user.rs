@@ -4,6 +4,7 @@
  * This module defines the many structs related to user management, including
  * user registration, login, and the user model itself.
  */
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 /**
@@ -12,12 +13,245 @@ use serde::{Deserialize, Serialize};
  * This struct represents a user in the system, containing fields for the user's
  * ID, username, and password hash. The user database can contain more fields, but
  * these are the essential ones for authentication and identification.
+ *
+ * The timestamps are in UTC and set by the database; `updated_at` changes on
+ * every update of the user's row. They are `None` for users created before the
+ * columns existed, or when a query does not select them.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::Api;
+ * use rusty_api::core::auth::{get_user_by_id, register_user};
+ * use rusty_api::core::db::set_user_fields;
+ * use rusty_api::core::user::RegisterInput;
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let api = Api::new().in_memory_db();
+ *     let _app = api.test_app().await;
+ *     let pool = api.db_pool().unwrap();
+ *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+ *     assert!(user.created_at.is_some());
+ *
+ *     sqlx::query("UPDATE users SET updated_at = '2000-01-01 00:00:00' WHERE id = ?").bind(user.id).execute(&pool).await.unwrap();
+ *     set_user_fields(&pool, user.id, &[("username", "alicia")]).await.unwrap();
+ *     let user = get_user_by_id(&pool, user.id).await.unwrap().unwrap();
+ *     assert!(user.updated_at > user.created_at.map(|created| created - chrono::Duration::days(1)));
+ *     assert_eq!(serde_json::to_value(&user).unwrap()["created_at"], serde_json::json!(user.created_at));
+ * });
+ * ```
  */
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow))]
 pub struct User {
     pub id: i32,
     pub username: String,
+    #[serde(skip_serializing)]
     pub password_hash: String,
+    pub privilege_level: i32,
+    /// When the user was created.
+    #[serde(default)]
+    #[cfg_attr(feature = "sqlite", sqlx(default))]
+    pub created_at: Option<NaiveDateTime>,
+    /// When the user was last modified.
+    #[serde(default)]
+    #[cfg_attr(feature = "sqlite", sqlx(default))]
+    pub updated_at: Option<NaiveDateTime>,
+    /// Whether the user may log in. Disabled accounts are kept but cannot authenticate.
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+impl Default for User {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            username: String::new(),
+            password_hash: String::new(),
+            privilege_level: 0,
+            created_at: None,
+            updated_at: None,
+            enabled: true,
+        }
+    }
+}
+
+/// Users are enabled unless an admin disables them.
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl User {
+    /**
+     * Get the user's privilege level as the given `Privilege` type.
+     *
+     * # Returns
+     * The mapped privilege, or `None` if the stored level is unknown to `P`.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::PrivilegeLevel;
+     * use rusty_api::core::user::User;
+     *
+     * let user = User { id: 1, username: "admin".into(), privilege_level: 1, ..Default::default() };
+     * assert_eq!(user.privilege::<PrivilegeLevel>(), Some(PrivilegeLevel::Admin));
+     * assert_eq!(user.privilege::<i32>(), Some(1));
+     * ```
+     */
+    pub fn privilege<P: Privilege>(&self) -> Option<P> {
+        P::from_level(self.privilege_level)
+    }
+}
+
+/**
+ * Privilege trait
+ *
+ * This trait maps an application's roles onto the integer stored in the
+ * `privilege_level` column, with higher values granting more access. It is
+ * implemented for the built-in `PrivilegeLevel` and for plain `i32` levels, and
+ * can be implemented for your own enum to add roles such as a moderator or
+ * super-admin without changing this crate.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Privilege, PrivilegeLevel};
+ *
+ * #[derive(Clone, Copy)]
+ * enum Role {
+ *     Member,
+ *     Moderator,
+ *     SuperAdmin,
+ * }
+ *
+ * impl Privilege for Role {
+ *     fn level(&self) -> i32 {
+ *         match self {
+ *             Role::Member => 0,
+ *             Role::Moderator => 5,
+ *             Role::SuperAdmin => 10,
+ *         }
+ *     }
+ *
+ *     fn from_level(level: i32) -> Option<Self> {
+ *         match level {
+ *             0 => Some(Role::Member),
+ *             5 => Some(Role::Moderator),
+ *             10 => Some(Role::SuperAdmin),
+ *             _ => None,
+ *         }
+ *     }
+ * }
+ *
+ * assert!(Role::Moderator.level() > PrivilegeLevel::User.level());
+ * assert!(Role::SuperAdmin.level() > PrivilegeLevel::Admin.level());
+ * ```
+ */
+pub trait Privilege: Sized {
+    /// The integer stored in the `privilege_level` column for this privilege.
+    fn level(&self) -> i32;
+
+    /// Map a stored level back to a privilege, or `None` if the level is unknown.
+    fn from_level(level: i32) -> Option<Self>;
+}
+
+impl Privilege for i32 {
+    fn level(&self) -> i32 {
+        *self
+    }
+
+    fn from_level(level: i32) -> Option<Self> {
+        Some(level)
+    }
+}
+
+/**
+ * PrivilegeLevel enum
+ *
+ * This enum represents the privilege levels a user can hold. Levels are stored
+ * as integers in the `privilege_level` column of the `users` table, with higher
+ * values granting more access.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::PrivilegeLevel;
+ *
+ * assert_eq!(i32::from(PrivilegeLevel::Admin), 1);
+ * assert_eq!(PrivilegeLevel::try_from(0), Ok(PrivilegeLevel::User));
+ * assert!(PrivilegeLevel::Admin > PrivilegeLevel::User);
+ * ```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivilegeLevel {
+    User = 0,
+    Admin = 1,
+}
+
+impl From<PrivilegeLevel> for i32 {
+    fn from(level: PrivilegeLevel) -> Self {
+        level as i32
+    }
+}
+
+impl TryFrom<i32> for PrivilegeLevel {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PrivilegeLevel::User),
+            1 => Ok(PrivilegeLevel::Admin),
+            _ => Err(format!("Unknown privilege level: {}", value)),
+        }
+    }
+}
+
+impl Privilege for PrivilegeLevel {
+    fn level(&self) -> i32 {
+        i32::from(*self)
+    }
+
+    fn from_level(level: i32) -> Option<Self> {
+        PrivilegeLevel::try_from(level).ok()
+    }
+}
+
+/// Maximum length of a username, in characters.
+pub const MAX_USERNAME_LENGTH: usize = 64;
+
+/**
+ * Check that a username is acceptable.
+ *
+ * Usernames must not be empty, start or end with whitespace, contain control
+ * characters, or be longer than `MAX_USERNAME_LENGTH` characters. The same rules
+ * apply when registering and when users rename themselves.
+ *
+ * # Returns
+ * `Ok(())`, or a message describing why the username was refused.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::user::check_username;
+ *
+ * assert!(check_username("alice").is_ok());
+ * assert!(check_username("").is_err());
+ * assert!(check_username(" alice").is_err());
+ * assert!(check_username("ali\nce").is_err());
+ * assert!(check_username(&"a".repeat(65)).is_err());
+ * ```
+ */
+pub fn check_username(username: &str) -> Result<(), String> {
+    if username.is_empty() {
+        return Err("Username must not be empty".to_string());
+    }
+    if username.trim() != username {
+        return Err("Username must not start or end with whitespace".to_string());
+    }
+    if username.chars().any(char::is_control) {
+        return Err("Username must not contain control characters".to_string());
+    }
+    if username.chars().count() > MAX_USERNAME_LENGTH {
+        return Err(format!("Username must be at most {} characters", MAX_USERNAME_LENGTH));
+    }
+    Ok(())
 }
 
 /**
@@ -25,6 +259,8 @@ pub struct User {
  *
  * This struct is used to deserialize the input data for user registration.
  * It contains fields for the username and password.
+ *
+ * Unknown fields are ignored, unless `Api::deny_unknown_json_fields` is enabled.
  */
 #[derive(Debug, Deserialize)]
 pub struct RegisterInput {
@@ -46,12 +282,67 @@ pub struct LoginInput {
 
 
 /**
- * Response struct for user registration
+ * Input struct for password verification
+ *
+ * This struct is used to deserialize the input data for the route confirming
+ * the authenticated user's current password.
+ */
+#[derive(Debug, Deserialize)]
+pub struct VerifyPasswordInput {
+    pub password: String,
+}
+
+
+/**
+ * Response struct for user login
  *
- * This struct is used to serialize the response data for user registration.
+ * This struct is used to serialize the response data for user login.
  * It contains a field for the users token, which is used for authentication.
+ *
+ * `login_user` always fills in `user`, but the login route only sends it when
+ * `Api::login_includes_user` is enabled, so the default body stays `{"token": "..."}`.
+ * With the flag enabled, the body has the shape:
+ *
+ * ```json
+ * {"token": "...", "user": {"id": 1, "username": "alice", "privilege_level": 0, "created_at": "2024-05-01T12:00:00", "updated_at": "2024-05-01T12:00:00"}}
+ * ```
  */
 #[derive(Serialize)]
 pub struct LoginResponse {
     pub token: String,
-}
\ No newline at end of file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+}
+
+/**
+ * Response struct for API key rotation
+ *
+ * This struct is used to serialize a newly generated API key. The key is only
+ * returned once, when it is generated.
+ */
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    pub api_key: String,
+}
+
+/**
+ * Input struct for changing a user's privilege level
+ *
+ * This struct is used to deserialize the input data for the admin privilege route.
+ * Only levels known to `PrivilegeLevel` are accepted.
+ */
+#[derive(Debug, Deserialize)]
+pub struct PrivilegeInput {
+    pub privilege_level: PrivilegeLevel,
+}
+
+/**
+ * Input struct for enabling or disabling a user account
+ *
+ * This struct is used to deserialize the input data for the admin account status route.
+ *
+ */
+#[derive(Debug, Deserialize)]
+pub struct AccountStatusInput {
+    pub enabled: bool,
+}
@@ -0,0 +1,58 @@
+/*!
+ * The `version` module describes the running build.
+ *
+ * `VersionInfo` is served by the optional endpoint enabled with
+ * `Api::with_version_endpoint`, so operators can confirm which build is deployed.
+ */
+use actix_web::{web, HttpResponse};
+use chrono::DateTime;
+use serde::Serialize;
+
+/**
+ * Information about the running build.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::core::version::VersionInfo;
+ *
+ * let info = VersionInfo::current();
+ * assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionInfo {
+    /// The crate version, from `CARGO_PKG_VERSION`.
+    pub version: &'static str,
+    /// The git commit, from the `GIT_SHA` environment variable at build time.
+    pub git_sha: Option<&'static str>,
+    /// The build time in RFC 3339 format, from `SOURCE_DATE_EPOCH` or the time of the build.
+    pub build_timestamp: Option<String>,
+}
+
+impl VersionInfo {
+    /// Get the information embedded at build time.
+    pub fn current() -> Self {
+        let build_timestamp = env!("RUSTY_API_BUILD_TIMESTAMP")
+            .parse::<i64>()
+            .ok()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .map(|timestamp| timestamp.to_rfc3339());
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: option_env!("GIT_SHA").filter(|sha| !sha.is_empty()),
+            build_timestamp,
+        }
+    }
+}
+
+/**
+ * Configure a `GET` route serving `VersionInfo::current()` as JSON.
+ *
+ * # Arguments
+ * - `cfg`: A mutable reference to the Actix Web `ServiceConfig`.
+ * - `path`: The URL path of the route.
+ */
+pub fn configure_version_route(cfg: &mut web::ServiceConfig, path: &str) {
+    cfg.route(path, web::get().to(|| async { HttpResponse::Ok().json(VersionInfo::current()) }));
+}
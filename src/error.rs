@@ -0,0 +1,143 @@
+/*!
+ * The `error` module defines the errors returned while configuring and starting the API server.
+ *
+ * These errors are surfaced by `Api::build` and `BuiltApi::start`, so misconfiguration
+ * can be detected before the server starts accepting connections. `Api::validate`
+ * reports every problem at once as a list of `ConfigIssue`s.
+ */
+use std::fmt;
+use std::path::PathBuf;
+
+/**
+ * Errors that can occur while validating the configuration or starting the API server.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Api, ApiError};
+ *
+ * match Api::new().rate_limit(0, 20).build() {
+ *     Err(ApiError::InvalidConfig(message)) => assert!(message.contains("rate_limit")),
+ *     _ => panic!("expected an invalid configuration"),
+ * }
+ * ```
+ */
+#[derive(Debug)]
+pub enum ApiError {
+    /// A builder value is invalid, e.g. an empty address or a zero rate limit.
+    /// Lists every `ConfigIssue` found, separated by `; `.
+    InvalidConfig(String),
+    /// The TLS certificate or private key could not be loaded.
+    Tls(ConfigError),
+    /// The user database could not be initialized.
+    Database(String),
+    /// The server failed to bind or stopped with an I/O error.
+    Io(std::io::Error),
+    /// The `Api::on_startup` hook returned an error.
+    Startup(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::InvalidConfig(message) => write!(f, "Invalid configuration: {}", message),
+            ApiError::Tls(e) => write!(f, "TLS error: {}", e),
+            ApiError::Database(message) => write!(f, "Database error: {}", message),
+            ApiError::Io(e) => write!(f, "I/O error: {}", e),
+            ApiError::Startup(message) => write!(f, "Startup hook failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Io(e) => Some(e),
+            ApiError::Tls(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Errors that can occur while loading the TLS certificate and private key.
+ *
+ * A missing file is reported separately from a file that exists but cannot be
+ * parsed, so a wrong path can be told apart from a wrong file format.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Api, ApiError, ConfigError};
+ * use std::path::Path;
+ *
+ * match Api::new().certs("missing/cert.pem", "missing/key.pem").build() {
+ *     Err(ApiError::Tls(ConfigError::FileNotFound(path))) => assert_eq!(path, Path::new("missing/cert.pem")),
+ *     _ => panic!("expected a missing certificate"),
+ * }
+ *
+ * match Api::new().certs("Cargo.toml", "Cargo.toml").build() {
+ *     Err(ApiError::Tls(ConfigError::InvalidFile(path, _))) => assert_eq!(path, Path::new("Cargo.toml")),
+ *     _ => panic!("expected an unparseable certificate"),
+ * }
+ * ```
+ */
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file does not exist.
+    FileNotFound(PathBuf),
+    /// The file exists but could not be read or contains no usable PEM data.
+    InvalidFile(PathBuf, String),
+    /// The certificate and key were read but rejected, e.g. because they do not match.
+    InvalidTls(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::FileNotFound(path) => write!(f, "File not found: {}", path.display()),
+            ConfigError::InvalidFile(path, message) => write!(f, "Failed to parse {}: {}", path.display(), message),
+            ConfigError::InvalidTls(message) => write!(f, "Failed to build TLS configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        ApiError::Io(e)
+    }
+}
+
+/**
+ * A single problem found by `Api::validate`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Api, ConfigIssue};
+ *
+ * let issues = Api::new().bind("", 8443).rate_limit(0, 20).validate().unwrap_err();
+ * assert!(issues.contains(&ConfigIssue::new("addr", "must not be empty")));
+ * assert!(issues.iter().any(|issue| issue.field == "rate_limit"));
+ * assert_eq!(issues[0].to_string(), "addr: must not be empty");
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// The configuration field, named as in the config file or builder method, e.g. `rate_limit`.
+    pub field: String,
+    /// What is wrong with the value.
+    pub message: String,
+}
+
+impl ConfigIssue {
+    /// Create an issue for the given field.
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
@@ -22,6 +22,17 @@
  * [dependencies]
  * rusty-api = "0.1.8"
  * ```
+ *
+ * ### Feature Flags
+ * - `auth` (default): Login, registration, JWTs, sessions and privilege-checked routes. Enables `sqlite`.
+ * - `sqlite`: The SQLite user database, see `Api::enable_user_db`.
+ * - `cli`: Configure the server from command-line flags, see `Api::with_args`.
+ *
+ * A minimal TLS, routing and CORS build skips SQLx, `jsonwebtoken` and the password hashers:
+ * ```toml
+ * [dependencies]
+ * rusty-api = { version = "0.1.8", default-features = false }
+ * ```
  * 
  * ## Example
  * ### Setting up your API
@@ -47,12 +58,10 @@
  *         .rate_limit(3, 20)
  *         .bind("127.0.0.1", 8443)
  *         .configure_routes(routes)
- *         .configure_cors(|| {
- *             rusty_api::Cors::default()
- *                 .allow_any_origin()
- *                 .allow_any_method()
- *                 .allowed_header("ngrok-skip-browser-warning")
- *         })
+ *         .cors_settings(
+ *             rusty_api::core::settings::CorsSettings::strict(&["https://example.com"])
+ *                 .headers(&["Authorization", "Content-Type", "ngrok-skip-browser-warning"])
+ *         )
  *         .start();
  * }
  * ```
@@ -69,33 +78,61 @@
  * ```bash
  * cargo run
  * ```
+ * The scheme, listening addresses, user database status and configured routes are
+ * logged at `INFO` on startup through the `log` crate. Unless the application
+ * installs its own logger, such as `env_logger`, a built-in one prints them to
+ * standard output; see `Api::log_level`.
  */
 
 pub mod api;
+pub mod error;
 pub mod routes;
 pub mod core;
 
-pub use crate::api::Api;
-pub use crate::routes::Routes;
-pub use crate::core::config::load_rustls_config;
+pub use crate::api::{Api, BuiltApi, RunningApi, RunningHandle};
+pub use crate::error::{ApiError, ConfigError, ConfigIssue};
+pub use crate::routes::{ApiPassword, PasswordVerified, Routes};
+pub use crate::core::config::{load_rustls_config, load_sni_tls_config, load_tls_config, SniCert};
+#[cfg(feature = "sqlite")]
 pub use crate::core::db::{get_user_field, set_user_field};
-pub use crate::core::auth::validate_token;
-pub use crate::core::auth::Claims;
+#[cfg(feature = "auth")]
+pub use crate::core::auth::{validate_token, validate_token_detailed, TokenInfo};
+#[cfg(feature = "auth")]
+pub use crate::core::auth::{hash_password, verify_password};
+#[cfg(feature = "auth")]
+pub use crate::core::auth::{generate_test_jwt, require_privilege, require_token_privilege, set_jwt_secret, Claims, TokenValidator};
+pub use crate::core::ip::ClientIp;
+pub use crate::core::pagination::Pagination;
+pub use crate::core::user::{Privilege, PrivilegeLevel};
+pub use crate::core::settings::Profile;
+pub use crate::core::response::{json_created, json_error, json_ok, ndjson_response, stream_response, Negotiated};
+pub use crate::core::middleware::{BodyLogger, CatchPanic, EnvelopeMeta, Etag, HeaderLimit, IpFilter, NormalizePathRedirect, PathNormalization, RequireHeaders, RequireHttps, ResponseEnvelope, ServerHeader, SlowRequestLog, TrailingSlash, UriLimit};
+#[cfg(feature = "auth")]
+pub use crate::core::middleware::{AuthenticatedUser, RequirePrivilege, RequireTokenPrivilege, TokenClaims};
 
 pub use actix_web::{web, HttpResponse, HttpRequest};
 pub use actix_web::http::{StatusCode, Method};
 pub use actix_cors::Cors;
+pub use log::LevelFilter;
 
+#[cfg(feature = "sqlite")]
 use once_cell::sync::Lazy;
+#[cfg(feature = "sqlite")]
 use sqlx::SqlitePool;
 
 /**
  * The `DB_POOL` is a global connection pool for SQLite database.
  *
- * It is initialized lazily when first accessed, using the `DATABASE_URL`
- * environment variable to determine the database location.
+ * It is initialized lazily when first accessed. If an `Api` has already
+ * initialized its user database, that pool is used, otherwise a pool is created
+ * from the `DATABASE_URL` environment variable, or `core::db::DEFAULT_DATABASE_URL`.
+ * Prefer `Api::db_pool` or `core::db::global_pool`, which always return the pool
+ * used by the server.
  */
+#[cfg(feature = "sqlite")]
 pub static DB_POOL: Lazy<SqlitePool> = Lazy::new(|| {
-    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    SqlitePool::connect_lazy(&database_url).expect("Failed to create database pool")
+    crate::core::db::global_pool().unwrap_or_else(|| {
+        let database_url = crate::core::db::database_url_from_env();
+        SqlitePool::connect_lazy(&database_url).expect("Failed to create database pool")
+    })
 });
\ No newline at end of file
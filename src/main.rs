@@ -1,4 +1,3 @@
-use rusty_api;
 use rusty_api::Method;
 
 async fn password_route(_req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
@@ -21,7 +20,7 @@ async fn set_role(req: rusty_api::HttpRequest, user_id: i32) -> rusty_api::HttpR
         .and_then(|pair| pair.split('=').nth(1))
         .unwrap_or("default_role");
 
-    rusty_api::set_user_field(user_id, "role", &new_role).await
+    rusty_api::set_user_field(user_id, "role", new_role).await
 }
 
 fn main() {
@@ -34,6 +33,7 @@ fn main() {
     rusty_api::Api::new()
         .certs("certs/cert.pem", "certs/key.pem")
         .rate_limit(3, 20)
+        .log_level(rusty_api::LevelFilter::Info)
         .bind("127.0.0.1", 8443)
         .configure_routes(routes)
         .configure_cors(|| {
@@ -43,5 +43,6 @@ fn main() {
                 .allowed_header("ngrok-skip-browser-warning")
         })
         .enable_user_db()
+        .user_profile_fields(&["role"])
         .start();
 }
\ No newline at end of file
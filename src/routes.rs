@@ -7,14 +7,155 @@
  *
  * This module features:
  * - **Password-Protected Routes**: Easily secure specific routes with a password.
+ * - **Guarded Routes**: Gate routes on any request predicate, e.g. a feature flag or header value.
+ * - **Privilege-Gated JSON Routes**: Typed handlers with authentication, privilege checks and JSON handled for you, with the `auth` feature.
+ * - **Password Extractor**: `PasswordVerified` lets handlers branch on whether the password was supplied.
  * - **Public Routes**: Define routes that are accessible without authentication.
+ * - **Static Routes**: Serve constant JSON or text without writing a handler.
+ * - **Redirects**: Redirect old paths to new ones with a `301`, `302`, `307` or `308`.
+ * - **Reverse Proxy**: `Routes::add_proxy` streams requests under a path prefix to another backend.
+ * - **Cached Routes**: Serve responses of expensive read endpoints from an in-memory cache.
+ * - **Route Listing**: `Routes::list` describes every registered route and its authentication.
+ * - **Composition**: `Routes::merge` and `Routes::mount` combine routes defined in separate modules.
+ * - **Declarative Routes**: `Routes::from_specs` builds routes from a table of `RouteSpec`s, e.g. loaded from configuration.
  * - **Flexible Configuration**: Apply routes to an Actix Web `ServiceConfig` for seamless integration.
  *
  * The `Routes` struct serves as a container for all defined routes, allowing for
  * easy management and configuration.
  */
-use actix_web::{web, Responder, FromRequest, HttpRequest, HttpResponse, dev::Handler, http::Method};
-use crate::core::auth::{validate_token};
+use actix_web::{web, Responder, FromRequest, HttpRequest, HttpResponse, dev::{Handler, Payload}, http::{Method, StatusCode}};
+#[cfg(feature = "auth")]
+use crate::core::auth::{extract_token, token_error_response, validate_request_token};
+use crate::core::cache::RouteCache;
+use crate::core::middleware::{RequireHeaders, RequireHttps};
+#[cfg(feature = "auth")]
+use crate::core::middleware::{AuthenticatedUser, RequirePrivilege, RequireTokenPrivilege};
+use crate::core::response::json_error;
+#[cfg(feature = "auth")]
+use crate::core::user::Privilege;
+use crate::error::ApiError;
+use actix_web::http::header;
+use actix_web::middleware::DefaultHeaders;
+use actix_web::web::Bytes;
+use actix_web::body::SizedStream;
+use awc::error::SendRequestError;
+use serde::de::DeserializeOwned;
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// `Cache-Control` header value of routes added with `add_static_json` and `add_static_text`.
+pub const STATIC_CACHE_CONTROL: &str = "public, max-age=300";
+
+/// Time allowed for the upstream of a route added with `add_proxy` to send its response headers.
+pub const PROXY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Hop-by-hop headers, which only apply to a single connection and are not forwarded by `add_proxy`.
+const HOP_BY_HOP_HEADERS: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/**
+ * The passwords accepted by a password-protected route.
+ *
+ * More than one password can be valid at once, so a new password can be added
+ * before the old one is retired. Passwords are compared in constant time.
+ *
+ * Routes added with `add_route_with_password`, `add_route_with_passwords` or
+ * `add_route_with_optional_password` register their passwords as app data
+ * automatically. Other routes and scopes can register them with
+ * `app_data(ApiPassword::new(...))`, for the `PasswordVerified` extractor.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::ApiPassword;
+ *
+ * let password = ApiPassword::any(&["NewSecret", "OldSecret"]);
+ * assert!(password.verify("OldSecret"));
+ * assert!(!password.verify("Guess"));
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiPassword {
+    passwords: Arc<[&'static str]>,
+}
+
+impl ApiPassword {
+    /// Accept a single password.
+    pub fn new(password: &'static str) -> Self {
+        Self::any(&[password])
+    }
+
+    /// Accept any of the given passwords.
+    pub fn any(passwords: &[&'static str]) -> Self {
+        Self { passwords: passwords.into() }
+    }
+
+    /// Check a candidate against every accepted password, in constant time.
+    pub fn verify(&self, candidate: &str) -> bool {
+        self.passwords
+            .iter()
+            .fold(false, |matched, password| matched | constant_time_eq(password.as_bytes(), candidate.as_bytes()))
+    }
+}
+
+/**
+ * Extractor reporting whether the request supplied the route password.
+ *
+ * The password is read from the `password` query parameter and compared against
+ * the `ApiPassword` registered for the route. Extraction fails with a
+ * `500 Internal Server Error` if no `ApiPassword` is registered.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{Api, HttpResponse, Method, PasswordVerified, Routes};
+ * use actix_web::test;
+ *
+ * async fn report(verified: PasswordVerified) -> HttpResponse {
+ *     if verified.0 {
+ *         HttpResponse::Ok().body("full report")
+ *     } else {
+ *         HttpResponse::Ok().body("summary")
+ *     }
+ * }
+ *
+ * actix_web::rt::System::new().block_on(async {
+ *     let routes = Routes::new().add_route_with_optional_password(Method::GET, "/report", report, "SecretPassword");
+ *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+ *     let get = |uri: &str| test::TestRequest::get().uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+ *
+ *     assert_eq!(test::call_and_read_body(&app, get("/report")).await, "summary");
+ *     assert_eq!(test::call_and_read_body(&app, get("/report?password=SecretPassword")).await, "full report");
+ * });
+ * ```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordVerified(pub bool);
+
+impl FromRequest for PasswordVerified {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.app_data::<ApiPassword>()
+                .map(|password| PasswordVerified(check_password(req, password)))
+                .ok_or_else(|| actix_web::error::ErrorInternalServerError("No API password configured")),
+        )
+    }
+}
+
+/// A single route registration, applied to an Actix Web `ServiceConfig`.
+type RouteConfig = Box<dyn Fn(&mut web::ServiceConfig) + Send + Sync>;
 
 /**
  * The `Routes` struct is used to manage API routes.
@@ -41,7 +182,197 @@ use crate::core::auth::{validate_token};
  * ```
  */
 pub struct Routes {
-    routes: Vec<Box<dyn Fn(&mut web::ServiceConfig) + Send + Sync>>,
+    routes: Vec<RouteConfig>,
+    info: Vec<RouteInfo>,
+    /// Routes that could not be added, reported by `Api::validate` and `Api::build`.
+    problems: Vec<String>,
+}
+
+/// How a route registered through `Routes` authenticates its callers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteAuth {
+    /// The route is public.
+    None,
+    /// A password is required in the query string.
+    Password,
+    /// A password is checked, but the handler decides what to do without it.
+    OptionalPassword,
+    /// A valid bearer token is required.
+    Token,
+    /// A bearer token of a user with at least the given privilege level is required.
+    Privilege(i32),
+    /// A custom guard added with `Routes::add_route_with_guard` decides.
+    Guard,
+}
+
+impl std::fmt::Display for RouteAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteAuth::None => write!(f, "none"),
+            RouteAuth::Password => write!(f, "password"),
+            RouteAuth::OptionalPassword => write!(f, "optional password"),
+            RouteAuth::Token => write!(f, "token"),
+            RouteAuth::Privilege(level) => write!(f, "privilege {}", level),
+            RouteAuth::Guard => write!(f, "guard"),
+        }
+    }
+}
+
+/**
+ * A predicate run before a route's handler, see `Routes::add_route_with_guard`.
+ *
+ * The guard returns `Ok(())` to let the request through, or the response to
+ * send back instead of calling the handler.
+ */
+pub type RouteGuard = Arc<dyn Fn(&HttpRequest) -> Result<(), HttpResponse> + Send + Sync>;
+
+/**
+ * Metadata about a route registered through `Routes`, returned by `Routes::list`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HttpResponse, Method, PrivilegeLevel, Routes};
+ * use rusty_api::routes::{RouteAuth, RouteInfo};
+ *
+ * let routes = Routes::new()
+ *     .add_route(Method::GET, "/health", || async { HttpResponse::Ok().finish() })
+ *     .add_route_with_password(Method::DELETE, "/cache", || async { HttpResponse::Ok().finish() }, "Secret")
+ *     .add_route_with_privilege(Method::GET, "/admin", || async { HttpResponse::Ok().finish() }, PrivilegeLevel::Admin);
+ *
+ * let list = routes.list();
+ * assert_eq!(list[0], RouteInfo { method: Some(Method::GET), path: "/health", auth: RouteAuth::None });
+ * assert_eq!(list[1].auth, RouteAuth::Password);
+ * assert_eq!(list[2].auth, RouteAuth::Privilege(1));
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RouteInfo {
+    /// The HTTP method, or `None` if the route answers every method (e.g. redirects).
+    #[serde(serialize_with = "serialize_method")]
+    pub method: Option<Method>,
+    /// The URL path of the route.
+    pub path: &'static str,
+    /// How the route authenticates its callers.
+    #[serde(serialize_with = "serialize_display")]
+    pub auth: RouteAuth,
+}
+
+/// Serialize a route method as its name, or `*` for every method.
+fn serialize_method<S: serde::Serializer>(method: &Option<Method>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(method.as_ref().map_or("*", Method::as_str))
+}
+
+/// Serialize a value through its `Display` implementation.
+fn serialize_display<S: serde::Serializer>(value: &RouteAuth, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// A type-erased handler for routes declared with `RouteSpec`.
+pub type SpecHandler = Arc<dyn Fn(HttpRequest) -> LocalBoxFuture<'static, HttpResponse> + Send + Sync>;
+
+/**
+ * Box a handler function for use in a `RouteSpec` or `HandlerRegistry`.
+ *
+ * # Arguments
+ * - `handler`: An async function taking the request and returning a response.
+ *
+ * # Returns
+ * The boxed `SpecHandler`.
+ */
+pub fn spec_handler<F, R>(handler: F) -> SpecHandler
+where
+    F: Fn(HttpRequest) -> R + Send + Sync + 'static,
+    R: futures_util::Future<Output = HttpResponse> + 'static,
+{
+    Arc::new(move |req| handler(req).boxed_local())
+}
+
+/// The handler of a `RouteSpec`.
+#[derive(Clone)]
+pub enum RouteHandler {
+    /// A handler looked up by name in the `HandlerRegistry` passed to `Routes::from_specs`.
+    Named(String),
+    /// A handler provided directly.
+    Boxed(SpecHandler),
+}
+
+/// How a route declared with `RouteSpec` authenticates its callers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecAuth {
+    /// The route is public, as with `Routes::add_route`.
+    None,
+    /// The password must be given in the query string, as with `Routes::add_route_with_password`.
+    Password(String),
+    /// A valid token is required. The handler finds the claims in the `TokenClaims` request extension.
+    Token,
+    /// The user must hold at least the given privilege level, as with `Routes::add_route_with_privilege`.
+    /// The handler finds the user in the `AuthenticatedUser` request extension.
+    Privilege(i32),
+}
+
+/**
+ * A route declared as data, for `Routes::from_specs`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::{HttpResponse, Method};
+ * use rusty_api::routes::{RouteHandler, RouteSpec, SpecAuth};
+ *
+ * let spec = RouteSpec {
+ *     method: Method::GET,
+ *     path: "/users".into(),
+ *     auth: SpecAuth::Token,
+ *     handler: RouteHandler::Named("list_users".into()),
+ * };
+ * ```
+ */
+#[derive(Clone)]
+pub struct RouteSpec {
+    /// The HTTP method for the route.
+    pub method: Method,
+    /// The URL path for the route.
+    pub path: String,
+    /// How the route authenticates its callers.
+    pub auth: SpecAuth,
+    /// The handler serving the route.
+    pub handler: RouteHandler,
+}
+
+/**
+ * Named handlers that `RouteSpec`s can refer to with `RouteHandler::Named`.
+ *
+ * # Example
+ * ```rust
+ * use rusty_api::HttpResponse;
+ * use rusty_api::routes::{spec_handler, HandlerRegistry};
+ *
+ * let registry = HandlerRegistry::new()
+ *     .register("health", spec_handler(|_req| async { HttpResponse::Ok().body("ok") }));
+ * assert!(registry.get("health").is_some());
+ * assert!(registry.get("missing").is_none());
+ * ```
+ */
+#[derive(Clone, Default)]
+pub struct HandlerRegistry {
+    handlers: HashMap<String, SpecHandler>,
+}
+
+impl HandlerRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a handler under a name, replacing any handler with the same name.
+    pub fn register(mut self, name: &str, handler: SpecHandler) -> Self {
+        self.handlers.insert(name.to_string(), handler);
+        self
+    }
+
+    /// Get the handler registered under a name.
+    pub fn get(&self, name: &str) -> Option<SpecHandler> {
+        self.handlers.get(name).cloned()
+    }
 }
 
 impl Routes {
@@ -62,31 +393,46 @@ impl Routes {
      * ```
      */
     pub fn new() -> Self {
-        Self { routes: Vec::new() }
+        Self { routes: Vec::new(), info: Vec::new(), problems: Vec::new() }
     }
 
     /**
      * Add a new route to the `Routes` instance with password protection.
      *
      * This method allows you to define a route that requires a password to access.
-     * The password is passed as a query parameter in the request.
+     * The password is passed as a query parameter in the request. It is checked
+     * before the handler runs for every method, so destructive operations such
+     * as `DELETE` or `PUT` are guarded the same way as reads.
      *
      * # Arguments
-     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `method`: The HTTP method for the route (e.g., GET, POST, DELETE).
      * - `path`: The URL path for the route.
      * - `handler`: The handler function for the route.
      * - `password`: The password required to access the route.
      *
      * # Example
      * ```rust
-     * use rusty_api::{Routes, HttpRequest, HttpResponse, Method};
+     * use rusty_api::{Api, Routes, HttpRequest, HttpResponse, Method};
+     * use actix_web::test;
      *
      * async fn protected_route(_req: HttpRequest) -> HttpResponse {
      *    HttpResponse::Ok().body("Protected route accessed!")
      * }
      *
-     * let routes = Routes::new()
-     *    .add_route_with_password(Method::GET, "/protected", protected_route, "SecretPassword");
+     * async fn purge() -> HttpResponse {
+     *    HttpResponse::NoContent().finish()
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new()
+     *         .add_route_with_password(Method::GET, "/protected", protected_route, "SecretPassword")
+     *         .add_route_with_password(Method::DELETE, "/admin/cache", purge, "SecretPassword");
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let delete = |uri: &str| test::TestRequest::delete().uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     assert_eq!(test::call_service(&app, delete("/admin/cache")).await.status(), 401);
+     *     assert_eq!(test::call_service(&app, delete("/admin/cache?password=SecretPassword")).await.status(), 204);
+     * });
      * ```
      */
     pub fn add_route_with_password<H, Args, R>(
@@ -101,139 +447,97 @@ impl Routes {
         Args: FromRequest + 'static,
         R: Responder + 'static,
     {
-        self.add_route_internal(method, path, handler, Some(password))
+        self.add_route_internal(method, path, handler, Some(ApiPassword::new(password)))
     }
 
     /**
-     * Add a new route to the `Routes` instance without password protection.
+     * Add a new route to the `Routes` instance that accepts any of several passwords.
      *
-     * This method allows you to define a public route that does not require authentication.
+     * This behaves like `add_route_with_password`, but every password in the list is
+     * valid. To rotate a password without downtime, add the new password alongside
+     * the old one, update the clients, then remove the old password.
      *
      * # Arguments
      * - `method`: The HTTP method for the route (e.g., GET, POST).
      * - `path`: The URL path for the route.
      * - `handler`: The handler function for the route.
+     * - `passwords`: The passwords accepted for the route.
      *
      * # Example
      * ```rust
-     * use rusty_api::{Routes, HttpRequest, HttpResponse, Method};
-     * 
-     * async fn public_route(_req: HttpRequest) -> HttpResponse {
-     *    HttpResponse::Ok().body("Public route accessed!")
-     * }
+     * use rusty_api::{Api, Routes, HttpResponse, Method};
+     * use actix_web::test;
      *
-     * let routes = Routes::new()
-     *   .add_route(Method::GET, "/public", public_route);
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new()
+     *         .add_route_with_passwords(Method::GET, "/protected", || async { HttpResponse::Ok().finish() }, &["NewSecret", "OldSecret"]);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let get = |uri: &str| test::TestRequest::get().uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     assert_eq!(test::call_service(&app, get("/protected?password=NewSecret")).await.status(), 200);
+     *     assert_eq!(test::call_service(&app, get("/protected?password=OldSecret")).await.status(), 200);
+     *     assert_eq!(test::call_service(&app, get("/protected?password=Guess")).await.status(), 401);
+     * });
      * ```
      */
-    pub fn add_route<H, Args, R>(self, method: Method, path: &'static str, handler: H) -> Self
+    pub fn add_route_with_passwords<H, Args, R>(
+        self,
+        method: Method,
+        path: &'static str,
+        handler: H,
+        passwords: &[&'static str],
+    ) -> Self
     where
         H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
         Args: FromRequest + 'static,
         R: Responder + 'static,
     {
-        self.add_route_internal(method, path, handler, None)
+        self.add_route_internal(method, path, handler, Some(ApiPassword::any(passwords)))
     }
 
     /**
-     * Add a new route to the `Routes` instance with authentication.
+     * Add a new route whose handler decides what to do without the password.
      *
-     * This method allows you to define a route that requires authentication via a token.
-     * The token is passed in the `Authorization` header of the request.
+     * Unlike `add_route_with_password`, requests without a valid password are not
+     * rejected. The handler receives a `PasswordVerified` extractor instead and can
+     * branch on it, e.g. to return a reduced response to anonymous clients.
      *
      * # Arguments
      * - `method`: The HTTP method for the route (e.g., GET, POST).
      * - `path`: The URL path for the route.
      * - `handler`: The handler function for the route.
+     * - `password`: The password checked by `PasswordVerified`.
      *
      * # Example
      * ```rust
-     * use rusty_api::{Routes, HttpRequest, HttpResponse, Method};
+     * use rusty_api::{Routes, HttpResponse, Method, PasswordVerified};
      *
-     * async fn auth_route(_req: HttpRequest, userId: i32) -> HttpResponse {
-     *    HttpResponse::Ok().body(format!("Authenticated user ID: {}", userId))
+     * async fn stats(verified: PasswordVerified) -> HttpResponse {
+     *    HttpResponse::Ok().body(if verified.0 { "detailed" } else { "public" })
      * }
      *
      * let routes = Routes::new()
-     *    .add_route_with_auth(Method::GET, "/auth", auth_route);
+     *    .add_route_with_optional_password(Method::GET, "/stats", stats, "SecretPassword");
      * ```
      */
-    pub fn add_route_with_auth<H, R>(mut self, method: Method, path: &'static str, handler: H) -> Self
-    where
-        H: Fn(HttpRequest, i32) -> R + Clone + Send + Sync + 'static,
-        R: futures_util::Future<Output = HttpResponse> + 'static,
-    {
-        let wrapped_handler = move |req: HttpRequest| {
-            let handler = handler.clone();
-            async move {
-                // Extract and validate the token
-                let token = match req
-                    .headers()
-                    .get("Authorization")
-                    .and_then(|h| h.to_str().ok())
-                    .and_then(|h| h.strip_prefix("Bearer "))
-                {
-                    Some(token) => token,
-                    None => return HttpResponse::Unauthorized().body("Missing or invalid token"),
-                };
-
-                // Validate the token and extract the user ID
-                let user_id = match validate_token(token) {
-                    Ok(claims) => claims.sub,
-                    Err(_) => return HttpResponse::Unauthorized().body("Invalid token"),
-                };
-
-                // Call the handler with the user ID
-                handler(req, user_id).await
-            }
-        };
-
-        let m = method.clone();
-        let route = {
-            let wrapped_handler = wrapped_handler.clone(); // Clone the handler inside the closure
-            move |cfg: &mut web::ServiceConfig| {
-                cfg.service(
-                    web::resource(path).route(web::method(m.clone()).to(wrapped_handler.clone()))
-                );
-            }
-        };
-
-        self.routes.push(Box::new(route));
-        self
-    }
-
-    /// Internal function to handle adding routes with or without passwords.
-    fn add_route_internal<H, Args, R>(
+    pub fn add_route_with_optional_password<H, Args, R>(
         mut self,
         method: Method,
         path: &'static str,
         handler: H,
-        password: Option<&'static str>,
+        password: &'static str,
     ) -> Self
     where
         H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
         Args: FromRequest + 'static,
         R: Responder + 'static,
     {
-        let handler = handler.clone(); // Clone the handler to avoid moving it
-        let wrapped_handler = move |req: HttpRequest, args: Args| {
-            let handler = handler.clone(); // Clone the handler inside the closure
-            async move {
-                if let Some(expected_password) = password {
-                    if !check_password(&req, expected_password) {
-                        return HttpResponse::Unauthorized().body("Invalid password").into();
-                    }
-                }
-                // Call the original handler and convert its output to an HttpResponse
-                handler.call(args).await.respond_to(&req).map_into_boxed_body()
-            }
-        };
-
-        let m = method.clone();
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::OptionalPassword });
         let route = move |cfg: &mut web::ServiceConfig| {
-            let wrapped_handler = wrapped_handler.clone(); // Clone the wrapped handler inside the route closure
             cfg.service(
-                web::resource(path).route(web::method(m.clone()).to(wrapped_handler.clone()))
+                web::resource(path)
+                    .app_data(ApiPassword::new(password))
+                    .route(web::method(method.clone()).to(handler.clone()))
             );
         };
         self.routes.push(Box::new(route));
@@ -241,43 +545,1538 @@ impl Routes {
     }
 
     /**
-     * Apply the routes to a `ServiceConfig`.
+     * Add a new route that only runs its handler if a guard accepts the request.
      *
-     * This method iterates over all defined routes and applies them to the
-     * provided Axtix Web `ServiceConfig`. It is used internally by the `Api` struct.
+     * The guard runs before the handler is called. It returns `Ok(())` to let
+     * the request through, or the response to send back instead, so any gating
+     * not covered by passwords or privileges can be expressed, e.g. a feature
+     * flag, a specific header value or the time of day. Password-protected routes are built on the same mechanism.
      *
      * # Arguments
-     * - `cfg`: A mutable reference to the `ServiceConfig` to which the routes will be applied.
+     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     * - `guard`: The predicate deciding whether the handler runs.
      *
      * # Example
      * ```rust
-     * use rusty_api::{Routes, Api};
+     * use rusty_api::{Api, Routes, HttpRequest, HttpResponse, Method};
+     * use actix_web::test;
      *
-     * let routes = Routes::new();
+     * fn beta_only(req: &HttpRequest) -> Result<(), HttpResponse> {
+     *     match req.headers().get("X-Beta") {
+     *         Some(value) if value == "on" => Ok(()),
+     *         _ => Err(HttpResponse::NotFound().finish()),
+     *     }
+     * }
      *
-     * let api = Api::new()
-     *    .configure_routes(routes); // The configure_routes method calls the configure method internally.
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new()
+     *         .add_route_with_guard(Method::GET, "/beta", || async { HttpResponse::Ok().body("beta") }, beta_only);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let get = |beta: &str| test::TestRequest::get()
+     *         .uri("/beta")
+     *         .insert_header(("X-Beta", beta))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *
+     *     assert_eq!(test::call_service(&app, get("on")).await.status(), 200);
+     *     assert_eq!(test::call_service(&app, get("off")).await.status(), 404);
+     * });
      * ```
      */
-    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
-        for route in &self.routes {
-            route(cfg);
-        }
+    pub fn add_route_with_guard<H, Args, R, G>(self, method: Method, path: &'static str, handler: H, guard: G) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+        G: Fn(&HttpRequest) -> Result<(), HttpResponse> + Send + Sync + 'static,
+    {
+        self.add_guarded_route(method, path, handler, RouteAuth::Guard, Some(Arc::new(guard)), None)
     }
-}
-
-/// Check if the request contains the expected password in the query string.
-fn check_password(req: &HttpRequest, expected_password: &str) -> bool {
-    let query_string = req.query_string();
 
-    for pair in query_string.split('&') {
-        let mut key_value = pair.splitn(2, '=');
-        if let (Some(key), Some(value)) = (key_value.next(), key_value.next()) {
-            if key == "password" && value == expected_password {
-                return true;
-            }
-        }
+    /**
+     * Add a new route to the `Routes` instance without password protection.
+     *
+     * This method allows you to define a public route that does not require authentication.
+     *
+     * The handler may return any `Responder`, including `Result<T, E>` where `E`
+     * implements `ResponseError`. Errors are rendered with their own
+     * `error_response()` and stay attached to the response, so middleware and
+     * loggers still see the original error. The same holds for every route added
+     * with a method taking an Actix `Handler`, e.g. `add_route_with_password`.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Routes, HttpRequest, HttpResponse, Method};
+     * 
+     * async fn public_route(_req: HttpRequest) -> HttpResponse {
+     *    HttpResponse::Ok().body("Public route accessed!")
+     * }
+     *
+     * let routes = Routes::new()
+     *   .add_route(Method::GET, "/public", public_route);
+     * ```
+     *
+     * A handler returning a custom error type:
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes, StatusCode};
+     * use actix_web::{test, web, ResponseError};
+     *
+     * #[derive(Debug)]
+     * enum OrderError {
+     *     NotFound(u32),
+     *     Conflict,
+     * }
+     *
+     * impl std::fmt::Display for OrderError {
+     *     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+     *         match self {
+     *             OrderError::NotFound(id) => write!(f, "Order {} not found", id),
+     *             OrderError::Conflict => write!(f, "Order was modified"),
+     *         }
+     *     }
+     * }
+     *
+     * impl ResponseError for OrderError {
+     *     fn status_code(&self) -> StatusCode {
+     *         match self {
+     *             OrderError::NotFound(_) => StatusCode::NOT_FOUND,
+     *             OrderError::Conflict => StatusCode::CONFLICT,
+     *         }
+     *     }
+     *
+     *     fn error_response(&self) -> HttpResponse {
+     *         HttpResponse::build(self.status_code()).json(serde_json::json!({ "error": self.to_string() }))
+     *     }
+     * }
+     *
+     * async fn get_order(id: web::Path<u32>) -> Result<HttpResponse, OrderError> {
+     *     match id.into_inner() {
+     *         1 => Ok(HttpResponse::Ok().body("order 1")),
+     *         2 => Err(OrderError::Conflict),
+     *         id => Err(OrderError::NotFound(id)),
+     *     }
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route(Method::GET, "/orders/{id}", get_order);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let get = |uri: &str| test::TestRequest::get().uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     assert_eq!(test::call_and_read_body(&app, get("/orders/1")).await, "order 1");
+     *     assert_eq!(test::call_service(&app, get("/orders/2")).await.status(), 409);
+     *     let response = test::call_service(&app, get("/orders/7")).await;
+     *     assert_eq!(response.status(), 404);
+     *     assert!(response.response().error().is_some());
+     *     let body: serde_json::Value = test::read_body_json(response).await;
+     *     assert_eq!(body["error"], "Order 7 not found");
+     * });
+     * ```
+     */
+    pub fn add_route<H, Args, R>(self, method: Method, path: &'static str, handler: H) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        self.add_route_internal(method, path, handler, None)
     }
 
-    false
-}
\ No newline at end of file
+    /**
+     * Add a `GET` route whose responses are cached in memory for the given TTL.
+     *
+     * Successful responses are stored per path and query string, and served
+     * without invoking the handler until they are older than `ttl`. Requests
+     * carrying credentials are never cached; use `add_route_with_cache` with
+     * `RouteCache::include_authenticated` to opt in. See `RouteCache` for the
+     * exact rules.
+     *
+     * # Arguments
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     * - `ttl`: How long a cached response is served.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Routes};
+     * use actix_web::test;
+     * use std::sync::atomic::{AtomicUsize, Ordering};
+     * use std::time::Duration;
+     *
+     * static CALLS: AtomicUsize = AtomicUsize::new(0);
+     *
+     * async fn report() -> HttpResponse {
+     *     let call = CALLS.fetch_add(1, Ordering::SeqCst) + 1;
+     *     HttpResponse::Ok().body(format!("report #{}", call))
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route_cached("/report", report, Duration::from_secs(60));
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let get = |uri: &str| test::TestRequest::get().uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *
+     *     assert_eq!(test::call_and_read_body(&app, get("/report")).await, "report #1");
+     *     assert_eq!(test::call_and_read_body(&app, get("/report")).await, "report #1");
+     *     assert_eq!(test::call_and_read_body(&app, get("/report?page=2")).await, "report #2");
+     * });
+     * ```
+     */
+    pub fn add_route_cached<H, Args, R>(self, path: &'static str, handler: H, ttl: Duration) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        self.add_route_with_cache(path, handler, RouteCache::new(ttl))
+    }
+
+    /**
+     * Add a `GET` route whose responses are cached in the given `RouteCache`.
+     *
+     * # Arguments
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     * - `cache`: The cache, configuring the TTL, capacity and whether authenticated requests are cached.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{HttpResponse, Routes};
+     * use rusty_api::core::cache::RouteCache;
+     * use std::time::Duration;
+     *
+     * let cache = RouteCache::new(Duration::from_secs(10)).capacity(100).include_authenticated();
+     * let routes = Routes::new().add_route_with_cache("/stats", || async { HttpResponse::Ok().body("stats") }, cache);
+     * ```
+     */
+    pub fn add_route_with_cache<H, Args, R>(mut self, path: &'static str, handler: H, cache: RouteCache) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(Method::GET), path, auth: RouteAuth::None });
+        let cached_handler = move |req: HttpRequest, args: Args| {
+            let handler = handler.clone();
+            let cache = cache.clone();
+            async move {
+                let Some(key) = cache.key(&req) else {
+                    return handler.call(args).await.respond_to(&req).map_into_boxed_body();
+                };
+                if let Some(response) = cache.lookup(&key) {
+                    return response;
+                }
+                let response = handler.call(args).await.respond_to(&req).map_into_boxed_body();
+                cache.store(key, response).await
+            }
+        };
+
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.route(path, web::get().to(cached_handler.clone()));
+        };
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a new route to the `Routes` instance with authentication.
+     *
+     * This method allows you to define a route that requires authentication via a token.
+     * The token is passed in the `Authorization` header of the request.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Routes, HttpRequest, HttpResponse, Method};
+     *
+     * async fn auth_route(_req: HttpRequest, userId: i32) -> HttpResponse {
+     *    HttpResponse::Ok().body(format!("Authenticated user ID: {}", userId))
+     * }
+     *
+     * let routes = Routes::new()
+     *    .add_route_with_auth(Method::GET, "/auth", auth_route);
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn add_route_with_auth<H, R>(mut self, method: Method, path: &'static str, handler: H) -> Self
+    where
+        H: Fn(HttpRequest, i32) -> R + Clone + Send + Sync + 'static,
+        R: futures_util::Future<Output = HttpResponse> + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::Token });
+        let wrapped_handler = move |req: HttpRequest| {
+            let handler = handler.clone();
+            async move {
+                let user_id = match authenticate(&req).await {
+                    Ok(user_id) => user_id,
+                    Err(response) => return response,
+                };
+
+                // Call the handler with the user ID
+                handler(req, user_id).await
+            }
+        };
+
+        let m = method.clone();
+        let route = {
+            let wrapped_handler = wrapped_handler.clone(); // Clone the handler inside the closure
+            move |cfg: &mut web::ServiceConfig| {
+                cfg.service(
+                    web::resource(path).route(web::method(m.clone()).to(wrapped_handler.clone()))
+                );
+            }
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a new route to the `Routes` instance whose JSON body is deserialized for the handler.
+     *
+     * The request body is parsed into `T` before the handler runs. Malformed JSON, or
+     * JSON that does not match `T`, is rejected with a `400 Bad Request` and never
+     * reaches the handler.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., POST, PUT).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function, receiving the request and the deserialized body.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Routes, Api, HttpRequest, HttpResponse, Method};
+     * use actix_web::test;
+     * use serde::Deserialize;
+     *
+     * #[derive(Deserialize)]
+     * struct Greeting {
+     *     name: String,
+     * }
+     *
+     * async fn greet(_req: HttpRequest, body: Greeting) -> HttpResponse {
+     *     HttpResponse::Ok().body(format!("Hello, {}!", body.name))
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route_with_json_body(Method::POST, "/greet", greet);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *
+     *     let req = test::TestRequest::post()
+     *         .uri("/greet")
+     *         .set_payload(r#"{"name": "Alice"}"#)
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     assert_eq!(test::call_and_read_body(&app, req).await, "Hello, Alice!");
+     *
+     *     let req = test::TestRequest::post()
+     *         .uri("/greet")
+     *         .set_payload("{not json")
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     assert_eq!(test::call_service(&app, req).await.status(), 400);
+     * });
+     * ```
+     */
+    pub fn add_route_with_json_body<T, H, R>(mut self, method: Method, path: &'static str, handler: H) -> Self
+    where
+        T: DeserializeOwned + 'static,
+        H: Fn(HttpRequest, T) -> R + Clone + Send + Sync + 'static,
+        R: futures_util::Future<Output = HttpResponse> + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::None });
+        let wrapped_handler = move |req: HttpRequest, body: web::Bytes| {
+            let handler = handler.clone();
+            async move {
+                let body = match parse_json_body::<T>(&body) {
+                    Ok(body) => body,
+                    Err(response) => return response,
+                };
+                handler(req, body).await
+            }
+        };
+
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.service(
+                web::resource(path).route(web::method(method.clone()).to(wrapped_handler.clone()))
+            );
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a new route to the `Routes` instance with authentication and a JSON body.
+     *
+     * This combines `add_route_with_auth` and `add_route_with_json_body`: the bearer
+     * token is validated first (`401` on failure), then the body is deserialized
+     * into `T` (`400` on failure), and only then is the handler called.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., POST, PUT).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function, receiving the request, the user ID and the deserialized body.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Routes, HttpRequest, HttpResponse, Method};
+     * use serde::Deserialize;
+     *
+     * #[derive(Deserialize)]
+     * struct NewPost {
+     *     title: String,
+     * }
+     *
+     * async fn create_post(_req: HttpRequest, user_id: i32, post: NewPost) -> HttpResponse {
+     *     HttpResponse::Created().body(format!("User {} created '{}'", user_id, post.title))
+     * }
+     *
+     * let routes = Routes::new()
+     *     .add_route_with_auth_and_json_body(Method::POST, "/posts", create_post);
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn add_route_with_auth_and_json_body<T, H, R>(mut self, method: Method, path: &'static str, handler: H) -> Self
+    where
+        T: DeserializeOwned + 'static,
+        H: Fn(HttpRequest, i32, T) -> R + Clone + Send + Sync + 'static,
+        R: futures_util::Future<Output = HttpResponse> + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::Token });
+        let wrapped_handler = move |req: HttpRequest, body: web::Bytes| {
+            let handler = handler.clone();
+            async move {
+                let user_id = match authenticate(&req).await {
+                    Ok(user_id) => user_id,
+                    Err(response) => return response,
+                };
+                let body = match parse_json_body::<T>(&body) {
+                    Ok(body) => body,
+                    Err(response) => return response,
+                };
+                handler(req, user_id, body).await
+            }
+        };
+
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.service(
+                web::resource(path).route(web::method(method.clone()).to(wrapped_handler.clone()))
+            );
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a new route to the `Routes` instance that requires a minimum privilege level.
+     *
+     * The route is wrapped in the `RequirePrivilege` middleware, so the check runs
+     * before the handler and cannot be forgotten. The handler can access the caller
+     * through the `AuthenticatedUser` extractor. Requires the user database to be enabled.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     * - `level`: The minimum privilege level required to access the route.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Routes, AuthenticatedUser, HttpResponse, Method, PrivilegeLevel};
+     *
+     * async fn admin_route(user: AuthenticatedUser) -> HttpResponse {
+     *    HttpResponse::Ok().body(format!("Welcome, admin {}", user.0.username))
+     * }
+     *
+     * let routes = Routes::new()
+     *    .add_route_with_privilege(Method::GET, "/admin", admin_route, PrivilegeLevel::Admin);
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn add_route_with_privilege<H, Args, R>(
+        mut self,
+        method: Method,
+        path: &'static str,
+        handler: H,
+        level: impl Privilege,
+    ) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::Privilege(level.level()) });
+        let level = level.level();
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.service(
+                web::resource(path)
+                    .wrap(RequirePrivilege(level))
+                    .route(web::method(method.clone()).to(handler.clone()))
+            );
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a new route to the `Routes` instance whose token must grant a minimum privilege level.
+     *
+     * The route is wrapped in the `RequireTokenPrivilege` middleware, which reads the
+     * level from the token's claims instead of the database, so it also works without
+     * the user database. Privilege changes take effect when the user receives a new
+     * token. The handler can access the claims through the `TokenClaims` extractor.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     * - `level`: The minimum privilege level the token must grant.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{generate_test_jwt, set_jwt_secret, Api, HttpResponse, Method, PrivilegeLevel, Routes, TokenClaims};
+     * use actix_web::test;
+     * use std::time::Duration;
+     *
+     * async fn admin_route(claims: TokenClaims) -> HttpResponse {
+     *    HttpResponse::Ok().body(format!("Welcome, admin {}", claims.0.sub))
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     set_jwt_secret("test-secret");
+     *     let routes = Routes::new()
+     *        .add_route_with_token_privilege(Method::GET, "/admin", admin_route, PrivilegeLevel::Admin);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let get = |privilege| test::TestRequest::get()
+     *         .uri("/admin")
+     *         .insert_header(("Authorization", format!("Bearer {}", generate_test_jwt(5, privilege, Duration::from_secs(60)))))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *
+     *     assert_eq!(test::call_service(&app, get(PrivilegeLevel::User)).await.status(), 403);
+     *     assert_eq!(test::call_and_read_body(&app, get(PrivilegeLevel::Admin)).await, "Welcome, admin 5");
+     * });
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn add_route_with_token_privilege<H, Args, R>(
+        mut self,
+        method: Method,
+        path: &'static str,
+        handler: H,
+        level: impl Privilege,
+    ) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::Privilege(level.level()) });
+        let level = level.level();
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.service(
+                web::resource(path)
+                    .wrap(RequireTokenPrivilege(level))
+                    .route(web::method(method.clone()).to(handler.clone()))
+            );
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a JSON route that requires a minimum privilege level.
+     *
+     * This collapses the usual privilege-gated JSON handler into a typed function:
+     * the `RequirePrivilege` middleware authenticates the caller (`401`) and checks
+     * their privilege (`403`), the body is deserialized into `In` (`400` on failure),
+     * and the handler's `Out` is serialized as a `200 OK` JSON response. Errors
+     * returned by the handler are rendered through their `ResponseError` implementation.
+     * Requires the user database to be enabled.
+     *
+     * # Arguments
+     * - `path`: The URL path for the route.
+     * - `method`: The HTTP method for the route (e.g., POST, PUT).
+     * - `level`: The minimum privilege level required to access the route.
+     * - `handler`: The handler function, receiving the caller and the deserialized body.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{generate_test_jwt, set_jwt_secret, Api, AuthenticatedUser, Method, PrivilegeLevel, Routes};
+     * use rusty_api::core::auth::register_user;
+     * use rusty_api::core::user::RegisterInput;
+     * use actix_web::test;
+     * use serde::{Deserialize, Serialize};
+     * use std::time::Duration;
+     *
+     * #[derive(Deserialize)]
+     * struct NewNote {
+     *     text: String,
+     * }
+     *
+     * #[derive(Serialize)]
+     * struct Note {
+     *     author: String,
+     *     text: String,
+     * }
+     *
+     * async fn create_note(user: AuthenticatedUser, note: NewNote) -> Result<Note, actix_web::Error> {
+     *     if note.text.is_empty() {
+     *         return Err(actix_web::error::ErrorBadRequest("Note is empty"));
+     *     }
+     *     Ok(Note { author: user.0.username, text: note.text })
+     * }
+     *
+     * set_jwt_secret("test-secret");
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new()
+     *         .add_json_route_with_privilege("/notes", Method::POST, PrivilegeLevel::User, create_note)
+     *         .add_json_route_with_privilege("/admin/notes", Method::POST, PrivilegeLevel::Admin, create_note);
+     *     let api = Api::new().in_memory_db().configure_routes(routes);
+     *     let app = test::init_service(api.test_app().await).await;
+     *     let pool = api.db_pool().unwrap();
+     *     let user = register_user(&pool, RegisterInput { username: "alice".into(), password: "Secret123".into() }).await.unwrap();
+     *     let token = generate_test_jwt(user.id, PrivilegeLevel::User, Duration::from_secs(60));
+     *
+     *     let post = |uri: &str, body: &str| test::TestRequest::post()
+     *         .uri(uri)
+     *         .insert_header(("Authorization", format!("Bearer {}", token)))
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .set_payload(body.to_string())
+     *         .to_request();
+     *
+     *     let note: serde_json::Value = test::call_and_read_body_json(&app, post("/notes", r#"{"text": "hi"}"#)).await;
+     *     assert_eq!(note["author"], "alice");
+     *
+     *     assert_eq!(test::call_service(&app, post("/notes", "{not json")).await.status(), 400);
+     *     assert_eq!(test::call_service(&app, post("/notes", r#"{"text": ""}"#)).await.status(), 400);
+     *     assert_eq!(test::call_service(&app, post("/admin/notes", r#"{"text": "hi"}"#)).await.status(), 403);
+     * });
+     * ```
+     */
+    #[cfg(feature = "auth")]
+    pub fn add_json_route_with_privilege<In, Out, E, H, R>(
+        mut self,
+        path: &'static str,
+        method: Method,
+        level: impl Privilege,
+        handler: H,
+    ) -> Self
+    where
+        In: DeserializeOwned + 'static,
+        Out: serde::Serialize + 'static,
+        E: Into<actix_web::Error> + 'static,
+        H: Fn(AuthenticatedUser, In) -> R + Clone + Send + Sync + 'static,
+        R: futures_util::Future<Output = Result<Out, E>> + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::Privilege(level.level()) });
+        let wrapped_handler = move |user: AuthenticatedUser, body: web::Bytes| {
+            let handler = handler.clone();
+            async move {
+                let body = match parse_json_body::<In>(&body) {
+                    Ok(body) => body,
+                    Err(response) => return response,
+                };
+                match handler(user, body).await {
+                    Ok(out) => HttpResponse::Ok().json(out),
+                    Err(e) => HttpResponse::from_error(e),
+                }
+            }
+        };
+
+        let level = level.level();
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.service(
+                web::resource(path)
+                    .wrap(RequirePrivilege(level))
+                    .route(web::method(method.clone()).to(wrapped_handler.clone()))
+            );
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a new route to the `Routes` instance that is only served over HTTPS.
+     *
+     * The route is wrapped in the `RequireHttps` middleware, so requests over plain
+     * HTTP receive a `403 Forbidden` before the handler runs. This keeps sensitive
+     * routes secure when a plain HTTP listener serves e.g. health checks.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Routes, HttpResponse, Method};
+     *
+     * async fn rotate_keys() -> HttpResponse {
+     *    HttpResponse::Ok().body("Rotated")
+     * }
+     *
+     * let routes = Routes::new()
+     *    .add_route_secure_only(Method::POST, "/keys/rotate", rotate_keys);
+     * ```
+     */
+    pub fn add_route_secure_only<H, Args, R>(mut self, method: Method, path: &'static str, handler: H) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::None });
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.service(
+                web::resource(path)
+                    .wrap(RequireHttps::new())
+                    .route(web::method(method.clone()).to(handler.clone()))
+            );
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a new route to the `Routes` instance that requires the given headers.
+     *
+     * The route is wrapped in the `RequireHeaders` middleware, so requests missing
+     * any of the headers receive a `400 Bad Request` naming the missing header
+     * before the handler runs.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     * - `headers`: The names of the headers the request must carry.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Routes, HttpResponse, Method};
+     *
+     * async fn upload() -> HttpResponse {
+     *    HttpResponse::Ok().body("Uploaded")
+     * }
+     *
+     * let routes = Routes::new()
+     *    .add_route_require_headers(Method::POST, "/upload", upload, &["Content-Type", "ngrok-skip-browser-warning"]);
+     * ```
+     */
+    pub fn add_route_require_headers<H, Args, R>(
+        mut self,
+        method: Method,
+        path: &'static str,
+        handler: H,
+        headers: &[&str],
+    ) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::None });
+        let required = RequireHeaders::new(headers);
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.service(
+                web::resource(path)
+                    .wrap(required.clone())
+                    .route(web::method(method.clone()).to(handler.clone()))
+            );
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a new route to the `Routes` instance whose responses carry extra headers.
+     *
+     * The headers are appended to every response from the route, including error
+     * responses, but never replace a header the handler already set. This keeps
+     * `Cache-Control` or `Content-Disposition` for download endpoints out of the
+     * handler code.
+     *
+     * # Arguments
+     * - `method`: The HTTP method for the route (e.g., GET, POST).
+     * - `path`: The URL path for the route.
+     * - `handler`: The handler function for the route.
+     * - `headers`: The `(name, value)` pairs to add to each response. An invalid
+     *   name or value is reported by `problems`, and the route is not added.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, Routes, HttpResponse, Method};
+     * use actix_web::test;
+     *
+     * async fn report() -> HttpResponse {
+     *    HttpResponse::Ok().insert_header(("Cache-Control", "no-store")).body("id,total")
+     * }
+     *
+     * let invalid = Routes::new().add_route_with_headers(Method::GET, "/report.csv", report, &[("Bad Header", "x")]);
+     * assert_eq!(invalid.problems().len(), 1);
+     * assert!(invalid.list().is_empty());
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_route_with_headers(Method::GET, "/report.csv", report, &[
+     *         ("Content-Disposition", "attachment; filename=\"report.csv\""),
+     *         ("Cache-Control", "public, max-age=60"),
+     *     ]);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *
+     *     let req = test::TestRequest::get().uri("/report.csv").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.headers().get("Content-Disposition").unwrap(), "attachment; filename=\"report.csv\"");
+     *     assert_eq!(response.headers().get("Cache-Control").unwrap(), "no-store");
+     * });
+     * ```
+     */
+    pub fn add_route_with_headers<H, Args, R>(
+        mut self,
+        method: Method,
+        path: &'static str,
+        handler: H,
+        headers: &[(&str, &str)],
+    ) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        let headers: Result<Vec<(header::HeaderName, header::HeaderValue)>, String> = headers
+            .iter()
+            .map(|&(name, value)| {
+                let header_name = header::HeaderName::try_from(name)
+                    .map_err(|_| format!("headers for {}: invalid header name {:?}", path, name))?;
+                let header_value = header::HeaderValue::try_from(value)
+                    .map_err(|_| format!("headers for {}: invalid value for header {:?}", path, name))?;
+                Ok((header_name, header_value))
+            })
+            .collect();
+        let headers = match headers {
+            Ok(headers) => headers,
+            Err(problem) => {
+                self.problems.push(problem);
+                return self;
+            }
+        };
+
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth: RouteAuth::None });
+        let route = move |cfg: &mut web::ServiceConfig| {
+            let defaults = headers.iter().cloned().fold(DefaultHeaders::new(), |defaults, header| defaults.add(header));
+            cfg.service(
+                web::resource(path)
+                    .wrap(defaults)
+                    .route(web::method(method.clone()).to(handler.clone()))
+            );
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Add a `GET` route serving a constant JSON value.
+     *
+     * The value is serialized once and served with `Content-Type: application/json`
+     * and `Cache-Control: public, max-age=300`. The route still passes through the
+     * rate limiter and CORS like any other route.
+     *
+     * # Arguments
+     * - `path`: The URL path for the route.
+     * - `value`: The JSON value to serve.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, Routes};
+     * use rusty_api::routes::STATIC_CACHE_CONTROL;
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_static_json("/features", serde_json::json!({ "dark_mode": true }));
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *
+     *     let req = test::TestRequest::get().uri("/features").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.headers().get("Content-Type").unwrap(), "application/json");
+     *     assert_eq!(response.headers().get("Cache-Control").unwrap(), STATIC_CACHE_CONTROL);
+     *     assert_eq!(test::read_body(response).await, r#"{"dark_mode":true}"#);
+     * });
+     * ```
+     */
+    pub fn add_static_json(self, path: &'static str, value: serde_json::Value) -> Self {
+        self.add_static(path, "application/json", Bytes::from(value.to_string()))
+    }
+
+    /**
+     * Add a `GET` route serving constant plain text.
+     *
+     * The text is served with `Content-Type: text/plain; charset=utf-8` and
+     * `Cache-Control: public, max-age=300`.
+     *
+     * # Arguments
+     * - `path`: The URL path for the route.
+     * - `text`: The text to serve.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::Routes;
+     *
+     * let routes = Routes::new().add_static_text("/robots.txt", "User-agent: *\nDisallow: /");
+     * ```
+     */
+    pub fn add_static_text(self, path: &'static str, text: impl Into<String>) -> Self {
+        self.add_static(path, "text/plain; charset=utf-8", Bytes::from(text.into()))
+    }
+
+    /**
+     * Add a route redirecting every request for `from` to `to`.
+     *
+     * # Arguments
+     * - `from`: The URL path to redirect.
+     * - `to`: The target sent in the `Location` header, a path or a full URL.
+     * - `status`: The redirect status: `301`, `302`, `307` or `308`. Other
+     *   statuses are reported by `problems`, and the route is not added.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, Routes, StatusCode};
+     * use actix_web::test;
+     *
+     * let invalid = Routes::new().add_redirect("/", "/docs", StatusCode::OK);
+     * assert_eq!(invalid.problems(), ["redirect from /: unsupported status 200 OK, expected 301, 302, 307 or 308"]);
+     * assert!(Api::new().configure_routes(invalid).validate().is_err());
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_redirect("/", "/docs", StatusCode::FOUND);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *
+     *     let req = test::TestRequest::get().uri("/").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.status(), 302);
+     *     assert_eq!(response.headers().get("Location").unwrap(), "/docs");
+     * });
+     * ```
+     */
+    pub fn add_redirect(self, from: &'static str, to: &'static str, status: StatusCode) -> Self {
+        self.add_redirect_internal(from, to, status, false)
+    }
+
+    /**
+     * Add a route redirecting every request for `from` to `to`, keeping the query string.
+     *
+     * A request for `/old?page=2` redirected to `/new` is sent to `/new?page=2`.
+     *
+     * # Arguments
+     * - `from`: The URL path to redirect.
+     * - `to`: The target path or URL, without a query string.
+     * - `status`: The redirect status: `301`, `302`, `307` or `308`. Other
+     *   statuses are reported by `problems`, and the route is not added.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, Routes, StatusCode};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().add_redirect_with_query("/old", "/new", StatusCode::PERMANENT_REDIRECT);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *
+     *     let req = test::TestRequest::post().uri("/old?page=2").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.status(), 308);
+     *     assert_eq!(response.headers().get("Location").unwrap(), "/new?page=2");
+     * });
+     * ```
+     */
+    pub fn add_redirect_with_query(self, from: &'static str, to: &'static str, status: StatusCode) -> Self {
+        self.add_redirect_internal(from, to, status, true)
+    }
+
+    /**
+     * Add a reverse-proxy route forwarding every request under a path prefix to an upstream server.
+     *
+     * The method, headers and body of the request are sent to the upstream URL
+     * followed by the full original path and query string, so `/legacy/users?page=2`
+     * proxied to `http://legacy:8080` is sent to `http://legacy:8080/legacy/users?page=2`.
+     * The upstream status, headers and body are relayed back. Hop-by-hop headers,
+     * such as `Connection` and `Transfer-Encoding`, are dropped in both directions.
+     *
+     * Bodies are streamed rather than buffered, and compressed upstream responses
+     * are relayed as they are. Redirects are relayed to the client, not followed.
+     * The proxied requests pass through the middleware of the `Api`, so the rate
+     * limiter, IP filter and request limits apply at the edge.
+     *
+     * An unreachable upstream produces a `502 Bad Gateway`, and an upstream that
+     * does not respond within `PROXY_TIMEOUT` a `504 Gateway Timeout`.
+     *
+     * # Arguments
+     * - `path`: The path prefix to forward, e.g. `/legacy`. The prefix itself and every path below it are forwarded.
+     * - `upstream`: The base URL of the upstream server, e.g. `http://legacy:8080`.
+     *   Anything but an absolute URL with a scheme and host is reported by
+     *   `problems`, and the route is not added.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpRequest, HttpResponse, Method, Routes};
+     * use actix_web::{test, web, App, HttpServer};
+     *
+     * let invalid = Routes::new().add_proxy("/legacy", "legacy:8080/api");
+     * assert_eq!(invalid.problems().len(), 1);
+     * assert!(invalid.list().is_empty());
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     // A stand-in for the legacy backend.
+     *     let legacy = HttpServer::new(|| App::new().default_service(web::to(|req: HttpRequest, body: String| async move {
+     *         HttpResponse::Ok().insert_header(("X-Legacy", "yes")).body(format!("{} {} {}", req.method(), req.uri(), body))
+     *     })))
+     *     .workers(1)
+     *     .bind("127.0.0.1:0")
+     *     .unwrap();
+     *     let upstream = format!("http://{}", legacy.addrs()[0]);
+     *     actix_web::rt::spawn(legacy.run());
+     *
+     *     let routes = Routes::new().add_proxy("/legacy", upstream);
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let req = test::TestRequest::post()
+     *         .uri("/legacy/users?page=2")
+     *         .set_payload("hello")
+     *         .peer_addr("127.0.0.1:12345".parse().unwrap())
+     *         .to_request();
+     *     let response = test::call_service(&app, req).await;
+     *     assert_eq!(response.status(), 200);
+     *     assert_eq!(response.headers().get("X-Legacy").unwrap(), "yes");
+     *     assert_eq!(test::read_body(response).await, "POST /legacy/users?page=2 hello");
+     * });
+     * ```
+     */
+    pub fn add_proxy(mut self, path: &'static str, upstream: impl Into<String>) -> Self {
+        let upstream = upstream.into();
+        match upstream.parse::<awc::http::Uri>() {
+            Ok(uri) if uri.scheme().is_some() && uri.authority().is_some() => {}
+            Ok(_) => {
+                self.problems.push(format!(
+                    "proxy {}: invalid upstream '{}', expected an absolute URL such as http://host:port",
+                    path, upstream
+                ));
+                return self;
+            }
+            Err(e) => {
+                self.problems.push(format!("proxy {}: invalid upstream '{}': {}", path, upstream, e));
+                return self;
+            }
+        }
+        let upstream: Arc<str> = Arc::from(upstream.trim_end_matches('/'));
+        self.info.push(RouteInfo { method: None, path, auth: RouteAuth::None });
+
+        let prefix = path.trim_end_matches('/');
+        let route = move |cfg: &mut web::ServiceConfig| {
+            // `awc::Client` is not `Send`, so each worker gets its own when its app is built.
+            let client = awc::Client::builder().timeout(PROXY_TIMEOUT).disable_redirects().finish();
+            let upstream = Arc::clone(&upstream);
+            let patterns = vec![if prefix.is_empty() { "/".to_string() } else { prefix.to_string() }, format!("{}/{{tail:.*}}", prefix)];
+            cfg.service(web::resource(patterns).to(move |req: HttpRequest, payload: web::Payload| {
+                proxy_request(req, payload, client.clone(), Arc::clone(&upstream))
+            }));
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /// Internal function to add a redirect route for any method.
+    fn add_redirect_internal(mut self, from: &'static str, to: &'static str, status: StatusCode, keep_query: bool) -> Self {
+        if !matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY
+                | StatusCode::FOUND
+                | StatusCode::TEMPORARY_REDIRECT
+                | StatusCode::PERMANENT_REDIRECT
+        ) {
+            self.problems.push(format!("redirect from {}: unsupported status {}, expected 301, 302, 307 or 308", from, status));
+            return self;
+        }
+        self.info.push(RouteInfo { method: None, path: from, auth: RouteAuth::None });
+
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.route(from, web::route().to(move |req: HttpRequest| async move {
+                let location = match req.query_string() {
+                    query if keep_query && !query.is_empty() => format!("{}?{}", to, query),
+                    _ => to.to_string(),
+                };
+                HttpResponse::build(status)
+                    .insert_header((header::LOCATION, location))
+                    .finish()
+            }));
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /// Internal function to add a `GET` route serving a constant body.
+    fn add_static(mut self, path: &'static str, content_type: &'static str, body: Bytes) -> Self {
+        self.info.push(RouteInfo { method: Some(Method::GET), path, auth: RouteAuth::None });
+        let route = move |cfg: &mut web::ServiceConfig| {
+            let body = body.clone();
+            cfg.route(path, web::get().to(move || {
+                let body = body.clone();
+                async move {
+                    HttpResponse::Ok()
+                        .content_type(content_type)
+                        .insert_header((header::CACHE_CONTROL, STATIC_CACHE_CONTROL))
+                        .body(body)
+                }
+            }));
+        };
+
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /// Internal function to handle adding routes with or without passwords.
+    fn add_route_internal<H, Args, R>(
+        self,
+        method: Method,
+        path: &'static str,
+        handler: H,
+        password: Option<ApiPassword>,
+    ) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        let auth = if password.is_some() { RouteAuth::Password } else { RouteAuth::None };
+        let guard = password.clone().map(password_guard);
+        self.add_guarded_route(method, path, handler, auth, guard, password)
+    }
+
+    /// Register a route whose handler only runs if the guard, if any, accepts the request.
+    fn add_guarded_route<H, Args, R>(
+        mut self,
+        method: Method,
+        path: &'static str,
+        handler: H,
+        auth: RouteAuth,
+        guard: Option<RouteGuard>,
+        password: Option<ApiPassword>,
+    ) -> Self
+    where
+        H: Handler<Args, Output = R> + Clone + Send + Sync + 'static,
+        Args: FromRequest + 'static,
+        R: Responder + 'static,
+    {
+        self.info.push(RouteInfo { method: Some(method.clone()), path, auth });
+        let handler = handler.clone(); // Clone the handler to avoid moving it
+        let wrapped_handler = move |req: HttpRequest, args: Args| {
+            let handler = handler.clone(); // Clone the handler inside the closure
+            let guard = guard.clone();
+            async move {
+                if let Some(guard) = &guard
+                    && let Err(response) = guard(&req)
+                {
+                    return response;
+                }
+                // Call the original handler and convert its output to an HttpResponse
+                handler.call(args).await.respond_to(&req).map_into_boxed_body()
+            }
+        };
+
+        let m = method.clone();
+        let route = move |cfg: &mut web::ServiceConfig| {
+            let wrapped_handler = wrapped_handler.clone(); // Clone the wrapped handler inside the route closure
+            let mut resource = web::resource(path);
+            if let Some(password) = &password {
+                resource = resource.app_data(password.clone());
+            }
+            cfg.service(resource.route(web::method(m.clone()).to(wrapped_handler.clone())));
+        };
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /**
+     * Append every route registered in another `Routes` instance.
+     *
+     * This lets routes be defined in separate modules, e.g. `user_routes()` and
+     * `admin_routes()`, and composed into one `Routes`. A warning is logged for
+     * each route whose method and path are already registered, since only the
+     * first one would ever be matched.
+     *
+     * # Arguments
+     * - `other`: The routes to append.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{HttpResponse, Method, Routes};
+     *
+     * fn user_routes() -> Routes {
+     *     Routes::new().add_route(Method::GET, "/users", || async { HttpResponse::Ok().finish() })
+     * }
+     *
+     * fn health_routes() -> Routes {
+     *     Routes::new().add_route(Method::GET, "/health", || async { HttpResponse::Ok().finish() })
+     * }
+     *
+     * let routes = user_routes().merge(health_routes());
+     * assert_eq!(routes.list().len(), 2);
+     * ```
+     */
+    pub fn merge(mut self, other: Routes) -> Self {
+        self.warn_duplicates(&other.info);
+        self.routes.extend(other.routes);
+        self.info.extend(other.info);
+        self.problems.extend(other.problems);
+        self
+    }
+
+    /**
+     * Mount every route registered in another `Routes` instance under a path prefix.
+     *
+     * The routes are registered in an Actix Web scope, so `/users` mounted at
+     * `/admin` is served at `/admin/users`. The scope handles every request
+     * under the prefix: routes outside the mounted set that share the prefix
+     * must be part of it. A warning is logged for each mounted route whose
+     * method and path are already registered.
+     *
+     * # Arguments
+     * - `prefix`: The path prefix, e.g. `/admin`.
+     * - `other`: The routes to mount.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use actix_web::test;
+     *
+     * fn admin_routes() -> Routes {
+     *     Routes::new().add_route(Method::GET, "/stats", || async { HttpResponse::Ok().body("stats") })
+     * }
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let routes = Routes::new().mount("/admin", admin_routes());
+     *     assert_eq!(routes.list()[0].path, "/admin/stats");
+     *
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let req = test::TestRequest::get().uri("/admin/stats").peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     assert_eq!(test::call_and_read_body(&app, req).await, "stats");
+     * });
+     * ```
+     */
+    pub fn mount(mut self, prefix: &'static str, other: Routes) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        // Route paths are `'static`; mounting happens once at startup, so the
+        // prefixed paths are leaked rather than changing `RouteInfo`.
+        let info: Vec<RouteInfo> = other
+            .info
+            .into_iter()
+            .map(|info| RouteInfo { path: Box::leak(format!("{}{}", prefix, info.path).into_boxed_str()), ..info })
+            .collect();
+        self.warn_duplicates(&info);
+        self.info.extend(info);
+        self.problems.extend(other.problems);
+
+        let routes = other.routes;
+        let route = move |cfg: &mut web::ServiceConfig| {
+            cfg.service(web::scope(prefix).configure(|scope| {
+                for route in &routes {
+                    route(scope);
+                }
+            }));
+        };
+        self.routes.push(Box::new(route));
+        self
+    }
+
+    /// Log a warning for each route that is already registered with the same method and path.
+    fn warn_duplicates(&self, other: &[RouteInfo]) {
+        for info in other {
+            let duplicate = self.info.iter().any(|existing| {
+                existing.path == info.path
+                    && (existing.method.is_none() || info.method.is_none() || existing.method == info.method)
+            });
+            if duplicate {
+                let method = info.method.as_ref().map_or("*", Method::as_str);
+                log::warn!("Route {} {} is registered more than once", method, info.path);
+            }
+        }
+    }
+
+    /**
+     * Create routes from a table of `RouteSpec`s.
+     *
+     * This complements the fluent builder for config-driven setups: each spec is
+     * registered with the builder method matching its `SpecAuth`, so the routes
+     * behave exactly like ones added by hand. `Named` handlers are looked up in
+     * the registry. Paths and passwords are leaked to give them the `'static`
+     * lifetime routes require, so build the routes once at startup.
+     *
+     * # Arguments
+     * - `specs`: The routes to register.
+     * - `registry`: The handlers `RouteHandler::Named` specs refer to.
+     *
+     * # Returns
+     * The routes, or `ApiError::InvalidConfig` if a spec names an unknown handler,
+     * or requires a token or privilege level without the `auth` feature.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Api, HttpResponse, Method, Routes};
+     * use rusty_api::routes::{spec_handler, HandlerRegistry, RouteHandler, RouteSpec, SpecAuth};
+     * use actix_web::test;
+     *
+     * actix_web::rt::System::new().block_on(async {
+     *     let registry = HandlerRegistry::new()
+     *         .register("health", spec_handler(|_req| async { HttpResponse::Ok().body("ok") }));
+     *     let specs = vec![
+     *         RouteSpec { method: Method::GET, path: "/health".into(), auth: SpecAuth::None, handler: RouteHandler::Named("health".into()) },
+     *         RouteSpec {
+     *             method: Method::POST,
+     *             path: "/purge".into(),
+     *             auth: SpecAuth::Password("Secret".into()),
+     *             handler: RouteHandler::Boxed(spec_handler(|_req| async { HttpResponse::Ok().body("purged") })),
+     *         },
+     *     ];
+     *     let routes = Routes::from_specs(specs, &registry).unwrap();
+     *     assert_eq!(routes.list().len(), 2);
+     *
+     *     let app = test::init_service(Api::new().configure_routes(routes).test_app().await).await;
+     *     let req = |method, uri| test::TestRequest::default().method(method).uri(uri).peer_addr("127.0.0.1:12345".parse().unwrap()).to_request();
+     *     assert_eq!(test::call_and_read_body(&app, req(Method::GET, "/health")).await, "ok");
+     *     assert_eq!(test::call_service(&app, req(Method::POST, "/purge")).await.status(), 401);
+     *     assert_eq!(test::call_and_read_body(&app, req(Method::POST, "/purge?password=Secret")).await, "purged");
+     *
+     *     let unknown = RouteSpec { method: Method::GET, path: "/x".into(), auth: SpecAuth::None, handler: RouteHandler::Named("nope".into()) };
+     *     assert!(Routes::from_specs(vec![unknown], &registry).is_err());
+     * });
+     * ```
+     */
+    pub fn from_specs(specs: Vec<RouteSpec>, registry: &HandlerRegistry) -> Result<Self, ApiError> {
+        let mut routes = Self::new();
+        for spec in specs {
+            let handler = match spec.handler {
+                RouteHandler::Boxed(handler) => handler,
+                RouteHandler::Named(name) => registry.get(&name).ok_or_else(|| {
+                    ApiError::InvalidConfig(format!("Unknown handler '{}' for {} {}", name, spec.method, spec.path))
+                })?,
+            };
+            let handler = move |req: HttpRequest| handler(req);
+            let path: &'static str = Box::leak(spec.path.into_boxed_str());
+            routes = match spec.auth {
+                SpecAuth::None => routes.add_route(spec.method, path, handler),
+                SpecAuth::Password(password) => {
+                    routes.add_route_with_password(spec.method, path, handler, Box::leak(password.into_boxed_str()))
+                }
+                #[cfg(feature = "auth")]
+                SpecAuth::Token => {
+                    let mut routes = routes.add_route_with_token_privilege(spec.method, path, handler, i32::MIN);
+                    if let Some(info) = routes.info.last_mut() {
+                        info.auth = RouteAuth::Token;
+                    }
+                    routes
+                }
+                #[cfg(feature = "auth")]
+                SpecAuth::Privilege(level) => routes.add_route_with_privilege(spec.method, path, handler, level),
+                #[cfg(not(feature = "auth"))]
+                SpecAuth::Token | SpecAuth::Privilege(_) => {
+                    return Err(ApiError::InvalidConfig(format!("{} {} requires the `auth` feature", spec.method, path)));
+                }
+            };
+        }
+        Ok(routes)
+    }
+
+    /**
+     * List the registered routes, in the order they were added.
+     *
+     * This helps to catch typos in paths and to confirm that authentication is
+     * applied where expected. See `RouteInfo` for an example.
+     *
+     * # Returns
+     * The method, path and authentication requirement of every route.
+     */
+    pub fn list(&self) -> Vec<RouteInfo> {
+        self.info.clone()
+    }
+
+    /**
+     * List the routes that could not be added, such as redirects with an
+     * unsupported status, proxies with an invalid upstream or routes with
+     * invalid extra headers.
+     *
+     * `Api::validate` reports them, and `Api::build` refuses to start with them.
+     *
+     * # Returns
+     * A description of each problem, in the order the routes were added.
+     */
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    /**
+     * Serve the list of registered routes as JSON at the given path.
+     *
+     * Used by `Api::with_routes_endpoint`.
+     */
+    pub(crate) fn configure_list_route(cfg: &mut web::ServiceConfig, path: &str, routes: Arc<Vec<RouteInfo>>) {
+        cfg.route(path, web::get().to(move || {
+            let routes = Arc::clone(&routes);
+            async move { HttpResponse::Ok().json(&*routes) }
+        }));
+    }
+
+    /**
+     * Apply the routes to a `ServiceConfig`.
+     *
+     * This method iterates over all defined routes and applies them to the
+     * provided Axtix Web `ServiceConfig`. It is used internally by the `Api` struct.
+     *
+     * # Arguments
+     * - `cfg`: A mutable reference to the `ServiceConfig` to which the routes will be applied.
+     *
+     * # Example
+     * ```rust
+     * use rusty_api::{Routes, Api};
+     *
+     * let routes = Routes::new();
+     *
+     * let api = Api::new()
+     *    .configure_routes(routes); // The configure_routes method calls the configure method internally.
+     * ```
+     */
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        for route in &self.routes {
+            route(cfg);
+        }
+    }
+}
+
+impl Default for Routes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Validate the token of the request and return the user ID it belongs to.
+#[cfg(feature = "auth")]
+async fn authenticate(req: &HttpRequest) -> Result<i32, HttpResponse> {
+    let token = extract_token(req)
+        .ok_or_else(|| HttpResponse::Unauthorized().body("Missing or invalid token"))?;
+    validate_request_token(req, &token)
+        .await
+        .map(|claims| claims.sub)
+        .map_err(token_error_response)
+}
+
+/// Forward a request to the upstream of a proxy route and stream the upstream response back.
+async fn proxy_request(req: HttpRequest, payload: web::Payload, client: awc::Client, upstream: Arc<str>) -> HttpResponse {
+    let path_and_query = req.uri().path_and_query().map_or(req.path(), |pq| pq.as_str());
+    let url = format!("{}{}", upstream, path_and_query);
+    let mut request = client.request(req.method().clone(), &url).no_decompress();
+    for (name, value) in forwarded_headers(req.headers()) {
+        request = request.append_header((name.clone(), value.clone()));
+    }
+
+    let sent = match content_length(req.headers()) {
+        Some(length) => request.send_body(SizedStream::new(length, payload)).await,
+        None if req.headers().contains_key(header::TRANSFER_ENCODING) => request.send_stream(payload).await,
+        None => request.send().await,
+    };
+    let upstream_response = match sent {
+        Ok(response) => response,
+        Err(SendRequestError::Timeout) => return json_error(StatusCode::GATEWAY_TIMEOUT, "Upstream timed out"),
+        Err(e) => {
+            log::warn!("Proxy request to {} failed: {}", url, e);
+            return json_error(StatusCode::BAD_GATEWAY, "Bad gateway");
+        }
+    };
+
+    let mut response = HttpResponse::build(upstream_response.status());
+    for (name, value) in forwarded_headers(upstream_response.headers()) {
+        response.append_header((name.clone(), value.clone()));
+    }
+    match content_length(upstream_response.headers()) {
+        Some(length) => response.body(SizedStream::new(length, upstream_response)),
+        None => response.streaming(upstream_response),
+    }
+}
+
+/// Get the headers a proxy forwards: all but the hop-by-hop headers, those named in `Connection`, `Host` and `Content-Length`.
+fn forwarded_headers(headers: &header::HeaderMap) -> impl Iterator<Item = (&header::HeaderName, &header::HeaderValue)> {
+    let connection: Vec<String> = headers
+        .get_all(header::CONNECTION)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    headers.iter().filter(move |(name, _)| {
+        let name = name.as_str();
+        !HOP_BY_HOP_HEADERS.contains(&name)
+            && !connection.iter().any(|listed| listed == name)
+            && name != "host"
+            && name != "content-length"
+    })
+}
+
+/// Get the `Content-Length` of a message, if it is set and valid.
+fn content_length(headers: &header::HeaderMap) -> Option<u64> {
+    headers.get(header::CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+/// Deserialize a JSON request body, producing a `400 Bad Request` response on failure.
+fn parse_json_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, HttpResponse> {
+    serde_json::from_slice(body).map_err(|e| {
+        json_error(StatusCode::BAD_REQUEST, format!("Invalid JSON body: {}", e))
+    })
+}
+
+/// Check if the request contains one of the accepted passwords in the query string.
+fn check_password(req: &HttpRequest, expected_password: &ApiPassword) -> bool {
+    let query_string = req.query_string();
+
+    for pair in query_string.split('&') {
+        let mut key_value = pair.splitn(2, '=');
+        if let (Some(key), Some(value)) = (key_value.next(), key_value.next())
+            && key == "password"
+            && expected_password.verify(value)
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Build the guard rejecting requests without one of the accepted passwords.
+fn password_guard(password: ApiPassword) -> RouteGuard {
+    Arc::new(move |req| {
+        if check_password(req, &password) {
+            Ok(())
+        } else {
+            Err(HttpResponse::Unauthorized().body("Invalid password"))
+        }
+    })
+}
+
+/// Compare two byte strings without exiting early on the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}